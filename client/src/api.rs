@@ -0,0 +1,571 @@
+use hmac::{Hmac, Mac};
+use reqwest::StatusCode;
+use serde_json::Value;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::types::*;
+use crate::{
+    Client, ClientResult, HeaderMap, Method, Paid, RawResponse, TASK_SIGNATURE_HEADER,
+    error_for_status, read_json, settlement,
+};
+
+/// Outcome of `POST /sponsored-apis/{api_id}/run`.
+#[derive(Debug, Clone)]
+pub enum SponsoredApiRun {
+    Completed(SponsoredApiRunResponse),
+    /// `mode: async` was requested; poll [`Client::get_job`] for the result.
+    Queued(SponsoredApiJobAccepted),
+}
+
+impl Client {
+    pub async fn health(&self) -> ClientResult<MessageResponse> {
+        self.json(self.request(Method::GET, "/health")).await
+    }
+
+    /// Prometheus text exposition.
+    pub async fn metrics(&self) -> ClientResult<String> {
+        self.text(self.request(Method::GET, "/metrics")).await
+    }
+
+    /// The server's OpenAPI 3.1 document.
+    pub async fn openapi(&self) -> ClientResult<Value> {
+        self.json(self.request(Method::GET, "/openapi.json")).await
+    }
+
+    pub async fn create_profile(&self, request: &CreateUserRequest) -> ClientResult<UserProfile> {
+        self.json(self.request(Method::POST, "/profiles").json(request))
+            .await
+    }
+
+    pub async fn register(&self, request: &CreateUserRequest) -> ClientResult<UserProfile> {
+        self.json(self.request(Method::POST, "/register").json(request))
+            .await
+    }
+
+    pub async fn list_profiles(&self) -> ClientResult<Vec<UserProfile>> {
+        self.json(self.request(Method::GET, "/profiles")).await
+    }
+
+    pub async fn get_profile(&self, user_id: Uuid) -> ClientResult<UserProfile> {
+        self.json(self.request(Method::GET, &format!("/profiles/{user_id}")))
+            .await
+    }
+
+    pub async fn update_profile(
+        &self,
+        user_id: Uuid,
+        request: &UpdateUserProfileRequest,
+    ) -> ClientResult<UserProfile> {
+        self.json(
+            self.request(Method::PATCH, &format!("/profiles/{user_id}"))
+                .json(request),
+        )
+        .await
+    }
+
+    pub async fn delete_profile(&self, user_id: Uuid) -> ClientResult<MessageResponse> {
+        self.json(self.request(Method::DELETE, &format!("/profiles/{user_id}")))
+            .await
+    }
+
+    pub async fn export_profile(&self, user_id: Uuid) -> ClientResult<UserDataExport> {
+        self.json(self.request(Method::GET, &format!("/profiles/{user_id}/export")))
+            .await
+    }
+
+    pub async fn create_campaign(
+        &self,
+        request: &CreateCampaignRequest,
+    ) -> ClientResult<CreateCampaignResponse> {
+        self.json(self.request(Method::POST, "/campaigns").json(request))
+            .await
+    }
+
+    pub async fn list_campaigns(&self) -> ClientResult<Vec<Campaign>> {
+        self.json(self.request(Method::GET, "/campaigns")).await
+    }
+
+    pub async fn get_campaign(&self, campaign_id: Uuid) -> ClientResult<Campaign> {
+        self.json(self.request(Method::GET, &format!("/campaigns/{campaign_id}")))
+            .await
+    }
+
+    pub async fn campaign_discovery(&self) -> ClientResult<Vec<CampaignDiscoveryItem>> {
+        self.json(self.request(Method::GET, "/campaigns/discovery"))
+            .await
+    }
+
+    pub async fn x402_manifest(&self) -> ClientResult<X402Manifest> {
+        self.json(self.request(Method::GET, "/.well-known/x402"))
+            .await
+    }
+
+    pub async fn discovery_resources(
+        &self,
+        query: &DiscoveryQuery,
+    ) -> ClientResult<DiscoveryResourcePage> {
+        self.json(
+            self.request(Method::GET, "/discovery/resources")
+                .query(query),
+        )
+        .await
+    }
+
+    pub async fn complete_task(
+        &self,
+        request: &TaskCompletionRequest,
+    ) -> ClientResult<TaskCompletionResponse> {
+        self.json(self.request(Method::POST, "/tasks/complete").json(request))
+            .await
+    }
+
+    pub async fn get_task_completion(&self, completion_id: Uuid) -> ClientResult<TaskCompletion> {
+        self.json(self.request(Method::GET, &format!("/tasks/{completion_id}")))
+            .await
+    }
+
+    /// Follows the OAuth redirect the way a browser would, for tests and headless flows.
+    /// `signature` comes from the sponsor; see [`oauth_callback_signature`].
+    pub async fn task_oauth_callback(
+        &self,
+        state: &str,
+        error: Option<&str>,
+        signature: &str,
+    ) -> ClientResult<TaskCompletion> {
+        let mut query = vec![("state", state), ("signature", signature)];
+        query.extend(error.map(|error| ("error", error)));
+        self.json(
+            self.request(Method::GET, "/tasks/oauth/callback")
+                .query(&query),
+        )
+        .await
+    }
+
+    /// Resolves a sponsor-verified task, signing the body with the campaign's task secret.
+    pub async fn task_callback(
+        &self,
+        completion_id: Uuid,
+        task_secret: &str,
+        request: &TaskCallbackRequest,
+    ) -> ClientResult<TaskCompletion> {
+        let body = serde_json::to_vec(request).expect("callback bodies serialize");
+        let signature = sign_with_task_secret(task_secret, &body);
+        self.json(
+            self.request(Method::POST, &format!("/tasks/{completion_id}/callback"))
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(TASK_SIGNATURE_HEADER, signature)
+                .body(body),
+        )
+        .await
+    }
+
+    /// Always paid by the caller.
+    pub async fn run_tool(
+        &self,
+        service: &str,
+        request: &ServiceRunRequest,
+    ) -> ClientResult<Paid<ServiceRunResponse>> {
+        self.paid_json(
+            self.request(Method::POST, &format!("/tool/{service}/run"))
+                .json(request),
+        )
+        .await
+    }
+
+    /// One payment covers every input.
+    pub async fn run_tool_batch(
+        &self,
+        service: &str,
+        request: &ServiceBatchRunRequest,
+    ) -> ClientResult<Paid<ServiceBatchRunResponse>> {
+        self.paid_json(
+            self.request(Method::POST, &format!("/tool/{service}/run-batch"))
+                .json(request),
+        )
+        .await
+    }
+
+    /// Sponsored by a campaign when the user qualifies, paid by the caller otherwise.
+    pub async fn run_proxy(
+        &self,
+        service: &str,
+        request: &ServiceRunRequest,
+    ) -> ClientResult<Paid<ServiceRunResponse>> {
+        self.paid_json(
+            self.request(Method::POST, &format!("/proxy/{service}/run"))
+                .json(request),
+        )
+        .await
+    }
+
+    /// Sends one JSON-RPC message to the MCP endpoint. Notifications return `Value::Null`.
+    pub async fn mcp(&self, message: &Value) -> ClientResult<Value> {
+        let (response, _) = self
+            .send(self.request(Method::POST, "/mcp").json(message))
+            .await?;
+        if response.status() == StatusCode::ACCEPTED {
+            return Ok(Value::Null);
+        }
+        read_json(response).await
+    }
+
+    /// Creating an API is itself paid with x402.
+    pub async fn create_sponsored_api(
+        &self,
+        request: &CreateSponsoredApiRequest,
+    ) -> ClientResult<Paid<SponsoredApi>> {
+        self.paid_json(self.request(Method::POST, "/sponsored-apis").json(request))
+            .await
+    }
+
+    pub async fn list_sponsored_apis(&self) -> ClientResult<Vec<SponsoredApi>> {
+        self.json(self.request(Method::GET, "/sponsored-apis"))
+            .await
+    }
+
+    pub async fn import_sponsored_apis(
+        &self,
+        request: &ImportSponsoredApisRequest,
+    ) -> ClientResult<Paid<ImportSponsoredApisResponse>> {
+        self.paid_json(
+            self.request(Method::POST, "/sponsored-apis/import")
+                .json(request),
+        )
+        .await
+    }
+
+    pub async fn get_sponsored_api(&self, api_id: Uuid) -> ClientResult<SponsoredApi> {
+        self.json(self.request(Method::GET, &format!("/sponsored-apis/{api_id}")))
+            .await
+    }
+
+    pub async fn update_sponsored_api(
+        &self,
+        api_id: Uuid,
+        request: &UpdateSponsoredApiRequest,
+    ) -> ClientResult<SponsoredApi> {
+        self.json(
+            self.request(Method::PATCH, &format!("/sponsored-apis/{api_id}"))
+                .json(request),
+        )
+        .await
+    }
+
+    pub async fn delete_sponsored_api(&self, api_id: Uuid) -> ClientResult<MessageResponse> {
+        self.json(self.request(Method::DELETE, &format!("/sponsored-apis/{api_id}")))
+            .await
+    }
+
+    pub async fn rotate_sponsored_api_headers(
+        &self,
+        api_id: Uuid,
+        request: &RotateSponsoredApiHeadersRequest,
+    ) -> ClientResult<SponsoredApi> {
+        self.json(
+            self.request(Method::PUT, &format!("/sponsored-apis/{api_id}/headers"))
+                .json(request),
+        )
+        .await
+    }
+
+    pub async fn pause_sponsored_api(&self, api_id: Uuid) -> ClientResult<SponsoredApi> {
+        self.json(self.request(Method::POST, &format!("/sponsored-apis/{api_id}/pause")))
+            .await
+    }
+
+    pub async fn resume_sponsored_api(&self, api_id: Uuid) -> ClientResult<SponsoredApi> {
+        self.json(self.request(Method::POST, &format!("/sponsored-apis/{api_id}/resume")))
+            .await
+    }
+
+    pub async fn top_up_sponsored_api(
+        &self,
+        api_id: Uuid,
+        amount_cents: u64,
+    ) -> ClientResult<SponsoredApi> {
+        self.json(
+            self.request(Method::POST, &format!("/sponsored-apis/{api_id}/top-up"))
+                .json(&TopUpRequest { amount_cents }),
+        )
+        .await
+    }
+
+    pub async fn sponsored_api_audit(
+        &self,
+        api_id: Uuid,
+    ) -> ClientResult<Vec<SponsoredApiAuditEntry>> {
+        self.json(self.request(Method::GET, &format!("/sponsored-apis/{api_id}/audit")))
+            .await
+    }
+
+    pub async fn sponsored_api_calls(
+        &self,
+        api_id: Uuid,
+        query: &SponsoredApiCallsQuery,
+    ) -> ClientResult<SponsoredApiCallPage> {
+        self.json(
+            self.request(Method::GET, &format!("/sponsored-apis/{api_id}/calls"))
+                .query(query),
+        )
+        .await
+    }
+
+    /// The call log as CSV or NDJSON. The next page's cursor is in `x-next-cursor`, so this
+    /// returns it alongside the body.
+    pub async fn export_sponsored_api_calls(
+        &self,
+        api_id: Uuid,
+        query: &SponsoredApiCallsQuery,
+        format: CallLogExportFormat,
+    ) -> ClientResult<(String, Option<String>)> {
+        let (response, _) = self
+            .send(
+                self.request(Method::GET, &format!("/sponsored-apis/{api_id}/calls"))
+                    .query(query)
+                    .query(&[("format", format)]),
+            )
+            .await?;
+        let response = error_for_status(response).await?;
+        let next_cursor = response
+            .headers()
+            .get(crate::NEXT_CURSOR_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Ok((response.text().await?, next_cursor))
+    }
+
+    pub async fn run_sponsored_api(
+        &self,
+        api_id: Uuid,
+        request: &SponsoredApiRunRequest,
+    ) -> ClientResult<Paid<SponsoredApiRun>> {
+        let (response, paid_cents) = self
+            .send(
+                self.request(Method::POST, &format!("/sponsored-apis/{api_id}/run"))
+                    .json(request),
+            )
+            .await?;
+        let settlement = settlement(&response)?;
+        let body = if response.status() == StatusCode::ACCEPTED {
+            SponsoredApiRun::Queued(read_json(response).await?)
+        } else {
+            SponsoredApiRun::Completed(read_json(response).await?)
+        };
+        Ok(Paid {
+            body,
+            paid_cents,
+            settlement,
+        })
+    }
+
+    /// One charge covers every input; items that fail before the upstream answers are
+    /// refunded to the sponsor.
+    pub async fn run_sponsored_api_batch(
+        &self,
+        api_id: Uuid,
+        request: &SponsoredApiBatchRunRequest,
+    ) -> ClientResult<Paid<SponsoredApiBatchRunResponse>> {
+        self.paid_json(
+            self.request(Method::POST, &format!("/sponsored-apis/{api_id}/run-batch"))
+                .json(request),
+        )
+        .await
+    }
+
+    pub async fn get_job(&self, job_id: Uuid) -> ClientResult<SponsoredApiJob> {
+        self.json(self.request(Method::GET, &format!("/jobs/{job_id}")))
+            .await
+    }
+
+    pub async fn preview_sponsored_api_transform(
+        &self,
+        api_id: Uuid,
+        request: &TransformPreviewRequest,
+    ) -> ClientResult<TransformPreviewResponse> {
+        self.json(
+            self.request(
+                Method::POST,
+                &format!("/sponsored-apis/{api_id}/transform/preview"),
+            )
+            .json(request),
+        )
+        .await
+    }
+
+    /// Forwards a request to the API's upstream as-is. `path` is appended to the upstream
+    /// URL; the upstream's status and body come back unchanged, so they are not checked.
+    pub async fn proxy_sponsored_api(
+        &self,
+        api_id: Uuid,
+        method: Method,
+        path: &str,
+        headers: HeaderMap,
+        body: impl Into<Vec<u8>>,
+    ) -> ClientResult<Paid<RawResponse>> {
+        let path = path.trim_start_matches('/');
+        let route = if path.is_empty() {
+            format!("/sponsored-apis/{api_id}/proxy")
+        } else {
+            format!("/sponsored-apis/{api_id}/proxy/{path}")
+        };
+        let (response, paid_cents) = self
+            .send(
+                self.request(method, &route)
+                    .headers(headers)
+                    .body(body.into()),
+            )
+            .await?;
+        let settlement = settlement(&response)?;
+        Ok(Paid {
+            body: RawResponse {
+                status: response.status().as_u16(),
+                headers: response.headers().clone(),
+                body: response.bytes().await?.to_vec(),
+            },
+            paid_cents,
+            settlement,
+        })
+    }
+
+    pub async fn create_budget_pool(
+        &self,
+        request: &CreateBudgetPoolRequest,
+    ) -> ClientResult<BudgetPool> {
+        self.json(self.request(Method::POST, "/budget-pools").json(request))
+            .await
+    }
+
+    pub async fn list_budget_pools(&self, sponsor: Option<&str>) -> ClientResult<Vec<BudgetPool>> {
+        let query: Vec<(&str, &str)> = sponsor
+            .map(|sponsor| ("sponsor", sponsor))
+            .into_iter()
+            .collect();
+        self.json(self.request(Method::GET, "/budget-pools").query(&query))
+            .await
+    }
+
+    pub async fn get_budget_pool(&self, pool_id: Uuid) -> ClientResult<BudgetPoolDetail> {
+        self.json(self.request(Method::GET, &format!("/budget-pools/{pool_id}")))
+            .await
+    }
+
+    pub async fn top_up_budget_pool(
+        &self,
+        pool_id: Uuid,
+        amount_cents: u64,
+    ) -> ClientResult<BudgetPool> {
+        self.json(
+            self.request(Method::POST, &format!("/budget-pools/{pool_id}/top-up"))
+                .json(&TopUpRequest { amount_cents }),
+        )
+        .await
+    }
+
+    pub async fn ingest_x402scan_settlement(
+        &self,
+        request: &X402ScanSettlementRequest,
+    ) -> ClientResult<MessageResponse> {
+        self.json(
+            self.request(Method::POST, "/webhooks/x402scan/settlement")
+                .json(request),
+        )
+        .await
+    }
+
+    pub async fn create_sponsor_webhook(
+        &self,
+        request: &CreateSponsorWebhookRequest,
+    ) -> ClientResult<CreateSponsorWebhookResponse> {
+        self.json(
+            self.request(Method::POST, "/sponsor-webhooks")
+                .json(request),
+        )
+        .await
+    }
+
+    pub async fn list_sponsor_webhooks(&self, sponsor: &str) -> ClientResult<Vec<SponsorWebhook>> {
+        self.json(
+            self.request(Method::GET, "/sponsor-webhooks")
+                .query(&[("sponsor", sponsor)]),
+        )
+        .await
+    }
+
+    pub async fn delete_sponsor_webhook(&self, webhook_id: Uuid) -> ClientResult<MessageResponse> {
+        self.json(self.request(Method::DELETE, &format!("/sponsor-webhooks/{webhook_id}")))
+            .await
+    }
+
+    pub async fn list_webhook_deliveries(
+        &self,
+        webhook_id: Uuid,
+    ) -> ClientResult<Vec<WebhookDelivery>> {
+        self.json(self.request(
+            Method::GET,
+            &format!("/sponsor-webhooks/{webhook_id}/deliveries"),
+        ))
+        .await
+    }
+
+    pub async fn redeliver_webhook(&self, delivery_id: Uuid) -> ClientResult<WebhookDelivery> {
+        self.json(self.request(
+            Method::POST,
+            &format!("/sponsor-webhooks/deliveries/{delivery_id}/redeliver"),
+        ))
+        .await
+    }
+
+    pub async fn sponsor_dashboard(&self, campaign_id: Uuid) -> ClientResult<SponsorDashboard> {
+        self.json(self.request(Method::GET, &format!("/dashboard/sponsor/{campaign_id}")))
+            .await
+    }
+
+    pub async fn sponsor_timeseries(
+        &self,
+        campaign_id: Uuid,
+        query: &SponsorTimeseriesQuery,
+    ) -> ClientResult<SponsorTimeseries> {
+        self.json(
+            self.request(
+                Method::GET,
+                &format!("/dashboard/sponsor/{campaign_id}/timeseries"),
+            )
+            .query(query),
+        )
+        .await
+    }
+
+    pub async fn record_creator_metric_event(
+        &self,
+        request: &CreatorMetricEventRequest,
+    ) -> ClientResult<CreatorEvent> {
+        self.json(
+            self.request(Method::POST, "/creator/metrics/event")
+                .json(request),
+        )
+        .await
+    }
+
+    pub async fn creator_metrics(&self) -> ClientResult<CreatorMetricSummary> {
+        self.json(self.request(Method::GET, "/creator/metrics"))
+            .await
+    }
+}
+
+/// The `signature` a sponsor adds when redirecting an OAuth task back: an HMAC of the
+/// `state` it was given and the outcome it reports, keyed with the campaign's task secret.
+pub fn oauth_callback_signature(task_secret: &str, state: &str, error: Option<&str>) -> String {
+    let payload = match error {
+        Some(error) => format!("{state}\nrejected\n{error}"),
+        None => format!("{state}\nverified"),
+    };
+    sign_with_task_secret(task_secret, payload.as_bytes())
+}
+
+fn sign_with_task_secret(task_secret: &str, payload: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(task_secret.as_bytes())
+        .expect("hmac accepts keys of any length");
+    mac.update(payload);
+    hex::encode(mac.finalize().into_bytes())
+}
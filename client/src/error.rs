@@ -0,0 +1,60 @@
+use thiserror::Error;
+
+use crate::payment::PaymentChallenge;
+use crate::types::ApiErrorBody;
+
+pub type ClientResult<T> = Result<T, ClientError>;
+
+/// Errors a [`PaymentSigner`](crate::PaymentSigner) may return.
+pub type SignerError = Box<dyn std::error::Error + Send + Sync>;
+
+#[derive(Debug, Error)]
+pub enum ClientError {
+    #[error("request failed: {0}")]
+    Transport(#[from] reqwest::Error),
+    /// The server's error envelope, for every failure other than 402.
+    #[error("{status} {}: {}", error.code, error.message)]
+    Api {
+        status: u16,
+        error: ApiErrorBody,
+        /// From `Retry-After`, on 429 and 503 responses.
+        retry_after_secs: Option<u64>,
+    },
+    /// No signer is configured, so the challenge is handed back to the caller.
+    #[error("payment required: {}", .0.message)]
+    PaymentRequired(Box<PaymentChallenge>),
+    /// The server answered 402 again after the signed retry.
+    #[error("payment rejected: {}", .0.message)]
+    PaymentRejected(Box<PaymentChallenge>),
+    #[error(
+        "paying {amount_cents} cents would exceed the {limit_cents} cent {limit} limit \
+        ({spent_cents} cents spent so far)"
+    )]
+    SpendLimitExceeded {
+        amount_cents: u64,
+        limit: SpendLimitKind,
+        limit_cents: u64,
+        spent_cents: u64,
+    },
+    #[error("payment signer failed: {0}")]
+    Signer(SignerError),
+    #[error("invalid x402 header: {0}")]
+    InvalidPaymentHeader(String),
+    #[error("unexpected {status} response: {message}")]
+    UnexpectedResponse { status: u16, message: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpendLimitKind {
+    PerCall,
+    Total,
+}
+
+impl std::fmt::Display for SpendLimitKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::PerCall => "per-call",
+            Self::Total => "total",
+        })
+    }
+}
@@ -0,0 +1,228 @@
+//! Typed client for the payloadexchange HTTP API.
+//!
+//! Paid routes answer `402` with an x402 challenge. With a [`PaymentSigner`] configured the
+//! client signs the challenge, retries once with `PAYMENT-SIGNATURE`, and keeps its spending
+//! within [`SpendLimits`]. Without one, the challenge comes back as
+//! [`ClientError::PaymentRequired`].
+
+mod api;
+mod error;
+mod payment;
+pub mod types;
+
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+
+pub use api::{SponsoredApiRun, oauth_callback_signature};
+pub use error::{ClientError, ClientResult, SignerError, SpendLimitKind};
+pub use payment::{
+    PaymentChallenge, PaymentSigner, SpendLimits, StaticSigner, decode_payment_requirements,
+    decode_payment_response, encode_payment_payload, requirement_amount_cents,
+};
+pub use reqwest::{Method, header::HeaderMap};
+
+use crate::payment::Payer;
+use crate::types::{ErrorResponse, PaymentRequired, X402SettleResponse};
+
+pub const PAYMENT_SIGNATURE_HEADER: &str = "payment-signature";
+pub const PAYMENT_REQUIRED_HEADER: &str = "payment-required";
+pub const PAYMENT_RESPONSE_HEADER: &str = "payment-response";
+pub const TASK_SIGNATURE_HEADER: &str = "x-task-signature";
+pub const SPONSORED_CALLER_HEADER: &str = "x-sponsored-caller";
+pub const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    payer: Option<Arc<Payer>>,
+}
+
+/// Response of a route that may take an x402 payment.
+#[derive(Debug, Clone)]
+pub struct Paid<T> {
+    pub body: T,
+    /// What this client paid with x402; zero when the call was sponsored or already paid.
+    pub paid_cents: u64,
+    /// The decoded `PAYMENT-RESPONSE` header, present when a payment settled.
+    pub settlement: Option<X402SettleResponse>,
+}
+
+/// An upstream reply passed through unchanged by the transparent proxy.
+#[derive(Debug, Clone)]
+pub struct RawResponse {
+    pub status: u16,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+impl Client {
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self::with_http_client(base_url, reqwest::Client::new())
+    }
+
+    /// Uses a preconfigured `reqwest` client, e.g. with timeouts or default headers.
+    pub fn with_http_client(base_url: impl Into<String>, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            payer: None,
+        }
+    }
+
+    /// Pays 402 challenges with `signer` as long as `limits` allow it.
+    pub fn with_signer(
+        mut self,
+        signer: impl PaymentSigner + 'static,
+        limits: SpendLimits,
+    ) -> Self {
+        self.payer = Some(Arc::new(Payer::new(Box::new(signer), limits)));
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Total paid with x402 so far, shared by clones of this client.
+    pub fn spent_cents(&self) -> u64 {
+        self.payer.as_ref().map_or(0, |payer| payer.spent_cents())
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        self.http
+            .request(method, format!("{}{path}", self.base_url))
+    }
+
+    /// Sends `request`, paying and retrying once if it answers 402. Returns the final
+    /// response and what was paid for it.
+    ///
+    /// A payment is counted as spent once the signed retry is sent, unless the server
+    /// answers 402 again, which means it did not take the payment.
+    async fn send(&self, request: RequestBuilder) -> ClientResult<(Response, u64)> {
+        let retry = request.try_clone();
+        let response = request.send().await?;
+        if response.status() != StatusCode::PAYMENT_REQUIRED {
+            return Ok((response, 0));
+        }
+
+        let challenge = read_challenge(response).await?;
+        let (Some(payer), Some(retry)) = (&self.payer, retry) else {
+            return Err(ClientError::PaymentRequired(Box::new(challenge)));
+        };
+        let requirement = challenge.accepts.first().ok_or_else(|| {
+            ClientError::InvalidPaymentHeader("challenge has no payment requirements".to_string())
+        })?;
+
+        let amount_cents = requirement_amount_cents(requirement)?;
+        if amount_cents != challenge.amount_cents {
+            return Err(ClientError::InvalidPaymentHeader(format!(
+                "challenge quotes {} cents but its requirement asks for {amount_cents}",
+                challenge.amount_cents
+            )));
+        }
+        payer.reserve(amount_cents)?;
+        let signature = match payer.signer.sign(requirement).await {
+            Ok(signature) => signature,
+            Err(err) => {
+                payer.release(amount_cents);
+                return Err(ClientError::Signer(err));
+            }
+        };
+
+        let response = retry
+            .header(PAYMENT_SIGNATURE_HEADER, signature)
+            .send()
+            .await?;
+        if response.status() == StatusCode::PAYMENT_REQUIRED {
+            payer.release(amount_cents);
+            return Err(ClientError::PaymentRejected(Box::new(
+                read_challenge(response).await?,
+            )));
+        }
+        Ok((response, amount_cents))
+    }
+
+    async fn json<T: DeserializeOwned>(&self, request: RequestBuilder) -> ClientResult<T> {
+        let (response, _) = self.send(request).await?;
+        read_json(response).await
+    }
+
+    async fn paid_json<T: DeserializeOwned>(
+        &self,
+        request: RequestBuilder,
+    ) -> ClientResult<Paid<T>> {
+        let (response, paid_cents) = self.send(request).await?;
+        let settlement = settlement(&response)?;
+        Ok(Paid {
+            body: read_json(response).await?,
+            paid_cents,
+            settlement,
+        })
+    }
+
+    async fn text(&self, request: RequestBuilder) -> ClientResult<String> {
+        let (response, _) = self.send(request).await?;
+        let response = error_for_status(response).await?;
+        Ok(response.text().await?)
+    }
+}
+
+fn settlement(response: &Response) -> ClientResult<Option<X402SettleResponse>> {
+    response
+        .headers()
+        .get(PAYMENT_RESPONSE_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(decode_payment_response)
+        .transpose()
+}
+
+async fn read_challenge(response: Response) -> ClientResult<PaymentChallenge> {
+    let status = response.status().as_u16();
+    let bytes = response.bytes().await?;
+    let body: PaymentRequired =
+        serde_json::from_slice(&bytes).map_err(|err| ClientError::UnexpectedResponse {
+            status,
+            message: format!("invalid 402 body: {err}"),
+        })?;
+    body.try_into()
+}
+
+/// Turns a non-2xx response into [`ClientError::Api`].
+async fn error_for_status(response: Response) -> ClientResult<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let retry_after_secs = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+    let bytes = response.bytes().await?;
+    match serde_json::from_slice::<ErrorResponse>(&bytes) {
+        Ok(envelope) => Err(ClientError::Api {
+            status: status.as_u16(),
+            error: envelope.error,
+            retry_after_secs,
+        }),
+        Err(_) => Err(ClientError::UnexpectedResponse {
+            status: status.as_u16(),
+            message: String::from_utf8_lossy(&bytes).into_owned(),
+        }),
+    }
+}
+
+async fn read_json<T: DeserializeOwned>(response: Response) -> ClientResult<T> {
+    let response = error_for_status(response).await?;
+    let status = response.status().as_u16();
+    let bytes = response.bytes().await?;
+    serde_json::from_slice(&bytes).map_err(|err| ClientError::UnexpectedResponse {
+        status,
+        message: format!("invalid response body: {err}"),
+    })
+}
+
+#[cfg(test)]
+mod test;
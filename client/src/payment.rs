@@ -0,0 +1,167 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use futures_util::future::BoxFuture;
+use serde_json::Value;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::{ClientError, ClientResult, SignerError, SpendLimitKind};
+use crate::types::{PaymentRequired, X402PaymentRequirement, X402SettleResponse};
+
+/// Turns an x402 payment requirement into a `PAYMENT-SIGNATURE` header value: a
+/// base64-encoded x402 payment payload, usually an EIP-3009 authorization signed by a wallet.
+pub trait PaymentSigner: Send + Sync {
+    fn sign<'a>(
+        &'a self,
+        requirement: &'a X402PaymentRequirement,
+    ) -> BoxFuture<'a, Result<String, SignerError>>;
+}
+
+/// Answers every challenge with the same pre-made payment, e.g. one produced by an external
+/// wallet for a known price.
+#[derive(Debug, Clone)]
+pub struct StaticSigner(pub String);
+
+impl PaymentSigner for StaticSigner {
+    fn sign<'a>(
+        &'a self,
+        _requirement: &'a X402PaymentRequirement,
+    ) -> BoxFuture<'a, Result<String, SignerError>> {
+        Box::pin(async move { Ok(self.0.clone()) })
+    }
+}
+
+/// Encodes an x402 payment payload as a `PAYMENT-SIGNATURE` header value.
+pub fn encode_payment_payload(payload: &Value) -> String {
+    STANDARD.encode(payload.to_string())
+}
+
+/// Decodes a `PAYMENT-REQUIRED` header, or the `payment_required` field of a 402 body.
+pub fn decode_payment_requirements(value: &str) -> ClientResult<Vec<X402PaymentRequirement>> {
+    decode_header_json(value)
+}
+
+/// Decodes a `PAYMENT-RESPONSE` header.
+pub fn decode_payment_response(value: &str) -> ClientResult<X402SettleResponse> {
+    decode_header_json(value)
+}
+
+fn decode_header_json<T: serde::de::DeserializeOwned>(value: &str) -> ClientResult<T> {
+    let bytes = STANDARD
+        .decode(value.trim())
+        .map_err(|err| ClientError::InvalidPaymentHeader(format!("not base64: {err}")))?;
+    serde_json::from_slice(&bytes)
+        .map_err(|err| ClientError::InvalidPaymentHeader(format!("not x402 JSON: {err}")))
+}
+
+/// The server prices in cents and asks for USDC, which has six decimals.
+const USDC_BASE_UNITS_PER_CENT: u128 = 10_000;
+
+/// What paying `requirement` costs in cents, rounded up. This is what the signer authorizes,
+/// so spend limits are checked against it rather than the unsigned `amount_cents`.
+pub fn requirement_amount_cents(requirement: &X402PaymentRequirement) -> ClientResult<u64> {
+    let units: u128 = requirement
+        .max_amount_required
+        .trim()
+        .parse()
+        .map_err(|_| {
+            ClientError::InvalidPaymentHeader(format!(
+                "maxAmountRequired is not an integer: {}",
+                requirement.max_amount_required
+            ))
+        })?;
+    u64::try_from(units.div_ceil(USDC_BASE_UNITS_PER_CENT)).map_err(|_| {
+        ClientError::InvalidPaymentHeader("maxAmountRequired is out of range".to_string())
+    })
+}
+
+/// A 402 response with its requirements decoded.
+#[derive(Debug, Clone)]
+pub struct PaymentChallenge {
+    pub service: String,
+    /// The body's quote. Must match [`requirement_amount_cents`] before the client pays.
+    pub amount_cents: u64,
+    pub message: String,
+    pub next_step: String,
+    pub accepts: Vec<X402PaymentRequirement>,
+}
+
+impl TryFrom<PaymentRequired> for PaymentChallenge {
+    type Error = ClientError;
+
+    fn try_from(body: PaymentRequired) -> Result<Self, Self::Error> {
+        Ok(Self {
+            accepts: decode_payment_requirements(&body.payment_required)?,
+            service: body.service,
+            amount_cents: body.amount_cents,
+            message: body.message,
+            next_step: body.next_step,
+        })
+    }
+}
+
+/// Caps on what the client pays on its own. Unset limits do not restrict spending.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpendLimits {
+    pub max_per_call_cents: Option<u64>,
+    /// Across every call made through the client and its clones.
+    pub max_total_cents: Option<u64>,
+}
+
+/// A signer together with the limits it pays under.
+pub(crate) struct Payer {
+    pub signer: Box<dyn PaymentSigner>,
+    pub limits: SpendLimits,
+    spent_cents: AtomicU64,
+}
+
+impl Payer {
+    pub fn new(signer: Box<dyn PaymentSigner>, limits: SpendLimits) -> Self {
+        Self {
+            signer,
+            limits,
+            spent_cents: AtomicU64::new(0),
+        }
+    }
+
+    pub fn spent_cents(&self) -> u64 {
+        self.spent_cents.load(Ordering::SeqCst)
+    }
+
+    /// Counts `amount_cents` as spent if the limits allow it. Concurrent calls cannot
+    /// overshoot the total together.
+    pub fn reserve(&self, amount_cents: u64) -> ClientResult<()> {
+        if let Some(limit_cents) = self.limits.max_per_call_cents
+            && amount_cents > limit_cents
+        {
+            return Err(ClientError::SpendLimitExceeded {
+                amount_cents,
+                limit: SpendLimitKind::PerCall,
+                limit_cents,
+                spent_cents: self.spent_cents(),
+            });
+        }
+        self.spent_cents
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |spent| {
+                let next = spent.checked_add(amount_cents)?;
+                match self.limits.max_total_cents {
+                    Some(limit_cents) if next > limit_cents => None,
+                    _ => Some(next),
+                }
+            })
+            .map(|_| ())
+            .map_err(|spent_cents| ClientError::SpendLimitExceeded {
+                amount_cents,
+                limit: SpendLimitKind::Total,
+                limit_cents: self.limits.max_total_cents.unwrap_or(u64::MAX),
+                spent_cents,
+            })
+    }
+
+    /// Gives back a reservation for a payment the server did not take.
+    pub fn release(&self, amount_cents: u64) {
+        let _ = self
+            .spent_cents
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |spent| {
+                Some(spent.saturating_sub(amount_cents))
+            });
+    }
+}
@@ -0,0 +1,219 @@
+use super::*;
+use crate::types::{ServiceRunRequest, X402PaymentRequirement};
+use axum::Router;
+use axum::extract::Path;
+use axum::http::{HeaderMap as AxumHeaderMap, StatusCode as AxumStatusCode};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde_json::json;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+const PRICE_CENTS: u64 = 5;
+const GOOD_SIGNATURE: &str = "signed-payment";
+
+fn requirement() -> X402PaymentRequirement {
+    X402PaymentRequirement {
+        scheme: "exact".to_string(),
+        network: "base-sepolia".to_string(),
+        max_amount_required: "50000".to_string(),
+        resource: "http://localhost/tool/design/run".to_string(),
+        description: "Access paid service 'design'".to_string(),
+        mime_type: "application/json".to_string(),
+        pay_to: "0x000000000000000000000000000000000000dEaD".to_string(),
+        max_timeout_seconds: 300,
+        asset: "0x036CbD53842c5426634e7929541eC2318f3dCF7e".to_string(),
+        output_schema: None,
+        extra: HashMap::new(),
+    }
+}
+
+fn encode(value: &serde_json::Value) -> String {
+    STANDARD.encode(value.to_string())
+}
+
+/// A paid tool route that settles only `GOOD_SIGNATURE`, and a route that always fails. The
+/// `underquoted` service claims a lower `amount_cents` than its signed requirement.
+async fn mock_server() -> String {
+    async fn run_tool(
+        Path(service): Path<String>,
+        headers: AxumHeaderMap,
+    ) -> axum::response::Response {
+        let signature = headers
+            .get(PAYMENT_SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok());
+        if signature != Some(GOOD_SIGNATURE) {
+            let challenge = json!({
+                "service": "design",
+                "amount_cents": if service == "underquoted" { 1 } else { PRICE_CENTS },
+                "accepted_header": PAYMENT_SIGNATURE_HEADER,
+                "payment_required": encode(&json!([requirement()])),
+                "message": if signature.is_some() { "payment rejected" } else { "missing" },
+                "next_step": "retry",
+            });
+            return (AxumStatusCode::PAYMENT_REQUIRED, axum::Json(challenge)).into_response();
+        }
+        let settlement = encode(&json!({ "success": true, "transaction": "0xabc" }));
+        (
+            [(PAYMENT_RESPONSE_HEADER, settlement)],
+            axum::Json(json!({
+                "service": "design",
+                "output": "done",
+                "payment_mode": "user_direct",
+                "sponsored_by": null,
+                "tx_hash": "0xabc",
+            })),
+        )
+            .into_response()
+    }
+
+    async fn missing_profile() -> axum::response::Response {
+        let body = json!({ "error": { "code": "not_found", "message": "profile not found" } });
+        (AxumStatusCode::NOT_FOUND, axum::Json(body)).into_response()
+    }
+
+    let app = Router::new()
+        .route("/tool/{service}/run", post(run_tool))
+        .route("/profiles/{user_id}", get(missing_profile));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("listener has an address");
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{addr}/")
+}
+
+#[test]
+fn decodes_challenges_and_settlements() {
+    let body = PaymentRequired {
+        service: "design".to_string(),
+        amount_cents: PRICE_CENTS,
+        accepted_header: PAYMENT_SIGNATURE_HEADER.to_string(),
+        payment_required: encode(&json!([requirement()])),
+        message: "missing PAYMENT-SIGNATURE header".to_string(),
+        next_step: "retry".to_string(),
+    };
+    let challenge = PaymentChallenge::try_from(body).expect("challenge should decode");
+    assert_eq!(challenge.accepts, vec![requirement()]);
+    assert_eq!(challenge.amount_cents, PRICE_CENTS);
+    assert_eq!(
+        requirement_amount_cents(&challenge.accepts[0]).expect("amount is numeric"),
+        PRICE_CENTS
+    );
+    let fractional = X402PaymentRequirement {
+        max_amount_required: "50001".to_string(),
+        ..requirement()
+    };
+    assert_eq!(
+        requirement_amount_cents(&fractional).expect("amount is numeric"),
+        PRICE_CENTS + 1
+    );
+
+    let settlement = decode_payment_response(&encode(&json!({
+        "success": true,
+        "transaction": "0xabc",
+        "payer": "0x1",
+    })))
+    .expect("settlement should decode");
+    assert_eq!(settlement.transaction.as_deref(), Some("0xabc"));
+
+    assert!(matches!(
+        decode_payment_requirements("not base64!"),
+        Err(ClientError::InvalidPaymentHeader(_))
+    ));
+}
+
+#[tokio::test]
+async fn pays_challenges_within_spend_limits() {
+    let base_url = mock_server().await;
+    let request = ServiceRunRequest {
+        user_id: Uuid::nil(),
+        input: "logo".to_string(),
+    };
+
+    let unpaid = Client::new(&base_url).run_tool("design", &request).await;
+    let Err(ClientError::PaymentRequired(challenge)) = unpaid else {
+        panic!("expected the challenge without a signer, got {unpaid:?}");
+    };
+    assert_eq!(challenge.accepts[0].pay_to, requirement().pay_to);
+
+    let limits = SpendLimits {
+        max_per_call_cents: Some(PRICE_CENTS),
+        max_total_cents: Some(PRICE_CENTS + 2),
+    };
+    let client =
+        Client::new(&base_url).with_signer(StaticSigner(GOOD_SIGNATURE.to_string()), limits);
+    let paid = client
+        .run_tool("design", &request)
+        .await
+        .expect("signed retry should succeed");
+    assert_eq!(paid.body.output, "done");
+    assert_eq!(paid.paid_cents, PRICE_CENTS);
+    assert_eq!(
+        paid.settlement
+            .and_then(|settlement| settlement.transaction),
+        Some("0xabc".to_string())
+    );
+    assert_eq!(client.spent_cents(), PRICE_CENTS);
+
+    let over_budget = client.clone().run_tool("design", &request).await;
+    assert!(matches!(
+        over_budget,
+        Err(ClientError::SpendLimitExceeded {
+            limit: SpendLimitKind::Total,
+            ..
+        })
+    ));
+    assert_eq!(client.spent_cents(), PRICE_CENTS);
+
+    let per_call = Client::new(&base_url).with_signer(
+        StaticSigner(GOOD_SIGNATURE.to_string()),
+        SpendLimits {
+            max_per_call_cents: Some(PRICE_CENTS - 1),
+            max_total_cents: None,
+        },
+    );
+    assert!(matches!(
+        per_call.run_tool("design", &request).await,
+        Err(ClientError::SpendLimitExceeded {
+            limit: SpendLimitKind::PerCall,
+            ..
+        })
+    ));
+
+    let rejected = Client::new(&base_url)
+        .with_signer(StaticSigner("forged".to_string()), SpendLimits::default());
+    let result = rejected.run_tool("design", &request).await;
+    let Err(ClientError::PaymentRejected(challenge)) = result else {
+        panic!("expected a rejected payment, got {result:?}");
+    };
+    assert_eq!(challenge.message, "payment rejected");
+    assert_eq!(rejected.spent_cents(), 0);
+
+    // The limit applies to what the signer authorizes, not the body's unsigned quote.
+    let underquoted = Client::new(&base_url).with_signer(
+        StaticSigner(GOOD_SIGNATURE.to_string()),
+        SpendLimits {
+            max_per_call_cents: Some(1),
+            max_total_cents: None,
+        },
+    );
+    assert!(matches!(
+        underquoted.run_tool("underquoted", &request).await,
+        Err(ClientError::InvalidPaymentHeader(_))
+    ));
+    assert_eq!(underquoted.spent_cents(), 0);
+}
+
+#[tokio::test]
+async fn error_envelopes_become_api_errors() {
+    let client = Client::new(mock_server().await);
+    match client.get_profile(Uuid::nil()).await {
+        Err(ClientError::Api { status, error, .. }) => {
+            assert_eq!(status, 404);
+            assert_eq!(error.code, "not_found");
+        }
+        other => panic!("expected an API error, got {other:?}"),
+    }
+}
@@ -0,0 +1,1161 @@
+//! Request and response bodies, mirroring the server's JSON. Optional request fields are
+//! left out of the body when unset so the server's defaults apply.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageResponse {
+    pub message: String,
+}
+
+/// The `error` object of every non-402 error response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiErrorBody {
+    pub code: String,
+    pub message: String,
+    #[serde(default)]
+    pub details: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorResponse {
+    pub error: ApiErrorBody,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserProfile {
+    pub id: Uuid,
+    pub email: String,
+    pub region: String,
+    pub roles: Vec<String>,
+    pub tools_used: Vec<String>,
+    pub attributes: HashMap<String, String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateUserRequest {
+    pub email: String,
+    pub region: String,
+    pub roles: Vec<String>,
+    pub tools_used: Vec<String>,
+    #[serde(default)]
+    pub attributes: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateUserProfileRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roles: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools_used: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserDataExport {
+    pub profile: UserProfile,
+    pub task_completions: Vec<TaskCompletion>,
+    pub payments: Vec<PaymentRecord>,
+    pub sponsored_api_calls: Vec<SponsoredApiCall>,
+    pub exported_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Campaign {
+    pub id: Uuid,
+    pub name: String,
+    pub sponsor: String,
+    pub target_roles: Vec<String>,
+    pub target_tools: Vec<String>,
+    pub required_task: String,
+    pub subsidy_per_call_cents: u64,
+    pub budget_total_cents: u64,
+    pub budget_remaining_cents: u64,
+    #[serde(default)]
+    pub query_urls: Vec<String>,
+    pub task_verification: TaskVerification,
+    pub active: bool,
+    #[serde(default)]
+    pub budget_pool_id: Option<Uuid>,
+    #[serde(default)]
+    pub pool_limit_cents: Option<u64>,
+    #[serde(default)]
+    pub pool_spent_cents: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// How a campaign's required task is proven before it unlocks sponsored usage.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TaskVerification {
+    SponsorCallback,
+    OauthRedirect {
+        authorize_url: String,
+    },
+    FormSubmission {
+        #[serde(default)]
+        required_fields: Vec<String>,
+    },
+    OnchainAction {
+        rpc_url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        contract_address: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskCompletionStatus {
+    Pending,
+    Verified,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCampaignRequest {
+    pub name: String,
+    pub sponsor: String,
+    #[serde(default)]
+    pub target_roles: Vec<String>,
+    #[serde(default)]
+    pub target_tools: Vec<String>,
+    pub required_task: String,
+    pub subsidy_per_call_cents: u64,
+    /// Must be 0 when the campaign draws on a budget pool.
+    #[serde(default)]
+    pub budget_cents: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget_pool_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_limit_cents: Option<u64>,
+    #[serde(default)]
+    pub query_urls: Vec<String>,
+    pub task_verification: TaskVerification,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCampaignResponse {
+    pub campaign: Campaign,
+    pub campaign_url: String,
+    pub dashboard_url: String,
+    /// Shared secret for signing task callbacks; only returned at creation time.
+    pub task_secret: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CampaignDiscoveryItem {
+    pub campaign_id: Uuid,
+    pub name: String,
+    pub sponsor: String,
+    pub active: bool,
+    pub query_urls: Vec<String>,
+    /// Deprecated: the same listing as `resources_url`. Use `service_run_urls`.
+    pub service_run_url: String,
+    #[serde(default)]
+    pub service_run_urls: Vec<String>,
+    pub service_schema: Value,
+    pub sponsored_api_discovery_url: String,
+    pub resources_url: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceKind {
+    Tool,
+    Proxy,
+    SponsoredApi,
+}
+
+/// One paid resource, shaped like an x402 Bazaar discovery item.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryResource {
+    pub resource: String,
+    #[serde(rename = "type")]
+    pub resource_type: String,
+    pub x402_version: u8,
+    pub accepts: Vec<X402PaymentRequirement>,
+    pub last_updated: DateTime<Utc>,
+    pub metadata: DiscoveryMetadata,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveryMetadata {
+    pub kind: ResourceKind,
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    pub method: String,
+    pub price_cents: u64,
+    #[serde(default)]
+    pub sponsored_api_id: Option<Uuid>,
+    pub sponsorship: Sponsorship,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sponsorship {
+    pub available: bool,
+    pub sponsors: Vec<String>,
+    pub conditional: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiscoveryQuery {
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ResourceKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryResourcePage {
+    pub x402_version: u8,
+    pub items: Vec<DiscoveryResource>,
+    pub pagination: DiscoveryPagination,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveryPagination {
+    pub limit: usize,
+    pub offset: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct X402Manifest {
+    pub version: u8,
+    pub resources: Vec<String>,
+    pub instructions: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskCompletion {
+    pub id: Uuid,
+    pub campaign_id: Uuid,
+    pub user_id: Uuid,
+    pub task_name: String,
+    pub details: Option<String>,
+    pub status: TaskCompletionStatus,
+    pub evidence: Option<Value>,
+    pub rejection_reason: Option<String>,
+    pub verified_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskCompletionRequest {
+    pub campaign_id: Uuid,
+    pub user_id: Uuid,
+    pub task_name: String,
+    pub details: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub evidence: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskCompletionResponse {
+    #[serde(flatten)]
+    pub completion: TaskCompletion,
+    #[serde(default)]
+    pub redirect_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskCallbackRequest {
+    pub status: TaskCompletionStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceRunRequest {
+    pub user_id: Uuid,
+    pub input: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceRunResponse {
+    pub service: String,
+    pub output: String,
+    pub payment_mode: String,
+    pub sponsored_by: Option<String>,
+    pub tx_hash: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceBatchRunRequest {
+    pub user_id: Uuid,
+    pub inputs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServiceBatchRunResponse {
+    pub service: String,
+    pub outputs: Vec<String>,
+    pub payment_mode: String,
+    pub sponsored_by: Option<String>,
+    pub tx_hash: Option<String>,
+    pub amount_cents: u64,
+}
+
+/// Body of a 402 response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentRequired {
+    pub service: String,
+    pub amount_cents: u64,
+    pub accepted_header: String,
+    /// Base64-encoded x402 requirements, the same value as the `PAYMENT-REQUIRED` header.
+    pub payment_required: String,
+    pub message: String,
+    pub next_step: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct X402PaymentRequirement {
+    pub scheme: String,
+    pub network: String,
+    /// In the asset's base units.
+    pub max_amount_required: String,
+    pub resource: String,
+    pub description: String,
+    pub mime_type: String,
+    pub pay_to: String,
+    pub max_timeout_seconds: u64,
+    pub asset: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub extra: HashMap<String, Value>,
+}
+
+/// Settlement reported in the `PAYMENT-RESPONSE` header.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct X402SettleResponse {
+    pub success: bool,
+    #[serde(default)]
+    pub transaction: Option<String>,
+    #[serde(default)]
+    pub payer: Option<String>,
+    #[serde(default)]
+    pub error_reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentSource {
+    User,
+    Sponsor,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PaymentStatus {
+    Settled,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentRecord {
+    pub tx_hash: String,
+    pub campaign_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub service: String,
+    pub amount_cents: u64,
+    pub payer: String,
+    pub source: PaymentSource,
+    pub status: PaymentStatus,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct X402ScanSettlementRequest {
+    pub tx_hash: String,
+    pub service: String,
+    pub amount_cents: u64,
+    pub payer: String,
+    pub source: PaymentSource,
+    pub status: PaymentStatus,
+    pub campaign_id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatorEvent {
+    pub id: Uuid,
+    pub skill_name: String,
+    pub platform: String,
+    pub event_type: String,
+    pub duration_ms: Option<u64>,
+    pub success: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatorMetricEventRequest {
+    pub skill_name: String,
+    pub platform: String,
+    pub event_type: String,
+    pub duration_ms: Option<u64>,
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatorMetricSummary {
+    pub total_events: usize,
+    pub success_events: usize,
+    pub success_rate: f64,
+    pub per_skill: Vec<SkillMetrics>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkillMetrics {
+    pub skill_name: String,
+    pub total_events: usize,
+    pub success_events: usize,
+    pub avg_duration_ms: Option<f64>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsorDashboard {
+    pub campaign: Campaign,
+    pub tasks_completed: usize,
+    pub sponsored_calls: usize,
+    pub spend_cents: u64,
+    pub remaining_budget_cents: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DashboardBucket {
+    Hour,
+    #[default]
+    Day,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SponsorTimeseriesQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub bucket: DashboardBucket,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsorTimeseriesPoint {
+    pub bucket_start: DateTime<Utc>,
+    pub spend_cents: u64,
+    pub sponsored_calls: u64,
+    pub unique_users: u64,
+    pub task_completions: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsorBreakdownRow {
+    pub key: String,
+    pub spend_cents: u64,
+    pub sponsored_calls: u64,
+    pub unique_users: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsorTimeseries {
+    pub campaign_id: Uuid,
+    pub bucket: DashboardBucket,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub series: Vec<SponsorTimeseriesPoint>,
+    pub by_service: Vec<SponsorBreakdownRow>,
+    pub by_region: Vec<SponsorBreakdownRow>,
+    pub spend_cents: u64,
+    pub task_completed_users: u64,
+    pub converted_users: u64,
+    pub conversion_rate: f64,
+    pub cost_per_acquired_user_cents: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum UpstreamBodyEncoding {
+    #[default]
+    Json,
+    Form,
+    Raw,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ResponseCachePolicy {
+    pub ttl_secs: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_entry_bytes: Option<u64>,
+    #[serde(default)]
+    pub hit_price_cents: u64,
+}
+
+/// Paths are JSON Pointers (RFC 6901).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TransformPolicy {
+    #[serde(default)]
+    pub request: RequestTransform,
+    #[serde(default)]
+    pub response: ResponseTransform,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RequestTransform {
+    #[serde(default)]
+    pub rename: Vec<PointerMapping>,
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+    #[serde(default)]
+    pub set: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PointerMapping {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct ResponseTransform {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct EligibilityPolicy {
+    #[serde(default)]
+    pub require_profile: bool,
+    #[serde(default)]
+    pub target_roles: Vec<String>,
+    #[serde(default)]
+    pub target_tools: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub required_campaign_id: Option<Uuid>,
+    /// Profile ids. Not access control: callers are not authenticated.
+    #[serde(default)]
+    pub allowed_callers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HealthCheckPolicy {
+    pub path: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_status: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    Unknown,
+    Healthy,
+    Degraded,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealthProbeResult {
+    pub ok: bool,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+    pub checked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpstreamHealth {
+    pub status: HealthState,
+    pub circuit: CircuitState,
+    #[serde(default)]
+    pub circuit_open_until: Option<DateTime<Utc>>,
+    pub recent_calls: u64,
+    pub recent_failures: u64,
+    #[serde(default)]
+    pub last_probe: Option<HealthProbeResult>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    #[default]
+    Auto,
+    Identity,
+    Ip,
+    Caller,
+    UserId,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RateLimitPolicy {
+    pub capacity: u32,
+    pub refill_per_sec: f64,
+    #[serde(default)]
+    pub key: RateLimitKey,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsoredApi {
+    pub id: Uuid,
+    pub name: String,
+    pub sponsor: String,
+    pub description: Option<String>,
+    pub upstream_url: String,
+    pub upstream_method: String,
+    /// Public headers only; secret header values are never returned.
+    #[serde(default)]
+    pub upstream_headers: HashMap<String, String>,
+    #[serde(default)]
+    pub secret_header_names: Vec<String>,
+    pub price_cents: u64,
+    pub budget_total_cents: u64,
+    pub budget_remaining_cents: u64,
+    pub active: bool,
+    #[serde(default)]
+    pub paused: bool,
+    pub service_key: String,
+    #[serde(default)]
+    pub forward_headers: Vec<String>,
+    #[serde(default)]
+    pub body_encoding: UpstreamBodyEncoding,
+    #[serde(default)]
+    pub cache: Option<ResponseCachePolicy>,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitPolicy>,
+    #[serde(default)]
+    pub health_check: Option<HealthCheckPolicy>,
+    #[serde(default)]
+    pub budget_pool_id: Option<Uuid>,
+    #[serde(default)]
+    pub pool_limit_cents: Option<u64>,
+    #[serde(default)]
+    pub pool_spent_cents: u64,
+    #[serde(default)]
+    pub input_schema: Option<Value>,
+    #[serde(default)]
+    pub output_schema: Option<Value>,
+    #[serde(default)]
+    pub validate_output: bool,
+    #[serde(default)]
+    pub transform: Option<TransformPolicy>,
+    #[serde(default)]
+    pub eligibility: Option<EligibilityPolicy>,
+    #[serde(default)]
+    pub health: Option<UpstreamHealth>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateSponsoredApiRequest {
+    pub name: String,
+    pub sponsor: String,
+    pub description: Option<String>,
+    pub upstream_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_method: Option<String>,
+    #[serde(default)]
+    pub upstream_headers: HashMap<String, String>,
+    /// Header names that may be shown publicly; every other header is stored encrypted.
+    #[serde(default)]
+    pub public_headers: Vec<String>,
+    #[serde(default)]
+    pub forward_headers: Vec<String>,
+    #[serde(default)]
+    pub body_encoding: UpstreamBodyEncoding,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<ResponseCachePolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<RateLimitPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<HealthCheckPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_schema: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    #[serde(default)]
+    pub validate_output: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eligibility: Option<EligibilityPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_cents: Option<u64>,
+    pub budget_cents: u64,
+}
+
+/// Fields left as `None` are unchanged. For the nested options, `Some(None)` sends an
+/// explicit `null`, which clears the setting.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UpdateSponsoredApiRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_method: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forward_headers: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_encoding: Option<UpstreamBodyEncoding>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache: Option<Option<ResponseCachePolicy>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<Option<RateLimitPolicy>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub health_check: Option<Option<HealthCheckPolicy>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget_pool_id: Option<Option<Uuid>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_limit_cents: Option<Option<u64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_schema: Option<Option<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Option<Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validate_output: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<Option<TransformPolicy>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub eligibility: Option<Option<EligibilityPolicy>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_cents: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RotateSponsoredApiHeadersRequest {
+    pub upstream_headers: HashMap<String, String>,
+    #[serde(default)]
+    pub public_headers: Vec<String>,
+    /// Keep headers that are not mentioned instead of replacing the whole set.
+    #[serde(default)]
+    pub merge: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TopUpRequest {
+    pub amount_cents: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsoredApiAuditEntry {
+    pub id: Uuid,
+    pub sponsored_api_id: Uuid,
+    pub action: String,
+    pub changes: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Raw streaming (`stream: true` on the server) is not typed; use
+/// [`Client::proxy_sponsored_api`](crate::Client::proxy_sponsored_api) for streamed bodies.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SponsoredApiRunRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caller: Option<String>,
+    #[serde(default)]
+    pub input: Value,
+    #[serde(default)]
+    pub mode: RunMode,
+    /// Async mode only: notified with the finished job.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub callback_url: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RunMode {
+    #[default]
+    Sync,
+    /// The server answers `202` with a job to poll.
+    Async,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsoredApiRunResponse {
+    pub api_id: Uuid,
+    pub payment_mode: String,
+    pub sponsored_by: Option<String>,
+    pub tx_hash: Option<String>,
+    pub upstream_status: u16,
+    pub upstream_body: String,
+    #[serde(default)]
+    pub cache: Option<CacheStatus>,
+    #[serde(default)]
+    pub truncated: bool,
+    #[serde(default)]
+    pub output_violations: Vec<SchemaViolation>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SponsoredApiBatchRunRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caller: Option<String>,
+    pub inputs: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsoredApiBatchItem {
+    pub index: usize,
+    pub status: BatchItemStatus,
+    pub amount_cents: u64,
+    #[serde(default)]
+    pub refunded: bool,
+    #[serde(default)]
+    pub upstream_status: Option<u16>,
+    #[serde(default)]
+    pub upstream_body: Option<String>,
+    #[serde(default)]
+    pub truncated: bool,
+    #[serde(default)]
+    pub output_violations: Vec<SchemaViolation>,
+    #[serde(default)]
+    pub error: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsoredApiBatchRunResponse {
+    pub api_id: Uuid,
+    pub payment_mode: String,
+    pub sponsored_by: Option<String>,
+    pub tx_hash: Option<String>,
+    pub amount_cents: u64,
+    pub refunded_cents: u64,
+    pub results: Vec<SponsoredApiBatchItem>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum JobBudgetState {
+    Reserved,
+    Committed,
+    Released,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsoredApiJob {
+    pub id: Uuid,
+    pub sponsored_api_id: Uuid,
+    pub status: JobStatus,
+    pub payment_mode: String,
+    pub amount_cents: u64,
+    pub sponsored_by: Option<String>,
+    pub tx_hash: Option<String>,
+    pub budget: Option<JobBudgetState>,
+    pub callback_url: Option<String>,
+    pub callback_status: Option<String>,
+    pub upstream_status: Option<u16>,
+    pub upstream_body: Option<String>,
+    #[serde(default)]
+    pub truncated: bool,
+    pub error: Option<Value>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsoredApiJobAccepted {
+    #[serde(flatten)]
+    pub job: SponsoredApiJob,
+    /// Signs callback deliveries like sponsor webhooks. Only shown here.
+    #[serde(default)]
+    pub callback_secret: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SchemaViolation {
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransformPreviewRequest {
+    #[serde(default)]
+    pub input: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_body: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transform: Option<TransformPolicy>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformPreviewResponse {
+    pub input_violations: Vec<SchemaViolation>,
+    pub upstream_method: String,
+    pub upstream_url: String,
+    pub upstream_input: Value,
+    #[serde(default)]
+    pub response: Option<TransformedResponsePreview>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransformedResponsePreview {
+    pub body: String,
+    pub truncated: bool,
+    pub output_violations: Vec<SchemaViolation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsoredApiCall {
+    pub id: Uuid,
+    pub sponsored_api_id: Uuid,
+    pub payment_mode: String,
+    pub amount_cents: u64,
+    pub tx_hash: Option<String>,
+    pub caller: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub upstream_status: Option<u16>,
+    pub response_bytes: Option<u64>,
+    pub outcome: Option<String>,
+    pub duration_ms: Option<u64>,
+    pub error_class: Option<CallErrorClass>,
+    pub finalized_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CallErrorClass {
+    UpstreamClientError,
+    UpstreamServerError,
+    Timeout,
+    Connection,
+    ResponseTooLarge,
+    ClientAborted,
+    OutputContractViolation,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CallLogExportFormat {
+    Csv,
+    Ndjson,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SponsoredApiCallsQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payment_mode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u32>,
+    /// `next_cursor` from the previous page.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsoredApiCallPage {
+    pub calls: Vec<SponsoredApiCall>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetPool {
+    pub id: Uuid,
+    pub sponsor: String,
+    pub name: String,
+    pub budget_total_cents: u64,
+    pub budget_remaining_cents: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateBudgetPoolRequest {
+    pub sponsor: String,
+    pub name: String,
+    pub budget_cents: u64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPoolMemberKind {
+    SponsoredApi,
+    Campaign,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetPoolMember {
+    pub kind: BudgetPoolMemberKind,
+    pub id: Uuid,
+    pub name: String,
+    pub pool_limit_cents: Option<u64>,
+    pub pool_spent_cents: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetPoolDetail {
+    #[serde(flatten)]
+    pub pool: BudgetPool,
+    pub members: Vec<BudgetPoolMember>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportSponsoredApisRequest {
+    pub sponsor: String,
+    /// An OpenAPI 3 document, either as JSON or as a JSON or YAML string.
+    pub document: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+    /// Operation ids or `METHOD /path` keys; empty imports every operation.
+    #[serde(default)]
+    pub operations: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name_prefix: Option<String>,
+    #[serde(default)]
+    pub upstream_headers: HashMap<String, String>,
+    #[serde(default)]
+    pub public_headers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_cents: Option<u64>,
+    pub budget_cents: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pool_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedOperation {
+    pub operation: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportSponsoredApisResponse {
+    pub budget_pool: BudgetPool,
+    pub apis: Vec<SponsoredApi>,
+    pub skipped: Vec<SkippedOperation>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum WebhookEventType {
+    #[serde(rename = "task.completed")]
+    TaskCompleted,
+    #[serde(rename = "sponsored_call.charged")]
+    SponsoredCallCharged,
+    #[serde(rename = "budget.low")]
+    BudgetLow,
+    #[serde(rename = "budget.exhausted")]
+    BudgetExhausted,
+    #[serde(rename = "campaign.ended")]
+    CampaignEnded,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SponsorWebhook {
+    pub id: Uuid,
+    pub sponsor: String,
+    pub campaign_id: Option<Uuid>,
+    pub url: String,
+    pub events: Vec<WebhookEventType>,
+    pub budget_low_threshold_percent: u8,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreateSponsorWebhookRequest {
+    pub sponsor: String,
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub campaign_id: Option<Uuid>,
+    /// Empty subscribes to every event.
+    #[serde(default)]
+    pub events: Vec<WebhookEventType>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub budget_low_threshold_percent: Option<u8>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateSponsorWebhookResponse {
+    pub webhook: SponsorWebhook,
+    /// HMAC key for verifying `x-webhook-signature`; only returned at creation time.
+    pub secret: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event_type: String,
+    pub payload: Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,41 @@
+update users set email = lower(trim(email));
+
+-- Collapse duplicate profiles onto the oldest row per email before adding the constraint.
+with ranked as (
+  select id,
+    first_value(id) over (partition by email order by created_at asc, id asc) as keep_id
+  from users
+)
+update task_completions tc
+set user_id = ranked.keep_id
+from ranked
+where tc.user_id = ranked.id
+  and ranked.id <> ranked.keep_id;
+
+delete from users u
+using users keep
+where u.email = keep.email
+  and (keep.created_at, keep.id) < (u.created_at, u.id);
+
+alter table users
+  add constraint users_email_key unique (email);
+
+-- Deleting a profile anonymizes ledger rows instead of cascading them away.
+alter table task_completions
+  alter column user_id drop not null;
+
+alter table task_completions
+  drop constraint if exists task_completions_user_id_fkey;
+
+alter table task_completions
+  add constraint task_completions_user_id_fkey
+  foreign key (user_id) references users(id) on delete set null;
+
+alter table payments
+  add column if not exists user_id uuid references users(id) on delete set null;
+
+create index if not exists payments_user_id_idx
+  on payments(user_id);
+
+create index if not exists sponsored_api_calls_caller_idx
+  on sponsored_api_calls(caller);
@@ -0,0 +1,37 @@
+alter table campaigns
+  add column if not exists task_verification jsonb not null
+    default '{"type":"form_submission","required_fields":[]}'::jsonb;
+
+alter table campaigns
+  add column if not exists task_secret text;
+
+-- random() is not a CSPRNG; these secrets sign callbacks.
+create extension if not exists pgcrypto;
+
+update campaigns
+set task_secret = encode(gen_random_bytes(32), 'hex')
+where task_secret is null;
+
+alter table campaigns
+  alter column task_secret set not null;
+
+-- Completions recorded before verification existed keep counting as verified.
+alter table task_completions
+  add column if not exists status text not null default 'verified'
+    check (status in ('pending', 'verified', 'rejected'));
+
+alter table task_completions
+  alter column status set default 'pending';
+
+alter table task_completions
+  add column if not exists evidence jsonb;
+
+alter table task_completions
+  add column if not exists rejection_reason text;
+
+alter table task_completions
+  add column if not exists verified_at timestamptz;
+
+create index if not exists task_completions_verified_idx
+  on task_completions(campaign_id, user_id, task_name)
+  where status = 'verified';
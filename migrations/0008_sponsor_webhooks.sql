@@ -0,0 +1,38 @@
+create table if not exists sponsor_webhooks (
+  id uuid primary key,
+  sponsor text not null,
+  campaign_id uuid references campaigns(id) on delete cascade,
+  url text not null,
+  secret text not null,
+  events text[] not null,
+  budget_low_threshold_percent integer not null default 20
+    check (budget_low_threshold_percent between 1 and 99),
+  active boolean not null default true,
+  created_at timestamptz not null default now()
+);
+
+create index if not exists sponsor_webhooks_sponsor_idx
+  on sponsor_webhooks(sponsor)
+  where active;
+
+create table if not exists webhook_deliveries (
+  id uuid primary key,
+  webhook_id uuid not null references sponsor_webhooks(id) on delete cascade,
+  event_type text not null,
+  payload jsonb not null,
+  status text not null default 'pending'
+    check (status in ('pending', 'succeeded', 'failed')),
+  attempts integer not null default 0,
+  next_attempt_at timestamptz not null default now(),
+  last_status_code integer,
+  last_error text,
+  delivered_at timestamptz,
+  created_at timestamptz not null default now()
+);
+
+create index if not exists webhook_deliveries_due_idx
+  on webhook_deliveries(next_attempt_at)
+  where status = 'pending';
+
+create index if not exists webhook_deliveries_webhook_id_idx
+  on webhook_deliveries(webhook_id, created_at desc);
@@ -0,0 +1,24 @@
+alter table sponsored_apis
+  add column if not exists paused boolean not null default false;
+
+alter table sponsored_apis
+  add column if not exists deleted_at timestamptz;
+
+-- Soft deletion keeps call history; never cascade it away.
+alter table sponsored_api_calls
+  drop constraint if exists sponsored_api_calls_sponsored_api_id_fkey;
+
+alter table sponsored_api_calls
+  add constraint sponsored_api_calls_sponsored_api_id_fkey
+  foreign key (sponsored_api_id) references sponsored_apis(id) on delete restrict;
+
+create table if not exists sponsored_api_audit_log (
+  id uuid primary key,
+  sponsored_api_id uuid not null references sponsored_apis(id) on delete restrict,
+  action text not null,
+  changes jsonb not null default '{}'::jsonb,
+  created_at timestamptz not null default now()
+);
+
+create index if not exists sponsored_api_audit_log_api_id_idx
+  on sponsored_api_audit_log(sponsored_api_id, created_at desc);
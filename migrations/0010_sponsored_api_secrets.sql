@@ -0,0 +1,13 @@
+alter table sponsored_apis
+  add column if not exists upstream_secret_headers jsonb not null default '{}'::jsonb;
+
+-- Existing headers were never classified, so treat them all as secrets. They are tagged as
+-- legacy plaintext, which no sealed value can look like, and stay readable that way until the
+-- server re-seals them with UPSTREAM_SECRET_KEYS at startup.
+update sponsored_apis
+set upstream_secret_headers = coalesce(
+      (select jsonb_object_agg(key, 'plain:' || value) from jsonb_each_text(upstream_headers)),
+      '{}'::jsonb
+    ),
+    upstream_headers = '{}'::jsonb
+where upstream_secret_headers = '{}'::jsonb;
@@ -0,0 +1,10 @@
+alter table sponsored_apis
+  add column if not exists forward_headers text[] not null default '{}',
+  add column if not exists body_encoding text not null default 'json';
+
+alter table sponsored_apis
+  drop constraint if exists sponsored_apis_body_encoding_check;
+
+alter table sponsored_apis
+  add constraint sponsored_apis_body_encoding_check
+  check (body_encoding in ('json', 'form', 'raw'));
@@ -0,0 +1,11 @@
+-- Calls are recorded before the upstream responds and finalized once the body has been
+-- delivered, failed or abandoned; finalized_at stays null while a stream is in flight.
+alter table sponsored_api_calls
+  add column if not exists upstream_status integer,
+  add column if not exists response_bytes bigint,
+  add column if not exists outcome text,
+  add column if not exists finalized_at timestamptz;
+
+update sponsored_api_calls
+set outcome = 'completed', finalized_at = created_at
+where finalized_at is null;
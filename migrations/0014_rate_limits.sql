@@ -0,0 +1,14 @@
+alter table sponsored_apis
+  add column if not exists rate_limit_policy jsonb;
+
+-- Token buckets shared between instances when RATE_LIMIT_BACKEND=postgres. Idle buckets
+-- are full again by definition, so rows untouched for an hour are pruned.
+create table if not exists rate_limit_buckets (
+  key text primary key,
+  tokens double precision not null,
+  allowed boolean not null,
+  updated_at timestamptz not null default now()
+);
+
+create index if not exists rate_limit_buckets_updated_at_idx
+  on rate_limit_buckets (updated_at);
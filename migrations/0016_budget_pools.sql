@@ -0,0 +1,16 @@
+create table if not exists budget_pools (
+  id uuid primary key,
+  sponsor text not null,
+  name text not null,
+  budget_total_cents bigint not null check (budget_total_cents >= 0),
+  budget_remaining_cents bigint not null check (budget_remaining_cents >= 0),
+  created_at timestamptz not null default now()
+);
+
+create index if not exists budget_pools_sponsor_idx on budget_pools (sponsor);
+
+alter table sponsored_apis
+  add column if not exists budget_pool_id uuid references budget_pools (id),
+  add column if not exists input_schema jsonb;
+
+create index if not exists sponsored_apis_budget_pool_idx on sponsored_apis (budget_pool_id);
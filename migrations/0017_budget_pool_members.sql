@@ -0,0 +1,10 @@
+alter table sponsored_apis
+  add column if not exists pool_limit_cents bigint check (pool_limit_cents >= 0),
+  add column if not exists pool_spent_cents bigint not null default 0 check (pool_spent_cents >= 0);
+
+alter table campaigns
+  add column if not exists budget_pool_id uuid references budget_pools (id),
+  add column if not exists pool_limit_cents bigint check (pool_limit_cents >= 0),
+  add column if not exists pool_spent_cents bigint not null default 0 check (pool_spent_cents >= 0);
+
+create index if not exists campaigns_budget_pool_idx on campaigns (budget_pool_id);
@@ -0,0 +1,3 @@
+alter table sponsored_apis
+  add column if not exists output_schema jsonb,
+  add column if not exists validate_output boolean not null default false;
@@ -0,0 +1,8 @@
+-- Latency and a coarse failure class for each call, so the call log can be filtered and
+-- exported without re-deriving them from outcome and upstream_status.
+alter table sponsored_api_calls
+  add column if not exists duration_ms bigint,
+  add column if not exists error_class text;
+
+create index if not exists sponsored_api_calls_api_created_idx
+  on sponsored_api_calls(sponsored_api_id, created_at desc, id desc);
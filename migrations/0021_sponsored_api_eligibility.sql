@@ -0,0 +1,2 @@
+alter table sponsored_apis
+  add column if not exists eligibility_policy jsonb;
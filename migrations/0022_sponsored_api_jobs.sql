@@ -0,0 +1,36 @@
+-- Sponsored API calls run in the background. The upstream request is stored as prepared
+-- at submission; forwarded caller headers are cleared once the job finishes.
+create table if not exists sponsored_api_jobs (
+  id uuid primary key,
+  sponsored_api_id uuid not null references sponsored_apis(id),
+  call_id uuid not null,
+  budget_pool_id uuid,
+  status text not null default 'queued',
+  caller text,
+  payment_mode text not null,
+  amount_cents bigint not null,
+  sponsored_by text,
+  tx_hash text,
+  upstream_url text not null,
+  upstream_input jsonb not null,
+  forwarded_headers jsonb not null default '{}'::jsonb,
+  callback_url text,
+  callback_secret text,
+  callback_status text,
+  attempts integer not null default 0,
+  lease_until timestamptz,
+  upstream_status integer,
+  upstream_body text,
+  truncated boolean not null default false,
+  error jsonb,
+  created_at timestamptz not null default now(),
+  started_at timestamptz,
+  completed_at timestamptz,
+  expires_at timestamptz
+);
+
+create index if not exists sponsored_api_jobs_due_idx
+  on sponsored_api_jobs(status, lease_until);
+
+create index if not exists sponsored_api_jobs_expires_idx
+  on sponsored_api_jobs(expires_at);
@@ -0,0 +1,9 @@
+-- Campaigns no longer default to an empty form, which verified any completion without evidence.
+alter table campaigns
+  alter column task_verification drop default;
+
+-- Campaigns that relied on that default now wait for the sponsor to confirm each completion.
+update campaigns
+set task_verification = '{"type":"sponsor_callback"}'::jsonb
+where task_verification->>'type' = 'form_submission'
+  and coalesce(jsonb_array_length(task_verification->'required_fields'), 0) = 0;
@@ -0,0 +1,6 @@
+-- A transaction proves an on-chain task once per campaign.
+alter table task_completions
+  add column if not exists tx_hash text;
+
+create unique index if not exists task_completions_campaign_tx_hash_key
+  on task_completions(campaign_id, tx_hash);
@@ -0,0 +1,725 @@
+use schemars::{JsonSchema, Schema, SchemaGenerator, generate::SchemaSettings};
+use serde_json::{Map, Value, json};
+
+use crate::error::ErrorResponse;
+use crate::types::*;
+
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+fn schema<T: JsonSchema>(generator: &mut SchemaGenerator) -> Schema {
+    generator.subschema_for::<T>()
+}
+
+enum Content {
+    Json(SchemaFn),
+    /// Bodies the server does not parse or produce itself, like proxied upstream payloads.
+    Raw(&'static str),
+}
+
+struct Operation {
+    method: &'static str,
+    path: &'static str,
+    tag: &'static str,
+    summary: &'static str,
+    query: Option<SchemaFn>,
+    body: Option<Content>,
+    status: u16,
+    response: Option<Content>,
+    alternate_media_types: &'static [&'static str],
+    /// 202 body when the request asks to run as a background job.
+    queued: Option<SchemaFn>,
+    paid: bool,
+}
+
+impl Operation {
+    fn new(
+        method: &'static str,
+        path: &'static str,
+        tag: &'static str,
+        summary: &'static str,
+    ) -> Self {
+        Self {
+            method,
+            path,
+            tag,
+            summary,
+            query: None,
+            body: None,
+            status: 200,
+            response: None,
+            alternate_media_types: &[],
+            queued: None,
+            paid: false,
+        }
+    }
+
+    fn query(mut self, query: SchemaFn) -> Self {
+        self.query = Some(query);
+        self
+    }
+
+    fn body(mut self, body: SchemaFn) -> Self {
+        self.body = Some(Content::Json(body));
+        self
+    }
+
+    fn raw_body(mut self, media_type: &'static str) -> Self {
+        self.body = Some(Content::Raw(media_type));
+        self
+    }
+
+    fn returns(mut self, status: u16, response: SchemaFn) -> Self {
+        self.status = status;
+        self.response = Some(Content::Json(response));
+        self
+    }
+
+    fn returns_raw(mut self, media_type: &'static str) -> Self {
+        self.response = Some(Content::Raw(media_type));
+        self
+    }
+
+    fn or_as(mut self, media_types: &'static [&'static str]) -> Self {
+        self.alternate_media_types = media_types;
+        self
+    }
+
+    fn or_queued(mut self, accepted: SchemaFn) -> Self {
+        self.queued = Some(accepted);
+        self
+    }
+
+    /// Answers 402 with an x402 challenge unless the call is sponsored or carries a payment.
+    fn paid(mut self) -> Self {
+        self.paid = true;
+        self
+    }
+}
+
+/// Every route served by `build_app`, except the docs themselves.
+fn operations() -> Vec<Operation> {
+    use Operation as Op;
+    vec![
+        Op::new("get", "/health", "system", "Liveness check")
+            .returns(200, schema::<MessageResponse>),
+        Op::new("get", "/metrics", "system", "Prometheus metrics").returns_raw("text/plain"),
+        Op::new(
+            "post",
+            "/profiles",
+            "profiles",
+            "Create or update a profile by email",
+        )
+        .body(schema::<CreateUserRequest>)
+        .returns(201, schema::<UserProfile>),
+        Op::new("get", "/profiles", "profiles", "List profiles")
+            .returns(200, schema::<Vec<UserProfile>>),
+        Op::new("get", "/profiles/{user_id}", "profiles", "Get a profile")
+            .returns(200, schema::<UserProfile>),
+        Op::new(
+            "patch",
+            "/profiles/{user_id}",
+            "profiles",
+            "Update a profile",
+        )
+        .body(schema::<UpdateUserProfileRequest>)
+        .returns(200, schema::<UserProfile>),
+        Op::new(
+            "delete",
+            "/profiles/{user_id}",
+            "profiles",
+            "Delete a profile and its data",
+        )
+        .returns(200, schema::<MessageResponse>),
+        Op::new(
+            "get",
+            "/profiles/{user_id}/export",
+            "profiles",
+            "Export a profile's data",
+        )
+        .returns(200, schema::<UserDataExport>),
+        Op::new("post", "/register", "profiles", "Register a user")
+            .body(schema::<CreateUserRequest>)
+            .returns(201, schema::<UserProfile>),
+        Op::new("post", "/campaigns", "campaigns", "Create a campaign")
+            .body(schema::<CreateCampaignRequest>)
+            .returns(201, schema::<CreateCampaignResponse>),
+        Op::new("get", "/campaigns", "campaigns", "List campaigns")
+            .returns(200, schema::<Vec<Campaign>>),
+        Op::new(
+            "get",
+            "/campaigns/discovery",
+            "campaigns",
+            "Campaigns agents can use",
+        )
+        .returns(200, schema::<Vec<CampaignDiscoveryItem>>),
+        Op::new(
+            "get",
+            "/campaigns/{campaign_id}",
+            "campaigns",
+            "Get a campaign",
+        )
+        .returns(200, schema::<Campaign>),
+        Op::new(
+            "get",
+            "/.well-known/x402",
+            "discovery",
+            "x402 resource manifest",
+        )
+        .returns(200, schema::<X402Manifest>),
+        Op::new(
+            "get",
+            "/discovery/resources",
+            "discovery",
+            "Paid resources with requirements",
+        )
+        .query(schema::<DiscoveryQuery>)
+        .returns(200, schema::<DiscoveryResourcePage>),
+        Op::new(
+            "post",
+            "/tasks/complete",
+            "tasks",
+            "Start or complete a campaign task",
+        )
+        .body(schema::<TaskCompletionRequest>)
+        .returns(201, schema::<TaskCompletionResponse>),
+        Op::new(
+            "get",
+            "/tasks/oauth/callback",
+            "tasks",
+            "OAuth redirect for task verification",
+        )
+        .query(schema::<TaskOauthCallbackQuery>)
+        .returns(200, schema::<TaskCompletion>),
+        Op::new(
+            "get",
+            "/tasks/{completion_id}",
+            "tasks",
+            "Get a task completion",
+        )
+        .returns(200, schema::<TaskCompletion>),
+        Op::new(
+            "post",
+            "/tasks/{completion_id}/callback",
+            "tasks",
+            "Signed task verification",
+        )
+        .body(schema::<TaskCallbackRequest>)
+        .returns(200, schema::<TaskCompletion>),
+        Op::new(
+            "post",
+            "/tool/{service}/run",
+            "tools",
+            "Run a built-in tool",
+        )
+        .body(schema::<ServiceRunRequest>)
+        .returns(200, schema::<ServiceRunResponse>)
+        .paid(),
+        Op::new(
+            "post",
+            "/tool/{service}/run-batch",
+            "tools",
+            "Run a built-in tool on many inputs",
+        )
+        .body(schema::<ServiceBatchRunRequest>)
+        .returns(200, schema::<ServiceBatchRunResponse>)
+        .paid(),
+        Op::new(
+            "post",
+            "/proxy/{service}/run",
+            "tools",
+            "Run a tool, sponsored when possible",
+        )
+        .body(schema::<ServiceRunRequest>)
+        .returns(200, schema::<ServiceRunResponse>)
+        .paid(),
+        Op::new("post", "/mcp", "tools", "MCP JSON-RPC endpoint")
+            .body(schema::<Value>)
+            .returns(200, schema::<Value>),
+        Op::new(
+            "post",
+            "/sponsored-apis",
+            "sponsored-apis",
+            "Create a sponsored API",
+        )
+        .body(schema::<CreateSponsoredApiRequest>)
+        .returns(201, schema::<SponsoredApi>)
+        .paid(),
+        Op::new(
+            "get",
+            "/sponsored-apis",
+            "sponsored-apis",
+            "List sponsored APIs",
+        )
+        .returns(200, schema::<Vec<SponsoredApi>>),
+        Op::new(
+            "post",
+            "/sponsored-apis/import",
+            "sponsored-apis",
+            "Import from an OpenAPI doc",
+        )
+        .body(schema::<ImportSponsoredApisRequest>)
+        .returns(201, schema::<ImportSponsoredApisResponse>)
+        .paid(),
+        Op::new(
+            "get",
+            "/sponsored-apis/{api_id}",
+            "sponsored-apis",
+            "Get a sponsored API",
+        )
+        .returns(200, schema::<SponsoredApi>),
+        Op::new(
+            "patch",
+            "/sponsored-apis/{api_id}",
+            "sponsored-apis",
+            "Update a sponsored API",
+        )
+        .body(schema::<UpdateSponsoredApiRequest>)
+        .returns(200, schema::<SponsoredApi>),
+        Op::new(
+            "delete",
+            "/sponsored-apis/{api_id}",
+            "sponsored-apis",
+            "Delete a sponsored API",
+        )
+        .returns(200, schema::<MessageResponse>),
+        Op::new(
+            "put",
+            "/sponsored-apis/{api_id}/headers",
+            "sponsored-apis",
+            "Rotate headers",
+        )
+        .body(schema::<RotateSponsoredApiHeadersRequest>)
+        .returns(200, schema::<SponsoredApi>),
+        Op::new(
+            "post",
+            "/sponsored-apis/{api_id}/pause",
+            "sponsored-apis",
+            "Pause sponsorship",
+        )
+        .returns(200, schema::<SponsoredApi>),
+        Op::new(
+            "post",
+            "/sponsored-apis/{api_id}/resume",
+            "sponsored-apis",
+            "Resume sponsorship",
+        )
+        .returns(200, schema::<SponsoredApi>),
+        Op::new(
+            "post",
+            "/sponsored-apis/{api_id}/top-up",
+            "sponsored-apis",
+            "Add budget",
+        )
+        .body(schema::<TopUpSponsoredApiRequest>)
+        .returns(200, schema::<SponsoredApi>),
+        Op::new(
+            "get",
+            "/sponsored-apis/{api_id}/audit",
+            "sponsored-apis",
+            "Configuration history",
+        )
+        .returns(200, schema::<Vec<SponsoredApiAuditEntry>>),
+        Op::new(
+            "get",
+            "/sponsored-apis/{api_id}/calls",
+            "sponsored-apis",
+            "Call log",
+        )
+        .query(schema::<SponsoredApiCallsQuery>)
+        .returns(200, schema::<SponsoredApiCallPage>)
+        .or_as(&["text/csv", "application/x-ndjson"]),
+        Op::new(
+            "post",
+            "/sponsored-apis/{api_id}/run",
+            "sponsored-apis",
+            "Call a sponsored API",
+        )
+        .body(schema::<SponsoredApiRunRequest>)
+        .returns(200, schema::<SponsoredApiRunResponse>)
+        .or_queued(schema::<SponsoredApiJobAccepted>)
+        .paid(),
+        Op::new(
+            "post",
+            "/sponsored-apis/{api_id}/run-batch",
+            "sponsored-apis",
+            "Batch call",
+        )
+        .body(schema::<SponsoredApiBatchRunRequest>)
+        .returns(200, schema::<SponsoredApiBatchRunResponse>)
+        .paid(),
+        Op::new(
+            "get",
+            "/jobs/{job_id}",
+            "sponsored-apis",
+            "Get a background job",
+        )
+        .returns(200, schema::<SponsoredApiJob>),
+        Op::new(
+            "post",
+            "/sponsored-apis/{api_id}/transform/preview",
+            "sponsored-apis",
+            "Preview request and response transforms",
+        )
+        .body(schema::<TransformPreviewRequest>)
+        .returns(200, schema::<TransformPreviewResponse>),
+        Op::new(
+            "post",
+            "/sponsored-apis/{api_id}/proxy",
+            "sponsored-apis",
+            "Proxy to upstream",
+        )
+        .raw_body("*/*")
+        .returns_raw("*/*")
+        .paid(),
+        Op::new(
+            "post",
+            "/sponsored-apis/{api_id}/proxy/{*path}",
+            "sponsored-apis",
+            "Proxy a sub-path to upstream; any method is accepted",
+        )
+        .raw_body("*/*")
+        .returns_raw("*/*")
+        .paid(),
+        Op::new(
+            "post",
+            "/budget-pools",
+            "budget-pools",
+            "Create a budget pool",
+        )
+        .body(schema::<CreateBudgetPoolRequest>)
+        .returns(201, schema::<BudgetPool>),
+        Op::new("get", "/budget-pools", "budget-pools", "List budget pools")
+            .query(schema::<BudgetPoolQuery>)
+            .returns(200, schema::<Vec<BudgetPool>>),
+        Op::new(
+            "get",
+            "/budget-pools/{pool_id}",
+            "budget-pools",
+            "Get a pool and its members",
+        )
+        .returns(200, schema::<BudgetPoolDetail>),
+        Op::new(
+            "post",
+            "/budget-pools/{pool_id}/top-up",
+            "budget-pools",
+            "Add pool budget",
+        )
+        .body(schema::<TopUpBudgetPoolRequest>)
+        .returns(200, schema::<BudgetPool>),
+        Op::new(
+            "post",
+            "/webhooks/x402scan/settlement",
+            "webhooks",
+            "Ingest a settlement",
+        )
+        .body(schema::<X402ScanSettlementRequest>)
+        .returns(202, schema::<MessageResponse>),
+        Op::new(
+            "post",
+            "/sponsor-webhooks",
+            "webhooks",
+            "Register a sponsor webhook",
+        )
+        .body(schema::<CreateSponsorWebhookRequest>)
+        .returns(201, schema::<CreateSponsorWebhookResponse>),
+        Op::new(
+            "get",
+            "/sponsor-webhooks",
+            "webhooks",
+            "List sponsor webhooks",
+        )
+        .query(schema::<SponsorWebhookListQuery>)
+        .returns(200, schema::<Vec<SponsorWebhook>>),
+        Op::new(
+            "delete",
+            "/sponsor-webhooks/{webhook_id}",
+            "webhooks",
+            "Delete a webhook",
+        )
+        .returns(200, schema::<MessageResponse>),
+        Op::new(
+            "get",
+            "/sponsor-webhooks/{webhook_id}/deliveries",
+            "webhooks",
+            "List deliveries",
+        )
+        .returns(200, schema::<Vec<WebhookDelivery>>),
+        Op::new(
+            "post",
+            "/sponsor-webhooks/deliveries/{delivery_id}/redeliver",
+            "webhooks",
+            "Redeliver a webhook event",
+        )
+        .returns(200, schema::<WebhookDelivery>),
+        Op::new(
+            "get",
+            "/dashboard/sponsor/{campaign_id}",
+            "dashboard",
+            "Sponsor totals",
+        )
+        .returns(200, schema::<SponsorDashboard>),
+        Op::new(
+            "get",
+            "/dashboard/sponsor/{campaign_id}/timeseries",
+            "dashboard",
+            "Sponsor spend over time",
+        )
+        .query(schema::<SponsorTimeseriesQuery>)
+        .returns(200, schema::<SponsorTimeseries>),
+        Op::new(
+            "post",
+            "/creator/metrics/event",
+            "creators",
+            "Record a creator metric event",
+        )
+        .body(schema::<CreatorMetricEventRequest>)
+        .returns(201, schema::<CreatorEvent>),
+        Op::new(
+            "get",
+            "/creator/metrics",
+            "creators",
+            "Creator metric summary",
+        )
+        .returns(200, schema::<CreatorMetricSummary>),
+    ]
+}
+
+/// The OpenAPI 3.1 document for the whole HTTP API, served at `/openapi.json`.
+pub fn openapi_document(config: &AppConfig) -> Value {
+    let mut generator = SchemaSettings::draft2020_12()
+        .with(|settings| {
+            settings.definitions_path = "/components/schemas".into();
+            settings.meta_schema = None;
+        })
+        .into_generator();
+    generator.subschema_for::<ErrorResponse>();
+    generator.subschema_for::<PaymentRequired>();
+
+    let mut paths = Map::new();
+    for operation in operations() {
+        let path = operation.path.replace("{*", "{");
+        let entry = paths.entry(path).or_insert_with(|| json!({}));
+        entry[operation.method] = operation_object(&operation, &mut generator);
+    }
+
+    json!({
+        "openapi": "3.1.0",
+        "info": {
+            "title": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Sponsored and x402-paid tool calls. Paid operations answer 402 with \
+                a PAYMENT-REQUIRED challenge; retry with a PAYMENT-SIGNATURE header.",
+        },
+        "servers": [{ "url": config.public_base_url.trim_end_matches('/') }],
+        "paths": paths,
+        "components": {
+            "schemas": generator.take_definitions(true),
+            "parameters": {
+                "PaymentSignature": {
+                    "name": PAYMENT_SIGNATURE_HEADER.to_uppercase(),
+                    "in": "header",
+                    "required": false,
+                    "description": "Base64-encoded x402 payment payload.",
+                    "schema": { "type": "string" },
+                },
+            },
+            "headers": {
+                "PaymentRequired": {
+                    "description": "Base64-encoded x402 payment requirements.",
+                    "schema": { "type": "string" },
+                },
+                "PaymentResponse": {
+                    "description": "Base64-encoded x402 settlement, sent when a payment settled.",
+                    "schema": { "type": "string" },
+                },
+                "X402Version": { "schema": { "type": "string", "const": "2" } },
+                "RetryAfter": {
+                    "description": "Seconds to wait, on 429 and 503 responses.",
+                    "schema": { "type": "integer" },
+                },
+            },
+            "responses": {
+                "PaymentRequired": {
+                    "description": "Payment required; the body and header carry the x402 \
+                        challenge.",
+                    "headers": {
+                        PAYMENT_REQUIRED_HEADER.to_uppercase():
+                            { "$ref": "#/components/headers/PaymentRequired" },
+                        X402_VERSION_HEADER: { "$ref": "#/components/headers/X402Version" },
+                    },
+                    "content": json_content(json!({
+                        "$ref": "#/components/schemas/PaymentRequired"
+                    })),
+                },
+                "Error": {
+                    "description": "Error envelope shared by every failure other than 402.",
+                    "headers": {
+                        "Retry-After": { "$ref": "#/components/headers/RetryAfter" },
+                    },
+                    "content": json_content(json!({
+                        "$ref": "#/components/schemas/ErrorResponse"
+                    })),
+                },
+            },
+        },
+    })
+}
+
+fn operation_object(operation: &Operation, generator: &mut SchemaGenerator) -> Value {
+    let mut parameters: Vec<Value> = path_parameters(operation.path);
+    if let Some(query) = operation.query {
+        parameters.extend(query_parameters(query(generator), generator));
+    }
+    if operation.paid {
+        parameters.push(json!({ "$ref": "#/components/parameters/PaymentSignature" }));
+    }
+
+    let mut success = json!({ "description": "Success" });
+    if let Some(response) = &operation.response {
+        let mut content = content_object(response, generator);
+        for media_type in operation.alternate_media_types {
+            content[*media_type] = json!({ "schema": { "type": "string" } });
+        }
+        success["content"] = content;
+    }
+    if operation.paid {
+        success["headers"] = json!({
+            PAYMENT_RESPONSE_HEADER.to_uppercase():
+                { "$ref": "#/components/headers/PaymentResponse" },
+        });
+    }
+    let mut responses = Map::new();
+    responses.insert(operation.status.to_string(), success);
+    if let Some(queued) = operation.queued {
+        responses.insert(
+            "202".to_string(),
+            json!({
+                "description": "Queued as a background job",
+                "headers": { "Location": { "schema": { "type": "string" } } },
+                "content": json_content(queued(generator).to_value()),
+            }),
+        );
+    }
+    if operation.paid {
+        responses.insert(
+            "402".to_string(),
+            json!({ "$ref": "#/components/responses/PaymentRequired" }),
+        );
+    }
+    responses.insert(
+        "default".to_string(),
+        json!({ "$ref": "#/components/responses/Error" }),
+    );
+
+    let mut object = json!({
+        "tags": [operation.tag],
+        "summary": operation.summary,
+        "parameters": parameters,
+        "responses": responses,
+    });
+    if let Some(body) = &operation.body {
+        object["requestBody"] = json!({
+            "required": true,
+            "content": content_object(body, generator),
+        });
+    }
+    object
+}
+
+fn content_object(content: &Content, generator: &mut SchemaGenerator) -> Value {
+    match content {
+        Content::Json(schema) => json_content(schema(generator).to_value()),
+        Content::Raw(media_type) => json!({ *media_type: {} }),
+    }
+}
+
+fn json_content(schema: Value) -> Value {
+    json!({ "application/json": { "schema": schema } })
+}
+
+/// `{name}` and axum's `{*name}` segments; ids are UUIDs.
+fn path_parameters(path: &str) -> Vec<Value> {
+    path.split('/')
+        .filter_map(|segment| segment.strip_prefix('{')?.strip_suffix('}'))
+        .map(|name| {
+            let name = name.trim_start_matches('*');
+            let schema = if name.ends_with("_id") {
+                json!({ "type": "string", "format": "uuid" })
+            } else {
+                json!({ "type": "string" })
+            };
+            json!({ "name": name, "in": "path", "required": true, "schema": schema })
+        })
+        .collect()
+}
+
+/// One parameter per property of a query struct's schema.
+fn query_parameters(query: Schema, generator: &SchemaGenerator) -> Vec<Value> {
+    let resolved = query
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.rsplit('/').next())
+        .and_then(|name| generator.definitions().get(name))
+        .unwrap_or(query.as_value());
+    let required: Vec<&str> = resolved["required"]
+        .as_array()
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    resolved["properties"]
+        .as_object()
+        .map(|properties| {
+            properties
+                .iter()
+                .map(|(name, schema)| {
+                    json!({
+                        "name": name,
+                        "in": "query",
+                        "required": required.contains(&name.as_str()),
+                        "schema": schema,
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Interactive reference for `/openapi.json`, rendered in the browser. The third-party
+/// renderer is only included when its integrity hash is configured, so a compromised CDN
+/// cannot run code on this origin.
+pub fn docs_page(config: &AppConfig) -> String {
+    let body = match &config.api_docs_script_integrity {
+        Some(integrity) => format!(
+            r#"<script id="api-reference" data-url="/openapi.json"></script>
+    <script src="{}" integrity="{}" crossorigin="anonymous"></script>"#,
+            escape_attribute(&config.api_docs_script_url),
+            escape_attribute(integrity.trim()),
+        ),
+        None => {
+            r#"<p>Set <code>API_DOCS_SCRIPT_INTEGRITY</code> to enable the interactive reference.
+    The OpenAPI document is at <a href="/openapi.json">/openapi.json</a>.</p>"#
+                .to_string()
+        }
+    };
+    format!(
+        r#"<!doctype html>
+<html>
+  <head>
+    <title>API reference</title>
+    <meta charset="utf-8" />
+    <meta name="viewport" content="width=device-width, initial-scale=1" />
+  </head>
+  <body>
+    {body}
+  </body>
+</html>
+"#
+    )
+}
+
+fn escape_attribute(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
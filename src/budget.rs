@@ -0,0 +1,299 @@
+use axum::http::StatusCode;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::collections::HashSet;
+use uuid::Uuid;
+
+use crate::error::{ApiError, ApiResult};
+use crate::types::{BudgetPoolMemberKind, CAMPAIGN_COLUMNS, Campaign, CampaignRow, SponsoredApi};
+
+/// Balance of whichever budget a sponsored call was charged to, before and after the charge.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BudgetDraw {
+    pub previous_remaining: u64,
+    pub new_remaining: u64,
+    pub budget_total: u64,
+    /// Whether the budget can still cover another single call.
+    pub still_active: bool,
+}
+
+impl BudgetDraw {
+    /// `amount` may cover a whole batch; `per_call` is the price of one call.
+    fn after(remaining: i64, total: i64, amount: u64, per_call: u64) -> Self {
+        let new_remaining = u64::try_from(remaining).unwrap_or_default();
+        Self {
+            previous_remaining: new_remaining + amount,
+            new_remaining,
+            budget_total: u64::try_from(total).unwrap_or_default(),
+            still_active: new_remaining >= per_call && new_remaining > 0,
+        }
+    }
+}
+
+impl BudgetPoolMemberKind {
+    fn table(self) -> &'static str {
+        match self {
+            Self::SponsoredApi => "sponsored_apis",
+            Self::Campaign => "campaigns",
+        }
+    }
+}
+
+/// Takes `amount`, one call or a whole batch, from the API's pool or its own budget. `None`
+/// means there was not enough left, checked and debited atomically so concurrent calls
+/// cannot overdraw. The API stays active while the rest covers one more call at its price.
+pub async fn draw_api_budget(
+    db: &PgPool,
+    api: &SponsoredApi,
+    amount: u64,
+) -> ApiResult<Option<BudgetDraw>> {
+    if let Some(pool_id) = api.budget_pool_id {
+        return draw_from_pool(
+            db,
+            pool_id,
+            BudgetPoolMemberKind::SponsoredApi,
+            api.id,
+            amount,
+            api.price_cents,
+        )
+        .await;
+    }
+
+    sqlx::query_as::<_, (i64, i64, i64)>(
+        r#"
+        update sponsored_apis
+        set budget_remaining_cents = budget_remaining_cents - $2,
+            active = budget_remaining_cents - $2 >= price_cents and budget_remaining_cents > $2
+        where id = $1 and active and budget_remaining_cents >= $2
+        returning budget_remaining_cents, budget_total_cents, price_cents
+        "#,
+    )
+    .bind(api.id)
+    .bind(amount as i64)
+    .fetch_optional(db)
+    .await
+    .map(|row| {
+        row.map(|(remaining, total, price)| {
+            BudgetDraw::after(
+                remaining,
+                total,
+                amount,
+                u64::try_from(price).unwrap_or_default(),
+            )
+        })
+    })
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
+
+/// Same as [`draw_api_budget`] for a campaign subsidy, which always covers a single call.
+pub async fn draw_campaign_budget(
+    db: &PgPool,
+    campaign: &Campaign,
+    amount: u64,
+) -> ApiResult<Option<BudgetDraw>> {
+    if let Some(pool_id) = campaign.budget_pool_id {
+        return draw_from_pool(
+            db,
+            pool_id,
+            BudgetPoolMemberKind::Campaign,
+            campaign.id,
+            amount,
+            amount,
+        )
+        .await;
+    }
+
+    sqlx::query_as::<_, (i64, i64)>(
+        r#"
+        update campaigns
+        set budget_remaining_cents = budget_remaining_cents - $2,
+            active = budget_remaining_cents - $2 >= $2 and budget_remaining_cents > $2
+        where id = $1 and active and budget_remaining_cents >= $2
+        returning budget_remaining_cents, budget_total_cents
+        "#,
+    )
+    .bind(campaign.id)
+    .bind(amount as i64)
+    .fetch_optional(db)
+    .await
+    .map(|row| row.map(|(remaining, total)| BudgetDraw::after(remaining, total, amount, amount)))
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
+
+/// Debits the pool and the member's sub-limit together. The member row is locked before
+/// the pool row, here and in [`release_to_pool`], so the two never deadlock.
+async fn draw_from_pool(
+    db: &PgPool,
+    pool_id: Uuid,
+    kind: BudgetPoolMemberKind,
+    member_id: Uuid,
+    amount: u64,
+    per_call: u64,
+) -> ApiResult<Option<BudgetDraw>> {
+    let result: Result<Option<BudgetDraw>, sqlx::Error> = async {
+        let mut tx = db.begin().await?;
+        let within_limit = sqlx::query(&format!(
+            r#"
+            update {}
+            set pool_spent_cents = pool_spent_cents + $3
+            where id = $1 and budget_pool_id = $2
+                and (pool_limit_cents is null or pool_spent_cents + $3 <= pool_limit_cents)
+            "#,
+            kind.table()
+        ))
+        .bind(member_id)
+        .bind(pool_id)
+        .bind(amount as i64)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected()
+            > 0;
+        if !within_limit {
+            return Ok(None);
+        }
+
+        let Some((remaining, total)) = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            update budget_pools
+            set budget_remaining_cents = budget_remaining_cents - $2
+            where id = $1 and budget_remaining_cents >= $2
+            returning budget_remaining_cents, budget_total_cents
+            "#,
+        )
+        .bind(pool_id)
+        .bind(amount as i64)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        tx.commit().await?;
+        Ok(Some(BudgetDraw::after(remaining, total, amount, per_call)))
+    }
+    .await;
+
+    result.map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
+
+/// Active campaigns, newest first, whose own budget or pool can still cover `amount`.
+pub async fn load_fundable_campaigns(db: &PgPool, amount: u64) -> ApiResult<Vec<Campaign>> {
+    sqlx::query_as::<_, CampaignRow>(&format!(
+        r#"
+        select {CAMPAIGN_COLUMNS}
+        from campaigns
+        where active = true
+            and case
+                when budget_pool_id is null then budget_remaining_cents >= $1
+                else (pool_limit_cents is null or pool_spent_cents + $1 <= pool_limit_cents)
+                    and exists (
+                        select 1 from budget_pools
+                        where budget_pools.id = campaigns.budget_pool_id
+                            and budget_pools.budget_remaining_cents >= $1
+                    )
+            end
+        order by created_at desc
+        "#
+    ))
+    .bind(amount as i64)
+    .fetch_all(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .into_iter()
+    .map(Campaign::try_from)
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))
+}
+
+/// Sponsored APIs whose own budget or pool can still cover a call at their price, by the
+/// same rules as [`draw_api_budget`].
+pub async fn funded_sponsored_api_ids(db: &PgPool) -> ApiResult<HashSet<Uuid>> {
+    sqlx::query_scalar::<_, Uuid>(
+        r#"
+        select id
+        from sponsored_apis
+        where deleted_at is null
+            and case
+                when budget_pool_id is null then active and budget_remaining_cents >= price_cents
+                else (pool_limit_cents is null or pool_spent_cents + price_cents <= pool_limit_cents)
+                    and exists (
+                        select 1 from budget_pools
+                        where budget_pools.id = sponsored_apis.budget_pool_id
+                            and budget_pools.budget_remaining_cents >= sponsored_apis.price_cents
+                    )
+            end
+        "#,
+    )
+    .fetch_all(db)
+    .await
+    .map(|ids| ids.into_iter().collect())
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
+
+/// Hands a refunded sponsored API call back to wherever it was drawn from.
+pub async fn release_api_budget(
+    tx: &mut Transaction<'_, Postgres>,
+    api_id: Uuid,
+    pool_id: Option<Uuid>,
+    amount: u64,
+) -> Result<(), sqlx::Error> {
+    if let Some(pool_id) = pool_id {
+        return release_to_pool(
+            tx,
+            pool_id,
+            BudgetPoolMemberKind::SponsoredApi,
+            api_id,
+            amount,
+        )
+        .await;
+    }
+
+    sqlx::query(
+        r#"
+        update sponsored_apis
+        set budget_remaining_cents = budget_remaining_cents + $2,
+            active = budget_remaining_cents + $2 >= price_cents
+        where id = $1
+        "#,
+    )
+    .bind(api_id)
+    .bind(amount as i64)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
+
+async fn release_to_pool(
+    tx: &mut Transaction<'_, Postgres>,
+    pool_id: Uuid,
+    kind: BudgetPoolMemberKind,
+    member_id: Uuid,
+    amount: u64,
+) -> Result<(), sqlx::Error> {
+    // The member may have moved pools since; its new pool's sub-limit is left alone.
+    sqlx::query(&format!(
+        r#"
+        update {}
+        set pool_spent_cents = greatest(pool_spent_cents - $3, 0)
+        where id = $1 and budget_pool_id = $2
+        "#,
+        kind.table()
+    ))
+    .bind(member_id)
+    .bind(pool_id)
+    .bind(amount as i64)
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query(
+        r#"
+        update budget_pools
+        set budget_remaining_cents = budget_remaining_cents + $2
+        where id = $1
+        "#,
+    )
+    .bind(pool_id)
+    .bind(amount as i64)
+    .execute(&mut **tx)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use axum::{body::Bytes, http::HeaderMap};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use crate::types::ResponseCachePolicy;
+use crate::utils::UpstreamRequest;
+
+#[derive(Debug, Clone)]
+pub struct CachedResponse {
+    pub status: u16,
+    pub body: Bytes,
+    expires_at: Instant,
+    api_id: Uuid,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    entries: HashMap<String, CachedResponse>,
+    /// Insertion order, used to evict the oldest entries once the cache is full.
+    order: VecDeque<String>,
+    bytes: usize,
+}
+
+/// In-process cache of upstream responses for sponsored APIs that opt in.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    state: Arc<Mutex<CacheState>>,
+    max_bytes: usize,
+}
+
+impl ResponseCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(CacheState::default())),
+            max_bytes,
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<CachedResponse> {
+        let mut state = self.state.lock().expect("response cache lock poisoned");
+        let entry = state.entries.get(key)?;
+        if entry.expires_at > Instant::now() {
+            return Some(entry.clone());
+        }
+        state.remove(key);
+        None
+    }
+
+    pub fn insert(&self, key: String, api_id: Uuid, status: u16, body: Bytes, ttl: Duration) {
+        if body.len() > self.max_bytes {
+            return;
+        }
+        let mut state = self.state.lock().expect("response cache lock poisoned");
+        state.remove(&key);
+        while state.bytes + body.len() > self.max_bytes {
+            let Some(oldest) = state.order.pop_front() else {
+                break;
+            };
+            state.remove(&oldest);
+        }
+        state.bytes += body.len();
+        state.order.push_back(key.clone());
+        state.entries.insert(
+            key,
+            CachedResponse {
+                status,
+                body,
+                expires_at: Instant::now() + ttl,
+                api_id,
+            },
+        );
+    }
+
+    /// Drops every entry for an API, e.g. after its upstream or headers change.
+    pub fn invalidate(&self, api_id: Uuid) {
+        let mut state = self.state.lock().expect("response cache lock poisoned");
+        let keys: Vec<String> = state
+            .entries
+            .iter()
+            .filter(|(_, entry)| entry.api_id == api_id)
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in keys {
+            state.remove(&key);
+        }
+    }
+}
+
+impl CacheState {
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.bytes = self.bytes.saturating_sub(entry.body.len());
+            self.order.retain(|queued| queued != key);
+        }
+    }
+}
+
+/// Keys on the resolved URL, the remaining input and any forwarded caller headers, since
+/// all of them can change what the upstream returns.
+pub fn cache_key(api_id: Uuid, method: &str, upstream: &UpstreamRequest) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(api_id.as_bytes());
+    hasher.update(method.as_bytes());
+    hasher.update([0]);
+    hasher.update(upstream.url.as_bytes());
+    hasher.update([0]);
+    // serde_json maps are sorted, so equal inputs serialize identically.
+    hasher.update(upstream.input.to_string().as_bytes());
+    let mut headers: Vec<_> = upstream
+        .forwarded_headers
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_bytes()))
+        .collect();
+    headers.sort();
+    for (name, value) in headers {
+        hasher.update([0]);
+        hasher.update(name.as_bytes());
+        hasher.update(b":");
+        hasher.update(value);
+    }
+    hex::encode(hasher.finalize())
+}
+
+/// How long a response may be cached: the API's TTL, shortened by upstream `Cache-Control`.
+/// Returns `None` when the upstream forbids storing the response.
+pub fn cache_ttl(policy: &ResponseCachePolicy, headers: &HeaderMap) -> Option<Duration> {
+    let mut ttl = policy.ttl_secs;
+    let mut shared_max_age = None;
+
+    for value in headers.get_all(axum::http::header::CACHE_CONTROL) {
+        let Ok(value) = value.to_str() else {
+            continue;
+        };
+        for directive in value
+            .split(',')
+            .map(|part| part.trim().to_ascii_lowercase())
+        {
+            let (name, argument) = match directive.split_once('=') {
+                Some((name, argument)) => (name.trim().to_string(), Some(argument.trim())),
+                None => (directive.clone(), None),
+            };
+            let seconds =
+                argument.and_then(|argument| argument.trim_matches('"').parse::<u64>().ok());
+            match name.as_str() {
+                "no-store" | "no-cache" | "private" => return None,
+                "max-age" => ttl = ttl.min(seconds.unwrap_or(0)),
+                "s-maxage" => shared_max_age = seconds,
+                _ => {}
+            }
+        }
+    }
+
+    // A shared cache uses s-maxage in preference to max-age.
+    if let Some(shared_max_age) = shared_max_age {
+        ttl = policy.ttl_secs.min(shared_max_age);
+    }
+    (ttl > 0).then(|| Duration::from_secs(ttl))
+}
@@ -0,0 +1,113 @@
+use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{DateTime, SecondsFormat, Utc};
+use uuid::Uuid;
+
+use crate::error::{ApiError, ApiResult};
+use crate::streaming::StreamOutcome;
+use crate::types::{CallErrorClass, SponsoredApiCall};
+
+const CSV_HEADER: &str = "id,sponsored_api_id,payment_mode,amount_cents,tx_hash,caller,\
+    created_at,upstream_status,response_bytes,outcome,duration_ms,error_class,finalized_at";
+
+/// Classifies a finalized call. Successful replies, cache hits and local failures have no
+/// upstream error class.
+pub fn classify_call(
+    outcome: StreamOutcome,
+    upstream_status: Option<u16>,
+) -> Option<CallErrorClass> {
+    match outcome {
+        StreamOutcome::TimedOut => Some(CallErrorClass::Timeout),
+        StreamOutcome::UpstreamError => Some(CallErrorClass::Connection),
+        StreamOutcome::Truncated => Some(CallErrorClass::ResponseTooLarge),
+        StreamOutcome::Aborted => Some(CallErrorClass::ClientAborted),
+        StreamOutcome::Completed | StreamOutcome::CacheHit | StreamOutcome::LocalError => {
+            match upstream_status {
+                Some(500..) => Some(CallErrorClass::UpstreamServerError),
+                Some(400..500) => Some(CallErrorClass::UpstreamClientError),
+                _ => None,
+            }
+        }
+    }
+}
+
+/// Pages are ordered newest first, so the cursor is the position of the last call returned.
+pub fn encode_cursor(call: &SponsoredApiCall) -> String {
+    let position = format!(
+        "{}|{}",
+        call.created_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+        call.id
+    );
+    URL_SAFE_NO_PAD.encode(position)
+}
+
+pub fn decode_cursor(cursor: &str) -> ApiResult<(DateTime<Utc>, Uuid)> {
+    let invalid = || ApiError::validation("cursor is not a value returned by this endpoint");
+    let decoded = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let decoded = String::from_utf8(decoded).map_err(|_| invalid())?;
+    let (created_at, id) = decoded.split_once('|').ok_or_else(invalid)?;
+    let created_at = DateTime::parse_from_rfc3339(created_at)
+        .map_err(|_| invalid())?
+        .with_timezone(&Utc);
+    let id = Uuid::parse_str(id).map_err(|_| invalid())?;
+    Ok((created_at, id))
+}
+
+pub fn render_csv(calls: &[SponsoredApiCall]) -> String {
+    let mut out = String::with_capacity(CSV_HEADER.len() + calls.len() * 192);
+    out.push_str(CSV_HEADER);
+    out.push('\n');
+    for call in calls {
+        let fields = [
+            call.id.to_string(),
+            call.sponsored_api_id.to_string(),
+            csv_field(&call.payment_mode),
+            call.amount_cents.to_string(),
+            call.tx_hash.as_deref().map(csv_field).unwrap_or_default(),
+            call.caller.as_deref().map(csv_field).unwrap_or_default(),
+            call.created_at.to_rfc3339_opts(SecondsFormat::Micros, true),
+            optional(call.upstream_status),
+            optional(call.response_bytes),
+            call.outcome.as_deref().map(csv_field).unwrap_or_default(),
+            optional(call.duration_ms),
+            call.error_class
+                .map(|class| class.as_str().to_string())
+                .unwrap_or_default(),
+            call.finalized_at
+                .map(|at| at.to_rfc3339_opts(SecondsFormat::Micros, true))
+                .unwrap_or_default(),
+        ];
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+pub fn render_ndjson(calls: &[SponsoredApiCall]) -> ApiResult<String> {
+    let mut out = String::new();
+    for call in calls {
+        let line =
+            serde_json::to_string(call).map_err(|err| ApiError::internal(err.to_string()))?;
+        out.push_str(&line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// Quotes free-form values (callers in particular) so they cannot break the row, and
+/// defuses leading characters that spreadsheets would evaluate as a formula.
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{value}")
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
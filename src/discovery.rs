@@ -0,0 +1,146 @@
+use chrono::Utc;
+
+use crate::error::ApiResult;
+use crate::schema::{service_payment_schema, sponsored_api_payment_schema};
+use crate::types::{
+    AppConfig, DEFAULT_DISCOVERY_PAGE_SIZE, DiscoveryMetadata, DiscoveryPagination, DiscoveryQuery,
+    DiscoveryResource, DiscoveryResourcePage, MAX_DISCOVERY_PAGE_SIZE, ResourceKind, SponsoredApi,
+    Sponsorship, X402Manifest,
+};
+use crate::utils::build_payment_requirement;
+
+const X402_VERSION: u8 = 2;
+
+/// `/tool/{service}/run` and `/proxy/{service}/run` for one built-in service. `sponsors`
+/// are the campaigns that can currently cover the proxy price.
+pub fn service_resources(
+    config: &AppConfig,
+    service: &str,
+    price: u64,
+    sponsors: Vec<String>,
+) -> ApiResult<Vec<DiscoveryResource>> {
+    let schema = service_payment_schema();
+    [
+        (
+            ResourceKind::Tool,
+            Sponsorship {
+                available: false,
+                sponsors: Vec::new(),
+                conditional: false,
+            },
+        ),
+        (
+            ResourceKind::Proxy,
+            Sponsorship {
+                available: !sponsors.is_empty(),
+                sponsors,
+                conditional: true,
+            },
+        ),
+    ]
+    .into_iter()
+    .map(|(kind, sponsorship)| {
+        let path = match kind {
+            ResourceKind::Proxy => format!("/proxy/{service}/run"),
+            _ => format!("/tool/{service}/run"),
+        };
+        let requirement = build_payment_requirement(config, service, price, &path, Some(&schema))?;
+        Ok(DiscoveryResource {
+            resource: requirement.resource.clone(),
+            resource_type: "http".to_string(),
+            x402_version: X402_VERSION,
+            accepts: vec![requirement],
+            last_updated: Utc::now(),
+            metadata: DiscoveryMetadata {
+                kind,
+                name: service.to_string(),
+                description: None,
+                method: "POST".to_string(),
+                price_cents: price,
+                sponsored_api_id: None,
+                sponsorship,
+            },
+        })
+    })
+    .collect()
+}
+
+/// `funded` says whether the API's budget or pool can cover another call.
+pub fn sponsored_api_resource(
+    config: &AppConfig,
+    api: &SponsoredApi,
+    funded: bool,
+) -> ApiResult<DiscoveryResource> {
+    let requirement = build_payment_requirement(
+        config,
+        &api.service_key,
+        api.price_cents,
+        &format!("/sponsored-apis/{}/run", api.id),
+        Some(&sponsored_api_payment_schema(api)),
+    )?;
+    Ok(DiscoveryResource {
+        resource: requirement.resource.clone(),
+        resource_type: "http".to_string(),
+        x402_version: X402_VERSION,
+        accepts: vec![requirement],
+        last_updated: api.created_at,
+        metadata: DiscoveryMetadata {
+            kind: ResourceKind::SponsoredApi,
+            name: api.name.clone(),
+            description: api.description.clone(),
+            method: "POST".to_string(),
+            price_cents: api.price_cents,
+            sponsored_api_id: Some(api.id),
+            sponsorship: Sponsorship {
+                available: funded,
+                sponsors: if funded {
+                    vec![api.sponsor.clone()]
+                } else {
+                    Vec::new()
+                },
+                conditional: api.eligibility.is_some(),
+            },
+        },
+    })
+}
+
+pub fn paginate(
+    resources: Vec<DiscoveryResource>,
+    query: &DiscoveryQuery,
+) -> DiscoveryResourcePage {
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_DISCOVERY_PAGE_SIZE)
+        .clamp(1, MAX_DISCOVERY_PAGE_SIZE);
+    let offset = query.offset.unwrap_or_default();
+    let resources: Vec<DiscoveryResource> = resources
+        .into_iter()
+        .filter(|resource| query.kind.is_none_or(|kind| resource.metadata.kind == kind))
+        .collect();
+    let total = resources.len();
+    DiscoveryResourcePage {
+        x402_version: X402_VERSION,
+        items: resources.into_iter().skip(offset).take(limit).collect(),
+        pagination: DiscoveryPagination {
+            limit,
+            offset,
+            total,
+        },
+    }
+}
+
+pub fn manifest(config: &AppConfig, resources: &[DiscoveryResource]) -> X402Manifest {
+    X402Manifest {
+        version: 1,
+        resources: resources
+            .iter()
+            .map(|resource| resource.resource.clone())
+            .collect(),
+        instructions: format!(
+            "Payment requirements, prices, schemas and sponsorship for every resource are \
+            listed at {}/discovery/resources. Unpaid calls answer 402 with a PAYMENT-REQUIRED \
+            challenge; retry with PAYMENT-SIGNATURE.",
+            config.public_base_url.trim_end_matches('/')
+        ),
+    }
+}
@@ -0,0 +1,123 @@
+use axum::http::StatusCode;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::{ApiError, ApiResult};
+use crate::types::{EligibilityPolicy, UserProfile};
+use crate::utils::{has_completed_task, normalize_email, user_matches_targets};
+
+/// Normalizes allowlist entries and checks that the referenced campaign exists.
+pub async fn validate_eligibility_policy(
+    db: &PgPool,
+    policy: &mut EligibilityPolicy,
+) -> ApiResult<()> {
+    for entry in &mut policy.allowed_callers {
+        let user_id = Uuid::parse_str(entry.trim()).map_err(|_| {
+            ApiError::validation("eligibility.allowed_callers entries must be profile ids")
+        })?;
+        *entry = user_id.to_string();
+    }
+    policy.allowed_callers.sort();
+    policy.allowed_callers.dedup();
+
+    if let Some(campaign_id) = policy.required_campaign_id {
+        let exists =
+            sqlx::query_scalar::<_, bool>("select exists(select 1 from campaigns where id = $1)")
+                .bind(campaign_id)
+                .fetch_one(db)
+                .await
+                .map_err(|err| {
+                    ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                })?;
+        if !exists {
+            return Err(ApiError::validation(
+                "eligibility.required_campaign_id does not name a campaign",
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns why `caller` may not be subsidized, or `None` when every rule holds.
+pub async fn ineligibility_reason(
+    db: &PgPool,
+    policy: &EligibilityPolicy,
+    caller: Option<&str>,
+) -> ApiResult<Option<&'static str>> {
+    let Some(caller) = caller
+        .map(normalize_caller)
+        .filter(|caller| !caller.is_empty())
+    else {
+        return Ok(Some("sponsored calls require a caller"));
+    };
+
+    let profile = if policy.needs_profile() {
+        match find_profile(db, &caller).await? {
+            Some(profile) => Some(profile),
+            None => return Ok(Some("caller has no registered profile")),
+        }
+    } else {
+        None
+    };
+
+    // Only the profile id itself counts: an email is too easy to guess to stand in for it.
+    if !policy.allowed_callers.is_empty()
+        && !Uuid::parse_str(&caller)
+            .is_ok_and(|user_id| policy.allowed_callers.contains(&user_id.to_string()))
+    {
+        return Ok(Some("caller is not on the sponsor's allowlist"));
+    }
+
+    let Some(profile) = profile else {
+        return Ok(None);
+    };
+    if !user_matches_targets(&profile, &policy.target_roles, &policy.target_tools) {
+        return Ok(Some(
+            "caller profile does not match the sponsor's targeting",
+        ));
+    }
+    if let Some(campaign_id) = policy.required_campaign_id {
+        let required_task =
+            sqlx::query_scalar::<_, String>("select required_task from campaigns where id = $1")
+                .bind(campaign_id)
+                .fetch_optional(db)
+                .await
+                .map_err(|err| {
+                    ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                })?;
+        let completed = match required_task {
+            Some(task) => has_completed_task(db, campaign_id, profile.id, &task).await?,
+            None => false,
+        };
+        if !completed {
+            return Ok(Some("caller has not completed the sponsor's campaign task"));
+        }
+    }
+    Ok(None)
+}
+
+/// Callers are free-form: a profile id, an email, or anything else the client sends.
+fn normalize_caller(caller: &str) -> String {
+    if caller.contains('@') {
+        normalize_email(caller)
+    } else {
+        caller.trim().to_string()
+    }
+}
+
+async fn find_profile(db: &PgPool, caller: &str) -> ApiResult<Option<UserProfile>> {
+    let user_id = Uuid::parse_str(caller).ok();
+    sqlx::query_as::<_, UserProfile>(
+        r#"
+        select id, email, region, roles, tools_used, attributes, created_at
+        from users
+        where id = $1 or email = $2
+        limit 1
+        "#,
+    )
+    .bind(user_id)
+    .bind(caller)
+    .fetch_optional(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
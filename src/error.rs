@@ -1,22 +1,26 @@
 use axum::{
     Json,
-    http::{HeaderName, HeaderValue, StatusCode},
+    http::{HeaderName, HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
+use schemars::JsonSchema;
 use serde::Serialize;
 use serde_json::Value;
 use thiserror::Error;
 
-use crate::types::{PAYMENT_REQUIRED_HEADER, PaymentRequired, X402_VERSION_HEADER};
+use crate::types::{
+    PAYMENT_REQUIRED_HEADER, PaymentRequired, SchemaViolation, X402_VERSION_HEADER,
+};
 
 pub type ApiResult<T> = Result<T, ApiError>;
 
-#[derive(Debug, Serialize)]
-struct ErrorResponse {
+/// Body of every non-402 error response.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ErrorResponse {
     error: ErrorBody,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 struct ErrorBody {
     code: String,
     message: String,
@@ -27,13 +31,30 @@ struct ErrorBody {
 #[derive(Debug, Error)]
 pub enum ApiError {
     #[error("payment required")]
-    PaymentRequired(PaymentRequired),
+    PaymentRequired(Box<PaymentRequired>),
     #[error("{message}")]
     Http {
         status: StatusCode,
         code: String,
         message: String,
     },
+    #[error("{message}")]
+    RateLimited {
+        retry_after_secs: u64,
+        message: String,
+    },
+    #[error("{message}")]
+    Unavailable {
+        retry_after_secs: u64,
+        message: String,
+    },
+    #[error("{message}")]
+    Schema {
+        status: StatusCode,
+        code: String,
+        message: String,
+        violations: Vec<SchemaViolation>,
+    },
     #[error("database error: {message}")]
     Database { status: StatusCode, message: String },
     #[error("upstream error: {message}")]
@@ -69,6 +90,38 @@ impl ApiError {
         }
     }
 
+    pub fn rate_limited(retry_after_secs: u64, message: impl Into<String>) -> Self {
+        Self::RateLimited {
+            retry_after_secs,
+            message: message.into(),
+        }
+    }
+
+    pub fn unavailable(retry_after_secs: u64, message: impl Into<String>) -> Self {
+        Self::Unavailable {
+            retry_after_secs,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_input(violations: Vec<SchemaViolation>) -> Self {
+        Self::Schema {
+            status: StatusCode::BAD_REQUEST,
+            code: "invalid_input".to_string(),
+            message: "input does not match the input schema".to_string(),
+            violations,
+        }
+    }
+
+    pub fn invalid_output(violations: Vec<SchemaViolation>) -> Self {
+        Self::Schema {
+            status: StatusCode::BAD_GATEWAY,
+            code: "invalid_upstream_output".to_string(),
+            message: "upstream response does not match the output schema".to_string(),
+            violations,
+        }
+    }
+
     pub fn database(status: StatusCode, message: impl Into<String>) -> Self {
         Self::Database {
             status,
@@ -95,10 +148,52 @@ impl ApiError {
         }
     }
 
+    /// Points a rejected batch at the input that caused it.
+    pub fn for_batch_item(self, index: usize) -> Self {
+        match self {
+            Self::Http {
+                status,
+                code,
+                message,
+            } => Self::Http {
+                status,
+                code,
+                message: format!("inputs[{index}]: {message}"),
+            },
+            Self::Schema {
+                status,
+                code,
+                message,
+                violations,
+            } => Self::Schema {
+                status,
+                code,
+                message: format!("inputs[{index}]: {message}"),
+                violations,
+            },
+            other => other,
+        }
+    }
+
+    /// The `error` object of the response body, for places that report errors later, like
+    /// background jobs.
+    pub fn to_json(&self) -> Value {
+        match self {
+            Self::PaymentRequired(payload) => serde_json::json!({
+                "code": "payment_required",
+                "message": payload.message,
+            }),
+            other => serde_json::to_value(other.body()).unwrap_or(Value::Null),
+        }
+    }
+
     fn status_code(&self) -> StatusCode {
         match self {
             Self::PaymentRequired(_) => StatusCode::PAYMENT_REQUIRED,
             Self::Http { status, .. } => *status,
+            Self::RateLimited { .. } => StatusCode::TOO_MANY_REQUESTS,
+            Self::Unavailable { .. } => StatusCode::SERVICE_UNAVAILABLE,
+            Self::Schema { status, .. } => *status,
             Self::Database { status, .. } => *status,
             Self::Upstream { status, .. } => *status,
             Self::Config { .. } | Self::Internal { .. } => StatusCode::INTERNAL_SERVER_ERROR,
@@ -117,6 +212,32 @@ impl ApiError {
                 message: message.clone(),
                 details: None,
             },
+            Self::RateLimited {
+                retry_after_secs,
+                message,
+            } => ErrorBody {
+                code: "rate_limited".to_string(),
+                message: message.clone(),
+                details: Some(serde_json::json!({ "retry_after_secs": retry_after_secs })),
+            },
+            Self::Unavailable {
+                retry_after_secs,
+                message,
+            } => ErrorBody {
+                code: "upstream_unavailable".to_string(),
+                message: message.clone(),
+                details: Some(serde_json::json!({ "retry_after_secs": retry_after_secs })),
+            },
+            Self::Schema {
+                code,
+                message,
+                violations,
+                ..
+            } => ErrorBody {
+                code: code.clone(),
+                message: message.clone(),
+                details: Some(serde_json::json!({ "violations": violations })),
+            },
             Self::Database { message, .. } => ErrorBody {
                 code: "database_error".to_string(),
                 message: message.clone(),
@@ -144,13 +265,25 @@ impl ApiError {
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
         match self {
-            ApiError::PaymentRequired(payload) => payment_required_response(payload),
+            ApiError::PaymentRequired(payload) => payment_required_response(*payload),
             other => {
                 let status = other.status_code();
                 let body = ErrorResponse {
                     error: other.body(),
                 };
-                (status, Json(body)).into_response()
+                let mut response = (status, Json(body)).into_response();
+                if let ApiError::RateLimited {
+                    retry_after_secs, ..
+                }
+                | ApiError::Unavailable {
+                    retry_after_secs, ..
+                } = other
+                {
+                    response
+                        .headers_mut()
+                        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after_secs));
+                }
+                response
             }
         }
     }
@@ -0,0 +1,343 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+use uuid::Uuid;
+
+use crate::error::{ApiError, ApiResult};
+use crate::types::{
+    AppConfig, CircuitState, HealthProbeResult, HealthState, Metrics, SPONSORED_API_COLUMNS,
+    SharedState, SponsoredApi, SponsoredApiRow, UpstreamHealth,
+};
+use crate::utils::probe_upstream;
+
+/// How often the prober wakes up to look for APIs whose probe interval has elapsed.
+const PROBE_TICK: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy)]
+enum Circuit {
+    Closed,
+    Open {
+        until: Instant,
+        until_utc: DateTime<Utc>,
+    },
+    /// One trial call at a time decides whether the circuit closes. A trial that never
+    /// reports back, e.g. one that failed locally, gives up its slot after `open_for`.
+    HalfOpen {
+        trial_since: Option<Instant>,
+    },
+}
+
+#[derive(Debug)]
+struct Entry {
+    /// `(finished_at, succeeded)` for upstream calls inside the rolling window.
+    calls: VecDeque<(Instant, bool)>,
+    circuit: Circuit,
+    last_probe: Option<HealthProbeResult>,
+    last_probe_at: Option<Instant>,
+    probing: bool,
+}
+
+impl Default for Entry {
+    fn default() -> Self {
+        Self {
+            calls: VecDeque::new(),
+            circuit: Circuit::Closed,
+            last_probe: None,
+            last_probe_at: None,
+            probing: false,
+        }
+    }
+}
+
+/// Per-instance upstream health and circuit breaker state for sponsored APIs.
+#[derive(Clone)]
+pub struct HealthRegistry {
+    entries: Arc<Mutex<HashMap<Uuid, Entry>>>,
+    window: Duration,
+    min_calls: usize,
+    error_percent: u64,
+    open_for: Duration,
+    metrics: Metrics,
+}
+
+impl HealthRegistry {
+    pub fn new(config: &AppConfig, metrics: Metrics) -> Self {
+        Self {
+            entries: Arc::default(),
+            window: Duration::from_secs(config.circuit_breaker_window_secs.max(1)),
+            min_calls: usize::try_from(config.circuit_breaker_min_calls.max(1))
+                .unwrap_or(usize::MAX),
+            error_percent: config.circuit_breaker_error_percent.clamp(1, 100),
+            open_for: Duration::from_secs(config.circuit_breaker_open_secs.max(1)),
+            metrics,
+        }
+    }
+
+    /// Lets a call through unless the circuit is open, or half-open with a trial call
+    /// already in flight. Rejections carry a `Retry-After` and happen before anything is
+    /// charged.
+    pub fn check(&self, api_id: Uuid) -> ApiResult<()> {
+        let mut entries = self.entries.lock().expect("health registry lock poisoned");
+        let Some(entry) = entries.get_mut(&api_id) else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let (retry_after, message) = match entry.circuit {
+            Circuit::Closed => return Ok(()),
+            Circuit::Open { until, .. } if now >= until => {
+                // This call is the trial; its upstream result decides what happens next.
+                self.set_circuit(
+                    api_id,
+                    entry,
+                    Circuit::HalfOpen {
+                        trial_since: Some(now),
+                    },
+                );
+                return Ok(());
+            }
+            Circuit::HalfOpen { trial_since } => match trial_since {
+                Some(since) if now.duration_since(since) < self.open_for => (
+                    self.open_for - now.duration_since(since),
+                    "sponsored api upstream is recovering; a trial call is in flight",
+                ),
+                _ => {
+                    entry.circuit = Circuit::HalfOpen {
+                        trial_since: Some(now),
+                    };
+                    return Ok(());
+                }
+            },
+            Circuit::Open { until, .. } => (
+                until.duration_since(now),
+                "sponsored api upstream is unavailable; circuit breaker is open",
+            ),
+        };
+
+        self.metrics
+            .sponsored_api_circuit_rejected_total
+            .with_label_values(&[&api_id.to_string()])
+            .inc();
+        let retry_after_secs = retry_after.as_secs_f64().ceil() as u64;
+        Err(ApiError::unavailable(retry_after_secs.max(1), message))
+    }
+
+    /// Feeds the outcome of a real upstream call into the breaker.
+    pub fn record_call(&self, api_id: Uuid, succeeded: bool) {
+        let mut entries = self.entries.lock().expect("health registry lock poisoned");
+        let entry = entries.entry(api_id).or_default();
+        let now = Instant::now();
+        entry.calls.push_back((now, succeeded));
+        prune_window(entry, now, self.window);
+
+        match entry.circuit {
+            Circuit::HalfOpen { .. } if succeeded => {
+                entry.calls.clear();
+                self.set_circuit(api_id, entry, Circuit::Closed);
+            }
+            Circuit::HalfOpen { .. } => self.trip(api_id, entry),
+            Circuit::Closed => {
+                let failures = entry.calls.iter().filter(|(_, ok)| !ok).count() as u64;
+                let total = entry.calls.len();
+                if total >= self.min_calls && failures * 100 >= self.error_percent * total as u64 {
+                    self.trip(api_id, entry);
+                }
+            }
+            // Late results from calls started before the circuit opened.
+            Circuit::Open { .. } => {}
+        }
+    }
+
+    /// A failed probe opens the circuit straight away; a passing probe while open lets
+    /// traffic back in without waiting out the full open period.
+    pub fn record_probe(&self, api_id: Uuid, result: HealthProbeResult) {
+        let mut entries = self.entries.lock().expect("health registry lock poisoned");
+        let entry = entries.entry(api_id).or_default();
+        self.metrics
+            .sponsored_api_up
+            .with_label_values(&[&api_id.to_string()])
+            .set(i64::from(result.ok));
+
+        match (result.ok, entry.circuit) {
+            (false, Circuit::Closed | Circuit::HalfOpen { .. }) => self.trip(api_id, entry),
+            (true, Circuit::Open { .. }) => {
+                self.set_circuit(api_id, entry, Circuit::HalfOpen { trial_since: None })
+            }
+            _ => {}
+        }
+        entry.last_probe = Some(result);
+        entry.last_probe_at = Some(Instant::now());
+        entry.probing = false;
+    }
+
+    /// Claims the next probe of an API once its interval has passed and no probe of it is
+    /// still running.
+    fn start_probe(&self, api_id: Uuid, interval: Duration) -> bool {
+        let mut entries = self.entries.lock().expect("health registry lock poisoned");
+        let entry = entries.entry(api_id).or_default();
+        let due = !entry.probing
+            && entry
+                .last_probe_at
+                .is_none_or(|last| last.elapsed() >= interval);
+        entry.probing |= due;
+        due
+    }
+
+    pub fn status(&self, api_id: Uuid) -> UpstreamHealth {
+        let mut entries = self.entries.lock().expect("health registry lock poisoned");
+        let Some(entry) = entries.get_mut(&api_id) else {
+            return UpstreamHealth {
+                status: HealthState::Unknown,
+                circuit: CircuitState::Closed,
+                circuit_open_until: None,
+                recent_calls: 0,
+                recent_failures: 0,
+                last_probe: None,
+            };
+        };
+        prune_window(entry, Instant::now(), self.window);
+
+        let recent_calls = entry.calls.len() as u64;
+        let recent_failures = entry.calls.iter().filter(|(_, ok)| !ok).count() as u64;
+        let (circuit, circuit_open_until) = match entry.circuit {
+            Circuit::Closed => (CircuitState::Closed, None),
+            Circuit::Open { until_utc, .. } => (CircuitState::Open, Some(until_utc)),
+            Circuit::HalfOpen { .. } => (CircuitState::HalfOpen, None),
+        };
+        let probe_ok = entry.last_probe.as_ref().map(|probe| probe.ok);
+        let status = match (circuit, probe_ok) {
+            (CircuitState::Open, _) | (_, Some(false)) => HealthState::Unhealthy,
+            (CircuitState::HalfOpen, _) => HealthState::Degraded,
+            (CircuitState::Closed, Some(true)) => HealthState::Healthy,
+            (CircuitState::Closed, None) if recent_calls > 0 => HealthState::Healthy,
+            (CircuitState::Closed, None) => HealthState::Unknown,
+        };
+
+        UpstreamHealth {
+            status,
+            circuit,
+            circuit_open_until,
+            recent_calls,
+            recent_failures,
+            last_probe: entry.last_probe.clone(),
+        }
+    }
+
+    /// Attaches the current health to APIs on their way out to a client.
+    pub fn annotate(&self, api: &mut SponsoredApi) {
+        api.health = Some(self.status(api.id));
+    }
+
+    /// Drops state for a deleted API, or one whose upstream changed.
+    pub fn forget(&self, api_id: Uuid) {
+        let mut entries = self.entries.lock().expect("health registry lock poisoned");
+        entries.remove(&api_id);
+        let label = api_id.to_string();
+        let _ = self.metrics.sponsored_api_up.remove_label_values(&[&label]);
+        let _ = self
+            .metrics
+            .sponsored_api_circuit_open
+            .remove_label_values(&[&label]);
+    }
+
+    fn trip(&self, api_id: Uuid, entry: &mut Entry) {
+        let until_utc = Utc::now()
+            + chrono::Duration::from_std(self.open_for).unwrap_or(chrono::Duration::zero());
+        self.set_circuit(
+            api_id,
+            entry,
+            Circuit::Open {
+                until: Instant::now() + self.open_for,
+                until_utc,
+            },
+        );
+    }
+
+    fn set_circuit(&self, api_id: Uuid, entry: &mut Entry, circuit: Circuit) {
+        let was_open = matches!(entry.circuit, Circuit::Open { .. });
+        let is_open = matches!(circuit, Circuit::Open { .. });
+        entry.circuit = circuit;
+        self.metrics
+            .sponsored_api_circuit_open
+            .with_label_values(&[&api_id.to_string()])
+            .set(i64::from(is_open));
+        if is_open && !was_open {
+            tracing::warn!("circuit opened for sponsored api {api_id}");
+        } else if was_open && !is_open {
+            tracing::info!("circuit half-open for sponsored api {api_id}");
+        }
+    }
+}
+
+fn prune_window(entry: &mut Entry, now: Instant, window: Duration) {
+    while entry
+        .calls
+        .front()
+        .is_some_and(|(at, _)| now.duration_since(*at) > window)
+    {
+        entry.calls.pop_front();
+    }
+}
+
+/// Probes every sponsored API that has a health check configured, each on its own interval
+/// and in its own task, so a slow upstream never holds up the others.
+pub async fn run_health_prober(state: SharedState) {
+    loop {
+        let (db, upstream_http, config, health) = {
+            let state = state.inner.read().await;
+            (
+                state.db.clone(),
+                state.upstream_http.clone(),
+                state.config.clone(),
+                state.health.clone(),
+            )
+        };
+        let Some(db) = db else {
+            return;
+        };
+
+        match load_probed_apis(&db).await {
+            Ok(apis) => {
+                for api in apis {
+                    let Some(policy) = api.health_check.clone() else {
+                        continue;
+                    };
+                    if !health.start_probe(api.id, Duration::from_secs(policy.interval_secs.max(1)))
+                    {
+                        continue;
+                    }
+                    let (upstream_http, config, health) =
+                        (upstream_http.clone(), config.clone(), health.clone());
+                    tokio::spawn(async move {
+                        let result = probe_upstream(&upstream_http, &config, &api, &policy).await;
+                        health.record_probe(api.id, result);
+                    });
+                }
+            }
+            Err(err) => tracing::warn!("failed to load sponsored apis for health probes: {err}"),
+        }
+
+        tokio::time::sleep(PROBE_TICK).await;
+    }
+}
+
+async fn load_probed_apis(db: &sqlx::PgPool) -> ApiResult<Vec<SponsoredApi>> {
+    sqlx::query_as::<_, SponsoredApiRow>(&format!(
+        r#"
+        select {SPONSORED_API_COLUMNS}
+        from sponsored_apis
+        where deleted_at is null and not paused and health_check_policy is not null
+        "#
+    ))
+    .fetch_all(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .into_iter()
+    .map(SponsoredApi::try_from)
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))
+}
@@ -0,0 +1,326 @@
+use axum::http::{HeaderName, HeaderValue, StatusCode};
+use chrono::Utc;
+use serde_json::Value;
+use sqlx::{PgPool, types::Json as DbJson};
+use std::{collections::HashMap, time::Duration};
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::error::{ApiError, ApiResult};
+use crate::secrets::SecretKeyring;
+use crate::types::{
+    JobStatus, SchemaViolation, SponsoredApiJob, SponsoredApiJobRow, WEBHOOK_DELIVERY_HEADER,
+    WEBHOOK_EVENT_HEADER, WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER,
+};
+use crate::utils::UpstreamRequest;
+use crate::webhooks::sign_webhook_payload;
+
+/// Jobs claimed again after this many interrupted runs are failed instead.
+pub const MAX_JOB_ATTEMPTS: i32 = 3;
+const CALLBACK_ATTEMPTS: u32 = 3;
+const CALLBACK_TIMEOUT_SECS: u64 = 10;
+const MAX_ERROR_LEN: usize = 500;
+
+const JOB_SELECT: &str = r#"
+    select j.id, j.sponsored_api_id, j.status, j.payment_mode, j.amount_cents, j.sponsored_by,
+        j.tx_hash, c.amount_cents as charged_cents, j.callback_url, j.callback_status,
+        j.upstream_status, j.upstream_body, j.truncated, j.error, j.created_at, j.started_at,
+        j.completed_at, j.expires_at
+    from sponsored_api_jobs j
+    left join sponsored_api_calls c on c.id = j.call_id
+"#;
+
+/// A run accepted in async mode, already charged and recorded in the call log.
+pub struct NewJob {
+    pub id: Uuid,
+    pub sponsored_api_id: Uuid,
+    pub call_id: Uuid,
+    pub budget_pool_id: Option<Uuid>,
+    pub caller: Option<String>,
+    pub payment_mode: String,
+    pub amount_cents: u64,
+    pub sponsored_by: Option<String>,
+    pub tx_hash: Option<String>,
+    pub upstream: UpstreamRequest,
+    pub callback_url: Option<String>,
+    pub callback_secret: Option<String>,
+}
+
+#[derive(Debug, sqlx::FromRow)]
+pub struct ClaimedJob {
+    pub id: Uuid,
+    pub sponsored_api_id: Uuid,
+    pub call_id: Uuid,
+    pub budget_pool_id: Option<Uuid>,
+    pub payment_mode: String,
+    pub amount_cents: i64,
+    pub upstream_url: String,
+    pub upstream_input: Value,
+    pub forwarded_headers: DbJson<HashMap<String, String>>,
+    pub callback_url: Option<String>,
+    pub callback_secret: Option<String>,
+    pub attempts: i32,
+}
+
+impl ClaimedJob {
+    pub fn upstream_request(&self, keyring: &SecretKeyring) -> ApiResult<UpstreamRequest> {
+        let forwarded_headers = keyring
+            .open_headers(&self.forwarded_headers)?
+            .into_iter()
+            .filter_map(|(name, value)| {
+                Some((
+                    HeaderName::from_bytes(name.as_bytes()).ok()?,
+                    HeaderValue::from_str(&value).ok()?,
+                ))
+            })
+            .collect();
+        Ok(UpstreamRequest {
+            url: self.upstream_url.clone(),
+            input: self.upstream_input.clone(),
+            forwarded_headers,
+        })
+    }
+}
+
+/// How a job ended, as stored for polling and callbacks.
+pub enum JobResult {
+    Succeeded {
+        upstream_status: u16,
+        body: String,
+        truncated: bool,
+        /// Stored in the job's `error` as an `invalid_upstream_output` envelope.
+        output_violations: Vec<SchemaViolation>,
+    },
+    Failed {
+        /// Set when the upstream answered but the reply could not be used, e.g. it was cut off.
+        upstream_status: Option<u16>,
+        error: ApiError,
+    },
+}
+
+/// Forwarded caller headers may carry credentials, so they are sealed at rest whenever
+/// upstream secret keys are configured.
+pub async fn insert_job(
+    db: &PgPool,
+    keyring: &SecretKeyring,
+    job: NewJob,
+) -> ApiResult<SponsoredApiJob> {
+    let forwarded_headers: HashMap<String, String> = job
+        .upstream
+        .forwarded_headers
+        .iter()
+        .filter_map(|(name, value)| {
+            Some((name.as_str().to_string(), value.to_str().ok()?.to_string()))
+        })
+        .collect();
+    let forwarded_headers = keyring.store_headers(&forwarded_headers)?;
+
+    sqlx::query(
+        r#"
+        insert into sponsored_api_jobs (
+            id, sponsored_api_id, call_id, budget_pool_id, caller, payment_mode, amount_cents,
+            sponsored_by, tx_hash, upstream_url, upstream_input, forwarded_headers, callback_url,
+            callback_secret, created_at
+        ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+        "#,
+    )
+    .bind(job.id)
+    .bind(job.sponsored_api_id)
+    .bind(job.call_id)
+    .bind(job.budget_pool_id)
+    .bind(job.caller)
+    .bind(job.payment_mode)
+    .bind(job.amount_cents as i64)
+    .bind(job.sponsored_by)
+    .bind(job.tx_hash)
+    .bind(job.upstream.url)
+    .bind(job.upstream.input)
+    .bind(DbJson(forwarded_headers))
+    .bind(job.callback_url)
+    .bind(job.callback_secret)
+    .bind(Utc::now())
+    .execute(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    load_job(db, job.id).await
+}
+
+/// Finished jobs disappear once their retention period is over.
+pub async fn load_job(db: &PgPool, job_id: Uuid) -> ApiResult<SponsoredApiJob> {
+    let row = sqlx::query_as::<_, SponsoredApiJobRow>(&format!(
+        "{JOB_SELECT} where j.id = $1 and (j.expires_at is null or j.expires_at > now())"
+    ))
+    .bind(job_id)
+    .fetch_optional(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .ok_or_else(|| ApiError::not_found("job not found"))?;
+
+    SponsoredApiJob::try_from(row)
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))
+}
+
+/// Claims queued jobs, and running ones whose worker went away, by leasing them so
+/// concurrent workers skip them.
+pub async fn claim_due_jobs(
+    db: &PgPool,
+    limit: usize,
+    lease_secs: u64,
+) -> ApiResult<Vec<ClaimedJob>> {
+    sqlx::query_as::<_, ClaimedJob>(
+        r#"
+        update sponsored_api_jobs
+        set status = 'running', attempts = attempts + 1,
+            lease_until = now() + make_interval(secs => $2),
+            started_at = coalesce(started_at, now())
+        where id in (
+            select id from sponsored_api_jobs
+            where status = 'queued' or (status = 'running' and lease_until <= now())
+            order by created_at
+            limit $1
+            for update skip locked
+        )
+        returning id, sponsored_api_id, call_id, budget_pool_id, payment_mode, amount_cents,
+            upstream_url, upstream_input, forwarded_headers, callback_url, callback_secret,
+            attempts
+        "#,
+    )
+    .bind(i64::try_from(limit).unwrap_or(i64::MAX))
+    .bind(lease_secs as f64)
+    .fetch_all(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
+
+/// Stores the result, starts the retention clock and drops the forwarded caller headers.
+pub async fn finish_job(
+    db: &PgPool,
+    job_id: Uuid,
+    result: JobResult,
+    retention_secs: u64,
+) -> ApiResult<SponsoredApiJob> {
+    let (status, upstream_status, body, truncated, error) = match result {
+        JobResult::Succeeded {
+            upstream_status,
+            body,
+            truncated,
+            output_violations,
+        } => (
+            JobStatus::Succeeded,
+            Some(i32::from(upstream_status)),
+            Some(body),
+            truncated,
+            (!output_violations.is_empty())
+                .then(|| ApiError::invalid_output(output_violations).to_json()),
+        ),
+        JobResult::Failed {
+            upstream_status,
+            error,
+        } => (
+            JobStatus::Failed,
+            upstream_status.map(i32::from),
+            None,
+            false,
+            Some(error.to_json()),
+        ),
+    };
+
+    sqlx::query(
+        r#"
+        update sponsored_api_jobs
+        set status = $2, upstream_status = $3, upstream_body = $4, truncated = $5, error = $6,
+            forwarded_headers = '{}'::jsonb, lease_until = null, completed_at = now(),
+            expires_at = now() + make_interval(secs => $7)
+        where id = $1
+        "#,
+    )
+    .bind(job_id)
+    .bind(status)
+    .bind(upstream_status)
+    .bind(body)
+    .bind(truncated)
+    .bind(error)
+    .bind(retention_secs as f64)
+    .execute(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    load_job(db, job_id).await
+}
+
+/// Posts the finished job to its callback URL, signed like a sponsor webhook, retrying a
+/// few times. Clients that miss it can still poll `GET /jobs/{id}`.
+pub async fn deliver_job_callback(
+    db: &PgPool,
+    http: &reqwest::Client,
+    job: &SponsoredApiJob,
+    url: &str,
+    secret: &str,
+) {
+    let event = match job.status {
+        JobStatus::Succeeded => "job.succeeded",
+        _ => "job.failed",
+    };
+    let body = match serde_json::to_vec(job) {
+        Ok(body) => body,
+        Err(err) => {
+            warn!("failed to encode callback for job {}: {err}", job.id);
+            return;
+        }
+    };
+
+    let mut last_error = String::new();
+    for attempt in 0..CALLBACK_ATTEMPTS {
+        if attempt > 0 {
+            tokio::time::sleep(Duration::from_secs(1 << attempt)).await;
+        }
+        let timestamp = Utc::now().timestamp();
+        let outcome = http
+            .post(url)
+            .timeout(Duration::from_secs(CALLBACK_TIMEOUT_SECS))
+            .header("content-type", "application/json")
+            .header(
+                WEBHOOK_SIGNATURE_HEADER,
+                sign_webhook_payload(secret, timestamp, &body),
+            )
+            .header(WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string())
+            .header(WEBHOOK_EVENT_HEADER, event)
+            .header(WEBHOOK_DELIVERY_HEADER, job.id.to_string())
+            .body(body.clone())
+            .send()
+            .await;
+        match outcome {
+            Ok(response) if response.status().is_success() => {
+                last_error.clear();
+                break;
+            }
+            Ok(response) => last_error = format!("endpoint responded with {}", response.status()),
+            Err(err) => last_error = err.to_string(),
+        }
+    }
+
+    let status = if last_error.is_empty() {
+        "delivered".to_string()
+    } else {
+        let error: String = last_error.chars().take(MAX_ERROR_LEN).collect();
+        format!("failed: {error}")
+    };
+    if let Err(err) =
+        sqlx::query("update sponsored_api_jobs set callback_status = $2 where id = $1")
+            .bind(job.id)
+            .bind(status)
+            .execute(db)
+            .await
+    {
+        warn!("failed to record callback status for job {}: {err}", job.id);
+    }
+}
+
+pub async fn purge_expired_jobs(db: &PgPool) -> ApiResult<u64> {
+    sqlx::query("delete from sponsored_api_jobs where expires_at <= now()")
+        .execute(db)
+        .await
+        .map(|result| result.rows_affected())
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
@@ -1,66 +1,227 @@
+mod api_docs;
+mod budget;
+mod cache;
+mod call_log;
+mod discovery;
+mod eligibility;
 mod error;
+mod health;
+mod jobs;
+mod mcp;
+mod network;
 mod onchain;
+mod openapi;
+mod ratelimit;
+mod schema;
+mod secrets;
+mod streaming;
+mod transform;
 mod types;
 mod utils;
+mod verification;
+mod webhooks;
 
 use axum::{
-    Json, Router,
-    extract::{Path, State},
+    Extension, Json, Router,
+    body::Bytes,
+    extract::{Path, Query, State},
     http::{HeaderMap, HeaderName, HeaderValue, Method, StatusCode, header},
-    response::{IntoResponse, Response},
-    routing::{get, post},
+    middleware,
+    response::{Html, IntoResponse, Response},
+    routing::{any, delete, get, post, put},
 };
 use chrono::Utc;
+use futures_util::StreamExt;
 use prometheus::{Encoder, TextEncoder};
+use serde_json::Value;
 use sqlx::types::Json as DbJson;
-use std::{net::SocketAddr, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc, time::Duration};
 use tokio::sync::RwLock;
 use tower_http::cors::{AllowOrigin, Any, CorsLayer};
 use tracing::info;
 use uuid::Uuid;
 
+use crate::api_docs::{docs_page, openapi_document};
+use crate::budget::{
+    BudgetDraw, draw_api_budget, draw_campaign_budget, funded_sponsored_api_ids,
+    load_fundable_campaigns, release_api_budget,
+};
+use crate::cache::{ResponseCache, cache_key, cache_ttl};
+use crate::call_log::{classify_call, decode_cursor, encode_cursor, render_csv, render_ndjson};
+use crate::discovery::{manifest, paginate, service_resources, sponsored_api_resource};
+use crate::eligibility::{ineligibility_reason, validate_eligibility_policy};
 use crate::error::{ApiError, ApiResult};
+use crate::health::{HealthRegistry, run_health_prober};
+use crate::jobs::{
+    ClaimedJob, JobResult, MAX_JOB_ATTEMPTS, NewJob, claim_due_jobs, deliver_job_callback,
+    finish_job, insert_job, load_job, purge_expired_jobs,
+};
+use crate::mcp::{
+    INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, McpTool, PARSE_ERROR,
+    RpcError, RpcRequest, RpcResponse, SponsoredToolArguments, ToolCallParams, builtin_tool,
+    initialize_result, sponsored_api_tool, tool_error, tool_result,
+};
+use crate::ratelimit::{ClientKey, RateLimiter, rate_limit_layer, run_rate_limit_pruner};
+use crate::schema::{
+    output_violations, service_batch_payment_schema, service_payment_schema,
+    sponsored_api_batch_payment_schema, sponsored_api_payment_schema, validate_input,
+    validate_proxy_body, validate_schemas, violations as schema_violations,
+};
+use crate::secrets::reseal_upstream_secrets;
+use crate::streaming::{MeteredStream, StreamOutcome, StreamReport, collect_metered};
+use crate::transform::{
+    filter_response, transform_request, truncate_response, validate_transform_policy,
+};
 use crate::types::*;
 use crate::utils::*;
+use crate::verification::{
+    WALLET_ADDRESS_ATTRIBUTE, evaluate_task_proof, generate_task_secret, oauth_callback_payload,
+    oauth_state, parse_oauth_state, validate_task_verification, verify_task_signature,
+};
+use crate::webhooks::{
+    SponsorEvent, budget_events, emit_sponsor_events, redeliver, run_webhook_dispatcher,
+};
+
+const MAX_DASHBOARD_BUCKETS: i64 = 2_000;
+const MAX_PROXY_REQUEST_BYTES: usize = 10 * 1024 * 1024;
 
 fn build_app(state: SharedState) -> Router {
     Router::new()
         .route("/health", get(health))
         .route("/profiles", post(create_profile).get(list_profiles))
+        .route(
+            "/profiles/{user_id}",
+            get(get_profile)
+                .patch(update_profile)
+                .delete(delete_profile),
+        )
+        .route("/profiles/{user_id}/export", get(export_profile))
         .route("/register", post(register_user))
         .route("/campaigns", post(create_campaign).get(list_campaigns))
         .route("/campaigns/discovery", get(list_campaign_discovery))
+        .route("/.well-known/x402", get(x402_manifest))
+        .route("/discovery/resources", get(list_discovery_resources))
         .route("/campaigns/{campaign_id}", get(get_campaign))
         .route("/tasks/complete", post(complete_task))
+        .route("/tasks/oauth/callback", get(task_oauth_callback))
+        .route("/tasks/{completion_id}", get(get_task_completion))
+        .route("/tasks/{completion_id}/callback", post(task_callback))
         .route("/tool/{service}/run", post(run_tool))
+        .route("/tool/{service}/run-batch", post(run_tool_batch))
+        .route("/mcp", post(mcp))
         .route("/proxy/{service}/run", post(run_proxy))
         .route(
             "/sponsored-apis",
             post(create_sponsored_api).get(list_sponsored_apis),
         )
-        .route("/sponsored-apis/{api_id}", get(get_sponsored_api))
+        .route("/sponsored-apis/import", post(import_sponsored_apis))
+        .route(
+            "/budget-pools",
+            post(create_budget_pool).get(list_budget_pools),
+        )
+        .route("/budget-pools/{pool_id}", get(get_budget_pool))
+        .route("/budget-pools/{pool_id}/top-up", post(top_up_budget_pool))
+        .route(
+            "/sponsored-apis/{api_id}",
+            get(get_sponsored_api)
+                .patch(update_sponsored_api)
+                .delete(delete_sponsored_api),
+        )
+        .route(
+            "/sponsored-apis/{api_id}/headers",
+            put(rotate_sponsored_api_headers),
+        )
+        .route("/sponsored-apis/{api_id}/pause", post(pause_sponsored_api))
+        .route(
+            "/sponsored-apis/{api_id}/resume",
+            post(resume_sponsored_api),
+        )
+        .route(
+            "/sponsored-apis/{api_id}/top-up",
+            post(top_up_sponsored_api),
+        )
+        .route(
+            "/sponsored-apis/{api_id}/audit",
+            get(list_sponsored_api_audit),
+        )
+        .route(
+            "/sponsored-apis/{api_id}/calls",
+            get(list_sponsored_api_calls),
+        )
         .route("/sponsored-apis/{api_id}/run", post(run_sponsored_api))
+        .route(
+            "/sponsored-apis/{api_id}/run-batch",
+            post(run_sponsored_api_batch),
+        )
+        .route("/jobs/{job_id}", get(get_job))
+        .route(
+            "/sponsored-apis/{api_id}/transform/preview",
+            post(preview_sponsored_api_transform),
+        )
+        .route(
+            "/sponsored-apis/{api_id}/proxy",
+            any(proxy_sponsored_api_root),
+        )
+        .route(
+            "/sponsored-apis/{api_id}/proxy/{*path}",
+            any(proxy_sponsored_api),
+        )
         .route(
             "/webhooks/x402scan/settlement",
             post(ingest_x402scan_settlement),
         )
+        .route(
+            "/sponsor-webhooks",
+            post(create_sponsor_webhook).get(list_sponsor_webhooks),
+        )
+        .route(
+            "/sponsor-webhooks/{webhook_id}",
+            delete(delete_sponsor_webhook),
+        )
+        .route(
+            "/sponsor-webhooks/{webhook_id}/deliveries",
+            get(list_webhook_deliveries),
+        )
+        .route(
+            "/sponsor-webhooks/deliveries/{delivery_id}/redeliver",
+            post(redeliver_webhook),
+        )
         .route("/dashboard/sponsor/{campaign_id}", get(sponsor_dashboard))
+        .route(
+            "/dashboard/sponsor/{campaign_id}/timeseries",
+            get(sponsor_timeseries),
+        )
         .route("/creator/metrics/event", post(record_creator_metric_event))
         .route("/creator/metrics", get(creator_metrics))
         .route("/metrics", get(prometheus_metrics))
+        .route("/openapi.json", get(openapi_spec))
+        .route("/docs", get(api_docs_page))
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            rate_limit_layer,
+        ))
         .layer(cors_layer_from_env())
         .with_state(state)
 }
 
 fn cors_layer_from_env() -> CorsLayer {
     let layer = CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
         .allow_headers([
             header::CONTENT_TYPE,
             header::ACCEPT,
             header::AUTHORIZATION,
             HeaderName::from_static(PAYMENT_SIGNATURE_HEADER),
             HeaderName::from_static(X402_VERSION_HEADER),
+            HeaderName::from_static(TASK_SIGNATURE_HEADER),
+            HeaderName::from_static(SPONSORED_CALLER_HEADER),
         ]);
 
     let configured = std::env::var("CORS_ALLOW_ORIGINS").unwrap_or_else(|_| "*".to_string());
@@ -93,8 +254,12 @@ async fn main() {
         .compact()
         .init();
 
+    let app_state = AppState::new().unwrap_or_else(|err| {
+        eprintln!("invalid configuration: {err}");
+        std::process::exit(1);
+    });
     let state = SharedState {
-        inner: Arc::new(RwLock::new(AppState::new())),
+        inner: Arc::new(RwLock::new(app_state)),
     };
 
     if let Some(db) = {
@@ -109,6 +274,33 @@ async fn main() {
         if let Err(err) = load_campaigns_from_db(&state).await {
             eprintln!("failed to load campaigns from database: {err}");
         }
+
+        let keyring = {
+            let state = state.inner.read().await;
+            state.config.upstream_secret_keys.clone()
+        };
+        match reseal_upstream_secrets(&db, &keyring).await {
+            Ok(report) => {
+                if report.resealed > 0 {
+                    info!(
+                        "re-sealed upstream secrets for {} sponsored APIs",
+                        report.resealed
+                    );
+                }
+                if report.failed > 0 {
+                    eprintln!(
+                        "could not re-seal upstream secrets for {} sponsored APIs",
+                        report.failed
+                    );
+                }
+            }
+            Err(err) => eprintln!("failed to re-seal upstream secrets: {err}"),
+        }
+
+        tokio::spawn(run_webhook_dispatcher(state.clone()));
+        tokio::spawn(run_rate_limit_pruner(state.clone()));
+        tokio::spawn(run_health_prober(state.clone()));
+        tokio::spawn(run_job_worker(state.clone()));
     }
 
     let app = build_app(state);
@@ -124,7 +316,12 @@ async fn main() {
         .await
         .expect("bind should succeed");
 
-    if let Err(err) = axum::serve(listener, app).await {
+    if let Err(err) = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    {
         eprintln!("server error: {err}");
     }
 }
@@ -147,6 +344,48 @@ async fn create_profile(
     State(state): State<SharedState>,
     Json(payload): Json<CreateUserRequest>,
 ) -> Response {
+    save_profile(state, payload, "/profiles").await
+}
+
+async fn list_profiles(State(state): State<SharedState>) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<Vec<UserProfile>>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let profiles = sqlx::query_as::<_, UserProfile>(
+            r#"
+            select id, email, region, roles, tools_used, attributes, created_at
+            from users
+            order by created_at desc
+            "#,
+        )
+        .fetch_all(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+        Ok((StatusCode::OK, Json(profiles)))
+    }
+    .await;
+
+    respond(&metrics, "/profiles", result)
+}
+
+async fn register_user(
+    State(state): State<SharedState>,
+    Json(payload): Json<CreateUserRequest>,
+) -> Response {
+    save_profile(state, payload, "/register").await
+}
+
+async fn save_profile(state: SharedState, payload: CreateUserRequest, endpoint: &str) -> Response {
     let metrics = {
         let state = state.inner.read().await;
         state.metrics.clone()
@@ -159,149 +398,264 @@ async fn create_profile(
         }
         .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
 
-        if payload.email.trim().is_empty() {
-            return Err(ApiError::validation("email is required"));
+        let (profile, inserted) = upsert_user_profile(&db, payload).await?;
+        let status = if inserted {
+            StatusCode::CREATED
+        } else {
+            StatusCode::OK
+        };
+
+        Ok((status, Json(profile)))
+    }
+    .await;
+
+    respond(&metrics, endpoint, result)
+}
+
+async fn get_profile(State(state): State<SharedState>, Path(user_id): Path<Uuid>) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<UserProfile>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
         }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
 
-        if payload.region.trim().is_empty() {
-            return Err(ApiError::validation("region is required"));
+        let profile = load_user_profile(&db, user_id).await?;
+        Ok((StatusCode::OK, Json(profile)))
+    }
+    .await;
+
+    respond(&metrics, "/profiles/:user_id", result)
+}
+
+async fn update_profile(
+    State(state): State<SharedState>,
+    Path(user_id): Path<Uuid>,
+    Json(payload): Json<UpdateUserProfileRequest>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<UserProfile>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
         }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
 
-        let profile = UserProfile {
-            id: Uuid::new_v4(),
-            email: payload.email,
-            region: payload.region,
-            roles: payload.roles,
-            tools_used: payload.tools_used,
-            attributes: payload.attributes,
-            created_at: Utc::now(),
-        };
+        if payload.roles.is_none() && payload.tools_used.is_none() && payload.attributes.is_none() {
+            return Err(ApiError::validation(
+                "at least one of roles, tools_used or attributes is required",
+            ));
+        }
 
-        let inserted = sqlx::query_as::<_, UserProfile>(
+        let updated = sqlx::query_as::<_, UserProfile>(
             r#"
-            insert into users (id, email, region, roles, tools_used, attributes, created_at)
-            values ($1, $2, $3, $4, $5, $6, $7)
+            update users
+            set roles = coalesce($2, roles),
+                tools_used = coalesce($3, tools_used),
+                attributes = coalesce($4, attributes)
+            where id = $1
             returning id, email, region, roles, tools_used, attributes, created_at
             "#,
         )
-        .bind(profile.id)
-        .bind(profile.email)
-        .bind(profile.region)
-        .bind(profile.roles)
-        .bind(profile.tools_used)
-        .bind(DbJson(profile.attributes))
-        .bind(profile.created_at)
-        .fetch_one(&db)
+        .bind(user_id)
+        .bind(payload.roles)
+        .bind(payload.tools_used)
+        .bind(payload.attributes.map(DbJson))
+        .fetch_optional(&db)
         .await
-        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .ok_or_else(|| ApiError::not_found("user not found"))?;
 
-        Ok((StatusCode::CREATED, Json(inserted)))
+        Ok((StatusCode::OK, Json(updated)))
     }
     .await;
 
-    respond(&metrics, "/profiles", result)
+    respond(&metrics, "/profiles/:user_id", result)
 }
 
-async fn list_profiles(State(state): State<SharedState>) -> Response {
+async fn export_profile(State(state): State<SharedState>, Path(user_id): Path<Uuid>) -> Response {
     let metrics = {
         let state = state.inner.read().await;
         state.metrics.clone()
     };
 
-    let result: ApiResult<(StatusCode, Json<Vec<UserProfile>>)> = async {
+    let result: ApiResult<(StatusCode, Json<UserDataExport>)> = async {
         let db = {
             let state = state.inner.read().await;
             state.db.clone()
         }
         .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
 
-        let profiles = sqlx::query_as::<_, UserProfile>(
+        let profile = load_user_profile(&db, user_id).await?;
+
+        let task_completions = sqlx::query_as::<_, TaskCompletion>(
             r#"
-            select id, email, region, roles, tools_used, attributes, created_at
-            from users
-            order by created_at desc
+            select id, campaign_id, user_id, task_name, details, status, evidence,
+                rejection_reason, verified_at, created_at
+            from task_completions
+            where user_id = $1
+            order by created_at asc
             "#,
         )
+        .bind(user_id)
         .fetch_all(&db)
         .await
         .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
 
-        Ok((StatusCode::OK, Json(profiles)))
+        let payments = sqlx::query_as::<_, PaymentRow>(
+            r#"
+            select tx_hash, campaign_id, user_id, service, amount_cents, payer, source, status,
+                created_at
+            from payments
+            where user_id = $1
+            order by created_at asc
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .into_iter()
+        .map(PaymentRecord::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+
+        // Sponsored API callers are free-form, so match both the profile id and email.
+        let sponsored_api_calls = sqlx::query_as::<_, SponsoredApiCallRow>(&format!(
+            r#"
+            select {SPONSORED_API_CALL_COLUMNS}
+            from sponsored_api_calls
+            where caller = $1 or caller = $2
+            order by created_at asc
+            "#
+        ))
+        .bind(user_id.to_string())
+        .bind(&profile.email)
+        .fetch_all(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .into_iter()
+        .map(SponsoredApiCall::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+
+        Ok((
+            StatusCode::OK,
+            Json(UserDataExport {
+                profile,
+                task_completions,
+                payments,
+                sponsored_api_calls,
+                exported_at: Utc::now(),
+            }),
+        ))
     }
     .await;
 
-    respond(&metrics, "/profiles", result)
+    respond(&metrics, "/profiles/:user_id/export", result)
 }
 
-async fn register_user(
-    State(state): State<SharedState>,
-    Json(payload): Json<CreateUserRequest>,
-) -> Response {
+async fn delete_profile(State(state): State<SharedState>, Path(user_id): Path<Uuid>) -> Response {
     let metrics = {
         let state = state.inner.read().await;
         state.metrics.clone()
     };
 
-    let result: ApiResult<(StatusCode, Json<UserProfile>)> = async {
+    let result: ApiResult<(StatusCode, Json<MessageResponse>)> = async {
         let db = {
             let state = state.inner.read().await;
             state.db.clone()
         }
         .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
 
-        if payload.email.trim().is_empty() {
-            return Err(ApiError::validation("email is required"));
-        }
+        let profile = load_user_profile(&db, user_id).await?;
 
-        if payload.region.trim().is_empty() {
-            return Err(ApiError::validation("region is required"));
-        }
+        let mut tx = db.begin().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
 
-        let profile = UserProfile {
-            id: Uuid::new_v4(),
-            email: payload.email,
-            region: payload.region,
-            roles: payload.roles,
-            tools_used: payload.tools_used,
-            attributes: payload.attributes,
-            created_at: Utc::now(),
-        };
+        // Ledger rows are kept for accounting; only the link to the person is removed.
+        sqlx::query("update task_completions set details = null where user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| {
+                ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+            })?;
 
-        let inserted = sqlx::query_as::<_, UserProfile>(
-            r#"
-            insert into users (id, email, region, roles, tools_used, attributes, created_at)
-            values ($1, $2, $3, $4, $5, $6, $7)
-            returning id, email, region, roles, tools_used, attributes, created_at
-            "#,
+        sqlx::query(
+            "update sponsored_api_calls set caller = null where caller = $1 or caller = $2",
         )
-        .bind(profile.id)
-        .bind(profile.email)
-        .bind(profile.region)
-        .bind(profile.roles)
-        .bind(profile.tools_used)
-        .bind(DbJson(profile.attributes))
-        .bind(profile.created_at)
-        .fetch_one(&db)
+        .bind(user_id.to_string())
+        .bind(&profile.email)
+        .execute(&mut *tx)
         .await
         .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
 
-        Ok((StatusCode::CREATED, Json(inserted)))
+        sqlx::query(
+            "update payments set payer = 'anonymized' where user_id = $1 and source = 'user'",
+        )
+        .bind(user_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+        // Foreign keys null out task_completions.user_id and payments.user_id.
+        sqlx::query("delete from users where id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|err| {
+                ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+            })?;
+
+        tx.commit().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+
+        Ok((
+            StatusCode::OK,
+            Json(MessageResponse {
+                message: "profile deleted; ledger history anonymized".to_string(),
+            }),
+        ))
     }
     .await;
 
-    respond(&metrics, "/register", result)
+    respond(&metrics, "/profiles/:user_id", result)
+}
+
+async fn load_user_profile(db: &sqlx::PgPool, user_id: Uuid) -> ApiResult<UserProfile> {
+    sqlx::query_as::<_, UserProfile>(
+        "select id, email, region, roles, tools_used, attributes, created_at from users where id = $1",
+    )
+    .bind(user_id)
+    .fetch_optional(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .ok_or_else(|| ApiError::not_found("user not found"))
 }
 
 async fn create_campaign(
     State(state): State<SharedState>,
     Json(payload): Json<CreateCampaignRequest>,
 ) -> Response {
-    let (metrics, db, public_base_url) = {
+    let (metrics, db, public_base_url, upstream_policy) = {
         let state = state.inner.read().await;
         (
             state.metrics.clone(),
             state.db.clone(),
             state.config.public_base_url.clone(),
+            state.config.upstream_policy.clone(),
         )
     };
 
@@ -322,15 +676,50 @@ async fn create_campaign(
                 "subsidy_per_call_cents must be greater than 0",
             ));
         }
-        if payload.budget_cents == 0 {
-            return Err(ApiError::validation("budget_cents must be greater than 0"));
+        match payload.budget_pool_id {
+            Some(pool_id) => {
+                if payload.budget_cents > 0 {
+                    return Err(ApiError::validation(
+                        "budget_cents must be 0 when the campaign draws on a budget pool",
+                    ));
+                }
+                let pool = load_budget_pool(&db, pool_id).await?;
+                if pool.sponsor != payload.sponsor {
+                    return Err(ApiError::validation(
+                        "budget pool belongs to a different sponsor",
+                    ));
+                }
+            }
+            None if payload.pool_limit_cents.is_some() => {
+                return Err(ApiError::validation(
+                    "pool_limit_cents requires budget_pool_id",
+                ));
+            }
+            None if payload.budget_cents == 0 => {
+                return Err(ApiError::validation("budget_cents must be greater than 0"));
+            }
+            None => {}
         }
+        let pool_limit_cents = payload
+            .pool_limit_cents
+            .map(|limit| {
+                i64::try_from(limit)
+                    .map_err(|_| ApiError::validation("pool_limit_cents is too large"))
+            })
+            .transpose()?;
 
         for url in &payload.query_urls {
             reqwest::Url::parse(url)
                 .map_err(|_| ApiError::validation(format!("invalid query URL: {url}")))?;
         }
+        validate_task_verification(&payload.task_verification)?;
+        if let TaskVerification::OnchainAction { rpc_url, .. } = &payload.task_verification {
+            upstream_policy
+                .validate_field("task_verification.rpc_url", rpc_url)
+                .await?;
+        }
 
+        let task_secret = generate_task_secret();
         let candidate = Campaign {
             id: Uuid::new_v4(),
             name: payload.name,
@@ -342,22 +731,25 @@ async fn create_campaign(
             budget_total_cents: payload.budget_cents,
             budget_remaining_cents: payload.budget_cents,
             query_urls: payload.query_urls,
+            task_verification: payload.task_verification,
             active: true,
+            budget_pool_id: payload.budget_pool_id,
+            pool_limit_cents: payload.pool_limit_cents,
+            pool_spent_cents: 0,
             created_at: Utc::now(),
         };
 
-        let row = sqlx::query_as::<_, CampaignRow>(
+        let row = sqlx::query_as::<_, CampaignRow>(&format!(
             r#"
             insert into campaigns (
                 id, name, sponsor, target_roles, target_tools, required_task,
                 subsidy_per_call_cents, budget_total_cents, budget_remaining_cents,
-                query_urls, active, created_at
-            ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
-            returning id, name, sponsor, target_roles, target_tools, required_task,
-                subsidy_per_call_cents, budget_total_cents, budget_remaining_cents,
-                query_urls, active, created_at
-            "#,
-        )
+                query_urls, task_verification, active, created_at, task_secret,
+                budget_pool_id, pool_limit_cents
+            ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            returning {CAMPAIGN_COLUMNS}
+            "#
+        ))
         .bind(candidate.id)
         .bind(candidate.name)
         .bind(candidate.sponsor)
@@ -368,8 +760,12 @@ async fn create_campaign(
         .bind(candidate.budget_total_cents as i64)
         .bind(candidate.budget_remaining_cents as i64)
         .bind(candidate.query_urls)
+        .bind(DbJson(candidate.task_verification))
         .bind(candidate.active)
         .bind(candidate.created_at)
+        .bind(&task_secret)
+        .bind(candidate.budget_pool_id)
+        .bind(pool_limit_cents)
         .fetch_one(&db)
         .await
         .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
@@ -382,6 +778,7 @@ async fn create_campaign(
             campaign: campaign.clone(),
             campaign_url: format!("{base}/campaigns/{}", campaign.id),
             dashboard_url: format!("{base}/dashboard/sponsor/{}", campaign.id),
+            task_secret,
         };
 
         Ok((StatusCode::CREATED, Json(response)))
@@ -451,8 +848,14 @@ async fn list_campaign_discovery(State(state): State<SharedState>) -> Response {
                 sponsor: campaign.sponsor,
                 active: campaign.active,
                 query_urls: campaign.query_urls,
-                service_run_url: format!("{base}/proxy/:service/run"),
+                service_run_url: format!("{base}/discovery/resources?type=proxy"),
+                service_run_urls: BUILTIN_SERVICES
+                    .iter()
+                    .map(|service| format!("{base}/proxy/{service}/run"))
+                    .collect(),
+                service_schema: service_payment_schema(),
                 sponsored_api_discovery_url: format!("{base}/sponsored-apis"),
+                resources_url: format!("{base}/discovery/resources?type=proxy"),
             })
             .collect();
         rows.sort_by_key(|item| item.name.clone());
@@ -463,92 +866,175 @@ async fn list_campaign_discovery(State(state): State<SharedState>) -> Response {
     respond(&metrics, "/campaigns/discovery", result)
 }
 
-async fn load_campaigns_from_db(state: &SharedState) -> ApiResult<Vec<Campaign>> {
-    let db = {
+async fn x402_manifest(State(state): State<SharedState>) -> Response {
+    let (metrics, config) = {
         let state = state.inner.read().await;
-        state.db.clone()
-    }
-    .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
-
-    let rows = sqlx::query_as::<_, CampaignRow>(
-        r#"
-        select id, name, sponsor, target_roles, target_tools, required_task,
-            subsidy_per_call_cents, budget_total_cents, budget_remaining_cents,
-            query_urls, active, created_at
-        from campaigns
-        order by created_at desc
-        "#,
-    )
-    .fetch_all(&db)
-    .await
-    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+        (state.metrics.clone(), state.config.clone())
+    };
 
-    let campaigns: Vec<Campaign> = rows
-        .into_iter()
-        .map(Campaign::try_from)
-        .collect::<Result<Vec<_>, _>>()
-        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+    let result: ApiResult<(StatusCode, Json<X402Manifest>)> = async {
+        let resources = load_discovery_resources(&state).await?;
+        Ok((StatusCode::OK, Json(manifest(&config, &resources))))
+    }
+    .await;
 
-    Ok(campaigns)
+    respond(&metrics, "/.well-known/x402", result)
 }
 
-async fn complete_task(
+async fn list_discovery_resources(
     State(state): State<SharedState>,
-    Json(payload): Json<TaskCompletionRequest>,
+    Query(query): Query<DiscoveryQuery>,
 ) -> Response {
     let metrics = {
         let state = state.inner.read().await;
         state.metrics.clone()
     };
 
-    let result: ApiResult<(StatusCode, Json<TaskCompletion>)> = async {
+    let result: ApiResult<(StatusCode, Json<DiscoveryResourcePage>)> = async {
+        let resources = load_discovery_resources(&state).await?;
+        Ok((StatusCode::OK, Json(paginate(resources, &query))))
+    }
+    .await;
+
+    respond(&metrics, "/discovery/resources", result)
+}
+
+/// Every paid resource: the built-in tools and proxy services, then sponsored APIs newest
+/// first. Free APIs need no payment and are left out. Without Postgres there are no
+/// sponsors, so only the built-in services are listed.
+async fn load_discovery_resources(state: &SharedState) -> ApiResult<Vec<DiscoveryResource>> {
+    let (db, config, services) = {
+        let state = state.inner.read().await;
+        let services: Vec<(&str, u64)> = BUILTIN_SERVICES
+            .iter()
+            .map(|service| (*service, state.service_price(service)))
+            .collect();
+        (state.db.clone(), state.config.clone(), services)
+    };
+
+    let mut resources = Vec::new();
+    for (service, price) in services {
+        let mut sponsors = match &db {
+            Some(db) => load_fundable_campaigns(db, price)
+                .await?
+                .into_iter()
+                .map(|campaign| campaign.sponsor)
+                .collect(),
+            None => Vec::new(),
+        };
+        sponsors.sort();
+        sponsors.dedup();
+        resources.extend(service_resources(&config, service, price, sponsors)?);
+    }
+
+    if let Some(db) = &db {
+        let funded = funded_sponsored_api_ids(db).await?;
+        for api in load_callable_sponsored_apis(db).await? {
+            if api.price_cents > 0 {
+                resources.push(sponsored_api_resource(
+                    &config,
+                    &api,
+                    funded.contains(&api.id),
+                )?);
+            }
+        }
+    }
+    Ok(resources)
+}
+
+async fn load_campaigns_from_db(state: &SharedState) -> ApiResult<Vec<Campaign>> {
+    let db = {
+        let state = state.inner.read().await;
+        state.db.clone()
+    }
+    .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+    let rows = sqlx::query_as::<_, CampaignRow>(&format!(
+        r#"
+        select {CAMPAIGN_COLUMNS}
+        from campaigns
+        order by created_at desc
+        "#
+    ))
+    .fetch_all(&db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    let campaigns: Vec<Campaign> = rows
+        .into_iter()
+        .map(Campaign::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+
+    Ok(campaigns)
+}
+
+async fn complete_task(
+    State(state): State<SharedState>,
+    Json(payload): Json<TaskCompletionRequest>,
+) -> Response {
+    let (metrics, upstream_http, upstream_policy, public_base_url) = {
+        let state = state.inner.read().await;
+        (
+            state.metrics.clone(),
+            state.upstream_http.clone(),
+            state.config.upstream_policy.clone(),
+            state.config.public_base_url.clone(),
+        )
+    };
+
+    let result: ApiResult<(StatusCode, Json<TaskCompletionResponse>)> = async {
         let db = {
             let state = state.inner.read().await;
             state.db.clone()
         }
         .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
 
-        // Verify campaign exists
-        let campaign_exists =
-            sqlx::query_scalar::<_, bool>("select exists(select 1 from campaigns where id = $1)")
-                .bind(payload.campaign_id)
-                .fetch_one(&db)
-                .await
-                .map_err(|err| {
-                    ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
-                })?;
+        let (campaign, task_secret) = load_campaign_with_secret(&db, payload.campaign_id).await?;
 
-        if !campaign_exists {
-            return Err(ApiError::not_found("campaign not found"));
-        }
+        let profile = load_user_profile(&db, payload.user_id).await?;
 
-        // Verify user exists
-        let user_exists =
-            sqlx::query_scalar::<_, bool>("select exists(select 1 from users where id = $1)")
-                .bind(payload.user_id)
-                .fetch_one(&db)
-                .await
-                .map_err(|err| {
-                    ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
-                })?;
+        if payload.task_name.trim() != campaign.required_task {
+            return Err(ApiError::validation(format!(
+                "task_name must match the campaign's required task '{}'",
+                campaign.required_task
+            )));
+        }
 
-        if !user_exists {
-            return Err(ApiError::not_found("user not found"));
+        if let TaskVerification::OnchainAction { rpc_url, .. } = &campaign.task_verification {
+            check_upstream_url(&upstream_policy, rpc_url)?;
         }
+        let outcome = evaluate_task_proof(
+            &upstream_http,
+            &campaign.task_verification,
+            payload.evidence.as_ref(),
+            profile
+                .attributes
+                .get(WALLET_ADDRESS_ATTRIBUTE)
+                .map(String::as_str),
+        )
+        .await?;
+        let now = Utc::now();
 
         let completion = TaskCompletion {
             id: Uuid::new_v4(),
             campaign_id: payload.campaign_id,
             user_id: payload.user_id,
-            task_name: payload.task_name,
+            task_name: campaign.required_task.clone(),
             details: payload.details,
-            created_at: Utc::now(),
+            status: outcome.status,
+            evidence: payload.evidence,
+            rejection_reason: outcome.rejection_reason,
+            verified_at: (outcome.status == TaskCompletionStatus::Verified).then_some(now),
+            created_at: now,
         };
 
         sqlx::query(
             r#"
-            insert into task_completions (id, campaign_id, user_id, task_name, details, created_at)
-            values ($1, $2, $3, $4, $5, $6)
+            insert into task_completions (
+                id, campaign_id, user_id, task_name, details, status, evidence,
+                rejection_reason, verified_at, created_at, tx_hash
+            ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             "#,
         )
         .bind(completion.id)
@@ -556,24 +1042,318 @@ async fn complete_task(
         .bind(completion.user_id)
         .bind(completion.task_name.clone())
         .bind(completion.details.clone())
+        .bind(completion.status)
+        .bind(completion.evidence.clone())
+        .bind(completion.rejection_reason.clone())
+        .bind(completion.verified_at)
         .bind(completion.created_at)
+        .bind(outcome.tx_hash)
         .execute(&db)
         .await
-        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+        .map_err(|err| match err.as_database_error() {
+            Some(db_err)
+                if db_err.constraint() == Some("task_completions_campaign_tx_hash_key") =>
+            {
+                ApiError::Http {
+                    status: StatusCode::CONFLICT,
+                    code: "tx_hash_used".to_string(),
+                    message: "this transaction is already claimed by a completion for the campaign"
+                        .to_string(),
+                }
+            }
+            _ => ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()),
+        })?;
+
+        if completion.status == TaskCompletionStatus::Verified {
+            emit_task_completed(&db, &campaign, &completion).await;
+        }
+
+        let redirect_url = match &campaign.task_verification {
+            TaskVerification::OauthRedirect { authorize_url } => {
+                let callback = format!(
+                    "{}/tasks/oauth/callback",
+                    public_base_url.trim_end_matches('/')
+                );
+                let mut url = reqwest::Url::parse(authorize_url)
+                    .map_err(|_| ApiError::internal("campaign authorize_url is invalid"))?;
+                url.query_pairs_mut()
+                    .append_pair("state", &oauth_state(&task_secret, completion.id))
+                    .append_pair("redirect_uri", &callback);
+                Some(url.to_string())
+            }
+            _ => None,
+        };
 
-        Ok((StatusCode::CREATED, Json(completion)))
+        Ok((
+            StatusCode::CREATED,
+            Json(TaskCompletionResponse {
+                completion,
+                redirect_url,
+            }),
+        ))
     }
     .await;
 
     respond(&metrics, "/tasks/complete", result)
 }
 
+async fn get_task_completion(
+    State(state): State<SharedState>,
+    Path(completion_id): Path<Uuid>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<TaskCompletion>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let completion = load_task_completion(&db, completion_id).await?;
+        Ok((StatusCode::OK, Json(completion)))
+    }
+    .await;
+
+    respond(&metrics, "/tasks/:completion_id", result)
+}
+
+async fn task_callback(
+    State(state): State<SharedState>,
+    Path(completion_id): Path<Uuid>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<TaskCompletion>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let completion = load_task_completion(&db, completion_id).await?;
+        let (campaign, task_secret) =
+            load_campaign_with_secret(&db, completion.campaign_id).await?;
+
+        let signature = headers
+            .get(TASK_SIGNATURE_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if !verify_task_signature(&task_secret, &body, signature) {
+            return Err(ApiError::Http {
+                status: StatusCode::UNAUTHORIZED,
+                code: "invalid_signature".to_string(),
+                message: format!("{TASK_SIGNATURE_HEADER} does not match the request body"),
+            });
+        }
+
+        let request: TaskCallbackRequest = serde_json::from_slice(&body)
+            .map_err(|err| ApiError::validation(format!("invalid callback body: {err}")))?;
+        if request.status == TaskCompletionStatus::Pending {
+            return Err(ApiError::validation("status must be verified or rejected"));
+        }
+
+        let updated =
+            resolve_task_completion(&db, completion.id, request.status, request.reason).await?;
+        if updated.status == TaskCompletionStatus::Verified {
+            emit_task_completed(&db, &campaign, &updated).await;
+        }
+        Ok((StatusCode::OK, Json(updated)))
+    }
+    .await;
+
+    respond(&metrics, "/tasks/:completion_id/callback", result)
+}
+
+async fn task_oauth_callback(
+    State(state): State<SharedState>,
+    Query(query): Query<TaskOauthCallbackQuery>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<TaskCompletion>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let (completion_id, signature) = parse_oauth_state(&query.state)
+            .ok_or_else(|| ApiError::validation("state is malformed"))?;
+        let completion = load_task_completion(&db, completion_id).await?;
+        let (campaign, task_secret) =
+            load_campaign_with_secret(&db, completion.campaign_id).await?;
+
+        if !matches!(
+            campaign.task_verification,
+            TaskVerification::OauthRedirect { .. }
+        ) {
+            return Err(ApiError::validation(
+                "campaign does not use redirect verification",
+            ));
+        }
+        if !verify_task_signature(&task_secret, completion_id.as_bytes(), signature) {
+            return Err(ApiError::Http {
+                status: StatusCode::UNAUTHORIZED,
+                code: "invalid_signature".to_string(),
+                message: "state signature is invalid".to_string(),
+            });
+        }
+        // The state travels through the caller, so only the sponsor's signature proves the
+        // redirect came back from the sponsor.
+        let payload = oauth_callback_payload(&query.state, query.error.as_deref());
+        if !verify_task_signature(&task_secret, payload.as_bytes(), &query.signature) {
+            return Err(ApiError::Http {
+                status: StatusCode::UNAUTHORIZED,
+                code: "invalid_signature".to_string(),
+                message: "signature does not match the state and outcome".to_string(),
+            });
+        }
+
+        let (status, reason) = match query.error {
+            Some(error) => (TaskCompletionStatus::Rejected, Some(error)),
+            None => (TaskCompletionStatus::Verified, None),
+        };
+        let updated = resolve_task_completion(&db, completion.id, status, reason).await?;
+        if updated.status == TaskCompletionStatus::Verified {
+            emit_task_completed(&db, &campaign, &updated).await;
+        }
+        Ok((StatusCode::OK, Json(updated)))
+    }
+    .await;
+
+    respond(&metrics, "/tasks/oauth/callback", result)
+}
+
+async fn emit_task_completed(db: &sqlx::PgPool, campaign: &Campaign, completion: &TaskCompletion) {
+    emit_sponsor_events(
+        db,
+        vec![SponsorEvent::new(
+            campaign.sponsor.clone(),
+            Some(campaign.id),
+            WebhookEventType::TaskCompleted,
+            serde_json::json!({
+                "campaign_id": campaign.id,
+                "completion_id": completion.id,
+                "user_id": completion.user_id,
+                "task_name": completion.task_name,
+                "verified_at": completion.verified_at,
+            }),
+        )],
+    )
+    .await;
+}
+
+async fn load_campaign_with_secret(
+    db: &sqlx::PgPool,
+    campaign_id: Uuid,
+) -> ApiResult<(Campaign, String)> {
+    #[derive(sqlx::FromRow)]
+    struct CampaignSecretRow {
+        #[sqlx(flatten)]
+        campaign: CampaignRow,
+        task_secret: String,
+    }
+
+    let row = sqlx::query_as::<_, CampaignSecretRow>(&format!(
+        r#"
+        select {CAMPAIGN_COLUMNS}, task_secret
+        from campaigns
+        where id = $1
+        "#
+    ))
+    .bind(campaign_id)
+    .fetch_optional(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .ok_or_else(|| ApiError::not_found("campaign not found"))?;
+
+    let campaign = Campaign::try_from(row.campaign)
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+    Ok((campaign, row.task_secret))
+}
+
+async fn load_task_completion(db: &sqlx::PgPool, completion_id: Uuid) -> ApiResult<TaskCompletion> {
+    sqlx::query_as::<_, TaskCompletion>(
+        r#"
+        select id, campaign_id, user_id, task_name, details, status, evidence,
+            rejection_reason, verified_at, created_at
+        from task_completions
+        where id = $1 and user_id is not null
+        "#,
+    )
+    .bind(completion_id)
+    .fetch_optional(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .ok_or_else(|| ApiError::not_found("task completion not found"))
+}
+
+async fn resolve_task_completion(
+    db: &sqlx::PgPool,
+    completion_id: Uuid,
+    status: TaskCompletionStatus,
+    reason: Option<String>,
+) -> ApiResult<TaskCompletion> {
+    sqlx::query_as::<_, TaskCompletion>(
+        r#"
+        update task_completions
+        set status = $2,
+            rejection_reason = $3,
+            verified_at = case when $2 = 'verified' then now() else null end
+        where id = $1 and status = 'pending'
+        returning id, campaign_id, user_id, task_name, details, status, evidence,
+            rejection_reason, verified_at, created_at
+        "#,
+    )
+    .bind(completion_id)
+    .bind(status)
+    .bind(reason)
+    .fetch_optional(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .ok_or_else(|| ApiError::Http {
+        status: StatusCode::CONFLICT,
+        code: "already_resolved".to_string(),
+        message: "task completion is no longer pending".to_string(),
+    })
+}
+
 async fn run_tool(
     State(state): State<SharedState>,
     Path(service): Path<String>,
     headers: HeaderMap,
     Json(payload): Json<ServiceRunRequest>,
 ) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result = execute_tool_run(&state, service, &headers, payload).await;
+
+    respond(&metrics, "/tool/:service/run", result)
+}
+
+/// Settles the caller's x402 payment and runs a built-in tool. Shared by the REST route and
+/// MCP `tools/call`.
+async fn execute_tool_run(
+    state: &SharedState,
+    service: String,
+    headers: &HeaderMap,
+    payload: ServiceRunRequest,
+) -> ApiResult<Response> {
     let (price, metrics, http, config) = {
         let state = state.inner.read().await;
         (
@@ -585,49 +1365,42 @@ async fn run_tool(
     };
 
     let resource_path = format!("/tool/{service}/run");
-    let result: ApiResult<Response> = match verify_x402_payment(
+    let payment = verify_x402_payment(
         &http,
         &config,
         &service,
         price,
         &resource_path,
-        &headers,
+        Some(&service_payment_schema()),
+        headers,
     )
-    .await
-    {
-        Ok(payment) => {
-            metrics
-                .payment_events_total
-                .with_label_values(&["user_direct", "settled"])
-                .inc();
-
-            Ok(build_paid_tool_response(
-                service,
-                payload,
-                "user_direct".to_string(),
-                None,
-                payment.tx_hash,
-                Some(payment.payment_response_header.as_str()),
-            ))
-        }
-        Err(err) => Err(err),
-    };
-
-    respond(&metrics, "/tool/:service/run", result)
+    .await?;
+    metrics
+        .payment_events_total
+        .with_label_values(&["user_direct", "settled"])
+        .inc();
+
+    Ok(build_paid_tool_response(
+        service,
+        payload,
+        "user_direct".to_string(),
+        None,
+        payment.tx_hash,
+        Some(payment.payment_response_header.as_str()),
+    ))
 }
 
-async fn run_proxy(
+/// Runs the built-in tool once per input for a single x402 payment covering all of them.
+/// Tool runs complete instantly and cannot fail, so nothing is ever refunded.
+async fn run_tool_batch(
     State(state): State<SharedState>,
     Path(service): Path<String>,
     headers: HeaderMap,
-    Json(payload): Json<ServiceRunRequest>,
+    Json(payload): Json<ServiceBatchRunRequest>,
 ) -> Response {
-    let has_header = headers.contains_key(PAYMENT_SIGNATURE_HEADER);
-
-    let (db, price, metrics, http, config) = {
+    let (price, metrics, http, config) = {
         let state = state.inner.read().await;
         (
-            state.db.clone(),
             state.service_price(&service),
             state.metrics.clone(),
             state.http.clone(),
@@ -635,20 +1408,229 @@ async fn run_proxy(
         )
     };
 
-    let db = match db {
-        Some(db) => db,
-        None => {
-            return respond(
-                &metrics,
-                "/proxy/:service/run",
-                Err::<Response, ApiError>(ApiError::config(
-                    "Postgres not configured; set DATABASE_URL",
-                )),
-            );
-        }
+    let result: ApiResult<Response> = async {
+        let calls = validate_batch_size(payload.inputs.len())?;
+        let total = batch_total_cents(price, calls)?;
+        let payment = verify_x402_payment(
+            &http,
+            &config,
+            &service,
+            total,
+            &format!("/tool/{service}/run-batch"),
+            Some(&service_batch_payment_schema()),
+            &headers,
+        )
+        .await?;
+        metrics
+            .payment_events_total
+            .with_label_values(&["user_direct", "settled"])
+            .inc();
+
+        let outputs = payload
+            .inputs
+            .iter()
+            .map(|input| tool_output(&service, payload.user_id, input))
+            .collect();
+        let mut response = (
+            StatusCode::OK,
+            Json(ServiceBatchRunResponse {
+                service,
+                outputs,
+                payment_mode: "user_direct".to_string(),
+                sponsored_by: None,
+                tx_hash: payment.tx_hash,
+                amount_cents: total,
+            }),
+        )
+            .into_response();
+        apply_payment_headers(&mut response, Some(&payment.payment_response_header));
+        Ok(response)
+    }
+    .await;
+
+    respond(&metrics, "/tool/:service/run-batch", result)
+}
+
+/// MCP over streamable HTTP: one JSON-RPC message per POST, answered with a JSON body.
+/// The server is stateless, so there is no session id and no server-initiated stream.
+async fn mcp(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    client: Option<Extension<ClientKey>>,
+    body: Bytes,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
     };
 
-    if has_header {
+    let request = serde_json::from_slice::<Value>(&body)
+        .map_err(|err| RpcError::new(PARSE_ERROR, err.to_string()))
+        .and_then(|value| {
+            serde_json::from_value::<RpcRequest>(value)
+                .map_err(|err| RpcError::new(INVALID_REQUEST, err.to_string()))
+        })
+        .and_then(|request| {
+            if request.jsonrpc == "2.0" {
+                Ok(request)
+            } else {
+                Err(RpcError::new(INVALID_REQUEST, "jsonrpc must be \"2.0\""))
+            }
+        });
+    let request = match request {
+        Ok(request) => request,
+        Err(error) => {
+            let response = RpcResponse::new(Value::Null, Err(error));
+            return respond(&metrics, "/mcp", Ok::<_, ApiError>(Json(response)));
+        }
+    };
+    // Notifications, like `notifications/initialized`, get no answer.
+    let Some(id) = request.id else {
+        return respond(&metrics, "/mcp", Ok::<_, ApiError>(StatusCode::ACCEPTED));
+    };
+
+    let outcome = match request.method.as_str() {
+        "initialize" => Ok(initialize_result(
+            request
+                .params
+                .get("protocolVersion")
+                .and_then(Value::as_str),
+        )),
+        "ping" => Ok(serde_json::json!({})),
+        "tools/list" => mcp_list_tools(&state).await,
+        "tools/call" => {
+            let client = client.map(|Extension(client)| client);
+            mcp_call_tool(&state, &headers, client, request.params).await
+        }
+        method => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("unknown method: {method}"),
+        )),
+    };
+
+    let response = RpcResponse::new(id, outcome);
+    respond(&metrics, "/mcp", Ok::<_, ApiError>(Json(response)))
+}
+
+/// Built-in tools and every sponsored API that is not paused. APIs whose budget ran out
+/// stay listed since callers can still pay for them.
+async fn mcp_list_tools(state: &SharedState) -> Result<Value, RpcError> {
+    let (db, mut tools) = {
+        let state = state.inner.read().await;
+        let tools: Vec<Value> = BUILTIN_SERVICES
+            .iter()
+            .map(|service| builtin_tool(service, state.service_price(service)))
+            .collect();
+        (state.db.clone(), tools)
+    };
+
+    // Without Postgres there are no sponsored APIs, only the built-in tools.
+    if let Some(db) = db {
+        let apis = load_callable_sponsored_apis(&db).await?;
+        tools.extend(apis.iter().map(sponsored_api_tool));
+    }
+
+    Ok(serde_json::json!({ "tools": tools }))
+}
+
+/// Runs a tool through the same charging path as its REST route. A payment sent in
+/// `_meta` stands in for the `PAYMENT-SIGNATURE` header.
+async fn mcp_call_tool(
+    state: &SharedState,
+    headers: &HeaderMap,
+    client: Option<ClientKey>,
+    params: Value,
+) -> Result<Value, RpcError> {
+    let params = serde_json::from_value::<ToolCallParams>(params)
+        .map_err(|err| RpcError::new(INVALID_PARAMS, err.to_string()))?;
+    let mut headers = headers.clone();
+    if let Some(signature) = params.payment_signature() {
+        let signature = HeaderValue::from_str(&signature).map_err(|_| {
+            RpcError::new(INVALID_PARAMS, "_meta x402/payment is not a valid payment")
+        })?;
+        headers.insert(HeaderName::from_static(PAYMENT_SIGNATURE_HEADER), signature);
+    }
+    let arguments = match params.arguments {
+        Value::Null => serde_json::json!({}),
+        arguments => arguments,
+    };
+    let invalid_arguments =
+        |err: serde_json::Error| RpcError::new(INVALID_PARAMS, format!("invalid arguments: {err}"));
+
+    let outcome = match McpTool::parse(&params.name) {
+        Some(McpTool::SponsoredApi(api_id)) => {
+            let SponsoredToolArguments { input, caller } =
+                serde_json::from_value(arguments).map_err(invalid_arguments)?;
+            let payload = SponsoredApiRunRequest {
+                caller,
+                input,
+                stream: false,
+                mode: RunMode::Sync,
+                callback_url: None,
+            };
+            execute_sponsored_api_run(state, api_id, &headers, client, payload).await
+        }
+        Some(McpTool::Builtin(service)) => {
+            let payload = serde_json::from_value(arguments).map_err(invalid_arguments)?;
+            execute_tool_run(state, service, &headers, payload).await
+        }
+        None => {
+            return Err(RpcError::new(
+                INVALID_PARAMS,
+                format!("unknown tool: {}", params.name),
+            ));
+        }
+    };
+
+    match outcome {
+        Ok(response) => {
+            let payment_response = response
+                .headers()
+                .get(PAYMENT_RESPONSE_HEADER)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .map_err(|err| RpcError::new(INTERNAL_ERROR, err.to_string()))?;
+            Ok(tool_result(&body, payment_response.as_deref()))
+        }
+        Err(err) => Ok(tool_error(&err)),
+    }
+}
+
+async fn run_proxy(
+    State(state): State<SharedState>,
+    Path(service): Path<String>,
+    headers: HeaderMap,
+    Json(payload): Json<ServiceRunRequest>,
+) -> Response {
+    let has_header = headers.contains_key(PAYMENT_SIGNATURE_HEADER);
+
+    let (db, price, metrics, http, config) = {
+        let state = state.inner.read().await;
+        (
+            state.db.clone(),
+            state.service_price(&service),
+            state.metrics.clone(),
+            state.http.clone(),
+            state.config.clone(),
+        )
+    };
+
+    let db = match db {
+        Some(db) => db,
+        None => {
+            return respond(
+                &metrics,
+                "/proxy/:service/run",
+                Err::<Response, ApiError>(ApiError::config(
+                    "Postgres not configured; set DATABASE_URL",
+                )),
+            );
+        }
+    };
+
+    if has_header {
         // Verify user exists in database
         let user_exists =
             sqlx::query_scalar::<_, bool>("select exists(select 1 from users where id = $1)")
@@ -671,27 +1653,34 @@ async fn run_proxy(
         }
 
         let resource_path = format!("/proxy/{service}/run");
-        let result =
-            match verify_x402_payment(&http, &config, &service, price, &resource_path, &headers)
-                .await
-            {
-                Ok(payment) => {
-                    metrics
-                        .payment_events_total
-                        .with_label_values(&["user_direct", "settled"])
-                        .inc();
-
-                    Ok(build_paid_tool_response(
-                        service,
-                        payload,
-                        "user_direct".to_string(),
-                        None,
-                        payment.tx_hash,
-                        Some(payment.payment_response_header.as_str()),
-                    ))
-                }
-                Err(err) => Err(err),
-            };
+        let result = match verify_x402_payment(
+            &http,
+            &config,
+            &service,
+            price,
+            &resource_path,
+            Some(&service_payment_schema()),
+            &headers,
+        )
+        .await
+        {
+            Ok(payment) => {
+                metrics
+                    .payment_events_total
+                    .with_label_values(&["user_direct", "settled"])
+                    .inc();
+
+                Ok(build_paid_tool_response(
+                    service,
+                    payload,
+                    "user_direct".to_string(),
+                    None,
+                    payment.tx_hash,
+                    Some(payment.payment_response_header.as_str()),
+                ))
+            }
+            Err(err) => Err(err),
+        };
 
         return respond(&metrics, "/proxy/:service/run", result);
     }
@@ -719,29 +1708,7 @@ async fn run_proxy(
         }
     };
 
-    // Load campaigns from database
-    let campaigns = sqlx::query_as::<_, CampaignRow>(
-        r#"
-        select id, name, sponsor, target_roles, target_tools, required_task,
-            subsidy_per_call_cents, budget_total_cents, budget_remaining_cents,
-            query_urls, active, created_at
-        from campaigns
-        where active = true and budget_remaining_cents >= $1
-        order by created_at desc
-        "#,
-    )
-    .bind(price as i64)
-    .fetch_all(&db)
-    .await
-    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
-    .and_then(|rows| {
-        rows.into_iter()
-            .map(Campaign::try_from)
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))
-    });
-
-    let campaigns = match campaigns {
+    let campaigns = match load_fundable_campaigns(&db, price).await {
         Ok(campaigns) => campaigns,
         Err(err) => {
             return respond(
@@ -780,43 +1747,35 @@ async fn run_proxy(
         }
     }
 
-    if let Some(campaign) = match_with_task {
-        let new_remaining = campaign.budget_remaining_cents.saturating_sub(price);
-        let still_active = new_remaining >= price && new_remaining > 0;
-
-        // Update campaign budget in database
-        let budget_update = sqlx::query(
-            r#"
-            update campaigns
-            set budget_remaining_cents = $1, active = $2
-            where id = $3
-            "#,
-        )
-        .bind(new_remaining as i64)
-        .bind(still_active)
-        .bind(campaign.id)
-        .execute(&db)
-        .await
-        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()));
-        if let Err(err) = budget_update {
-            return respond(
-                &metrics,
-                "/proxy/:service/run",
-                Err::<Response, ApiError>(err),
-            );
-        }
+    // Another call may have spent the budget since the campaigns were loaded; the caller
+    // then pays directly like anyone without a sponsor.
+    let sponsored = match match_with_task {
+        Some(campaign) => match draw_campaign_budget(&db, &campaign, price).await {
+            Ok(draw) => draw.map(|draw| (campaign, draw)),
+            Err(err) => {
+                return respond(
+                    &metrics,
+                    "/proxy/:service/run",
+                    Err::<Response, ApiError>(err),
+                );
+            }
+        },
+        None => None,
+    };
 
+    if let Some((campaign, draw)) = sponsored {
         let tx_hash = format!("sponsor-{}", Uuid::new_v4());
 
         // Save payment to database
         let payment_insert = sqlx::query(
             r#"
-            insert into payments (tx_hash, campaign_id, service, amount_cents, payer, source, status, created_at)
-            values ($1, $2, $3, $4, $5, $6, $7, $8)
+            insert into payments (tx_hash, campaign_id, user_id, service, amount_cents, payer, source, status, created_at)
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9)
             "#,
         )
         .bind(&tx_hash)
         .bind(campaign.id)
+        .bind(payload.user_id)
         .bind(&service)
         .bind(price as i64)
         .bind(&campaign.sponsor)
@@ -840,6 +1799,32 @@ async fn run_proxy(
             .inc();
         metrics.sponsor_spend_cents_total.inc_by(price);
 
+        let event_data = serde_json::json!({
+            "campaign_id": campaign.id,
+            "service": service,
+            "user_id": payload.user_id,
+            "tx_hash": tx_hash,
+            "budget_pool_id": campaign.budget_pool_id,
+            "amount_cents": price,
+            "budget_remaining_cents": draw.new_remaining,
+        });
+        let mut events = vec![SponsorEvent::new(
+            campaign.sponsor.clone(),
+            Some(campaign.id),
+            WebhookEventType::SponsoredCallCharged,
+            event_data.clone(),
+        )];
+        events.extend(budget_events(
+            &campaign.sponsor,
+            Some(campaign.id),
+            event_data,
+            draw.previous_remaining,
+            draw.new_remaining,
+            draw.budget_total,
+            draw.still_active,
+        ));
+        emit_sponsor_events(&db, events).await;
+
         return respond(
             &metrics,
             "/proxy/:service/run",
@@ -873,6 +1858,7 @@ async fn run_proxy(
             &service,
             price,
             &format!("/proxy/{service}/run"),
+            Some(&service_payment_schema()),
             "no eligible sponsor campaign found",
             "either complete a sponsor task or pay with PAYMENT-SIGNATURE",
         )),
@@ -882,7 +1868,7 @@ async fn run_proxy(
 async fn create_sponsored_api(
     State(state): State<SharedState>,
     headers: HeaderMap,
-    Json(payload): Json<CreateSponsoredApiRequest>,
+    Json(mut payload): Json<CreateSponsoredApiRequest>,
 ) -> Response {
     let metrics = {
         let state = state.inner.read().await;
@@ -908,21 +1894,39 @@ async fn create_sponsored_api(
         }
 
         let price_cents = payload.price_cents.unwrap_or(DEFAULT_PRICE_CENTS);
-        if price_cents == 0 {
-            return Err(ApiError::validation("price_cents must be greater than 0"));
+        validate_price_cents(price_cents)?;
+        validate_schemas(
+            payload.input_schema.as_ref(),
+            payload.output_schema.as_ref(),
+            payload.validate_output,
+        )?;
+        if let Some(transform) = &payload.transform {
+            validate_transform_policy(transform)?;
+        }
+        if let Some(eligibility) = &mut payload.eligibility {
+            validate_eligibility_policy(&db, eligibility).await?;
         }
 
         let upstream_method = normalize_upstream_method(payload.upstream_method)?;
-        reqwest::Url::parse(payload.upstream_url.trim())
-            .map_err(|_| ApiError::validation("upstream_url must be a valid URL"))?;
-
-        for (header, value) in &payload.upstream_headers {
-            HeaderName::from_bytes(header.as_bytes())
-                .map_err(|_| ApiError::validation(format!("invalid upstream header: {header}")))?;
-            HeaderValue::from_str(value).map_err(|_| {
-                ApiError::validation(format!("invalid upstream header value for: {header}"))
-            })?;
+        config
+            .upstream_policy
+            .validate(&payload.upstream_url)
+            .await?;
+        upstream_path_params(&payload.upstream_url)?;
+        validate_upstream_headers(&payload.upstream_headers)?;
+        let forward_headers = normalize_forward_headers(payload.forward_headers)?;
+        if let Some(cache) = &payload.cache {
+            validate_cache_policy(cache)?;
+        }
+        if let Some(rate_limit) = &payload.rate_limit {
+            validate_rate_limit_policy(rate_limit)?;
+        }
+        if let Some(health_check) = &payload.health_check {
+            validate_health_check_policy(health_check)?;
         }
+        let (public_headers, secret_headers) =
+            split_upstream_headers(payload.upstream_headers, &payload.public_headers)?;
+        let sealed_headers = config.upstream_secret_keys.seal_headers(&secret_headers)?;
 
         if config.sponsored_api_create_price_cents > 0 {
             let resource_path = "/sponsored-apis".to_string();
@@ -932,6 +1936,7 @@ async fn create_sponsored_api(
                 SPONSORED_API_CREATE_SERVICE,
                 config.sponsored_api_create_price_cents,
                 &resource_path,
+                None,
                 &headers,
             )
             .await?;
@@ -949,43 +1954,33 @@ async fn create_sponsored_api(
             description: payload.description,
             upstream_url: payload.upstream_url,
             upstream_method,
-            upstream_headers: payload.upstream_headers,
+            upstream_headers: public_headers,
+            secret_header_names: sorted_header_names(&sealed_headers),
+            sealed_headers,
             price_cents,
             budget_total_cents: payload.budget_cents,
             budget_remaining_cents: payload.budget_cents,
             active: true,
+            paused: false,
             service_key: sponsored_api_service_key(api_id),
+            forward_headers,
+            body_encoding: payload.body_encoding,
+            cache: payload.cache,
+            rate_limit: payload.rate_limit,
+            health_check: payload.health_check,
+            budget_pool_id: None,
+            pool_limit_cents: None,
+            pool_spent_cents: 0,
+            input_schema: payload.input_schema,
+            output_schema: payload.output_schema,
+            validate_output: payload.validate_output,
+            transform: payload.transform,
+            eligibility: payload.eligibility,
+            health: None,
             created_at: Utc::now(),
         };
 
-        let inserted_row = sqlx::query_as::<_, SponsoredApiRow>(
-            r#"
-            insert into sponsored_apis (
-                id, name, sponsor, description, upstream_url, upstream_method,
-                upstream_headers, price_cents, budget_total_cents, budget_remaining_cents,
-                active, service_key, created_at
-            ) values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
-            returning id, name, sponsor, description, upstream_url, upstream_method,
-                upstream_headers, price_cents, budget_total_cents, budget_remaining_cents,
-                active, service_key, created_at
-            "#,
-        )
-        .bind(api.id)
-        .bind(api.name)
-        .bind(api.sponsor)
-        .bind(api.description)
-        .bind(api.upstream_url)
-        .bind(api.upstream_method)
-        .bind(DbJson(api.upstream_headers))
-        .bind(api.price_cents as i64)
-        .bind(api.budget_total_cents as i64)
-        .bind(api.budget_remaining_cents as i64)
-        .bind(api.active)
-        .bind(api.service_key)
-        .bind(api.created_at)
-        .fetch_one(&db)
-        .await
-        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+        let inserted_row = insert_sponsored_api(&db, api).await?;
 
         let inserted = SponsoredApi::try_from(inserted_row)
             .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
@@ -996,314 +1991,2869 @@ async fn create_sponsored_api(
     respond(&metrics, "/sponsored-apis", result)
 }
 
-async fn list_sponsored_apis(State(state): State<SharedState>) -> Response {
+/// Creates one sponsored API per selected OpenAPI operation, all drawing on a new budget
+/// pool funded with `budget_cents`.
+async fn import_sponsored_apis(
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+    Json(payload): Json<ImportSponsoredApisRequest>,
+) -> Response {
     let metrics = {
         let state = state.inner.read().await;
         state.metrics.clone()
     };
 
-    let result: ApiResult<(StatusCode, Json<Vec<SponsoredApi>>)> = async {
+    let result: ApiResult<(StatusCode, Json<ImportSponsoredApisResponse>)> = async {
+        let (db, http, config) = {
+            let state = state.inner.read().await;
+            (state.db.clone(), state.http.clone(), state.config.clone())
+        };
+
+        let db = db.ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let sponsor = payload.sponsor.trim().to_string();
+        if sponsor.is_empty() {
+            return Err(ApiError::validation("sponsor is required"));
+        }
+        if payload.budget_cents == 0 {
+            return Err(ApiError::validation("budget_cents must be greater than 0"));
+        }
+        let price_cents = payload.price_cents.unwrap_or(DEFAULT_PRICE_CENTS);
+        validate_price_cents(price_cents)?;
+
+        let document = openapi::parse_document(payload.document)?;
+        let base_url = openapi::base_url(&document, payload.base_url.as_deref())?;
+        config.upstream_policy.validate(&base_url).await?;
+        let (operations, skipped) = openapi::operations(&document, &base_url, &payload.operations);
+        if operations.is_empty() {
+            return Err(ApiError::validation(
+                "no importable operations matched the selection",
+            ));
+        }
+
+        validate_upstream_headers(&payload.upstream_headers)?;
+        let (public_headers, secret_headers) =
+            split_upstream_headers(payload.upstream_headers, &payload.public_headers)?;
+        let sealed_headers = config.upstream_secret_keys.seal_headers(&secret_headers)?;
+
+        // Importing costs the same as creating each API individually.
+        let create_price_cents = config
+            .sponsored_api_create_price_cents
+            .saturating_mul(operations.len() as u64);
+        if create_price_cents > 0 {
+            verify_x402_payment(
+                &http,
+                &config,
+                SPONSORED_API_CREATE_SERVICE,
+                create_price_cents,
+                "/sponsored-apis/import",
+                None,
+                &headers,
+            )
+            .await?;
+            metrics
+                .payment_events_total
+                .with_label_values(&["user_direct", "settled"])
+                .inc();
+        }
+
+        let title = document
+            .pointer("/info/title")
+            .and_then(Value::as_str)
+            .unwrap_or("OpenAPI import");
+        let pool_name = payload
+            .pool_name
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| title.to_string());
+        let name_prefix = payload.name_prefix.unwrap_or_default();
+
+        let mut tx = db.begin().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+
+        let budget_pool =
+            insert_budget_pool(&mut *tx, &sponsor, &pool_name, payload.budget_cents).await?;
+
+        let mut apis = Vec::with_capacity(operations.len());
+        for operation in operations {
+            let api_id = Uuid::new_v4();
+            let api = SponsoredApi {
+                id: api_id,
+                name: format!("{name_prefix}{}", operation.name),
+                sponsor: sponsor.clone(),
+                description: operation.description,
+                upstream_url: operation.upstream_url,
+                upstream_method: operation.method,
+                upstream_headers: public_headers.clone(),
+                secret_header_names: sorted_header_names(&sealed_headers),
+                sealed_headers: sealed_headers.clone(),
+                price_cents,
+                budget_total_cents: 0,
+                budget_remaining_cents: 0,
+                active: true,
+                paused: false,
+                service_key: sponsored_api_service_key(api_id),
+                forward_headers: Vec::new(),
+                body_encoding: operation.body_encoding,
+                cache: None,
+                rate_limit: None,
+                health_check: None,
+                budget_pool_id: Some(budget_pool.id),
+                pool_limit_cents: None,
+                pool_spent_cents: 0,
+                input_schema: Some(operation.input_schema),
+                output_schema: operation.output_schema,
+                validate_output: false,
+                transform: None,
+                eligibility: None,
+                health: None,
+                created_at: Utc::now(),
+            };
+            let row = insert_sponsored_api(&mut *tx, api).await?;
+            record_sponsored_api_change(
+                &mut tx,
+                api_id,
+                "imported",
+                serde_json::json!({
+                    "operation": operation.key,
+                    "operation_id": operation.operation_id,
+                    "budget_pool_id": budget_pool.id,
+                }),
+            )
+            .await?;
+            apis.push(
+                SponsoredApi::try_from(row)
+                    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?,
+            );
+        }
+
+        tx.commit().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+
+        Ok((
+            StatusCode::CREATED,
+            Json(ImportSponsoredApisResponse {
+                budget_pool,
+                apis,
+                skipped,
+            }),
+        ))
+    }
+    .await;
+
+    respond(&metrics, "/sponsored-apis/import", result)
+}
+
+async fn insert_sponsored_api<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    api: SponsoredApi,
+) -> ApiResult<SponsoredApiRow> {
+    sqlx::query_as::<_, SponsoredApiRow>(&format!(
+        r#"
+        insert into sponsored_apis (
+            id, name, sponsor, description, upstream_url, upstream_method,
+            upstream_headers, upstream_secret_headers, price_cents, budget_total_cents,
+            budget_remaining_cents, active, service_key, forward_headers, body_encoding,
+            cache_policy, rate_limit_policy, health_check_policy, budget_pool_id, input_schema,
+            output_schema, validate_output, transform_policy, eligibility_policy, created_at
+        ) values (
+            $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18,
+            $19, $20, $21, $22, $23, $24, $25
+        )
+        returning {SPONSORED_API_COLUMNS}
+        "#
+    ))
+    .bind(api.id)
+    .bind(api.name)
+    .bind(api.sponsor)
+    .bind(api.description)
+    .bind(api.upstream_url)
+    .bind(api.upstream_method)
+    .bind(DbJson(api.upstream_headers))
+    .bind(DbJson(api.sealed_headers))
+    .bind(api.price_cents as i64)
+    .bind(api.budget_total_cents as i64)
+    .bind(api.budget_remaining_cents as i64)
+    .bind(api.active)
+    .bind(api.service_key)
+    .bind(api.forward_headers)
+    .bind(api.body_encoding)
+    .bind(api.cache.map(DbJson))
+    .bind(api.rate_limit.map(DbJson))
+    .bind(api.health_check.map(DbJson))
+    .bind(api.budget_pool_id)
+    .bind(api.input_schema)
+    .bind(api.output_schema)
+    .bind(api.validate_output)
+    .bind(api.transform.map(DbJson))
+    .bind(api.eligibility.map(DbJson))
+    .bind(api.created_at)
+    .fetch_one(executor)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
+
+async fn insert_budget_pool<'e>(
+    executor: impl sqlx::PgExecutor<'e>,
+    sponsor: &str,
+    name: &str,
+    budget_cents: u64,
+) -> ApiResult<BudgetPool> {
+    let row = sqlx::query_as::<_, BudgetPoolRow>(&format!(
+        r#"
+        insert into budget_pools (
+            id, sponsor, name, budget_total_cents, budget_remaining_cents, created_at
+        ) values ($1, $2, $3, $4, $4, $5)
+        returning {BUDGET_POOL_COLUMNS}
+        "#
+    ))
+    .bind(Uuid::new_v4())
+    .bind(sponsor)
+    .bind(name)
+    .bind(budget_cents as i64)
+    .bind(Utc::now())
+    .fetch_one(executor)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    BudgetPool::try_from(row)
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))
+}
+
+async fn load_budget_pool(db: &sqlx::PgPool, pool_id: Uuid) -> ApiResult<BudgetPool> {
+    sqlx::query_as::<_, BudgetPoolRow>(&format!(
+        "select {BUDGET_POOL_COLUMNS} from budget_pools where id = $1"
+    ))
+    .bind(pool_id)
+    .fetch_optional(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .ok_or_else(|| ApiError::not_found("budget pool not found"))
+    .and_then(|row| {
+        BudgetPool::try_from(row)
+            .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))
+    })
+}
+
+async fn create_budget_pool(
+    State(state): State<SharedState>,
+    Json(payload): Json<CreateBudgetPoolRequest>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<BudgetPool>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let sponsor = payload.sponsor.trim();
+        let name = payload.name.trim();
+        if sponsor.is_empty() {
+            return Err(ApiError::validation("sponsor is required"));
+        }
+        if name.is_empty() {
+            return Err(ApiError::validation("name is required"));
+        }
+        if payload.budget_cents == 0 {
+            return Err(ApiError::validation("budget_cents must be greater than 0"));
+        }
+        if i64::try_from(payload.budget_cents).is_err() {
+            return Err(ApiError::validation("budget_cents is too large"));
+        }
+
+        let pool = insert_budget_pool(&db, sponsor, name, payload.budget_cents).await?;
+        Ok((StatusCode::CREATED, Json(pool)))
+    }
+    .await;
+
+    respond(&metrics, "/budget-pools", result)
+}
+
+async fn list_budget_pools(
+    State(state): State<SharedState>,
+    Query(query): Query<BudgetPoolQuery>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<Vec<BudgetPool>>)> = async {
         let db = {
             let state = state.inner.read().await;
             state.db.clone()
         }
         .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
 
-        let api_rows = sqlx::query_as::<_, SponsoredApiRow>(
+        let pools = sqlx::query_as::<_, BudgetPoolRow>(&format!(
             r#"
-            select id, name, sponsor, description, upstream_url, upstream_method,
-                upstream_headers, price_cents, budget_total_cents, budget_remaining_cents,
-                active, service_key, created_at
-            from sponsored_apis
+            select {BUDGET_POOL_COLUMNS}
+            from budget_pools
+            where $1::text is null or sponsor = $1
             order by created_at desc
-            "#,
-        )
+            "#
+        ))
+        .bind(query.sponsor)
         .fetch_all(&db)
         .await
-        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
-
-        let apis: Vec<SponsoredApi> = api_rows
-            .into_iter()
-            .map(SponsoredApi::try_from)
-            .collect::<Result<Vec<_>, _>>()
-            .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .into_iter()
+        .map(BudgetPool::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
 
-        Ok((StatusCode::OK, Json(apis)))
+        Ok((StatusCode::OK, Json(pools)))
     }
     .await;
 
-    respond(&metrics, "/sponsored-apis", result)
+    respond(&metrics, "/budget-pools", result)
 }
 
-async fn get_sponsored_api(State(state): State<SharedState>, Path(api_id): Path<Uuid>) -> Response {
+async fn get_budget_pool(State(state): State<SharedState>, Path(pool_id): Path<Uuid>) -> Response {
     let metrics = {
         let state = state.inner.read().await;
         state.metrics.clone()
     };
 
-    let result: ApiResult<(StatusCode, Json<SponsoredApi>)> = async {
+    let result: ApiResult<(StatusCode, Json<BudgetPoolDetail>)> = async {
         let db = {
             let state = state.inner.read().await;
             state.db.clone()
         }
         .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
 
-        let api = sqlx::query_as::<_, SponsoredApiRow>(
+        let pool = load_budget_pool(&db, pool_id).await?;
+        let members = sqlx::query_as::<_, (String, Uuid, String, Option<i64>, i64)>(
             r#"
-            select id, name, sponsor, description, upstream_url, upstream_method,
-                upstream_headers, price_cents, budget_total_cents, budget_remaining_cents,
-                active, service_key, created_at
+            select 'sponsored_api', id, name, pool_limit_cents, pool_spent_cents
             from sponsored_apis
-            where id = $1
+            where budget_pool_id = $1 and deleted_at is null
+            union all
+            select 'campaign', id, name, pool_limit_cents, pool_spent_cents
+            from campaigns
+            where budget_pool_id = $1
+            order by 3
             "#,
         )
-        .bind(api_id)
-        .fetch_optional(&db)
+        .bind(pool_id)
+        .fetch_all(&db)
         .await
         .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
-        .ok_or_else(|| ApiError::not_found("sponsored api not found"))
-        .and_then(|row| {
-            SponsoredApi::try_from(row)
-                .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))
-        })?;
+        .into_iter()
+        .map(|(kind, id, name, limit, spent)| BudgetPoolMember {
+            kind: if kind == "campaign" {
+                BudgetPoolMemberKind::Campaign
+            } else {
+                BudgetPoolMemberKind::SponsoredApi
+            },
+            id,
+            name,
+            pool_limit_cents: limit.and_then(|limit| u64::try_from(limit).ok()),
+            pool_spent_cents: u64::try_from(spent).unwrap_or_default(),
+        })
+        .collect();
+
+        Ok((StatusCode::OK, Json(BudgetPoolDetail { pool, members })))
+    }
+    .await;
+
+    respond(&metrics, "/budget-pools/:pool_id", result)
+}
+
+async fn top_up_budget_pool(
+    State(state): State<SharedState>,
+    Path(pool_id): Path<Uuid>,
+    Json(payload): Json<TopUpBudgetPoolRequest>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<BudgetPool>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        if payload.amount_cents == 0 {
+            return Err(ApiError::validation("amount_cents must be greater than 0"));
+        }
+        let amount = i64::try_from(payload.amount_cents)
+            .map_err(|_| ApiError::validation("amount_cents is too large"))?;
+
+        let row = sqlx::query_as::<_, BudgetPoolRow>(&format!(
+            r#"
+            update budget_pools
+            set budget_total_cents = budget_total_cents + $2,
+                budget_remaining_cents = budget_remaining_cents + $2
+            where id = $1
+            returning {BUDGET_POOL_COLUMNS}
+            "#
+        ))
+        .bind(pool_id)
+        .bind(amount)
+        .fetch_optional(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .ok_or_else(|| ApiError::not_found("budget pool not found"))?;
+
+        let pool = BudgetPool::try_from(row)
+            .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+        Ok((StatusCode::OK, Json(pool)))
+    }
+    .await;
+
+    respond(&metrics, "/budget-pools/:pool_id/top-up", result)
+}
+
+async fn list_sponsored_apis(State(state): State<SharedState>) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<Vec<SponsoredApi>>)> = async {
+        let (db, health) = {
+            let state = state.inner.read().await;
+            (state.db.clone(), state.health.clone())
+        };
+        let db = db.ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let api_rows = sqlx::query_as::<_, SponsoredApiRow>(&format!(
+            r#"
+            select {SPONSORED_API_COLUMNS}
+            from sponsored_apis
+            where deleted_at is null
+            order by created_at desc
+            "#
+        ))
+        .fetch_all(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+        let mut apis: Vec<SponsoredApi> = api_rows
+            .into_iter()
+            .map(SponsoredApi::try_from)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+        for api in &mut apis {
+            health.annotate(api);
+        }
+
+        Ok((StatusCode::OK, Json(apis)))
+    }
+    .await;
+
+    respond(&metrics, "/sponsored-apis", result)
+}
+
+async fn get_sponsored_api(State(state): State<SharedState>, Path(api_id): Path<Uuid>) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<SponsoredApi>)> = async {
+        let (db, health) = {
+            let state = state.inner.read().await;
+            (state.db.clone(), state.health.clone())
+        };
+        let db = db.ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let mut api = load_sponsored_api(&db, api_id).await?;
+        health.annotate(&mut api);
+
+        Ok((StatusCode::OK, Json(api)))
+    }
+    .await;
+
+    respond(&metrics, "/sponsored-apis/:api_id", result)
+}
+
+async fn update_sponsored_api(
+    State(state): State<SharedState>,
+    Path(api_id): Path<Uuid>,
+    Json(mut payload): Json<UpdateSponsoredApiRequest>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<SponsoredApi>)> = async {
+        let (db, config) = {
+            let state = state.inner.read().await;
+            (state.db.clone(), state.config.clone())
+        };
+        let db = db.ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let current = load_sponsored_api(&db, api_id).await?;
+        let mut changes = serde_json::Map::new();
+
+        if let Some(name) = &payload.name {
+            if name.trim().is_empty() {
+                return Err(ApiError::validation("name is required"));
+            }
+            record_field_change(&mut changes, "name", &current.name, name);
+        }
+        if let Some(description) = &payload.description {
+            record_field_change(
+                &mut changes,
+                "description",
+                &current.description,
+                &Some(description.clone()),
+            );
+        }
+        // Sealed headers were issued for the old host; moving the API elsewhere drops them so
+        // they are never sent to the new one. The sponsor re-supplies them via PUT .../headers.
+        let mut clear_secret_headers = false;
+        if let Some(upstream_url) = &payload.upstream_url {
+            config.upstream_policy.validate(upstream_url).await?;
+            upstream_path_params(upstream_url)?;
+            record_field_change(
+                &mut changes,
+                "upstream_url",
+                &current.upstream_url,
+                upstream_url,
+            );
+            clear_secret_headers = !current.sealed_headers.is_empty()
+                && !same_upstream_origin(&current.upstream_url, upstream_url);
+            if clear_secret_headers {
+                record_field_change(
+                    &mut changes,
+                    "secret_headers",
+                    &current.secret_header_names,
+                    &Vec::new(),
+                );
+            }
+        }
+        let upstream_method = payload
+            .upstream_method
+            .map(|method| normalize_upstream_method(Some(method)))
+            .transpose()?;
+        if let Some(method) = &upstream_method {
+            record_field_change(
+                &mut changes,
+                "upstream_method",
+                &current.upstream_method,
+                method,
+            );
+        }
+        let forward_headers = payload
+            .forward_headers
+            .map(normalize_forward_headers)
+            .transpose()?;
+        if let Some(forward_headers) = &forward_headers {
+            record_field_change(
+                &mut changes,
+                "forward_headers",
+                &current.forward_headers,
+                forward_headers,
+            );
+        }
+        if let Some(body_encoding) = &payload.body_encoding {
+            record_field_change(
+                &mut changes,
+                "body_encoding",
+                &current.body_encoding,
+                body_encoding,
+            );
+        }
+        if let Some(cache) = &payload.cache {
+            if let Some(policy) = cache {
+                validate_cache_policy(policy)?;
+            }
+            record_field_change(&mut changes, "cache", &current.cache, cache);
+        }
+        if let Some(rate_limit) = &payload.rate_limit {
+            if let Some(policy) = rate_limit {
+                validate_rate_limit_policy(policy)?;
+            }
+            record_field_change(&mut changes, "rate_limit", &current.rate_limit, rate_limit);
+        }
+        if let Some(health_check) = &payload.health_check {
+            if let Some(policy) = health_check {
+                validate_health_check_policy(policy)?;
+            }
+            record_field_change(
+                &mut changes,
+                "health_check",
+                &current.health_check,
+                health_check,
+            );
+        }
+        if payload.input_schema.is_some()
+            || payload.output_schema.is_some()
+            || payload.validate_output.is_some()
+        {
+            let input_schema = payload
+                .input_schema
+                .clone()
+                .unwrap_or_else(|| current.input_schema.clone());
+            let output_schema = payload
+                .output_schema
+                .clone()
+                .unwrap_or_else(|| current.output_schema.clone());
+            let validate_output = payload.validate_output.unwrap_or(current.validate_output);
+            validate_schemas(
+                input_schema.as_ref(),
+                output_schema.as_ref(),
+                validate_output,
+            )?;
+            record_field_change(
+                &mut changes,
+                "input_schema",
+                &current.input_schema,
+                &input_schema,
+            );
+            record_field_change(
+                &mut changes,
+                "output_schema",
+                &current.output_schema,
+                &output_schema,
+            );
+            record_field_change(
+                &mut changes,
+                "validate_output",
+                &current.validate_output,
+                &validate_output,
+            );
+        }
+        if let Some(transform) = &payload.transform {
+            if let Some(policy) = transform {
+                validate_transform_policy(policy)?;
+            }
+            record_field_change(&mut changes, "transform", &current.transform, transform);
+        }
+        if let Some(eligibility) = &mut payload.eligibility {
+            if let Some(policy) = eligibility {
+                validate_eligibility_policy(&db, policy).await?;
+            }
+            record_field_change(
+                &mut changes,
+                "eligibility",
+                &current.eligibility,
+                eligibility,
+            );
+        }
+        // Moving to another pool starts a fresh sub-limit; staying keeps the current one
+        // unless a new limit is given.
+        let pool_membership =
+            if payload.budget_pool_id.is_some() || payload.pool_limit_cents.is_some() {
+                let pool_id = payload.budget_pool_id.unwrap_or(current.budget_pool_id);
+                let pool_changed = pool_id != current.budget_pool_id;
+                let pool_limit = match payload.pool_limit_cents {
+                    Some(limit) => limit,
+                    None if pool_changed => None,
+                    None => current.pool_limit_cents,
+                };
+                if pool_id.is_none() && pool_limit.is_some() {
+                    return Err(ApiError::validation(
+                        "pool_limit_cents requires budget_pool_id",
+                    ));
+                }
+                if pool_limit.is_some_and(|limit| i64::try_from(limit).is_err()) {
+                    return Err(ApiError::validation("pool_limit_cents is too large"));
+                }
+                if let Some(pool_id) = pool_id
+                    && pool_changed
+                    && load_budget_pool(&db, pool_id).await?.sponsor != current.sponsor
+                {
+                    return Err(ApiError::validation(
+                        "budget pool belongs to a different sponsor",
+                    ));
+                }
+                record_field_change(
+                    &mut changes,
+                    "budget_pool_id",
+                    &current.budget_pool_id,
+                    &pool_id,
+                );
+                record_field_change(
+                    &mut changes,
+                    "pool_limit_cents",
+                    &current.pool_limit_cents,
+                    &pool_limit,
+                );
+                Some((pool_id, pool_limit, pool_changed))
+            } else {
+                None
+            };
+        if let Some(price_cents) = payload.price_cents {
+            validate_price_cents(price_cents)?;
+            record_field_change(
+                &mut changes,
+                "price_cents",
+                &current.price_cents,
+                &price_cents,
+            );
+        }
+
+        if changes.is_empty() {
+            return Ok((StatusCode::OK, Json(current)));
+        }
+        // Health observed against the old upstream says nothing about the new one.
+        let upstream_changed = changes.contains_key("upstream_url");
+
+        let mut tx = db.begin().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+
+        let row = sqlx::query_as::<_, SponsoredApiRow>(&format!(
+            r#"
+            update sponsored_apis
+            set name = coalesce($2, name),
+                description = coalesce($3, description),
+                upstream_url = coalesce($4, upstream_url),
+                upstream_method = coalesce($5, upstream_method),
+                price_cents = coalesce($6, price_cents),
+                forward_headers = coalesce($7, forward_headers),
+                body_encoding = coalesce($8, body_encoding),
+                cache_policy = case when $9 then $10 else cache_policy end,
+                rate_limit_policy = case when $11 then $12 else rate_limit_policy end,
+                health_check_policy = case when $13 then $14 else health_check_policy end,
+                budget_pool_id = case when $15 then $16 else budget_pool_id end,
+                pool_limit_cents = case when $15 then $17 else pool_limit_cents end,
+                pool_spent_cents = case when $18 then 0 else pool_spent_cents end,
+                input_schema = case when $19 then $20 else input_schema end,
+                output_schema = case when $21 then $22 else output_schema end,
+                validate_output = coalesce($23, validate_output),
+                transform_policy = case when $24 then $25 else transform_policy end,
+                eligibility_policy = case when $26 then $27 else eligibility_policy end,
+                upstream_secret_headers = case when $28 then '{{}}'::jsonb
+                    else upstream_secret_headers end,
+                -- Pool members are funded by the pool; own budgets must cover the new price.
+                active = case
+                    when (case when $15 then $16 else budget_pool_id end) is null
+                        then budget_remaining_cents >= coalesce($6, price_cents)
+                    else active
+                end
+            where id = $1 and deleted_at is null
+            returning {SPONSORED_API_COLUMNS}
+            "#
+        ))
+        .bind(api_id)
+        .bind(payload.name.map(|name| name.trim().to_string()))
+        .bind(payload.description)
+        .bind(payload.upstream_url.map(|url| url.trim().to_string()))
+        .bind(upstream_method)
+        .bind(payload.price_cents.map(|price| price as i64))
+        .bind(forward_headers)
+        .bind(payload.body_encoding)
+        .bind(payload.cache.is_some())
+        .bind(payload.cache.flatten().map(DbJson))
+        .bind(payload.rate_limit.is_some())
+        .bind(payload.rate_limit.flatten().map(DbJson))
+        .bind(payload.health_check.is_some())
+        .bind(payload.health_check.flatten().map(DbJson))
+        .bind(pool_membership.is_some())
+        .bind(pool_membership.and_then(|(pool_id, _, _)| pool_id))
+        .bind(pool_membership.and_then(|(_, limit, _)| limit.map(|limit| limit as i64)))
+        .bind(pool_membership.is_some_and(|(_, _, changed)| changed))
+        .bind(payload.input_schema.is_some())
+        .bind(payload.input_schema.flatten())
+        .bind(payload.output_schema.is_some())
+        .bind(payload.output_schema.flatten())
+        .bind(payload.validate_output)
+        .bind(payload.transform.is_some())
+        .bind(payload.transform.flatten().map(DbJson))
+        .bind(payload.eligibility.is_some())
+        .bind(payload.eligibility.flatten().map(DbJson))
+        .bind(clear_secret_headers)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+        record_sponsored_api_change(&mut tx, api_id, "updated", Value::Object(changes)).await?;
+        tx.commit().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+        {
+            let state = state.inner.read().await;
+            state.response_cache.invalidate(api_id);
+            if upstream_changed {
+                state.health.forget(api_id);
+            }
+        }
+
+        let api = SponsoredApi::try_from(row)
+            .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+        Ok((StatusCode::OK, Json(api)))
+    }
+    .await;
+
+    respond(&metrics, "/sponsored-apis/:api_id", result)
+}
+
+async fn rotate_sponsored_api_headers(
+    State(state): State<SharedState>,
+    Path(api_id): Path<Uuid>,
+    Json(payload): Json<RotateSponsoredApiHeadersRequest>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<SponsoredApi>)> = async {
+        let (db, config) = {
+            let state = state.inner.read().await;
+            (state.db.clone(), state.config.clone())
+        };
+        let db = db.ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        validate_upstream_headers(&payload.upstream_headers)?;
+        let current = load_sponsored_api(&db, api_id).await?;
+
+        let (public, secret) =
+            split_upstream_headers(payload.upstream_headers, &payload.public_headers)?;
+        let sealed = config.upstream_secret_keys.seal_headers(&secret)?;
+
+        let (mut public_headers, mut sealed_headers) = if payload.merge {
+            (
+                current.upstream_headers.clone(),
+                current.sealed_headers.clone(),
+            )
+        } else {
+            (HashMap::new(), HashMap::new())
+        };
+        for name in public.keys().chain(sealed.keys()) {
+            public_headers.remove(name);
+            sealed_headers.remove(name);
+        }
+        public_headers.extend(public);
+        sealed_headers.extend(sealed);
+
+        // Only header names go into the audit log; values are secrets.
+        let changes = serde_json::json!({
+            "public_headers": {
+                "from": sorted_header_names(&current.upstream_headers),
+                "to": sorted_header_names(&public_headers),
+            },
+            "secret_headers": {
+                "from": current.secret_header_names,
+                "to": sorted_header_names(&sealed_headers),
+            },
+            "merge": payload.merge,
+        });
+
+        let mut tx = db.begin().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+
+        let row = sqlx::query_as::<_, SponsoredApiRow>(&format!(
+            r#"
+            update sponsored_apis
+            set upstream_headers = $2, upstream_secret_headers = $3
+            where id = $1 and deleted_at is null
+            returning {SPONSORED_API_COLUMNS}
+            "#
+        ))
+        .bind(api_id)
+        .bind(DbJson(&public_headers))
+        .bind(DbJson(&sealed_headers))
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+        record_sponsored_api_change(&mut tx, api_id, "headers_rotated", changes).await?;
+        tx.commit().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+        state.inner.read().await.response_cache.invalidate(api_id);
+
+        let api = SponsoredApi::try_from(row)
+            .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+        Ok((StatusCode::OK, Json(api)))
+    }
+    .await;
+
+    respond(&metrics, "/sponsored-apis/:api_id/headers", result)
+}
+
+async fn pause_sponsored_api(
+    State(state): State<SharedState>,
+    Path(api_id): Path<Uuid>,
+) -> Response {
+    set_sponsored_api_paused(state, api_id, true, "/sponsored-apis/:api_id/pause").await
+}
+
+async fn resume_sponsored_api(
+    State(state): State<SharedState>,
+    Path(api_id): Path<Uuid>,
+) -> Response {
+    set_sponsored_api_paused(state, api_id, false, "/sponsored-apis/:api_id/resume").await
+}
+
+async fn set_sponsored_api_paused(
+    state: SharedState,
+    api_id: Uuid,
+    paused: bool,
+    endpoint: &str,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<SponsoredApi>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let current = load_sponsored_api(&db, api_id).await?;
+        if current.paused == paused {
+            return Ok((StatusCode::OK, Json(current)));
+        }
+
+        let mut tx = db.begin().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+
+        let row = sqlx::query_as::<_, SponsoredApiRow>(&format!(
+            r#"
+            update sponsored_apis
+            set paused = $2
+            where id = $1 and deleted_at is null
+            returning {SPONSORED_API_COLUMNS}
+            "#
+        ))
+        .bind(api_id)
+        .bind(paused)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+        let action = if paused { "paused" } else { "resumed" };
+        record_sponsored_api_change(&mut tx, api_id, action, serde_json::json!({})).await?;
+        tx.commit().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+
+        let api = SponsoredApi::try_from(row)
+            .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+        Ok((StatusCode::OK, Json(api)))
+    }
+    .await;
+
+    respond(&metrics, endpoint, result)
+}
+
+async fn top_up_sponsored_api(
+    State(state): State<SharedState>,
+    Path(api_id): Path<Uuid>,
+    Json(payload): Json<TopUpSponsoredApiRequest>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<SponsoredApi>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        if payload.amount_cents == 0 {
+            return Err(ApiError::validation("amount_cents must be greater than 0"));
+        }
+        let amount = i64::try_from(payload.amount_cents)
+            .map_err(|_| ApiError::validation("amount_cents is too large"))?;
+        if let Some(pool_id) = load_sponsored_api(&db, api_id).await?.budget_pool_id {
+            return Err(ApiError::Http {
+                status: StatusCode::CONFLICT,
+                code: "budget_pool".to_string(),
+                message: format!(
+                    "sponsored api draws on budget pool {pool_id}; top up the pool instead"
+                ),
+            });
+        }
+
+        let mut tx = db.begin().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+
+        // Budget exhaustion deactivates an API; adding funds brings it back.
+        let row = sqlx::query_as::<_, SponsoredApiRow>(&format!(
+            r#"
+            update sponsored_apis
+            set budget_total_cents = budget_total_cents + $2,
+                budget_remaining_cents = budget_remaining_cents + $2,
+                active = budget_remaining_cents + $2 >= price_cents
+            where id = $1 and deleted_at is null
+            returning {SPONSORED_API_COLUMNS}
+            "#
+        ))
+        .bind(api_id)
+        .bind(amount)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .ok_or_else(|| ApiError::not_found("sponsored api not found"))?;
+
+        record_sponsored_api_change(
+            &mut tx,
+            api_id,
+            "topped_up",
+            serde_json::json!({
+                "amount_cents": payload.amount_cents,
+                "budget_remaining_cents": row.budget_remaining_cents,
+            }),
+        )
+        .await?;
+        tx.commit().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+
+        let api = SponsoredApi::try_from(row)
+            .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+        Ok((StatusCode::OK, Json(api)))
+    }
+    .await;
+
+    respond(&metrics, "/sponsored-apis/:api_id/top-up", result)
+}
+
+async fn delete_sponsored_api(
+    State(state): State<SharedState>,
+    Path(api_id): Path<Uuid>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<MessageResponse>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let mut tx = db.begin().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+
+        let deleted = sqlx::query(
+            r#"
+            update sponsored_apis
+            set deleted_at = now(), active = false
+            where id = $1 and deleted_at is null
+            "#,
+        )
+        .bind(api_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+        if deleted.rows_affected() == 0 {
+            return Err(ApiError::not_found("sponsored api not found"));
+        }
+
+        record_sponsored_api_change(&mut tx, api_id, "deleted", serde_json::json!({})).await?;
+        tx.commit().await.map_err(|err| {
+            ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+        })?;
+        {
+            let state = state.inner.read().await;
+            state.response_cache.invalidate(api_id);
+            state.health.forget(api_id);
+        }
+
+        Ok((
+            StatusCode::OK,
+            Json(MessageResponse {
+                message: "sponsored api deleted; call history retained".to_string(),
+            }),
+        ))
+    }
+    .await;
+
+    respond(&metrics, "/sponsored-apis/:api_id", result)
+}
+
+async fn list_sponsored_api_audit(
+    State(state): State<SharedState>,
+    Path(api_id): Path<Uuid>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<Vec<SponsoredApiAuditEntry>>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        // Audit history stays readable after soft deletion.
+        let entries = sqlx::query_as::<_, SponsoredApiAuditEntry>(
+            r#"
+            select id, sponsored_api_id, action, changes, created_at
+            from sponsored_api_audit_log
+            where sponsored_api_id = $1
+            order by created_at desc
+            "#,
+        )
+        .bind(api_id)
+        .fetch_all(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+        Ok((StatusCode::OK, Json(entries)))
+    }
+    .await;
+
+    respond(&metrics, "/sponsored-apis/:api_id/audit", result)
+}
+
+/// Call history for one sponsored API, newest first. Like the audit log it stays readable
+/// after the API is deleted.
+async fn list_sponsored_api_calls(
+    State(state): State<SharedState>,
+    Path(api_id): Path<Uuid>,
+    Query(query): Query<SponsoredApiCallsQuery>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<Response> = async {
+        if let (Some(from), Some(to)) = (query.from, query.to)
+            && from >= to
+        {
+            return Err(ApiError::validation("from must be before to"));
+        }
+        let limit = query.limit.unwrap_or(DEFAULT_CALL_LOG_PAGE_SIZE);
+        if !(1..=MAX_CALL_LOG_PAGE_SIZE).contains(&limit) {
+            return Err(ApiError::validation(format!(
+                "limit must be between 1 and {MAX_CALL_LOG_PAGE_SIZE}"
+            )));
+        }
+        let after = query.cursor.as_deref().map(decode_cursor).transpose()?;
+
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let api_exists = sqlx::query_scalar::<_, bool>(
+            "select exists(select 1 from sponsored_apis where id = $1)",
+        )
+        .bind(api_id)
+        .fetch_one(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+        if !api_exists {
+            return Err(ApiError::not_found("sponsored api not found"));
+        }
+
+        // One extra row tells whether there is another page.
+        let mut calls = sqlx::query_as::<_, SponsoredApiCallRow>(&format!(
+            r#"
+            select {SPONSORED_API_CALL_COLUMNS}
+            from sponsored_api_calls
+            where sponsored_api_id = $1
+                and ($2::timestamptz is null or created_at >= $2)
+                and ($3::timestamptz is null or created_at < $3)
+                and ($4::text is null or payment_mode = $4)
+                and ($5::timestamptz is null or (created_at, id) < ($5, $6))
+            order by created_at desc, id desc
+            limit $7
+            "#
+        ))
+        .bind(api_id)
+        .bind(query.from)
+        .bind(query.to)
+        .bind(query.payment_mode.as_deref())
+        .bind(after.map(|(created_at, _)| created_at))
+        .bind(after.map(|(_, id)| id))
+        .bind(i64::from(limit) + 1)
+        .fetch_all(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .into_iter()
+        .map(SponsoredApiCall::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+
+        let next_cursor = if calls.len() > limit as usize {
+            calls.truncate(limit as usize);
+            calls.last().map(encode_cursor)
+        } else {
+            None
+        };
+
+        let (content_type, body) = match query.format {
+            CallLogFormat::Json => {
+                return Ok((
+                    StatusCode::OK,
+                    Json(SponsoredApiCallPage { calls, next_cursor }),
+                )
+                    .into_response());
+            }
+            CallLogFormat::Csv => ("text/csv; charset=utf-8", render_csv(&calls)),
+            CallLogFormat::Ndjson => ("application/x-ndjson", render_ndjson(&calls)?),
+        };
+        let mut response = (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, HeaderValue::from_static(content_type))],
+            body,
+        )
+            .into_response();
+        if let Some(cursor) = next_cursor.and_then(|cursor| HeaderValue::from_str(&cursor).ok()) {
+            response
+                .headers_mut()
+                .insert(HeaderName::from_static(NEXT_CURSOR_HEADER), cursor);
+        }
+        Ok(response)
+    }
+    .await;
+
+    respond(&metrics, "/sponsored-apis/:api_id/calls", result)
+}
+
+fn record_field_change<T: serde::Serialize + PartialEq>(
+    changes: &mut serde_json::Map<String, Value>,
+    field: &str,
+    from: &T,
+    to: &T,
+) {
+    if to != from {
+        changes.insert(
+            field.to_string(),
+            serde_json::json!({ "from": from, "to": to }),
+        );
+    }
+}
+
+async fn record_sponsored_api_change(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    api_id: Uuid,
+    action: &str,
+    changes: Value,
+) -> ApiResult<()> {
+    sqlx::query(
+        r#"
+        insert into sponsored_api_audit_log (id, sponsored_api_id, action, changes, created_at)
+        values ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(api_id)
+    .bind(action)
+    .bind(changes)
+    .bind(Utc::now())
+    .execute(&mut **tx)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    Ok(())
+}
+
+fn sponsored_api_paused_error() -> ApiError {
+    ApiError::Http {
+        status: StatusCode::CONFLICT,
+        code: "sponsored_api_paused".to_string(),
+        message: "sponsored api is paused by its sponsor".to_string(),
+    }
+}
+
+/// Sponsored APIs that can be called right now, newest first, for tool and resource listings.
+async fn load_callable_sponsored_apis(db: &sqlx::PgPool) -> ApiResult<Vec<SponsoredApi>> {
+    sqlx::query_as::<_, SponsoredApiRow>(&format!(
+        r#"
+        select {SPONSORED_API_COLUMNS}
+        from sponsored_apis
+        where deleted_at is null and paused = false
+        order by created_at desc
+        "#
+    ))
+    .fetch_all(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .into_iter()
+    .map(SponsoredApi::try_from)
+    .collect::<Result<Vec<_>, _>>()
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))
+}
+
+async fn load_sponsored_api(db: &sqlx::PgPool, api_id: Uuid) -> ApiResult<SponsoredApi> {
+    sqlx::query_as::<_, SponsoredApiRow>(&format!(
+        r#"
+        select {SPONSORED_API_COLUMNS}
+        from sponsored_apis
+        where id = $1 and deleted_at is null
+        "#
+    ))
+    .bind(api_id)
+    .fetch_optional(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .ok_or_else(|| ApiError::not_found("sponsored api not found"))
+    .and_then(|row| {
+        SponsoredApi::try_from(row)
+            .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))
+    })
+}
+
+async fn run_sponsored_api(
+    State(state): State<SharedState>,
+    Path(api_id): Path<Uuid>,
+    headers: HeaderMap,
+    client: Option<Extension<ClientKey>>,
+    Json(payload): Json<SponsoredApiRunRequest>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let client = client.map(|Extension(client)| client);
+    let result = execute_sponsored_api_run(&state, api_id, &headers, client, payload).await;
+
+    respond(&metrics, "/sponsored-apis/:api_id/run", result)
+}
+
+/// One sponsored API run: charges the call, then answers from the cache, the upstream or,
+/// in async mode, with an accepted job. Shared by the REST route and MCP `tools/call`.
+async fn execute_sponsored_api_run(
+    state: &SharedState,
+    api_id: Uuid,
+    headers: &HeaderMap,
+    client: Option<ClientKey>,
+    payload: SponsoredApiRunRequest,
+) -> ApiResult<Response> {
+    let ctx = SponsoredCallContext::load(state).await?;
+
+    let api = load_sponsored_api(&ctx.db, api_id).await?;
+    if api.paused {
+        return Err(sponsored_api_paused_error());
+    }
+    // Catch URLs that a tightened policy no longer allows before anyone is charged.
+    check_upstream_url(&ctx.config.upstream_policy, &api.upstream_url)?;
+    let SponsoredApiRunRequest {
+        caller,
+        input,
+        stream,
+        mode,
+        callback_url,
+    } = payload;
+    if mode == RunMode::Async && stream {
+        return Err(ApiError::validation("async runs cannot stream"));
+    }
+    if let Some(url) = &callback_url {
+        if mode != RunMode::Async {
+            return Err(ApiError::validation("callback_url requires mode 'async'"));
+        }
+        ctx.config.upstream_policy.validate(url).await?;
+    }
+    validate_input(&api, &input)?;
+    let input = transform_request(api.transform.as_ref(), input)?;
+    let mut client = client.unwrap_or_default();
+    client.caller = client.caller.or_else(|| caller.clone());
+    ctx.check_rate_limit(&api, &client, 1).await?;
+    let upstream = prepare_upstream_request(&api, input, headers)?;
+
+    let resource_path = format!("/sponsored-apis/{api_id}/run");
+    let payment_schema = sponsored_api_payment_schema(&api);
+
+    // Only plain GET calls are cacheable; streams always go to the upstream.
+    let cache = api
+        .cache
+        .as_ref()
+        .filter(|_| api.upstream_method == "GET" && !stream && mode == RunMode::Sync)
+        .map(|policy| (policy, cache_key(api.id, &api.upstream_method, &upstream)));
+
+    if let Some((policy, key)) = &cache {
+        let result = ctx.response_cache.get(key);
+        let label = if result.is_some() {
+            CacheStatus::Hit
+        } else {
+            CacheStatus::Miss
+        };
+        ctx.metrics
+            .sponsored_api_cache_total
+            .with_label_values(&[api.id.to_string().as_str(), label.as_str()])
+            .inc();
+
+        if let Some(cached) = result {
+            let charge = charge_sponsored_call(
+                &ctx,
+                &api,
+                policy.hit_price_cents,
+                caller.as_deref(),
+                &resource_path,
+                Some(&payment_schema),
+                headers,
+            )
+            .await?;
+            record_sponsored_api_call(&ctx, &api, &charge, caller)
+                .await?
+                .with_status(cached.status)
+                .finish(StreamReport {
+                    outcome: StreamOutcome::CacheHit,
+                    bytes: cached.body.len() as u64,
+                })
+                .await;
+
+            let (body, truncated) = truncate_response(api.transform.as_ref(), cached.body.clone());
+            return Ok(sponsored_run_response(
+                &api,
+                &charge,
+                cached.status,
+                &body,
+                truncated,
+                Some(CacheStatus::Hit),
+                Vec::new(),
+            ));
+        }
+    }
+
+    // Cached responses are still served while the upstream is down.
+    ctx.health.check(api.id)?;
+    let charge = charge_sponsored_call(
+        &ctx,
+        &api,
+        api.price_cents,
+        caller.as_deref(),
+        &resource_path,
+        Some(&payment_schema),
+        headers,
+    )
+    .await?;
+
+    if mode == RunMode::Async {
+        let finalizer = record_sponsored_api_call(&ctx, &api, &charge, caller.clone()).await?;
+        let callback_secret = callback_url
+            .as_ref()
+            .map(|_| verification::generate_task_secret());
+        let job = insert_job(
+            &ctx.db,
+            &ctx.config.upstream_secret_keys,
+            NewJob {
+                id: Uuid::new_v4(),
+                sponsored_api_id: api.id,
+                call_id: finalizer.call_id,
+                budget_pool_id: api.budget_pool_id,
+                caller,
+                payment_mode: charge.payment_mode.clone(),
+                amount_cents: charge.amount_cents,
+                sponsored_by: charge.sponsored_by.clone(),
+                tx_hash: charge.tx_hash.clone(),
+                upstream,
+                callback_url: callback_url.map(|url| url.trim().to_string()),
+                callback_secret: callback_secret.clone(),
+            },
+        )
+        .await;
+        let job = match job {
+            Ok(job) => job,
+            Err(err) => {
+                finalizer.finish_without_response(&err).await;
+                return Err(err);
+            }
+        };
+
+        let location = HeaderValue::from_str(&format!("/jobs/{}", job.id))
+            .map_err(|err| ApiError::internal(err.to_string()))?;
+        let mut response = (
+            StatusCode::ACCEPTED,
+            [(header::LOCATION, location)],
+            Json(SponsoredApiJobAccepted {
+                job,
+                callback_secret,
+            }),
+        )
+            .into_response();
+        apply_charge_headers(&mut response, &charge);
+        return Ok(response);
+    }
+
+    let finalizer = record_sponsored_api_call(&ctx, &api, &charge, caller).await?;
+    let upstream = match call_upstream(&ctx.upstream_http, &ctx.config, &api, upstream).await {
+        Ok(upstream) => upstream,
+        Err(err) => {
+            finalizer.finish_without_response(&err).await;
+            return Err(err);
+        }
+    };
+
+    if stream {
+        let mut response = stream_upstream_response(&ctx.config, upstream, finalizer)?;
+        apply_charge_headers(&mut response, &charge);
+        return Ok(response);
+    }
+
+    let upstream_status = upstream.status().as_u16();
+    let cache_ttl = cache.as_ref().and_then(|(policy, _)| {
+        (upstream.status() == reqwest::StatusCode::OK)
+            .then(|| cache_ttl(policy, upstream.headers()))
+            .flatten()
+    });
+    let (body, output_violations) = read_upstream_reply(
+        &ctx.config,
+        &api,
+        upstream,
+        finalizer,
+        Duration::from_secs(ctx.config.sponsored_api_timeout_secs),
+    )
+    .await?;
+
+    if let (Some((policy, key)), Some(ttl)) = (cache.as_ref(), cache_ttl)
+        && output_violations.is_empty()
+        && body.len() as u64 <= policy.max_entry_bytes
+    {
+        ctx.response_cache
+            .insert(key.clone(), api.id, upstream_status, body.clone(), ttl);
+    }
+
+    let (body, truncated) = truncate_response(api.transform.as_ref(), body);
+    Ok(sponsored_run_response(
+        &api,
+        &charge,
+        upstream_status,
+        &body,
+        truncated,
+        cache.as_ref().map(|_| CacheStatus::Miss),
+        output_violations,
+    ))
+}
+
+/// Runs one sponsored API over many inputs. Every input is validated before the batch is
+/// charged as a whole, then items call the upstream with bounded concurrency and are logged
+/// and refunded one by one like single runs. Batches are never cached.
+async fn run_sponsored_api_batch(
+    State(state): State<SharedState>,
+    Path(api_id): Path<Uuid>,
+    headers: HeaderMap,
+    client: Option<Extension<ClientKey>>,
+    Json(payload): Json<SponsoredApiBatchRunRequest>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<Response> = async {
+        let ctx = SponsoredCallContext::load(&state).await?;
+
+        let api = load_sponsored_api(&ctx.db, api_id).await?;
+        if api.paused {
+            return Err(sponsored_api_paused_error());
+        }
+        check_upstream_url(&ctx.config.upstream_policy, &api.upstream_url)?;
+        let SponsoredApiBatchRunRequest { caller, inputs } = payload;
+        let calls = validate_batch_size(inputs.len())?;
+        let upstreams = inputs
+            .into_iter()
+            .enumerate()
+            .map(|(index, input)| {
+                validate_input(&api, &input)
+                    .and_then(|()| transform_request(api.transform.as_ref(), input))
+                    .and_then(|input| prepare_upstream_request(&api, input, &headers))
+                    .map_err(|err| err.for_batch_item(index))
+            })
+            .collect::<ApiResult<Vec<_>>>()?;
+        let mut client = client.map(|Extension(client)| client).unwrap_or_default();
+        client.caller = client.caller.or_else(|| caller.clone());
+        ctx.check_rate_limit(&api, &client, calls).await?;
+        ctx.health.check(api.id)?;
+
+        let charge = charge_sponsored_call(
+            &ctx,
+            &api,
+            batch_total_cents(api.price_cents, calls)?,
+            caller.as_deref(),
+            &format!("/sponsored-apis/{api_id}/run-batch"),
+            Some(&sponsored_api_batch_payment_schema(&api)),
+            &headers,
+        )
+        .await?;
+        // Each item is logged with its own share so refunds stay per item.
+        let item_charge = SponsoredCharge {
+            amount_cents: api.price_cents,
+            payment_response_header: None,
+            ..charge.clone()
+        };
+
+        let results: Vec<SponsoredApiBatchItem> =
+            futures_util::stream::iter(upstreams.into_iter().enumerate())
+                .map(|(index, upstream)| {
+                    run_batch_item(&ctx, &api, &item_charge, caller.clone(), index, upstream)
+                })
+                .buffered(BATCH_CONCURRENCY)
+                .collect()
+                .await;
+
+        let refunded_cents = results
+            .iter()
+            .filter(|item| item.refunded)
+            .map(|_| item_charge.amount_cents)
+            .sum::<u64>();
+        let mut response = (
+            StatusCode::OK,
+            Json(SponsoredApiBatchRunResponse {
+                api_id: api.id,
+                payment_mode: charge.payment_mode.clone(),
+                sponsored_by: charge.sponsored_by.clone(),
+                tx_hash: charge.tx_hash.clone(),
+                amount_cents: charge.amount_cents - refunded_cents,
+                refunded_cents,
+                results,
+            }),
+        )
+            .into_response();
+        apply_charge_headers(&mut response, &charge);
+        Ok(response)
+    }
+    .await;
+
+    respond(&metrics, "/sponsored-apis/:api_id/run-batch", result)
+}
+
+async fn run_batch_item(
+    ctx: &SponsoredCallContext,
+    api: &SponsoredApi,
+    charge: &SponsoredCharge,
+    caller: Option<String>,
+    index: usize,
+    upstream: UpstreamRequest,
+) -> SponsoredApiBatchItem {
+    let failed =
+        |error: ApiError, upstream_status: Option<u16>, refunded: bool| SponsoredApiBatchItem {
+            index,
+            status: BatchItemStatus::Failed,
+            amount_cents: if refunded { 0 } else { charge.amount_cents },
+            refunded,
+            upstream_status,
+            upstream_body: None,
+            truncated: false,
+            output_violations: Vec::new(),
+            error: Some(error.to_json()),
+        };
+    let refundable = charge.payment_mode == "sponsored";
+
+    let finalizer = match record_sponsored_api_call(ctx, api, charge, caller).await {
+        Ok(finalizer) => finalizer,
+        // The item never ran; recording hands its share back like any pre-response failure.
+        Err(error) => return failed(error, None, refundable),
+    };
+    let upstream = match call_upstream(&ctx.upstream_http, &ctx.config, api, upstream).await {
+        Ok(upstream) => upstream,
+        Err(error) => {
+            finalizer.finish_without_response(&error).await;
+            return failed(error, None, refundable);
+        }
+    };
+
+    let upstream_status = upstream.status().as_u16();
+    let timeout = Duration::from_secs(ctx.config.sponsored_api_timeout_secs);
+    match read_upstream_reply(&ctx.config, api, upstream, finalizer, timeout).await {
+        Ok((body, output_violations)) => {
+            let (body, truncated) = truncate_response(api.transform.as_ref(), body);
+            SponsoredApiBatchItem {
+                index,
+                status: BatchItemStatus::Succeeded,
+                amount_cents: charge.amount_cents,
+                refunded: false,
+                upstream_status: Some(upstream_status),
+                upstream_body: Some(String::from_utf8_lossy(&body).into_owned()),
+                truncated,
+                output_violations,
+                error: None,
+            }
+        }
+        Err(error) => failed(error, Some(upstream_status), false),
+    }
+}
+
+async fn refund_sponsored_charge(ctx: &SponsoredCallContext, api: &SponsoredApi, amount: u64) {
+    let result: Result<(), sqlx::Error> = async {
+        let mut tx = ctx.db.begin().await?;
+        release_api_budget(&mut tx, api.id, api.budget_pool_id, amount).await?;
+        tx.commit().await
+    }
+    .await;
+    if let Err(err) = result {
+        tracing::warn!(
+            "failed to refund unrecorded call for sponsored api {}: {err}",
+            api.id
+        );
+    }
+}
+
+/// Buffers an upstream reply for a wrapped response: metered, filtered and checked against
+/// the output schema.
+///
+/// Any x402 payment settled before the upstream was called, so a body that breaks the
+/// output schema is still delivered rather than withheld from a caller who paid for it. It
+/// comes back with its violations and the call is logged as an output contract violation
+/// against the API.
+async fn read_upstream_reply(
+    config: &AppConfig,
+    api: &SponsoredApi,
+    upstream: reqwest::Response,
+    finalizer: CallFinalizer,
+    timeout: Duration,
+) -> ApiResult<(Bytes, Vec<SchemaViolation>)> {
+    let upstream_status = upstream.status().as_u16();
+    let (report_tx, report_rx) = tokio::sync::oneshot::channel();
+    let metered = MeteredStream::new(
+        upstream,
+        config.sponsored_api_max_response_bytes,
+        timeout,
+        move |report| {
+            let _ = report_tx.send(report);
+        },
+    );
+    let reply = collect_metered(metered)
+        .await
+        .map_err(|err| ApiError::upstream(StatusCode::BAD_GATEWAY, err))
+        .and_then(|body| filter_response(api.transform.as_ref(), body))
+        .map(|body| {
+            let violations = output_violations(api, upstream_status, &body);
+            (body, violations)
+        });
+
+    // The stream has been consumed or dropped by now, so its hook has reported.
+    let report = report_rx.await.unwrap_or(StreamReport {
+        outcome: StreamOutcome::Aborted,
+        bytes: 0,
+    });
+    finalizer
+        .with_status(upstream_status)
+        .with_output_violated(
+            reply
+                .as_ref()
+                .is_ok_and(|(_, violations)| !violations.is_empty()),
+        )
+        .finish(report)
+        .await;
+    reply
+}
+
+async fn get_job(State(state): State<SharedState>, Path(job_id): Path<Uuid>) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<SponsoredApiJob>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        Ok((StatusCode::OK, Json(load_job(&db, job_id).await?)))
+    }
+    .await;
+
+    respond(&metrics, "/jobs/:job_id", result)
+}
+
+/// Jobs run at the same time by one worker.
+const JOB_CONCURRENCY: usize = 16;
+/// Extra lease time on top of the job timeout before another worker may take a job over.
+const JOB_LEASE_MARGIN_SECS: u64 = 60;
+
+/// Runs async sponsored API jobs in the background, and drops results past retention.
+async fn run_job_worker(state: SharedState) {
+    let slots = Arc::new(tokio::sync::Semaphore::new(JOB_CONCURRENCY));
+    loop {
+        let Ok(ctx) = SponsoredCallContext::load(&state).await else {
+            return;
+        };
+
+        if let Err(err) = purge_expired_jobs(&ctx.db).await {
+            tracing::warn!("failed to purge expired jobs: {err}");
+        }
+
+        let free = slots.available_permits();
+        if free > 0 {
+            let lease = ctx.config.sponsored_api_job_timeout_secs + JOB_LEASE_MARGIN_SECS;
+            match claim_due_jobs(&ctx.db, free, lease).await {
+                Ok(jobs) => {
+                    for job in jobs {
+                        let Ok(slot) = slots.clone().acquire_owned().await else {
+                            return;
+                        };
+                        let ctx = ctx.clone();
+                        tokio::spawn(async move {
+                            execute_job(ctx, job).await;
+                            drop(slot);
+                        });
+                    }
+                }
+                Err(err) => tracing::warn!("failed to claim jobs: {err}"),
+            }
+        }
+
+        tokio::time::sleep(Duration::from_secs(
+            ctx.config.job_poll_interval_secs.max(1),
+        ))
+        .await;
+    }
+}
+
+/// Calls the upstream for one job with the job timeout, then stores the result and notifies
+/// the callback URL. A failure before the upstream answers releases the reserved budget.
+async fn execute_job(ctx: SponsoredCallContext, job: ClaimedJob) {
+    let config = AppConfig {
+        sponsored_api_timeout_secs: ctx.config.sponsored_api_job_timeout_secs,
+        sponsored_api_stream_timeout_secs: ctx.config.sponsored_api_job_timeout_secs,
+        ..ctx.config.clone()
+    };
+    let timeout = Duration::from_secs(config.sponsored_api_job_timeout_secs);
+    let finalizer = CallFinalizer::for_job(&ctx, &job);
+
+    let prepared = async {
+        if job.attempts > MAX_JOB_ATTEMPTS {
+            // Our workers kept dying mid-call; that says nothing about the upstream.
+            return Err(ApiError::internal("job was interrupted too many times"));
+        }
+        let api = load_sponsored_api(&ctx.db, job.sponsored_api_id).await?;
+        let upstream = job.upstream_request(&config.upstream_secret_keys)?;
+        let response = call_upstream(&ctx.upstream_http, &config, &api, upstream).await?;
+        Ok((api, response))
+    }
+    .await;
+
+    let result = match prepared {
+        Err(error) => {
+            finalizer.finish_without_response(&error).await;
+            JobResult::Failed {
+                upstream_status: None,
+                error,
+            }
+        }
+        Ok((api, upstream)) => {
+            let upstream_status = upstream.status().as_u16();
+            match read_upstream_reply(&config, &api, upstream, finalizer, timeout).await {
+                Ok((body, output_violations)) => {
+                    let (body, truncated) = truncate_response(api.transform.as_ref(), body);
+                    JobResult::Succeeded {
+                        upstream_status,
+                        body: String::from_utf8_lossy(&body).into_owned(),
+                        truncated,
+                        output_violations,
+                    }
+                }
+                Err(error) => JobResult::Failed {
+                    upstream_status: Some(upstream_status),
+                    error,
+                },
+            }
+        }
+    };
+
+    let finished = match finish_job(&ctx.db, job.id, result, config.job_retention_secs).await {
+        Ok(finished) => finished,
+        Err(err) => {
+            tracing::warn!("failed to store result of job {}: {err}", job.id);
+            return;
+        }
+    };
+    if let (Some(url), Some(secret)) = (&job.callback_url, &job.callback_secret) {
+        deliver_job_callback(&ctx.db, &ctx.upstream_http, &finished, url, secret).await;
+    }
+}
+
+/// Dry run: shows what a call would send upstream and, given a sample reply, what the caller
+/// would get back. Nothing is charged and the upstream is never contacted.
+async fn preview_sponsored_api_transform(
+    State(state): State<SharedState>,
+    Path(api_id): Path<Uuid>,
+    headers: HeaderMap,
+    Json(payload): Json<TransformPreviewRequest>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<TransformPreviewResponse>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let mut api = load_sponsored_api(&db, api_id).await?;
+        if let Some(transform) = payload.transform {
+            validate_transform_policy(&transform)?;
+            api.transform = Some(transform);
+        }
+
+        let input_violations = api
+            .input_schema
+            .as_ref()
+            .map(|schema| schema_violations(schema, &payload.input))
+            .unwrap_or_default();
+        let input = transform_request(api.transform.as_ref(), payload.input)?;
+        let upstream = prepare_upstream_request(&api, input, &headers)?;
+
+        let response = payload
+            .upstream_body
+            .map(|sample| -> ApiResult<TransformedResponsePreview> {
+                let body = match sample {
+                    Value::String(raw) => Bytes::from(raw),
+                    other => Bytes::from(other.to_string()),
+                };
+                let body = filter_response(api.transform.as_ref(), body)?;
+                let output_violations = match (&api.output_schema, serde_json::from_slice(&body)) {
+                    (Some(schema), Ok(output)) => schema_violations(schema, &output),
+                    _ => Vec::new(),
+                };
+                let (body, truncated) = truncate_response(api.transform.as_ref(), body);
+                Ok(TransformedResponsePreview {
+                    body: String::from_utf8_lossy(&body).into_owned(),
+                    truncated,
+                    output_violations,
+                })
+            })
+            .transpose()?;
+
+        Ok((
+            StatusCode::OK,
+            Json(TransformPreviewResponse {
+                input_violations,
+                upstream_method: api.upstream_method,
+                upstream_url: upstream.url,
+                upstream_input: upstream.input,
+                response,
+            }),
+        ))
+    }
+    .await;
+
+    respond(
+        &metrics,
+        "/sponsored-apis/:api_id/transform/preview",
+        result,
+    )
+}
+
+async fn proxy_sponsored_api_root(
+    state: State<SharedState>,
+    Path(api_id): Path<Uuid>,
+    client: Option<Extension<ClientKey>>,
+    request: axum::extract::Request,
+) -> Response {
+    proxy_sponsored_api(state, Path((api_id, String::new())), client, request).await
+}
+
+/// Transparent mode: forwards the caller's method, path, query and body to the upstream and
+/// returns its status, selected headers and body bytes unchanged. An input schema applies
+/// to the request body, which must then be JSON.
+async fn proxy_sponsored_api(
+    State(state): State<SharedState>,
+    Path((api_id, path)): Path<(Uuid, String)>,
+    client: Option<Extension<ClientKey>>,
+    request: axum::extract::Request,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<Response> = async {
+        let ctx = SponsoredCallContext::load(&state).await?;
+
+        let api = load_sponsored_api(&ctx.db, api_id).await?;
+        if api.paused {
+            return Err(sponsored_api_paused_error());
+        }
+        let client = client.map(|Extension(client)| client).unwrap_or_default();
+        ctx.check_rate_limit(&api, &client, 1).await?;
+        ctx.health.check(api.id)?;
+
+        let (parts, body) = request.into_parts();
+        let upstream_url = proxy_upstream_url(&api.upstream_url, &path, parts.uri.query())?;
+        check_upstream_url(&ctx.config.upstream_policy, &upstream_url)?;
+        let body = axum::body::to_bytes(body, MAX_PROXY_REQUEST_BYTES)
+            .await
+            .map_err(|_| {
+                ApiError::validation(format!(
+                    "request body exceeds {MAX_PROXY_REQUEST_BYTES} bytes"
+                ))
+            })?;
+        validate_proxy_body(&api, &body)?;
+
+        let caller = parts
+            .headers
+            .get(SPONSORED_CALLER_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let charge = charge_sponsored_call(
+            &ctx,
+            &api,
+            api.price_cents,
+            caller.as_deref(),
+            parts.uri.path(),
+            None,
+            &parts.headers,
+        )
+        .await?;
+
+        let finalizer = record_sponsored_api_call(&ctx, &api, &charge, caller).await?;
+        let upstream = match proxy_upstream(
+            &ctx.upstream_http,
+            &ctx.config,
+            &api,
+            parts.method,
+            &upstream_url,
+            &parts.headers,
+            body,
+        )
+        .await
+        {
+            Ok(upstream) => upstream,
+            Err(err) => {
+                finalizer.finish_without_response(&err).await;
+                return Err(err);
+            }
+        };
+
+        let mut response = stream_upstream_response(&ctx.config, upstream, finalizer)?;
+        apply_charge_headers(&mut response, &charge);
+        Ok(response)
+    }
+    .await;
+
+    respond(&metrics, "/sponsored-apis/:api_id/proxy", result)
+}
+
+/// Everything a sponsored API call needs from shared state, cloned out of the lock once.
+#[derive(Clone)]
+struct SponsoredCallContext {
+    db: sqlx::PgPool,
+    http: reqwest::Client,
+    upstream_http: reqwest::Client,
+    response_cache: ResponseCache,
+    rate_limiter: RateLimiter,
+    health: HealthRegistry,
+    config: AppConfig,
+    metrics: Metrics,
+}
+
+impl SponsoredCallContext {
+    async fn load(state: &SharedState) -> ApiResult<Self> {
+        let state = state.inner.read().await;
+        Ok(Self {
+            db: state
+                .db
+                .clone()
+                .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?,
+            http: state.http.clone(),
+            upstream_http: state.upstream_http.clone(),
+            response_cache: state.response_cache.clone(),
+            rate_limiter: state.rate_limiter.clone(),
+            health: state.health.clone(),
+            config: state.config.clone(),
+            metrics: state.metrics.clone(),
+        })
+    }
+
+    /// Applies the API's own rate limit, if it has one, before anything is charged. `calls`
+    /// is the number of upstream calls the request will make.
+    async fn check_rate_limit(
+        &self,
+        api: &SponsoredApi,
+        client: &ClientKey,
+        calls: u32,
+    ) -> ApiResult<()> {
+        let Some(policy) = &api.rate_limit else {
+            return Ok(());
+        };
+        match self
+            .rate_limiter
+            .check_api(api.id, policy, client, calls)
+            .await
+        {
+            Err(err @ ApiError::RateLimited { .. }) => {
+                self.metrics
+                    .rate_limited_total
+                    .with_label_values(&["sponsored_api"])
+                    .inc();
+                Err(err)
+            }
+            Err(err @ ApiError::Http { .. }) => Err(err),
+            Err(err) => {
+                tracing::warn!("rate limiter unavailable: {err}");
+                Ok(())
+            }
+            Ok(()) => Ok(()),
+        }
+    }
+}
+
+/// How a sponsored call was paid for.
+#[derive(Clone)]
+struct SponsoredCharge {
+    payment_mode: String,
+    amount_cents: u64,
+    sponsored_by: Option<String>,
+    tx_hash: Option<String>,
+    payment_response_header: Option<String>,
+}
+
+/// Settles an x402 payment if the caller sent one, otherwise draws on the sponsor budget.
+/// Returns `402 Payment Required` when neither is available.
+async fn charge_sponsored_call(
+    ctx: &SponsoredCallContext,
+    api: &SponsoredApi,
+    price: u64,
+    caller: Option<&str>,
+    resource_path: &str,
+    output_schema: Option<&Value>,
+    headers: &HeaderMap,
+) -> ApiResult<SponsoredCharge> {
+    let SponsoredCallContext {
+        db,
+        http,
+        config,
+        metrics,
+        ..
+    } = ctx;
+    let service_key = api.service_key.clone();
+    let paying = headers.contains_key(PAYMENT_SIGNATURE_HEADER);
+
+    // Eligibility gates everything the sponsor gives away, free cache hits included.
+    let ineligible = match &api.eligibility {
+        Some(policy) if !paying || price == 0 => ineligibility_reason(db, policy, caller).await?,
+        _ => None,
+    };
+    // Ineligible callers pay for a free cache hit at the API's regular price.
+    let price = if ineligible.is_some() && price == 0 {
+        api.price_cents
+    } else {
+        price
+    };
+
+    if price == 0 {
+        if let Some(reason) = ineligible {
+            return Err(ApiError::Http {
+                status: StatusCode::FORBIDDEN,
+                code: "not_eligible".to_string(),
+                message: reason.to_string(),
+            });
+        }
+        return Ok(SponsoredCharge {
+            payment_mode: "free".to_string(),
+            amount_cents: 0,
+            sponsored_by: None,
+            tx_hash: None,
+            payment_response_header: None,
+        });
+    }
+
+    if paying {
+        let payment = verify_x402_payment(
+            http,
+            config,
+            &service_key,
+            price,
+            resource_path,
+            output_schema,
+            headers,
+        )
+        .await?;
+        metrics
+            .payment_events_total
+            .with_label_values(&["user_direct", "settled"])
+            .inc();
+        return Ok(SponsoredCharge {
+            payment_mode: "user_direct".to_string(),
+            amount_cents: price,
+            sponsored_by: None,
+            tx_hash: payment.tx_hash,
+            payment_response_header: Some(payment.payment_response_header),
+        });
+    }
+
+    let budget_exhausted = || {
+        payment_required_error(
+            config,
+            &service_key,
+            price,
+            resource_path,
+            output_schema,
+            "sponsored budget exhausted",
+            "pay with PAYMENT-SIGNATURE and retry",
+        )
+    };
+
+    if let Some(reason) = ineligible {
+        return Err(payment_required_error(
+            config,
+            &service_key,
+            price,
+            resource_path,
+            output_schema,
+            reason,
+            "pay with PAYMENT-SIGNATURE and retry",
+        ));
+    }
+
+    let BudgetDraw {
+        previous_remaining,
+        new_remaining,
+        budget_total,
+        still_active,
+    } = draw_api_budget(db, api, price)
+        .await?
+        .ok_or_else(budget_exhausted)?;
+
+    metrics
+        .payment_events_total
+        .with_label_values(&["sponsored", "settled"])
+        .inc();
+    metrics.sponsor_spend_cents_total.inc_by(price);
+
+    let event_data = serde_json::json!({
+        "sponsored_api_id": api.id,
+        "budget_pool_id": api.budget_pool_id,
+        "service": service_key,
+        "caller": caller,
+        "amount_cents": price,
+        "budget_remaining_cents": new_remaining,
+    });
+    let mut events = vec![SponsorEvent::new(
+        api.sponsor.clone(),
+        None,
+        WebhookEventType::SponsoredCallCharged,
+        event_data.clone(),
+    )];
+    events.extend(budget_events(
+        &api.sponsor,
+        None,
+        event_data,
+        previous_remaining,
+        new_remaining,
+        budget_total,
+        still_active,
+    ));
+    emit_sponsor_events(db, events).await;
+
+    Ok(SponsoredCharge {
+        payment_mode: "sponsored".to_string(),
+        amount_cents: price,
+        sponsored_by: Some(api.sponsor.clone()),
+        tx_hash: None,
+        payment_response_header: None,
+    })
+}
+
+/// Inserts the call record before the upstream is contacted; `CallFinalizer` completes it
+/// once the response body has been fully delivered, failed or been abandoned.
+async fn record_sponsored_api_call(
+    ctx: &SponsoredCallContext,
+    api: &SponsoredApi,
+    charge: &SponsoredCharge,
+    caller: Option<String>,
+) -> ApiResult<CallFinalizer> {
+    let call_log = SponsoredApiCall {
+        id: Uuid::new_v4(),
+        sponsored_api_id: api.id,
+        payment_mode: charge.payment_mode.clone(),
+        amount_cents: charge.amount_cents,
+        tx_hash: charge.tx_hash.clone(),
+        caller,
+        created_at: Utc::now(),
+        upstream_status: None,
+        response_bytes: None,
+        outcome: None,
+        duration_ms: None,
+        error_class: None,
+        finalized_at: None,
+    };
+
+    let inserted = sqlx::query(
+        r#"
+        insert into sponsored_api_calls (
+            id, sponsored_api_id, payment_mode, amount_cents, tx_hash, caller, created_at
+        ) values ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(call_log.id)
+    .bind(call_log.sponsored_api_id)
+    .bind(&call_log.payment_mode)
+    .bind(call_log.amount_cents as i64)
+    .bind(call_log.tx_hash)
+    .bind(call_log.caller)
+    .bind(call_log.created_at)
+    .execute(&ctx.db)
+    .await;
+    if let Err(err) = inserted {
+        // The budget was drawn by `charge_sponsored_call`, and without a row there is no call
+        // for a finalizer to refund against.
+        if charge.payment_mode == "sponsored" {
+            refund_sponsored_charge(ctx, api, charge.amount_cents).await;
+        }
+        return Err(ApiError::database(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            err.to_string(),
+        ));
+    }
+
+    Ok(CallFinalizer {
+        db: ctx.db.clone(),
+        health: ctx.health.clone(),
+        call_id: call_log.id,
+        sponsored_api_id: api.id,
+        budget_pool_id: api.budget_pool_id,
+        amount_cents: charge.amount_cents,
+        refundable: call_log.payment_mode == "sponsored",
+        upstream_status: None,
+        started_at: std::time::Instant::now(),
+        output_violated: false,
+    })
+}
+
+struct CallFinalizer {
+    db: sqlx::PgPool,
+    health: HealthRegistry,
+    call_id: Uuid,
+    sponsored_api_id: Uuid,
+    budget_pool_id: Option<Uuid>,
+    amount_cents: u64,
+    /// Sponsor budget can be handed back; settled x402 payments cannot.
+    refundable: bool,
+    upstream_status: Option<u16>,
+    started_at: std::time::Instant,
+    /// The delivered body broke the output schema; logged as a contract violation.
+    output_violated: bool,
+}
+
+impl CallFinalizer {
+    /// Picks up the call recorded when an async job was accepted.
+    fn for_job(ctx: &SponsoredCallContext, job: &ClaimedJob) -> Self {
+        Self {
+            db: ctx.db.clone(),
+            health: ctx.health.clone(),
+            call_id: job.call_id,
+            sponsored_api_id: job.sponsored_api_id,
+            budget_pool_id: job.budget_pool_id,
+            amount_cents: u64::try_from(job.amount_cents).unwrap_or_default(),
+            refundable: job.payment_mode == "sponsored",
+            upstream_status: None,
+            started_at: std::time::Instant::now(),
+            output_violated: false,
+        }
+    }
+
+    fn with_status(mut self, status: u16) -> Self {
+        self.upstream_status = Some(status);
+        self
+    }
+
+    fn with_output_violated(mut self, violated: bool) -> Self {
+        self.output_violated = violated;
+        self
+    }
+
+    fn into_hook(self) -> impl FnOnce(StreamReport) + Send + 'static {
+        move |report| {
+            if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                runtime.spawn(self.finish(report));
+            }
+        }
+    }
+
+    /// Only transport failures and timeouts blame the upstream; anything else went wrong
+    /// before it was reached and must not trip its circuit breaker.
+    async fn finish_without_response(self, err: &ApiError) {
+        let outcome = match err {
+            ApiError::Upstream { status, .. } if *status == StatusCode::GATEWAY_TIMEOUT => {
+                StreamOutcome::TimedOut
+            }
+            ApiError::Upstream { .. } => StreamOutcome::UpstreamError,
+            _ => StreamOutcome::LocalError,
+        };
+        self.finish(StreamReport { outcome, bytes: 0 }).await;
+    }
+
+    /// Records how the call ended. A sponsored call that failed before the upstream sent a
+    /// single byte is refunded to the sponsor budget.
+    async fn finish(self, report: StreamReport) {
+        if !matches!(
+            report.outcome,
+            StreamOutcome::CacheHit | StreamOutcome::LocalError
+        ) {
+            let failed = matches!(
+                report.outcome,
+                StreamOutcome::UpstreamError | StreamOutcome::TimedOut
+            ) || self.upstream_status.is_some_and(|status| status >= 500);
+            self.health.record_call(self.sponsored_api_id, !failed);
+        }
+
+        let refund = self.refundable
+            && report.bytes == 0
+            && matches!(
+                report.outcome,
+                StreamOutcome::UpstreamError | StreamOutcome::TimedOut | StreamOutcome::LocalError
+            );
+
+        let result: Result<(), sqlx::Error> = async {
+            let mut tx = self.db.begin().await?;
+            sqlx::query(
+                r#"
+                update sponsored_api_calls
+                set upstream_status = $2, response_bytes = $3, outcome = $4, finalized_at = now(),
+                    amount_cents = case when $5 then 0 else amount_cents end,
+                    duration_ms = $6, error_class = $7
+                where id = $1
+                "#,
+            )
+            .bind(self.call_id)
+            .bind(self.upstream_status.map(i32::from))
+            .bind(i64::try_from(report.bytes).unwrap_or(i64::MAX))
+            .bind(report.outcome.as_str())
+            .bind(refund)
+            .bind(i64::try_from(self.started_at.elapsed().as_millis()).unwrap_or(i64::MAX))
+            .bind(
+                classify_call(report.outcome, self.upstream_status).or(self
+                    .output_violated
+                    .then_some(CallErrorClass::OutputContractViolation)),
+            )
+            .execute(&mut *tx)
+            .await?;
+
+            if refund {
+                release_api_budget(
+                    &mut tx,
+                    self.sponsored_api_id,
+                    self.budget_pool_id,
+                    self.amount_cents,
+                )
+                .await?;
+            }
+            tx.commit().await
+        }
+        .await;
+
+        if let Err(err) = result {
+            tracing::warn!(
+                "failed to finalize sponsored api call {}: {err}",
+                self.call_id
+            );
+        }
+    }
+}
+
+/// Passes an upstream response through as-is, metering the body as it streams.
+fn stream_upstream_response(
+    config: &AppConfig,
+    upstream: reqwest::Response,
+    finalizer: CallFinalizer,
+) -> ApiResult<Response> {
+    let mut response = Response::builder()
+        .status(upstream.status())
+        .body(axum::body::Body::empty())
+        .map_err(|err| ApiError::internal(err.to_string()))?;
+    for name in PROXY_RESPONSE_HEADERS {
+        for value in upstream.headers().get_all(*name) {
+            response
+                .headers_mut()
+                .append(HeaderName::from_static(name), value.clone());
+        }
+    }
+    if upstream
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/event-stream"))
+    {
+        // Keep reverse proxies from buffering server-sent events.
+        response.headers_mut().insert(
+            HeaderName::from_static("x-accel-buffering"),
+            HeaderValue::from_static("no"),
+        );
+    }
+
+    let status = upstream.status().as_u16();
+    let metered = MeteredStream::new(
+        upstream,
+        config.sponsored_api_max_response_bytes,
+        Duration::from_secs(config.sponsored_api_stream_timeout_secs),
+        finalizer.with_status(status).into_hook(),
+    );
+    *response.body_mut() = axum::body::Body::from_stream(metered);
+    Ok(response)
+}
+
+fn sponsored_run_response(
+    api: &SponsoredApi,
+    charge: &SponsoredCharge,
+    upstream_status: u16,
+    body: &[u8],
+    truncated: bool,
+    cache: Option<CacheStatus>,
+    output_violations: Vec<SchemaViolation>,
+) -> Response {
+    let response_payload = SponsoredApiRunResponse {
+        api_id: api.id,
+        payment_mode: charge.payment_mode.clone(),
+        sponsored_by: charge.sponsored_by.clone(),
+        tx_hash: charge.tx_hash.clone(),
+        upstream_status,
+        upstream_body: String::from_utf8_lossy(body).into_owned(),
+        cache,
+        truncated,
+        output_violations,
+    };
+
+    let mut response = (StatusCode::OK, Json(response_payload)).into_response();
+    if let Some(cache) = cache {
+        response.headers_mut().insert(
+            HeaderName::from_static(CACHE_STATUS_HEADER),
+            HeaderValue::from_static(match cache {
+                CacheStatus::Hit => "HIT",
+                CacheStatus::Miss => "MISS",
+            }),
+        );
+    }
+    apply_charge_headers(&mut response, charge);
+    response
+}
+
+fn apply_charge_headers(response: &mut Response, charge: &SponsoredCharge) {
+    apply_payment_headers(response, charge.payment_response_header.as_deref());
+}
+
+async fn ingest_x402scan_settlement(
+    State(state): State<SharedState>,
+    Json(payload): Json<X402ScanSettlementRequest>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<MessageResponse>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let source_str = match payload.source {
+            PaymentSource::User => "user",
+            PaymentSource::Sponsor => "sponsor",
+        };
+        let status_str = match payload.status {
+            PaymentStatus::Settled => "settled",
+            PaymentStatus::Failed => "failed",
+        };
+
+        if let Some(user_id) = payload.user_id {
+            load_user_profile(&db, user_id).await?;
+        }
+
+        sqlx::query(
+            r#"
+            insert into payments (tx_hash, campaign_id, user_id, service, amount_cents, payer, source, status, created_at)
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            on conflict (tx_hash) do nothing
+            "#,
+        )
+        .bind(&payload.tx_hash)
+        .bind(payload.campaign_id)
+        .bind(payload.user_id)
+        .bind(&payload.service)
+        .bind(payload.amount_cents as i64)
+        .bind(&payload.payer)
+        .bind(source_str)
+        .bind(status_str)
+        .bind(Utc::now())
+        .execute(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+        let mode = match payload.source {
+            PaymentSource::User => "user_direct",
+            PaymentSource::Sponsor => "sponsored",
+        };
+        let status = match payload.status {
+            PaymentStatus::Settled => "settled",
+            PaymentStatus::Failed => "failed",
+        };
+
+        metrics
+            .payment_events_total
+            .with_label_values(&[mode, status])
+            .inc();
+
+        Ok((
+            StatusCode::ACCEPTED,
+            Json(MessageResponse {
+                message: "settlement ingested".to_string(),
+            }),
+        ))
+    }
+    .await;
+
+    respond(&metrics, "/webhooks/x402scan/settlement", result)
+}
+
+async fn create_sponsor_webhook(
+    State(state): State<SharedState>,
+    Json(payload): Json<CreateSponsorWebhookRequest>,
+) -> Response {
+    let (metrics, upstream_policy) = {
+        let state = state.inner.read().await;
+        (state.metrics.clone(), state.config.upstream_policy.clone())
+    };
+
+    let result: ApiResult<(StatusCode, Json<CreateSponsorWebhookResponse>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        if payload.sponsor.trim().is_empty() {
+            return Err(ApiError::validation("sponsor is required"));
+        }
+        let url = reqwest::Url::parse(payload.url.trim())
+            .map_err(|_| ApiError::validation("url must be a valid URL"))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(ApiError::validation("url must use http or https"));
+        }
+        upstream_policy.validate_field("url", url.as_str()).await?;
+
+        let threshold = payload
+            .budget_low_threshold_percent
+            .unwrap_or(DEFAULT_WEBHOOK_BUDGET_LOW_PERCENT);
+        if !(1..=99).contains(&threshold) {
+            return Err(ApiError::validation(
+                "budget_low_threshold_percent must be between 1 and 99",
+            ));
+        }
+
+        if let Some(campaign_id) = payload.campaign_id {
+            let (campaign, _) = load_campaign_with_secret(&db, campaign_id).await?;
+            if campaign.sponsor != payload.sponsor {
+                return Err(ApiError::validation(
+                    "campaign_id must belong to the webhook's sponsor",
+                ));
+            }
+        }
+
+        let mut events = if payload.events.is_empty() {
+            WebhookEventType::ALL.to_vec()
+        } else {
+            payload.events
+        };
+        events.sort();
+        events.dedup();
+
+        let secret = generate_task_secret();
+        let row = sqlx::query_as::<_, SponsorWebhookRow>(
+            r#"
+            insert into sponsor_webhooks (
+                id, sponsor, campaign_id, url, secret, events,
+                budget_low_threshold_percent, active, created_at
+            ) values ($1, $2, $3, $4, $5, $6, $7, true, $8)
+            returning id, sponsor, campaign_id, url, events, budget_low_threshold_percent,
+                active, created_at
+            "#,
+        )
+        .bind(Uuid::new_v4())
+        .bind(payload.sponsor)
+        .bind(payload.campaign_id)
+        .bind(url.to_string())
+        .bind(&secret)
+        .bind(
+            events
+                .iter()
+                .map(|event| event.as_str().to_string())
+                .collect::<Vec<_>>(),
+        )
+        .bind(i32::from(threshold))
+        .bind(Utc::now())
+        .fetch_one(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+        let webhook = SponsorWebhook::try_from(row)
+            .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
+
+        Ok((
+            StatusCode::CREATED,
+            Json(CreateSponsorWebhookResponse { webhook, secret }),
+        ))
+    }
+    .await;
+
+    respond(&metrics, "/sponsor-webhooks", result)
+}
+
+async fn list_sponsor_webhooks(
+    State(state): State<SharedState>,
+    Query(query): Query<SponsorWebhookListQuery>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<Vec<SponsorWebhook>>)> = async {
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let webhooks = sqlx::query_as::<_, SponsorWebhookRow>(
+            r#"
+            select id, sponsor, campaign_id, url, events, budget_low_threshold_percent,
+                active, created_at
+            from sponsor_webhooks
+            where sponsor = $1
+            order by created_at desc
+            "#,
+        )
+        .bind(query.sponsor)
+        .fetch_all(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .into_iter()
+        .map(SponsorWebhook::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
 
-        Ok((StatusCode::OK, Json(api)))
+        Ok((StatusCode::OK, Json(webhooks)))
     }
     .await;
 
-    respond(&metrics, "/sponsored-apis/:api_id", result)
+    respond(&metrics, "/sponsor-webhooks", result)
 }
 
-async fn run_sponsored_api(
+async fn delete_sponsor_webhook(
     State(state): State<SharedState>,
-    Path(api_id): Path<Uuid>,
-    headers: HeaderMap,
-    Json(payload): Json<SponsoredApiRunRequest>,
+    Path(webhook_id): Path<Uuid>,
 ) -> Response {
     let metrics = {
         let state = state.inner.read().await;
         state.metrics.clone()
     };
 
-    let result: ApiResult<Response> = async {
-        let (db, http, config) = {
+    let result: ApiResult<(StatusCode, Json<MessageResponse>)> = async {
+        let db = {
             let state = state.inner.read().await;
-            (state.db.clone(), state.http.clone(), state.config.clone())
-        };
-
-        let db = db.ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
-
-        let api = sqlx::query_as::<_, SponsoredApiRow>(
-            r#"
-            select id, name, sponsor, description, upstream_url, upstream_method,
-                upstream_headers, price_cents, budget_total_cents, budget_remaining_cents,
-                active, service_key, created_at
-            from sponsored_apis
-            where id = $1
-            "#,
-        )
-        .bind(api_id)
-        .fetch_optional(&db)
-        .await
-        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
-        .ok_or_else(|| ApiError::not_found("sponsored api not found"))
-        .and_then(|row| {
-            SponsoredApi::try_from(row)
-                .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))
-        })?;
-
-        let price = api.price_cents;
-        let service_key = api.service_key.clone();
-        let mut payment_mode = "sponsored".to_string();
-        let mut sponsored_by = None;
-        let mut tx_hash: Option<String> = None;
-        let mut payment_response_header: Option<String> = None;
-
-        if headers.contains_key(PAYMENT_SIGNATURE_HEADER) {
-            let resource_path = format!("/sponsored-apis/{api_id}/run");
-            let payment = verify_x402_payment(
-                &http,
-                &config,
-                &service_key,
-                price,
-                &resource_path,
-                &headers,
-            )
-            .await?;
-            metrics
-                .payment_events_total
-                .with_label_values(&["user_direct", "settled"])
-                .inc();
-            payment_mode = "user_direct".to_string();
-            tx_hash = payment.tx_hash;
-            payment_response_header = Some(payment.payment_response_header);
-        } else if api.active && api.budget_remaining_cents >= price {
-            let new_remaining = api.budget_remaining_cents.saturating_sub(price);
-            let still_active = new_remaining >= price && new_remaining > 0;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
 
-            sqlx::query(
-                r#"
-                update sponsored_apis
-                set budget_remaining_cents = $1, active = $2
-                where id = $3
-                "#,
-            )
-            .bind(new_remaining as i64)
-            .bind(still_active)
-            .bind(api.id)
+        // Deactivate rather than delete so the delivery log stays queryable.
+        let updated = sqlx::query("update sponsor_webhooks set active = false where id = $1")
+            .bind(webhook_id)
             .execute(&db)
             .await
             .map_err(|err| {
                 ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
             })?;
-
-            metrics
-                .payment_events_total
-                .with_label_values(&["sponsored", "settled"])
-                .inc();
-            metrics.sponsor_spend_cents_total.inc_by(price);
-            sponsored_by = Some(api.sponsor.clone());
-        } else {
-            return Err(payment_required_error(
-                &config,
-                &service_key,
-                price,
-                &format!("/sponsored-apis/{api_id}/run"),
-                "sponsored budget exhausted",
-                "pay with PAYMENT-SIGNATURE and retry",
-            ));
-        }
-
-        let SponsoredApiRunRequest { caller, input } = payload;
-        let (upstream_status, upstream_body) =
-            call_upstream(&http, &api, input, config.sponsored_api_timeout_secs).await?;
-
-        let call_log = SponsoredApiCall {
-            id: Uuid::new_v4(),
-            sponsored_api_id: api.id,
-            payment_mode: payment_mode.clone(),
-            amount_cents: price,
-            tx_hash: tx_hash.clone(),
-            caller,
-            created_at: Utc::now(),
-        };
-
-        sqlx::query(
-            r#"
-            insert into sponsored_api_calls (
-                id, sponsored_api_id, payment_mode, amount_cents, tx_hash, caller, created_at
-            ) values ($1, $2, $3, $4, $5, $6, $7)
-            "#,
-        )
-        .bind(call_log.id)
-        .bind(call_log.sponsored_api_id)
-        .bind(call_log.payment_mode)
-        .bind(call_log.amount_cents as i64)
-        .bind(call_log.tx_hash)
-        .bind(call_log.caller)
-        .bind(call_log.created_at)
-        .execute(&db)
-        .await
-        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
-
-        let response_payload = SponsoredApiRunResponse {
-            api_id: api.id,
-            payment_mode,
-            sponsored_by,
-            tx_hash,
-            upstream_status,
-            upstream_body,
-        };
-
-        let mut response = (StatusCode::OK, Json(response_payload)).into_response();
-        response.headers_mut().insert(
-            HeaderName::from_static(X402_VERSION_HEADER),
-            HeaderValue::from_static("2"),
-        );
-        if let Some(settlement_header) = payment_response_header {
-            if let Ok(header_value) = HeaderValue::from_str(&settlement_header) {
-                response.headers_mut().insert(
-                    HeaderName::from_static(PAYMENT_RESPONSE_HEADER),
-                    header_value,
-                );
-            }
+        if updated.rows_affected() == 0 {
+            return Err(ApiError::not_found("webhook not found"));
         }
 
-        Ok(response)
+        Ok((
+            StatusCode::OK,
+            Json(MessageResponse {
+                message: "webhook disabled".to_string(),
+            }),
+        ))
     }
     .await;
 
-    respond(&metrics, "/sponsored-apis/:api_id/run", result)
+    respond(&metrics, "/sponsor-webhooks/:webhook_id", result)
 }
 
-async fn ingest_x402scan_settlement(
+async fn list_webhook_deliveries(
     State(state): State<SharedState>,
-    Json(payload): Json<X402ScanSettlementRequest>,
+    Path(webhook_id): Path<Uuid>,
 ) -> Response {
     let metrics = {
         let state = state.inner.read().await;
         state.metrics.clone()
     };
 
-    let result: ApiResult<(StatusCode, Json<MessageResponse>)> = async {
+    let result: ApiResult<(StatusCode, Json<Vec<WebhookDelivery>>)> = async {
         let db = {
             let state = state.inner.read().await;
             state.db.clone()
         }
         .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
 
-        let source_str = match payload.source {
-            PaymentSource::User => "user",
-            PaymentSource::Sponsor => "sponsor",
-        };
-        let status_str = match payload.status {
-            PaymentStatus::Settled => "settled",
-            PaymentStatus::Failed => "failed",
-        };
-
-        sqlx::query(
+        let deliveries = sqlx::query_as::<_, WebhookDelivery>(
             r#"
-            insert into payments (tx_hash, campaign_id, service, amount_cents, payer, source, status, created_at)
-            values ($1, $2, $3, $4, $5, $6, $7, $8)
-            on conflict (tx_hash) do nothing
+            select id, webhook_id, event_type, payload, status, attempts, next_attempt_at,
+                last_status_code, last_error, delivered_at, created_at
+            from webhook_deliveries
+            where webhook_id = $1
+            order by created_at desc
+            limit 200
             "#,
         )
-        .bind(&payload.tx_hash)
-        .bind(payload.campaign_id)
-        .bind(&payload.service)
-        .bind(payload.amount_cents as i64)
-        .bind(&payload.payer)
-        .bind(source_str)
-        .bind(status_str)
-        .bind(Utc::now())
-        .execute(&db)
+        .bind(webhook_id)
+        .fetch_all(&db)
         .await
         .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
 
-        let mode = match payload.source {
-            PaymentSource::User => "user_direct",
-            PaymentSource::Sponsor => "sponsored",
-        };
-        let status = match payload.status {
-            PaymentStatus::Settled => "settled",
-            PaymentStatus::Failed => "failed",
-        };
+        Ok((StatusCode::OK, Json(deliveries)))
+    }
+    .await;
 
-        metrics
-            .payment_events_total
-            .with_label_values(&[mode, status])
-            .inc();
+    respond(&metrics, "/sponsor-webhooks/:webhook_id/deliveries", result)
+}
 
-        Ok((
-            StatusCode::ACCEPTED,
-            Json(MessageResponse {
-                message: "settlement ingested".to_string(),
-            }),
-        ))
+async fn redeliver_webhook(
+    State(state): State<SharedState>,
+    Path(delivery_id): Path<Uuid>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<WebhookDelivery>)> = async {
+        let (db, http, config) = {
+            let state = state.inner.read().await;
+            (
+                state.db.clone(),
+                state.upstream_http.clone(),
+                state.config.clone(),
+            )
+        };
+        let db = db.ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let delivery = redeliver(&db, &http, &config, delivery_id).await?;
+        Ok((StatusCode::OK, Json(delivery)))
     }
     .await;
 
-    respond(&metrics, "/webhooks/x402scan/settlement", result)
+    respond(
+        &metrics,
+        "/sponsor-webhooks/deliveries/:delivery_id/redeliver",
+        result,
+    )
 }
 
 async fn sponsor_dashboard(
@@ -1323,15 +4873,13 @@ async fn sponsor_dashboard(
         .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
 
         // Load campaign from database
-        let campaign_row = sqlx::query_as::<_, CampaignRow>(
+        let campaign_row = sqlx::query_as::<_, CampaignRow>(&format!(
             r#"
-            select id, name, sponsor, target_roles, target_tools, required_task,
-                subsidy_per_call_cents, budget_total_cents, budget_remaining_cents,
-                query_urls, active, created_at
+            select {CAMPAIGN_COLUMNS}
             from campaigns
             where id = $1
-            "#,
-        )
+            "#
+        ))
         .bind(campaign_id)
         .fetch_optional(&db)
         .await
@@ -1341,9 +4889,9 @@ async fn sponsor_dashboard(
         let campaign = Campaign::try_from(campaign_row)
             .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err))?;
 
-        // Count task completions
+        // Count verified task completions
         let tasks_completed = sqlx::query_scalar::<_, i64>(
-            "select count(*) from task_completions where campaign_id = $1",
+            "select count(*) from task_completions where campaign_id = $1 and status = 'verified'",
         )
         .bind(campaign_id)
         .fetch_one(&db)
@@ -1351,10 +4899,10 @@ async fn sponsor_dashboard(
         .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
             as usize;
 
-        // Get sponsored payments
-        let payment_amounts: Vec<i64> = sqlx::query_scalar::<_, i64>(
+        // Aggregate sponsored payments
+        let (sponsored_calls, spend_cents) = sqlx::query_as::<_, (i64, i64)>(
             r#"
-            select amount_cents
+            select count(*), coalesce(sum(amount_cents), 0)::bigint
             from payments
             where campaign_id = $1
               and source = 'sponsor'
@@ -1362,15 +4910,11 @@ async fn sponsor_dashboard(
             "#,
         )
         .bind(campaign_id)
-        .fetch_all(&db)
+        .fetch_one(&db)
         .await
         .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
-
-        let sponsored_calls = payment_amounts.len();
-        let spend_cents: u64 = payment_amounts
-            .into_iter()
-            .map(|amount| amount as u64)
-            .sum();
+        let sponsored_calls = sponsored_calls as usize;
+        let spend_cents = spend_cents as u64;
 
         let response = SponsorDashboard {
             remaining_budget_cents: campaign.budget_remaining_cents,
@@ -1387,6 +4931,252 @@ async fn sponsor_dashboard(
     respond(&metrics, "/dashboard/sponsor/:campaign_id", result)
 }
 
+async fn sponsor_timeseries(
+    State(state): State<SharedState>,
+    Path(campaign_id): Path<Uuid>,
+    Query(query): Query<SponsorTimeseriesQuery>,
+) -> Response {
+    let metrics = {
+        let state = state.inner.read().await;
+        state.metrics.clone()
+    };
+
+    let result: ApiResult<(StatusCode, Json<SponsorTimeseries>)> = async {
+        let to = query.to.unwrap_or_else(Utc::now);
+        let from = query.from.unwrap_or(to - chrono::Duration::days(7));
+        if from >= to {
+            return Err(ApiError::validation("from must be before to"));
+        }
+        let bucket_count = (to - from).num_seconds() / query.bucket.duration().num_seconds();
+        if bucket_count > MAX_DASHBOARD_BUCKETS {
+            return Err(ApiError::validation(format!(
+                "time range covers more than {MAX_DASHBOARD_BUCKETS} {} buckets",
+                query.bucket.as_str()
+            )));
+        }
+
+        let db = {
+            let state = state.inner.read().await;
+            state.db.clone()
+        }
+        .ok_or_else(|| ApiError::config("Postgres not configured; set DATABASE_URL"))?;
+
+        let campaign_exists =
+            sqlx::query_scalar::<_, bool>("select exists(select 1 from campaigns where id = $1)")
+                .bind(campaign_id)
+                .fetch_one(&db)
+                .await
+                .map_err(|err| {
+                    ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                })?;
+        if !campaign_exists {
+            return Err(ApiError::not_found("campaign not found"));
+        }
+
+        #[derive(sqlx::FromRow)]
+        struct PointRow {
+            bucket_start: chrono::DateTime<chrono::Utc>,
+            spend_cents: i64,
+            sponsored_calls: i64,
+            unique_users: i64,
+            task_completions: i64,
+        }
+
+        let series = sqlx::query_as::<_, PointRow>(
+            r#"
+            with buckets as (
+                select generate_series(
+                    date_trunc($2, $3::timestamptz),
+                    $4::timestamptz - interval '1 microsecond',
+                    ('1 ' || $2)::interval
+                ) as bucket_start
+            ),
+            pay as (
+                select date_trunc($2, created_at) as bucket_start,
+                    sum(amount_cents)::bigint as spend_cents,
+                    count(*) as sponsored_calls,
+                    count(distinct user_id) as unique_users
+                from payments
+                where campaign_id = $1
+                  and source = 'sponsor'
+                  and status = 'settled'
+                  and created_at >= $3 and created_at < $4
+                group by 1
+            ),
+            tasks as (
+                select date_trunc($2, coalesce(verified_at, created_at)) as bucket_start,
+                    count(*) as task_completions
+                from task_completions
+                where campaign_id = $1
+                  and status = 'verified'
+                  and coalesce(verified_at, created_at) >= $3
+                  and coalesce(verified_at, created_at) < $4
+                group by 1
+            )
+            select b.bucket_start,
+                coalesce(p.spend_cents, 0)::bigint as spend_cents,
+                coalesce(p.sponsored_calls, 0)::bigint as sponsored_calls,
+                coalesce(p.unique_users, 0)::bigint as unique_users,
+                coalesce(t.task_completions, 0)::bigint as task_completions
+            from buckets b
+            left join pay p using (bucket_start)
+            left join tasks t using (bucket_start)
+            order by b.bucket_start
+            "#,
+        )
+        .bind(campaign_id)
+        .bind(query.bucket.as_str())
+        .bind(from)
+        .bind(to)
+        .fetch_all(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .into_iter()
+        .map(|row| SponsorTimeseriesPoint {
+            bucket_start: row.bucket_start,
+            spend_cents: row.spend_cents as u64,
+            sponsored_calls: row.sponsored_calls as u64,
+            unique_users: row.unique_users as u64,
+            task_completions: row.task_completions as u64,
+        })
+        .collect::<Vec<_>>();
+
+        #[derive(sqlx::FromRow)]
+        struct BreakdownRow {
+            key: String,
+            spend_cents: i64,
+            sponsored_calls: i64,
+            unique_users: i64,
+        }
+
+        let breakdown = |row: BreakdownRow| SponsorBreakdownRow {
+            key: row.key,
+            spend_cents: row.spend_cents as u64,
+            sponsored_calls: row.sponsored_calls as u64,
+            unique_users: row.unique_users as u64,
+        };
+
+        let by_service = sqlx::query_as::<_, BreakdownRow>(
+            r#"
+            select service as key,
+                sum(amount_cents)::bigint as spend_cents,
+                count(*) as sponsored_calls,
+                count(distinct user_id) as unique_users
+            from payments
+            where campaign_id = $1
+              and source = 'sponsor'
+              and status = 'settled'
+              and created_at >= $2 and created_at < $3
+            group by service
+            order by spend_cents desc, key
+            "#,
+        )
+        .bind(campaign_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .into_iter()
+        .map(breakdown)
+        .collect::<Vec<_>>();
+
+        let by_region = sqlx::query_as::<_, BreakdownRow>(
+            r#"
+            select coalesce(u.region, 'unknown') as key,
+                sum(p.amount_cents)::bigint as spend_cents,
+                count(*) as sponsored_calls,
+                count(distinct p.user_id) as unique_users
+            from payments p
+            left join users u on u.id = p.user_id
+            where p.campaign_id = $1
+              and p.source = 'sponsor'
+              and p.status = 'settled'
+              and p.created_at >= $2 and p.created_at < $3
+            group by 1
+            order by spend_cents desc, key
+            "#,
+        )
+        .bind(campaign_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+        .into_iter()
+        .map(breakdown)
+        .collect::<Vec<_>>();
+
+        let (task_completed_users, converted_users) = sqlx::query_as::<_, (i64, i64)>(
+            r#"
+            with task_users as (
+                select user_id, min(coalesce(verified_at, created_at)) as first_verified_at
+                from task_completions
+                where campaign_id = $1
+                  and status = 'verified'
+                  and user_id is not null
+                  and coalesce(verified_at, created_at) >= $2
+                  and coalesce(verified_at, created_at) < $3
+                group by user_id
+            ),
+            first_calls as (
+                select user_id, min(created_at) as first_call_at
+                from payments
+                where campaign_id = $1
+                  and source = 'sponsor'
+                  and status = 'settled'
+                  and user_id is not null
+                group by user_id
+            )
+            select count(*),
+                count(f.user_id) filter (where f.first_call_at >= t.first_verified_at)
+            from task_users t
+            left join first_calls f using (user_id)
+            "#,
+        )
+        .bind(campaign_id)
+        .bind(from)
+        .bind(to)
+        .fetch_one(&db)
+        .await
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+        let spend_cents: u64 = series.iter().map(|point| point.spend_cents).sum();
+        let conversion_rate = if task_completed_users == 0 {
+            0.0
+        } else {
+            converted_users as f64 / task_completed_users as f64
+        };
+        let cost_per_acquired_user_cents =
+            (converted_users > 0).then(|| spend_cents as f64 / converted_users as f64);
+
+        Ok((
+            StatusCode::OK,
+            Json(SponsorTimeseries {
+                campaign_id,
+                bucket: query.bucket,
+                from,
+                to,
+                series,
+                by_service,
+                by_region,
+                spend_cents,
+                task_completed_users: task_completed_users as u64,
+                converted_users: converted_users as u64,
+                conversion_rate,
+                cost_per_acquired_user_cents,
+            }),
+        ))
+    }
+    .await;
+
+    respond(
+        &metrics,
+        "/dashboard/sponsor/:campaign_id/timeseries",
+        result,
+    )
+}
+
 async fn record_creator_metric_event(
     State(state): State<SharedState>,
     Json(payload): Json<CreatorMetricEventRequest>,
@@ -1551,5 +5341,19 @@ async fn prometheus_metrics(State(state): State<SharedState>) -> Response {
         .into_response()
 }
 
+async fn openapi_spec(State(state): State<SharedState>) -> Response {
+    let state = state.inner.read().await;
+    respond(
+        &state.metrics,
+        "/openapi.json",
+        Ok((StatusCode::OK, Json(openapi_document(&state.config)))),
+    )
+}
+
+async fn api_docs_page(State(state): State<SharedState>) -> Response {
+    let state = state.inner.read().await;
+    respond(&state.metrics, "/docs", Ok(Html(docs_page(&state.config))))
+}
+
 #[cfg(test)]
 mod test;
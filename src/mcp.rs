@@ -0,0 +1,253 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value, json};
+use uuid::Uuid;
+
+use crate::error::ApiError;
+use crate::types::{BUILTIN_SERVICES, PaymentRequired, SPONSORED_API_SERVICE_PREFIX, SponsoredApi};
+
+pub const LATEST_PROTOCOL_VERSION: &str = "2025-06-18";
+const SUPPORTED_PROTOCOL_VERSIONS: [&str; 3] = ["2025-06-18", "2025-03-26", "2024-11-05"];
+/// `_meta` key under which x402-aware MCP clients send a payment with `tools/call`.
+pub const PAYMENT_META_KEY: &str = "x402/payment";
+/// `_meta` key of a tool result carrying the settlement of that payment.
+pub const PAYMENT_RESPONSE_META_KEY: &str = "x402/payment-response";
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+pub const INTERNAL_ERROR: i64 = -32603;
+
+#[derive(Debug, Deserialize)]
+pub struct RpcRequest {
+    pub jsonrpc: String,
+    /// Absent for notifications, which get no response.
+    #[serde(default)]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpcResponse {
+    jsonrpc: &'static str,
+    id: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    result: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<RpcError>,
+}
+
+impl RpcResponse {
+    pub fn new(id: Value, outcome: Result<Value, RpcError>) -> Self {
+        let (result, error) = match outcome {
+            Ok(result) => (Some(result), None),
+            Err(error) => (None, Some(error)),
+        };
+        Self {
+            jsonrpc: "2.0",
+            id,
+            result,
+            error,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+/// Failures outside a tool call, like a database error while listing tools.
+impl From<ApiError> for RpcError {
+    fn from(err: ApiError) -> Self {
+        Self::new(INTERNAL_ERROR, err.to_string())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ToolCallParams {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Value,
+    #[serde(default, rename = "_meta")]
+    pub meta: Map<String, Value>,
+}
+
+impl ToolCallParams {
+    /// The x402 payment sent in `_meta`, encoded as a `PAYMENT-SIGNATURE` header value.
+    pub fn payment_signature(&self) -> Option<String> {
+        match self.meta.get(PAYMENT_META_KEY)? {
+            Value::String(encoded) => Some(encoded.clone()),
+            Value::Null => None,
+            payload => Some(STANDARD.encode(payload.to_string())),
+        }
+    }
+}
+
+/// Arguments of a sponsored API tool: the run body without the REST-only options.
+#[derive(Debug, Deserialize)]
+pub struct SponsoredToolArguments {
+    #[serde(default)]
+    pub input: Value,
+    #[serde(default)]
+    pub caller: Option<String>,
+}
+
+/// What an MCP tool name refers to.
+#[derive(Debug, PartialEq, Eq)]
+pub enum McpTool {
+    SponsoredApi(Uuid),
+    Builtin(String),
+}
+
+impl McpTool {
+    /// Sponsored APIs are named by their service key, built-in tools by their service.
+    pub fn parse(name: &str) -> Option<Self> {
+        if let Some(api_id) = name
+            .strip_prefix(SPONSORED_API_SERVICE_PREFIX)
+            .and_then(|rest| rest.strip_prefix('-'))
+        {
+            return Uuid::parse_str(api_id).ok().map(Self::SponsoredApi);
+        }
+        BUILTIN_SERVICES
+            .contains(&name)
+            .then(|| Self::Builtin(name.to_string()))
+    }
+}
+
+/// Agrees on the client's protocol version when supported, otherwise offers the latest.
+pub fn initialize_result(requested: Option<&str>) -> Value {
+    let version = requested
+        .filter(|version| SUPPORTED_PROTOCOL_VERSIONS.contains(version))
+        .unwrap_or(LATEST_PROTOCOL_VERSION);
+    json!({
+        "protocolVersion": version,
+        "capabilities": { "tools": { "listChanged": false } },
+        "serverInfo": {
+            "name": env!("CARGO_PKG_NAME"),
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "instructions": "Sponsored tools are free while the sponsor's budget lasts. When a \
+            call returns a payment_required error, pay with x402 by retrying with the signed \
+            payment in _meta[\"x402/payment\"].",
+    })
+}
+
+pub fn sponsored_api_tool(api: &SponsoredApi) -> Value {
+    let description = api
+        .description
+        .clone()
+        .unwrap_or_else(|| format!("Calls {} through a sponsored proxy.", api.name));
+    json!({
+        "name": api.service_key,
+        "title": api.name,
+        "description": format!(
+            "{description} Costs {} cents per call, paid by {} while the budget lasts.",
+            api.price_cents, api.sponsor
+        ),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "input": api.input_schema.clone().unwrap_or_else(|| json!({})),
+                "caller": {
+                    "type": "string",
+                    "description":
+                        "Profile id or email, checked against the sponsor's eligibility rules.",
+                },
+            },
+            "required": ["input"],
+        },
+    })
+}
+
+pub fn builtin_tool(service: &str, price_cents: u64) -> Value {
+    json!({
+        "name": service,
+        "title": service,
+        "description":
+            format!("Runs the '{service}' tool. Costs {price_cents} cents, paid with x402."),
+        "inputSchema": {
+            "type": "object",
+            "properties": {
+                "user_id": { "type": "string", "format": "uuid" },
+                "input": { "type": "string" },
+            },
+            "required": ["user_id", "input"],
+        },
+    })
+}
+
+/// Wraps a REST response body as a tool result, keeping JSON bodies as structured content.
+pub fn tool_result(body: &[u8], payment_response: Option<&str>) -> Value {
+    let text = String::from_utf8_lossy(body).into_owned();
+    let mut result = json!({
+        "content": [{ "type": "text", "text": text }],
+        "isError": false,
+    });
+    if let Ok(structured @ Value::Object(_)) = serde_json::from_slice::<Value>(body) {
+        result["structuredContent"] = structured;
+    }
+    if let Some(payment_response) = payment_response {
+        result["_meta"] =
+            json!({ PAYMENT_RESPONSE_META_KEY: decode_header_json(payment_response) });
+    }
+    result
+}
+
+/// Errors from a tool call are tool results the agent can act on, not protocol errors.
+/// A payment challenge carries the decoded x402 requirements as `accepts`.
+pub fn tool_error(err: &ApiError) -> Value {
+    let structured = match err {
+        ApiError::PaymentRequired(payload) => payment_required_content(payload),
+        other => json!({ "error": other.to_json() }),
+    };
+    let message = match err {
+        ApiError::PaymentRequired(payload) => {
+            format!(
+                "payment required: {}; {}",
+                payload.message, payload.next_step
+            )
+        }
+        other => other.to_string(),
+    };
+    json!({
+        "content": [{ "type": "text", "text": message }],
+        "structuredContent": structured,
+        "isError": true,
+    })
+}
+
+fn payment_required_content(payload: &PaymentRequired) -> Value {
+    json!({
+        "x402Version": 2,
+        "error": payload.message,
+        "accepts": decode_header_json(&payload.payment_required),
+        "service": payload.service,
+        "amount_cents": payload.amount_cents,
+        "accepted_header": payload.accepted_header,
+        "payment_required": payload.payment_required,
+        "next_step": payload.next_step,
+    })
+}
+
+/// x402 headers are base64-encoded JSON; anything else is passed on as a string.
+fn decode_header_json(value: &str) -> Value {
+    STANDARD
+        .decode(value)
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
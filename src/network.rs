@@ -0,0 +1,283 @@
+use reqwest::{
+    Client, Url,
+    dns::{Addrs, Name, Resolve, Resolving},
+    redirect,
+};
+use std::{
+    error::Error as StdError,
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::error::{ApiError, ApiResult};
+
+pub const DEFAULT_UPSTREAM_ALLOWED_SCHEMES: &str = "https,http";
+pub const DEFAULT_UPSTREAM_MAX_REDIRECTS: u64 = 3;
+
+/// Where sponsored API upstreams may point. Checked when a URL is registered and again on
+/// every connection, since DNS answers can change between the two.
+#[derive(Debug, Clone)]
+pub struct UpstreamPolicy {
+    pub allowed_schemes: Vec<String>,
+    /// Exact hosts or `*.example.com` suffix patterns. Empty means any public host.
+    pub allowed_hosts: Vec<String>,
+    pub allow_private_networks: bool,
+    pub max_redirects: usize,
+}
+
+impl Default for UpstreamPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_schemes: parse_list(DEFAULT_UPSTREAM_ALLOWED_SCHEMES),
+            allowed_hosts: Vec::new(),
+            allow_private_networks: false,
+            max_redirects: DEFAULT_UPSTREAM_MAX_REDIRECTS as usize,
+        }
+    }
+}
+
+impl UpstreamPolicy {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            allowed_schemes: std::env::var("UPSTREAM_ALLOWED_SCHEMES")
+                .map(|value| parse_list(&value))
+                .unwrap_or(defaults.allowed_schemes),
+            allowed_hosts: std::env::var("UPSTREAM_ALLOWED_HOSTS")
+                .map(|value| parse_list(&value))
+                .unwrap_or_default(),
+            allow_private_networks: std::env::var("UPSTREAM_ALLOW_PRIVATE_NETWORKS")
+                .map(|value| matches!(value.trim(), "1" | "true" | "yes"))
+                .unwrap_or(false),
+            max_redirects: std::env::var("UPSTREAM_MAX_REDIRECTS")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(defaults.max_redirects),
+        }
+    }
+
+    /// Checks everything that can be decided without DNS: scheme, host allowlist and
+    /// literal IP addresses.
+    pub fn check_url(&self, url: &Url) -> Result<(), String> {
+        if !self
+            .allowed_schemes
+            .iter()
+            .any(|scheme| scheme == url.scheme())
+        {
+            return Err(format!(
+                "scheme '{}' is not allowed; use one of: {}",
+                url.scheme(),
+                self.allowed_schemes.join(", ")
+            ));
+        }
+
+        let host = url
+            .host_str()
+            .ok_or_else(|| "URL must include a host".to_string())?
+            .trim_matches(['[', ']'])
+            .trim_end_matches('.')
+            .to_ascii_lowercase();
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            self.check_ip(ip)?;
+        }
+
+        if !self.allowed_hosts.is_empty()
+            && !self
+                .allowed_hosts
+                .iter()
+                .any(|pattern| host_matches(pattern, &host))
+        {
+            return Err(format!("host '{host}' is not in the upstream allowlist"));
+        }
+
+        if !self.allow_private_networks && (host == "localhost" || host.ends_with(".localhost")) {
+            return Err(format!("host '{host}' is a loopback address"));
+        }
+
+        Ok(())
+    }
+
+    pub fn check_ip(&self, ip: IpAddr) -> Result<(), String> {
+        if self.allow_private_networks || is_public_ip(ip) {
+            Ok(())
+        } else {
+            Err(format!(
+                "address {ip} is private, loopback, link-local or otherwise reserved"
+            ))
+        }
+    }
+
+    /// Full registration-time check, including resolving the host.
+    pub async fn validate(&self, upstream_url: &str) -> ApiResult<()> {
+        self.validate_field("upstream_url", upstream_url).await
+    }
+
+    /// Same as `validate` for other sponsor-supplied URLs, naming `field` in errors.
+    pub async fn validate_field(&self, field: &str, value: &str) -> ApiResult<()> {
+        let url = Url::parse(value.trim())
+            .map_err(|_| ApiError::validation(format!("{field} must be a valid URL")))?;
+        self.check_url(&url)
+            .map_err(|err| ApiError::validation(format!("{field} is not allowed: {err}")))?;
+
+        let (Some(host), Some(port)) = (url.host_str(), url.port_or_known_default()) else {
+            return Err(ApiError::validation(format!("{field} must include a host")));
+        };
+        let addrs = resolve_checked(self, host.trim_matches(['[', ']']), port)
+            .await
+            .map_err(|err| ApiError::validation(format!("{field} is not allowed: {err}")))?;
+        if addrs.is_empty() {
+            return Err(ApiError::validation(format!(
+                "{field} host '{host}' did not resolve"
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Marker error so blocked connections can be told apart from ordinary network failures.
+#[derive(Debug)]
+pub struct BlockedUpstream(pub String);
+
+impl fmt::Display for BlockedUpstream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "upstream blocked by network policy: {}", self.0)
+    }
+}
+
+impl StdError for BlockedUpstream {}
+
+pub fn blocked_upstream_reason(err: &reqwest::Error) -> Option<String> {
+    let mut source = err.source();
+    while let Some(current) = source {
+        if let Some(blocked) = current.downcast_ref::<BlockedUpstream>() {
+            return Some(blocked.0.clone());
+        }
+        source = current.source();
+    }
+    None
+}
+
+/// Resolver for the upstream client. Rejects the whole lookup if any answer is blocked so a
+/// rebinding host cannot slip a private address in alongside a public one.
+#[derive(Debug, Clone)]
+struct GuardedResolver {
+    policy: Arc<UpstreamPolicy>,
+}
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            let addrs = resolve_checked(&policy, name.as_str(), 0).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+async fn resolve_checked(
+    policy: &UpstreamPolicy,
+    host: &str,
+    port: u16,
+) -> Result<Vec<SocketAddr>, Box<dyn StdError + Send + Sync>> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+    for addr in &addrs {
+        policy
+            .check_ip(addr.ip())
+            .map_err(|err| BlockedUpstream(format!("{host}: {err}")))?;
+    }
+    Ok(addrs)
+}
+
+/// Client used for sponsor-controlled upstreams. Every connection goes through the guarded
+/// resolver and every redirect hop is re-checked against the policy.
+pub fn build_upstream_client(policy: &UpstreamPolicy) -> Client {
+    let policy = Arc::new(policy.clone());
+    let redirect_policy = {
+        let policy = policy.clone();
+        redirect::Policy::custom(move |attempt| {
+            if attempt.previous().len() > policy.max_redirects {
+                return attempt.error(BlockedUpstream(format!(
+                    "more than {} redirects",
+                    policy.max_redirects
+                )));
+            }
+            match policy.check_url(attempt.url()) {
+                Ok(()) => attempt.follow(),
+                Err(err) => attempt.error(BlockedUpstream(format!("redirect target {err}"))),
+            }
+        })
+    };
+
+    Client::builder()
+        .timeout(Duration::from_secs(20))
+        .dns_resolver(Arc::new(GuardedResolver { policy }))
+        .redirect(redirect_policy)
+        .build()
+        .expect("upstream http client should build")
+}
+
+fn parse_list(raw: &str) -> Vec<String> {
+    raw.split(',')
+        .map(|entry| entry.trim().to_ascii_lowercase())
+        .filter(|entry| !entry.is_empty())
+        .collect()
+}
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => host
+            .strip_suffix(suffix)
+            .is_some_and(|prefix| prefix.ends_with('.')),
+        None => pattern == host,
+    }
+}
+
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => is_public_ipv6(ip),
+    }
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, c, _] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Carrier-grade NAT 100.64.0.0/10.
+        || (a == 100 && (b & 0xc0) == 64)
+        // IETF protocol assignments 192.0.0.0/24.
+        || (a == 192 && b == 0 && c == 0)
+        // Benchmarking 198.18.0.0/15.
+        || (a == 198 && (b & 0xfe) == 18)
+        // Reserved 240.0.0.0/4.
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    if let Some(mapped) = ip.to_ipv4_mapped() {
+        return is_public_ipv4(mapped);
+    }
+    let segments = ip.segments();
+    // NAT64 64:ff9b::/96 embeds an IPv4 address in the low 32 bits.
+    if segments[..6] == [0x64, 0xff9b, 0, 0, 0, 0] {
+        let [.., hi, lo] = segments;
+        return is_public_ipv4(Ipv4Addr::from((u32::from(hi) << 16) | u32::from(lo)));
+    }
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_multicast()
+        // Unique local fc00::/7.
+        || (segments[0] & 0xfe00) == 0xfc00
+        // Link-local fe80::/10.
+        || (segments[0] & 0xffc0) == 0xfe80
+        // Documentation 2001:db8::/32.
+        || (segments[0] == 0x2001 && segments[1] == 0x0db8))
+}
@@ -0,0 +1,391 @@
+use serde_json::{Map, Value, json};
+
+use crate::error::{ApiError, ApiResult};
+use crate::types::{SkippedOperation, UpstreamBodyEncoding};
+use crate::utils::upstream_path_params;
+
+/// `$ref` chains deeper than this are left as an unconstrained schema.
+const MAX_REF_DEPTH: usize = 32;
+const IMPORTABLE_METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+/// One operation from a sponsor's OpenAPI document, ready to become a sponsored API.
+#[derive(Debug, Clone)]
+pub struct ImportedOperation {
+    /// `METHOD /path`, as used to select operations.
+    pub key: String,
+    pub operation_id: Option<String>,
+    pub name: String,
+    pub description: Option<String>,
+    pub method: String,
+    pub upstream_url: String,
+    pub body_encoding: UpstreamBodyEncoding,
+    pub input_schema: Value,
+    /// Schema of the first JSON success response, if the document declares one.
+    pub output_schema: Option<Value>,
+}
+
+/// Accepts a parsed document, or a JSON or YAML string.
+pub fn parse_document(document: Value) -> ApiResult<Value> {
+    let document = match document {
+        Value::String(raw) => serde_json::from_str(&raw)
+            .or_else(|_| serde_yaml::from_str::<Value>(&raw))
+            .map_err(|err| {
+                ApiError::validation(format!("document is not valid JSON or YAML: {err}"))
+            })?,
+        other => other,
+    };
+
+    let version = document
+        .get("openapi")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if !version.starts_with("3.") {
+        return Err(ApiError::validation(
+            "document must be an OpenAPI 3 document with an `openapi: 3.x` field",
+        ));
+    }
+    if !document.get("paths").is_some_and(Value::is_object) {
+        return Err(ApiError::validation("document has no paths"));
+    }
+    Ok(document)
+}
+
+/// The first server URL with its variables set to their defaults, unless overridden.
+pub fn base_url(document: &Value, override_url: Option<&str>) -> ApiResult<String> {
+    if let Some(url) = override_url {
+        return Ok(url.trim().trim_end_matches('/').to_string());
+    }
+    let server = document
+        .pointer("/servers/0")
+        .ok_or_else(|| ApiError::validation("document has no servers; provide base_url"))?;
+    let mut url = server
+        .get("url")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    if let Some(variables) = server.get("variables").and_then(Value::as_object) {
+        for (name, variable) in variables {
+            if let Some(default) = variable.get("default").and_then(Value::as_str) {
+                url = url.replace(&format!("{{{name}}}"), default);
+            }
+        }
+    }
+    if !(url.starts_with("https://") || url.starts_with("http://")) {
+        return Err(ApiError::validation(format!(
+            "server url '{url}' is not absolute; provide base_url"
+        )));
+    }
+    Ok(url.trim_end_matches('/').to_string())
+}
+
+/// Lists the document's operations. `selected` holds operation ids or `METHOD /path` keys;
+/// an empty selection imports everything importable.
+pub fn operations(
+    document: &Value,
+    base_url: &str,
+    selected: &[String],
+) -> (Vec<ImportedOperation>, Vec<SkippedOperation>) {
+    let mut imported = Vec::new();
+    let mut skipped = Vec::new();
+    let paths = document
+        .get("paths")
+        .and_then(Value::as_object)
+        .cloned()
+        .unwrap_or_default();
+
+    for (path, item) in &paths {
+        let item = resolve_refs(item, document, 0);
+        let shared_parameters = item
+            .get("parameters")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+
+        let Some(item) = item.as_object() else {
+            continue;
+        };
+        for (method, operation) in item {
+            if matches!(
+                method.as_str(),
+                "parameters" | "summary" | "description" | "servers"
+            ) || method.starts_with("x-")
+            {
+                continue;
+            }
+            let key = format!("{} {path}", method.to_ascii_uppercase());
+            let operation_id = operation
+                .get("operationId")
+                .and_then(Value::as_str)
+                .map(str::to_string);
+            if !is_selected(selected, &key, operation_id.as_deref()) {
+                continue;
+            }
+            if !IMPORTABLE_METHODS.contains(&method.as_str()) {
+                skipped.push(SkippedOperation {
+                    operation: key,
+                    reason: format!("method {} is not supported", method.to_ascii_uppercase()),
+                });
+                continue;
+            }
+
+            match import_operation(&key, method, path, operation, &shared_parameters, base_url) {
+                Ok(operation) => imported.push(ImportedOperation {
+                    operation_id,
+                    ..operation
+                }),
+                Err(reason) => skipped.push(SkippedOperation {
+                    operation: key,
+                    reason,
+                }),
+            }
+        }
+    }
+
+    // Report selections that matched nothing so typos do not pass silently.
+    for wanted in selected {
+        let matched = imported.iter().any(|operation| {
+            matches_selection(wanted, &operation.key, operation.operation_id.as_deref())
+        }) || skipped
+            .iter()
+            .any(|skip| matches_selection(wanted, &skip.operation, None));
+        if !matched {
+            skipped.push(SkippedOperation {
+                operation: wanted.clone(),
+                reason: "no matching operation in the document".to_string(),
+            });
+        }
+    }
+
+    (imported, skipped)
+}
+
+fn is_selected(selected: &[String], key: &str, operation_id: Option<&str>) -> bool {
+    selected.is_empty()
+        || selected
+            .iter()
+            .any(|wanted| matches_selection(wanted, key, operation_id))
+}
+
+fn matches_selection(wanted: &str, key: &str, operation_id: Option<&str>) -> bool {
+    let wanted = wanted.trim();
+    operation_id == Some(wanted)
+        || wanted.split_once(' ').is_some_and(|(method, path)| {
+            format!("{} {}", method.to_ascii_uppercase(), path.trim()) == key
+        })
+}
+
+fn import_operation(
+    key: &str,
+    method: &str,
+    path: &str,
+    operation: &Value,
+    shared_parameters: &[Value],
+    base_url: &str,
+) -> Result<ImportedOperation, String> {
+    let upstream_url = format!("{base_url}{path}");
+    let path_params = upstream_path_params(&upstream_url).map_err(|err| err.to_string())?;
+
+    // Operation parameters override path-level ones with the same name and location.
+    let mut parameters: Vec<&Value> = Vec::new();
+    let operation_parameters = operation
+        .get("parameters")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    for parameter in shared_parameters.iter().chain(operation_parameters) {
+        let identity = (parameter.get("name"), parameter.get("in"));
+        parameters.retain(|existing| (existing.get("name"), existing.get("in")) != identity);
+        parameters.push(parameter);
+    }
+
+    let sends_body = !matches!(method, "get" | "delete");
+    let mut properties = Map::new();
+    let mut required = Vec::new();
+    for parameter in parameters {
+        let (Some(name), Some(location)) = (
+            parameter.get("name").and_then(Value::as_str),
+            parameter.get("in").and_then(Value::as_str),
+        ) else {
+            continue;
+        };
+        match location {
+            "path" => {}
+            "query" if !sends_body => {}
+            "query" if parameter.get("required").and_then(Value::as_bool) == Some(true) => {
+                return Err(format!(
+                    "required query parameter '{name}' cannot be sent with a request body"
+                ));
+            }
+            // Optional query parameters on body methods, headers and cookies are left to
+            // the sponsor's static headers or forward_headers.
+            _ => continue,
+        }
+        properties.insert(
+            name.to_string(),
+            parameter
+                .get("schema")
+                .cloned()
+                .unwrap_or_else(|| json!({})),
+        );
+        if location == "path" || parameter.get("required").and_then(Value::as_bool) == Some(true) {
+            required.push(Value::String(name.to_string()));
+        }
+    }
+    // Templated segments the document forgot to declare are still required.
+    for param in path_params {
+        if !properties.contains_key(&param) {
+            properties.insert(param.clone(), json!({ "type": "string" }));
+            required.push(Value::String(param));
+        }
+    }
+
+    let (body_encoding, body_schema) = if sends_body {
+        request_body(operation)?
+    } else {
+        (UpstreamBodyEncoding::Json, None)
+    };
+    let input_schema = match (body_encoding, body_schema) {
+        (_, None) => object_schema(properties, required),
+        (UpstreamBodyEncoding::Raw, Some(body)) if properties.is_empty() => body,
+        (UpstreamBodyEncoding::Raw, Some(body)) => {
+            properties.insert("body".to_string(), body);
+            required.push(Value::String("body".to_string()));
+            object_schema(properties, required)
+        }
+        (_, Some(body)) if properties.is_empty() => body,
+        (_, Some(mut body)) => {
+            // Path parameters are taken out of the input before the rest becomes the body,
+            // so they can only be merged into an object body schema.
+            let Some(fields) = body.as_object_mut() else {
+                return Err("path parameters cannot be combined with this body schema".to_string());
+            };
+            if fields.get("type").is_some_and(|kind| kind != "object") {
+                return Err("path parameters require an object request body".to_string());
+            }
+            fields.insert("type".to_string(), json!("object"));
+            let body_properties = fields
+                .entry("properties")
+                .or_insert_with(|| json!({}))
+                .as_object_mut()
+                .ok_or_else(|| "request body properties must be an object".to_string())?;
+            body_properties.extend(properties);
+            let body_required = fields
+                .entry("required")
+                .or_insert_with(|| json!([]))
+                .as_array_mut()
+                .ok_or_else(|| "request body required must be an array".to_string())?;
+            body_required.extend(required);
+            body
+        }
+    };
+
+    let summary = operation.get("summary").and_then(Value::as_str);
+    let details = operation.get("description").and_then(Value::as_str);
+    let description = match (summary, details) {
+        (Some(summary), Some(details)) => Some(format!("{summary}\n\n{details}")),
+        (Some(text), None) | (None, Some(text)) => Some(text.to_string()),
+        (None, None) => None,
+    };
+    let name = operation
+        .get("operationId")
+        .and_then(Value::as_str)
+        .or(summary)
+        .unwrap_or(key)
+        .to_string();
+
+    Ok(ImportedOperation {
+        key: key.to_string(),
+        operation_id: None,
+        name,
+        description,
+        method: method.to_ascii_uppercase(),
+        upstream_url,
+        body_encoding,
+        input_schema,
+        output_schema: success_schema(operation),
+    })
+}
+
+fn request_body(operation: &Value) -> Result<(UpstreamBodyEncoding, Option<Value>), String> {
+    let Some(content) = operation
+        .pointer("/requestBody/content")
+        .and_then(Value::as_object)
+    else {
+        return Ok((UpstreamBodyEncoding::Json, None));
+    };
+    let schema_of = |media: &Value| media.get("schema").cloned().unwrap_or_else(|| json!({}));
+
+    if let Some((_, media)) = content
+        .iter()
+        .find(|(kind, _)| kind.starts_with("application/json") || kind.ends_with("+json"))
+    {
+        return Ok((UpstreamBodyEncoding::Json, Some(schema_of(media))));
+    }
+    if let Some(media) = content.get("application/x-www-form-urlencoded") {
+        return Ok((UpstreamBodyEncoding::Form, Some(schema_of(media))));
+    }
+    if content.keys().any(|kind| kind.starts_with("text/")) {
+        return Ok((UpstreamBodyEncoding::Raw, Some(json!({ "type": "string" }))));
+    }
+    Err(format!(
+        "request body content type {} is not supported",
+        content.keys().cloned().collect::<Vec<_>>().join(", ")
+    ))
+}
+
+fn success_schema(operation: &Value) -> Option<Value> {
+    let responses = operation.get("responses")?.as_object()?;
+    let mut codes: Vec<&String> = responses
+        .keys()
+        .filter(|code| code.starts_with('2'))
+        .collect();
+    // Exact codes sort before ranges like `2XX`.
+    codes.sort();
+    codes.into_iter().find_map(|code| {
+        responses[code]
+            .get("content")?
+            .as_object()?
+            .iter()
+            .find(|(kind, _)| kind.starts_with("application/json") || kind.ends_with("+json"))
+            .and_then(|(_, media)| media.get("schema").cloned())
+    })
+}
+
+fn object_schema(properties: Map<String, Value>, required: Vec<Value>) -> Value {
+    let mut schema = json!({ "type": "object", "properties": properties });
+    if !required.is_empty() {
+        schema["required"] = Value::Array(required);
+    }
+    schema
+}
+
+/// Inlines local `$ref`s so each imported schema stands on its own.
+fn resolve_refs(value: &Value, document: &Value, depth: usize) -> Value {
+    match value {
+        Value::Object(fields) => {
+            if let Some(reference) = fields.get("$ref").and_then(Value::as_str) {
+                if depth >= MAX_REF_DEPTH {
+                    return json!({});
+                }
+                return reference
+                    .strip_prefix('#')
+                    .and_then(|pointer| document.pointer(pointer))
+                    .map(|target| resolve_refs(target, document, depth + 1))
+                    .unwrap_or_else(|| json!({}));
+            }
+            Value::Object(
+                fields
+                    .iter()
+                    .map(|(key, value)| (key.clone(), resolve_refs(value, document, depth)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| resolve_refs(item, document, depth))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
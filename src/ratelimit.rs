@@ -0,0 +1,434 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{ConnectInfo, MatchedPath, Request, State},
+    http::{HeaderMap, StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::{
+    collections::HashMap,
+    net::{IpAddr, SocketAddr},
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+
+use crate::error::{ApiError, ApiResult};
+use crate::types::{RateLimitKey, RateLimitPolicy, SPONSORED_CALLER_HEADER, SharedState};
+use crate::utils::validate_rate_limit_policy;
+
+/// Largest body the limiter will buffer to find `caller` or `user_id`.
+const MAX_KEY_BODY_BYTES: usize = 10 * 1024 * 1024;
+/// In-memory buckets are pruned once this many keys are tracked.
+const MAX_MEMORY_BUCKETS: usize = 100_000;
+/// Buckets idle this long have refilled for any sane policy and can be forgotten.
+const IDLE_BUCKET_SECS: u64 = 3600;
+/// Credential and claim keys are unverified, so each IP also gets a shared ceiling of this
+/// many times the policy; rotating `Authorization` headers cannot get past it.
+const IP_CEILING_FACTOR: u32 = 10;
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RouteRateLimit {
+    /// Route template as registered with the router, e.g. `/tool/{service}/run`.
+    pub route: String,
+    #[serde(flatten)]
+    pub policy: RateLimitPolicy,
+}
+
+/// Routes limited out of the box: everything that spends sponsor budget or settles payments.
+pub fn default_route_limits() -> Vec<RouteRateLimit> {
+    [
+        "/sponsored-apis/{api_id}/run",
+        "/sponsored-apis/{api_id}/run-batch",
+        "/sponsored-apis/{api_id}/proxy",
+        "/sponsored-apis/{api_id}/proxy/{*path}",
+        "/proxy/{service}/run",
+        "/tool/{service}/run",
+        "/tool/{service}/run-batch",
+        "/mcp",
+    ]
+    .into_iter()
+    .map(|route| RouteRateLimit {
+        route: route.to_string(),
+        policy: RateLimitPolicy {
+            capacity: 60,
+            refill_per_sec: 1.0,
+            key: RateLimitKey::Auto,
+        },
+    })
+    .collect()
+}
+
+/// Who is making a request, as far as the limiter can tell. Stored as a request extension
+/// so handlers can apply per-API limits with the same keys.
+#[derive(Debug, Clone, Default)]
+pub struct ClientKey {
+    pub identity: Option<String>,
+    pub ip: Option<IpAddr>,
+    pub caller: Option<String>,
+    pub user_id: Option<String>,
+}
+
+impl ClientKey {
+    /// Nothing in the request proves a `caller` or `user_id`, so a client that could pick
+    /// its own bucket would dodge every limit by rotating them. Claimed keys only subdivide
+    /// a credential-holder's bucket; everyone else is counted by IP.
+    pub fn resolve(&self, key: RateLimitKey) -> String {
+        let ip = self.ip_key();
+        let identity = self
+            .identity
+            .as_ref()
+            .map(|value| format!("identity:{value}"));
+        let claimed = match key {
+            RateLimitKey::Caller => self.caller.as_ref().map(|value| format!("caller:{value}")),
+            RateLimitKey::UserId => self.user_id.as_ref().map(|value| format!("user:{value}")),
+            RateLimitKey::Auto | RateLimitKey::Identity | RateLimitKey::Ip => None,
+        };
+
+        match (key, identity) {
+            (RateLimitKey::Auto | RateLimitKey::Ip, _) | (_, None) => ip,
+            (RateLimitKey::Identity, Some(identity)) => identity,
+            (_, Some(identity)) => match claimed {
+                Some(claimed) => format!("{identity}|{claimed}"),
+                None => identity,
+            },
+        }
+    }
+
+    fn ip_key(&self) -> String {
+        match self.ip {
+            Some(ip) => format!("ip:{ip}"),
+            None => "ip:unknown".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+#[derive(Debug, Clone)]
+enum Backend {
+    Memory(Arc<Mutex<HashMap<String, Bucket>>>),
+    /// Buckets live in `rate_limit_buckets` so every instance shares them.
+    Postgres(PgPool),
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    backend: Backend,
+    routes: Arc<HashMap<String, RateLimitPolicy>>,
+    trust_forwarded_for: bool,
+}
+
+impl RateLimiter {
+    pub fn new(routes: Vec<RouteRateLimit>, trust_forwarded_for: bool) -> Self {
+        Self {
+            backend: Backend::Memory(Arc::default()),
+            routes: Arc::new(
+                routes
+                    .into_iter()
+                    .map(|limit| (limit.route, limit.policy))
+                    .collect(),
+            ),
+            trust_forwarded_for,
+        }
+    }
+
+    pub fn with_postgres(mut self, db: PgPool) -> Self {
+        self.backend = Backend::Postgres(db);
+        self
+    }
+
+    /// `RATE_LIMIT_RULES` replaces the default route limits with a JSON array of
+    /// `{route, capacity, refill_per_sec, key}`; `RATE_LIMIT_BACKEND=postgres` shares buckets
+    /// between instances.
+    pub fn from_env(db: Option<&PgPool>) -> Result<Self, String> {
+        let routes = match std::env::var("RATE_LIMIT_RULES") {
+            Ok(raw) => serde_json::from_str::<Vec<RouteRateLimit>>(&raw).map_err(|err| {
+                format!("RATE_LIMIT_RULES should be a JSON array of route limits: {err}")
+            })?,
+            Err(_) => default_route_limits(),
+        };
+        for limit in &routes {
+            validate_rate_limit_policy(&limit.policy)
+                .map_err(|err| format!("invalid rate limit for {}: {err}", limit.route))?;
+        }
+        let trust_forwarded_for = std::env::var("RATE_LIMIT_TRUST_FORWARDED_FOR")
+            .map(|value| matches!(value.trim(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        let limiter = Self::new(routes, trust_forwarded_for);
+        match (std::env::var("RATE_LIMIT_BACKEND").as_deref(), db) {
+            (Ok("postgres"), Some(db)) => Ok(limiter.with_postgres(db.clone())),
+            (Ok("postgres"), None) => {
+                Err("RATE_LIMIT_BACKEND=postgres requires DATABASE_URL".to_string())
+            }
+            _ => Ok(limiter),
+        }
+    }
+
+    /// Takes `cost` tokens from `bucket` at once, or none at all. On success returns `None`;
+    /// otherwise the number of seconds until enough tokens will be available.
+    pub async fn acquire_many(
+        &self,
+        bucket: &str,
+        policy: &RateLimitPolicy,
+        cost: u32,
+    ) -> ApiResult<Option<u64>> {
+        let capacity = f64::from(policy.capacity);
+        let cost = f64::from(cost);
+        let (allowed, tokens) = match &self.backend {
+            Backend::Memory(buckets) => {
+                let mut buckets = buckets.lock().expect("rate limit lock poisoned");
+                let now = Instant::now();
+                if buckets.len() >= MAX_MEMORY_BUCKETS {
+                    buckets.retain(|_, bucket| {
+                        now.duration_since(bucket.updated_at).as_secs() < IDLE_BUCKET_SECS
+                    });
+                }
+                let entry = buckets.entry(bucket.to_string()).or_insert(Bucket {
+                    tokens: capacity,
+                    updated_at: now,
+                });
+                let elapsed = now.duration_since(entry.updated_at).as_secs_f64();
+                let refilled = (entry.tokens + elapsed * policy.refill_per_sec).min(capacity);
+                let allowed = refilled >= cost;
+                entry.tokens = if allowed { refilled - cost } else { refilled };
+                entry.updated_at = now;
+                (allowed, entry.tokens)
+            }
+            Backend::Postgres(db) => sqlx::query_as::<_, (bool, f64)>(
+                r#"
+                    insert into rate_limit_buckets as b (key, tokens, allowed, updated_at)
+                    values ($1, $2 - $4, true, now())
+                    on conflict (key) do update
+                    set allowed = least($2, b.tokens
+                            + extract(epoch from now() - b.updated_at)::float8 * $3) >= $4,
+                        tokens = least($2, b.tokens
+                            + extract(epoch from now() - b.updated_at)::float8 * $3)
+                            - case when least($2, b.tokens
+                                + extract(epoch from now() - b.updated_at)::float8 * $3) >= $4
+                              then $4 else 0 end,
+                        updated_at = now()
+                    returning allowed, tokens
+                    "#,
+            )
+            .bind(bucket)
+            .bind(capacity)
+            .bind(policy.refill_per_sec)
+            .bind(cost)
+            .fetch_one(db)
+            .await
+            .map_err(|err| {
+                ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+            })?,
+        };
+
+        if allowed {
+            return Ok(None);
+        }
+        let wait = ((cost - tokens) / policy.refill_per_sec).ceil();
+        Ok(Some((wait as u64).max(1)))
+    }
+
+    /// Takes `cost` tokens from `scope`'s bucket for `client`. Keys other than the IP are
+    /// only as trustworthy as the credential behind them, so those requests also draw on a
+    /// per-IP ceiling first.
+    pub async fn acquire_for(
+        &self,
+        scope: &str,
+        policy: &RateLimitPolicy,
+        client: &ClientKey,
+        cost: u32,
+    ) -> ApiResult<Option<u64>> {
+        let key = client.resolve(policy.key);
+        let ip = client.ip_key();
+        if key != ip {
+            let ceiling = RateLimitPolicy {
+                capacity: policy.capacity.saturating_mul(IP_CEILING_FACTOR),
+                refill_per_sec: policy.refill_per_sec * f64::from(IP_CEILING_FACTOR),
+                key: RateLimitKey::Ip,
+            };
+            let retry = self
+                .acquire_many(&format!("{scope}|ceiling|{ip}"), &ceiling, cost)
+                .await?;
+            if retry.is_some() {
+                return Ok(retry);
+            }
+        }
+        self.acquire_many(&format!("{scope}|{key}"), policy, cost)
+            .await
+    }
+
+    /// Drops idle shared buckets. A no-op for the in-memory backend, which prunes itself.
+    pub async fn prune(&self) -> ApiResult<u64> {
+        let Backend::Postgres(db) = &self.backend else {
+            return Ok(0);
+        };
+        sqlx::query(
+            "delete from rate_limit_buckets where updated_at < now() - make_interval(secs => $1)",
+        )
+        .bind(IDLE_BUCKET_SECS as f64)
+        .execute(db)
+        .await
+        .map(|result| result.rows_affected())
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+    }
+
+    /// Applies a sponsored API's own limit, on top of the route limit. Batch runs cost one
+    /// token per item.
+    pub async fn check_api(
+        &self,
+        api_id: uuid::Uuid,
+        policy: &RateLimitPolicy,
+        client: &ClientKey,
+        cost: u32,
+    ) -> ApiResult<()> {
+        if cost > policy.capacity {
+            return Err(ApiError::validation(format!(
+                "a batch of {cost} calls exceeds this sponsored api's rate limit capacity of {}",
+                policy.capacity
+            )));
+        }
+        match self
+            .acquire_for(&format!("api:{api_id}"), policy, client, cost)
+            .await?
+        {
+            None => Ok(()),
+            Some(retry_after_secs) => Err(ApiError::rate_limited(
+                retry_after_secs,
+                "rate limit exceeded for this sponsored api",
+            )),
+        }
+    }
+}
+
+/// Route-level limiter. Applied with `route_layer` so the matched route template is known.
+pub async fn rate_limit_layer(
+    State(state): State<SharedState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (limiter, metrics) = {
+        let state = state.inner.read().await;
+        (state.rate_limiter.clone(), state.metrics.clone())
+    };
+    let Some(route) = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+    else {
+        return next.run(request).await;
+    };
+    let policy = limiter.routes.get(&route).cloned();
+    // Sponsored API calls need the caller's key for per-API limits even when the route
+    // itself is unlimited.
+    if policy.is_none() && !route.starts_with("/sponsored-apis/{api_id}/") {
+        return next.run(request).await;
+    }
+
+    let (mut request, client) = match client_key(request, limiter.trust_forwarded_for).await {
+        Ok(parsed) => parsed,
+        Err(err) => return err.into_response(),
+    };
+
+    if let Some(policy) = policy {
+        match limiter
+            .acquire_for(&format!("route:{route}"), &policy, &client, 1)
+            .await
+        {
+            Ok(None) => {}
+            Ok(Some(retry_after_secs)) => {
+                metrics
+                    .rate_limited_total
+                    .with_label_values(&[route.as_str()])
+                    .inc();
+                return ApiError::rate_limited(retry_after_secs, "rate limit exceeded")
+                    .into_response();
+            }
+            // Fail open: a limiter outage should not take the API down with it.
+            Err(err) => tracing::warn!("rate limiter unavailable: {err}"),
+        }
+    }
+
+    request.extensions_mut().insert(client);
+    next.run(request).await
+}
+
+async fn client_key(
+    request: Request,
+    trust_forwarded_for: bool,
+) -> ApiResult<(Request, ClientKey)> {
+    let headers = request.headers();
+    let identity = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.trim().is_empty())
+        .map(|value| hex::encode(&Sha256::digest(value.trim().as_bytes())[..16]));
+    let ip = forwarded_ip(headers, trust_forwarded_for).or_else(|| {
+        request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| info.0.ip())
+    });
+    let caller_header = headers
+        .get(SPONSORED_CALLER_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+
+    let mut client = ClientKey {
+        identity,
+        ip,
+        caller: caller_header,
+        user_id: None,
+    };
+    if !is_json {
+        return Ok((request, client));
+    }
+
+    // JSON bodies may name the caller or user; buffer and hand the same bytes on.
+    let (parts, body) = request.into_parts();
+    let bytes: Bytes = axum::body::to_bytes(body, MAX_KEY_BODY_BYTES)
+        .await
+        .map_err(|_| ApiError::validation("request body is too large"))?;
+    if let Ok(Value::Object(fields)) = serde_json::from_slice::<Value>(&bytes) {
+        if let Some(caller) = fields.get("caller").and_then(Value::as_str) {
+            client.caller = Some(caller.to_string());
+        }
+        if let Some(user_id) = fields.get("user_id").and_then(Value::as_str) {
+            client.user_id = Some(user_id.to_string());
+        }
+    }
+    Ok((Request::from_parts(parts, Body::from(bytes)), client))
+}
+
+fn forwarded_ip(headers: &HeaderMap, trust_forwarded_for: bool) -> Option<IpAddr> {
+    if !trust_forwarded_for {
+        return None;
+    }
+    headers
+        .get("x-forwarded-for")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .and_then(|value| value.trim().parse().ok())
+}
+
+pub async fn run_rate_limit_pruner(state: SharedState) {
+    loop {
+        let limiter = state.inner.read().await.rate_limiter.clone();
+        if let Err(err) = limiter.prune().await {
+            tracing::warn!("rate limit bucket pruning failed: {err}");
+        }
+        tokio::time::sleep(std::time::Duration::from_secs(IDLE_BUCKET_SECS / 4)).await;
+    }
+}
@@ -0,0 +1,232 @@
+use serde_json::{Value, json};
+
+use crate::error::{ApiError, ApiResult};
+use crate::types::{SchemaViolation, SponsoredApi};
+
+/// Violations past this many are dropped from error responses.
+const MAX_REPORTED_VIOLATIONS: usize = 20;
+
+/// Rejects schemas that do not compile, so a bad schema fails when it is stored instead of
+/// on every call.
+pub fn validate_schemas(
+    input_schema: Option<&Value>,
+    output_schema: Option<&Value>,
+    validate_output: bool,
+) -> ApiResult<()> {
+    for (field, schema) in [
+        ("input_schema", input_schema),
+        ("output_schema", output_schema),
+    ] {
+        if let Some(schema) = schema {
+            jsonschema::validator_for(schema).map_err(|err| {
+                ApiError::validation(format!("{field} is not a valid JSON Schema: {err}"))
+            })?;
+        }
+    }
+    if validate_output && output_schema.is_none() {
+        return Err(ApiError::validation(
+            "validate_output requires output_schema",
+        ));
+    }
+    Ok(())
+}
+
+/// Validates `instance`, returning at most [`MAX_REPORTED_VIOLATIONS`] violations.
+pub fn violations(schema: &Value, instance: &Value) -> Vec<SchemaViolation> {
+    let validator = match jsonschema::validator_for(schema) {
+        Ok(validator) => validator,
+        // Schemas are checked when stored, so this only happens for rows written before that.
+        Err(err) => {
+            return vec![SchemaViolation {
+                path: String::new(),
+                message: format!("schema does not compile: {err}"),
+            }];
+        }
+    };
+    validator
+        .iter_errors(instance)
+        .take(MAX_REPORTED_VIOLATIONS)
+        .map(|err| SchemaViolation {
+            path: err.instance_path.to_string(),
+            message: err.to_string(),
+        })
+        .collect()
+}
+
+/// Checks a run's `input` against the API's input schema, if it has one.
+pub fn validate_input(api: &SponsoredApi, input: &Value) -> ApiResult<()> {
+    let Some(schema) = &api.input_schema else {
+        return Ok(());
+    };
+    let violations = violations(schema, input);
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::invalid_input(violations))
+    }
+}
+
+/// Proxy-mode counterpart of [`validate_input`]: the raw request body is the input, so with
+/// an input schema it has to be JSON.
+pub fn validate_proxy_body(api: &SponsoredApi, body: &[u8]) -> ApiResult<()> {
+    let Some(schema) = &api.input_schema else {
+        return Ok(());
+    };
+    let violations = match serde_json::from_slice::<Value>(body) {
+        Ok(input) => violations(schema, &input),
+        Err(err) => vec![SchemaViolation {
+            path: String::new(),
+            message: format!("request body is not JSON: {err}"),
+        }],
+    };
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(ApiError::invalid_input(violations))
+    }
+}
+
+/// Checks a successful upstream body against the output schema when the sponsor opted in.
+/// Error statuses are passed through untouched since they rarely follow the success shape.
+pub fn output_violations(
+    api: &SponsoredApi,
+    upstream_status: u16,
+    body: &[u8],
+) -> Vec<SchemaViolation> {
+    let (true, Some(schema)) = (api.validate_output, &api.output_schema) else {
+        return Vec::new();
+    };
+    if !(200..300).contains(&upstream_status) {
+        return Vec::new();
+    }
+    match serde_json::from_slice::<Value>(body) {
+        Ok(output) => violations(schema, &output),
+        Err(err) => vec![SchemaViolation {
+            path: String::new(),
+            message: format!("upstream body is not JSON: {err}"),
+        }],
+    }
+}
+
+/// The x402 `outputSchema` for `POST /sponsored-apis/{api_id}/run`: the request body callers
+/// send and the upstream body they get back.
+pub fn sponsored_api_payment_schema(api: &SponsoredApi) -> Value {
+    let input = api.input_schema.clone().unwrap_or_else(|| json!({}));
+    http_payment_schema(
+        json!({
+            "type": "object",
+            "properties": {
+                "input": input,
+                "caller": { "type": "string" },
+                "stream": { "type": "boolean" },
+            },
+            "required": ["input"],
+        }),
+        api.output_schema.clone(),
+    )
+}
+
+/// The x402 `outputSchema` for `POST /sponsored-apis/{api_id}/run-batch`.
+pub fn sponsored_api_batch_payment_schema(api: &SponsoredApi) -> Value {
+    let input = api.input_schema.clone().unwrap_or_else(|| json!({}));
+    http_payment_schema(
+        json!({
+            "type": "object",
+            "properties": {
+                "inputs": { "type": "array", "items": input, "minItems": 1 },
+                "caller": { "type": "string" },
+            },
+            "required": ["inputs"],
+        }),
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "api_id": { "type": "string", "format": "uuid" },
+                "payment_mode": { "type": "string" },
+                "amount_cents": { "type": "integer" },
+                "refunded_cents": { "type": "integer" },
+                "results": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "index": { "type": "integer" },
+                            "status": { "enum": ["succeeded", "failed"] },
+                            "amount_cents": { "type": "integer" },
+                            "upstream_status": { "type": "integer" },
+                            "upstream_body": { "type": "string" },
+                            "error": { "type": "object" },
+                        },
+                        "required": ["index", "status", "amount_cents"],
+                    },
+                },
+            },
+            "required": ["api_id", "payment_mode", "amount_cents", "results"],
+        })),
+    )
+}
+
+/// The x402 `outputSchema` shared by the built-in `/tool` and `/proxy` services.
+pub fn service_payment_schema() -> Value {
+    http_payment_schema(
+        json!({
+            "type": "object",
+            "properties": {
+                "user_id": { "type": "string", "format": "uuid" },
+                "input": { "type": "string" },
+            },
+            "required": ["user_id", "input"],
+        }),
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "service": { "type": "string" },
+                "output": { "type": "string" },
+                "payment_mode": { "type": "string" },
+                "sponsored_by": { "type": ["string", "null"] },
+                "tx_hash": { "type": ["string", "null"] },
+            },
+            "required": ["service", "output", "payment_mode"],
+        })),
+    )
+}
+
+/// The x402 `outputSchema` for `POST /tool/{service}/run-batch`.
+pub fn service_batch_payment_schema() -> Value {
+    http_payment_schema(
+        json!({
+            "type": "object",
+            "properties": {
+                "user_id": { "type": "string", "format": "uuid" },
+                "inputs": { "type": "array", "items": { "type": "string" }, "minItems": 1 },
+            },
+            "required": ["user_id", "inputs"],
+        }),
+        Some(json!({
+            "type": "object",
+            "properties": {
+                "service": { "type": "string" },
+                "outputs": { "type": "array", "items": { "type": "string" } },
+                "payment_mode": { "type": "string" },
+                "tx_hash": { "type": ["string", "null"] },
+                "amount_cents": { "type": "integer" },
+            },
+            "required": ["service", "outputs", "payment_mode", "amount_cents"],
+        })),
+    )
+}
+
+fn http_payment_schema(body: Value, output: Option<Value>) -> Value {
+    let mut schema = json!({
+        "input": {
+            "type": "http",
+            "method": "POST",
+            "bodyType": "json",
+            "bodyFields": body,
+        },
+    });
+    if let Some(output) = output {
+        schema["output"] = output;
+    }
+    schema
+}
@@ -0,0 +1,226 @@
+use aes_gcm::{
+    Aes256Gcm, Key, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng},
+};
+use axum::http::StatusCode;
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use sqlx::{PgPool, types::Json as DbJson};
+use std::{collections::HashMap, fmt};
+use uuid::Uuid;
+
+use crate::error::{ApiError, ApiResult};
+
+const SEALED_VERSION: &str = "v1";
+/// Marks stored values that are not encrypted: legacy headers tagged by the migration, and
+/// job headers kept while no keys are configured. Sealing never produces it, so no plaintext
+/// can pass for ciphertext or the other way round.
+const PLAINTEXT_PREFIX: &str = "plain:";
+const NONCE_LEN: usize = 12;
+
+/// AES-256-GCM keys for sponsored API secrets. The first key seals new values; the rest are
+/// kept so values sealed before a rotation can still be opened and re-sealed.
+#[derive(Clone, Default)]
+pub struct SecretKeyring {
+    keys: Vec<(String, Key<Aes256Gcm>)>,
+}
+
+impl fmt::Debug for SecretKeyring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SecretKeyring")
+            .field(
+                "key_ids",
+                &self.keys.iter().map(|(id, _)| id).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+impl SecretKeyring {
+    /// Parses `id:base64key[,id:base64key...]`, active key first.
+    pub fn parse(raw: &str) -> Result<Self, String> {
+        let mut keys = Vec::new();
+        for entry in raw
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (id, encoded) = entry
+                .split_once(':')
+                .ok_or_else(|| format!("secret key entry '{entry}' must be id:base64key"))?;
+            if id.is_empty() || id.contains(':') {
+                return Err(format!("invalid secret key id '{id}'"));
+            }
+            let bytes = STANDARD
+                .decode(encoded)
+                .map_err(|err| format!("secret key '{id}' is not base64: {err}"))?;
+            if bytes.len() != 32 {
+                return Err(format!("secret key '{id}' must decode to 32 bytes"));
+            }
+            keys.push((id.to_string(), *Key::<Aes256Gcm>::from_slice(&bytes)));
+        }
+        Ok(Self { keys })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    pub fn seal(&self, plaintext: &str) -> ApiResult<String> {
+        let (key_id, key) = self.keys.first().ok_or_else(|| {
+            ApiError::config("UPSTREAM_SECRET_KEYS is required to store secret upstream headers")
+        })?;
+
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = Aes256Gcm::new(key)
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| ApiError::internal("failed to encrypt upstream secret"))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!(
+            "{SEALED_VERSION}:{key_id}:{}",
+            STANDARD.encode(sealed)
+        ))
+    }
+
+    /// Opens a stored value: decrypts sealed ones and unwraps ones marked as plaintext.
+    pub fn open(&self, value: &str) -> ApiResult<String> {
+        if let Some(plaintext) = value.strip_prefix(PLAINTEXT_PREFIX) {
+            return Ok(plaintext.to_string());
+        }
+        let Some((key_id, payload)) = parse_sealed(value) else {
+            return Err(ApiError::internal(
+                "stored upstream secret is neither sealed nor marked as plaintext",
+            ));
+        };
+
+        let (_, key) = self
+            .keys
+            .iter()
+            .find(|(id, _)| id == key_id)
+            .ok_or_else(|| {
+                ApiError::config(format!(
+                    "upstream secret sealed with unknown key '{key_id}'"
+                ))
+            })?;
+
+        let bytes = STANDARD
+            .decode(payload)
+            .map_err(|_| ApiError::internal("sealed upstream secret is corrupt"))?;
+        if bytes.len() <= NONCE_LEN {
+            return Err(ApiError::internal("sealed upstream secret is corrupt"));
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = Aes256Gcm::new(key)
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| ApiError::internal("failed to decrypt upstream secret"))?;
+
+        String::from_utf8(plaintext)
+            .map_err(|_| ApiError::internal("upstream secret is not valid UTF-8"))
+    }
+
+    fn needs_reseal(&self, value: &str) -> bool {
+        match (parse_sealed(value), self.keys.first()) {
+            (_, None) => false,
+            (None, Some(_)) => true,
+            (Some((key_id, _)), Some((active_id, _))) => key_id != active_id,
+        }
+    }
+
+    /// Seals headers when keys are configured and marks them as plaintext otherwise, for
+    /// values that may be stored either way.
+    pub fn store_headers(
+        &self,
+        headers: &HashMap<String, String>,
+    ) -> ApiResult<HashMap<String, String>> {
+        if !self.is_empty() {
+            return self.seal_headers(headers);
+        }
+        Ok(headers
+            .iter()
+            .map(|(name, value)| (name.clone(), format!("{PLAINTEXT_PREFIX}{value}")))
+            .collect())
+    }
+
+    pub fn seal_headers(
+        &self,
+        headers: &HashMap<String, String>,
+    ) -> ApiResult<HashMap<String, String>> {
+        headers
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), self.seal(value)?)))
+            .collect()
+    }
+
+    pub fn open_headers(
+        &self,
+        headers: &HashMap<String, String>,
+    ) -> ApiResult<HashMap<String, String>> {
+        headers
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), self.open(value)?)))
+            .collect()
+    }
+}
+
+fn parse_sealed(value: &str) -> Option<(&str, &str)> {
+    let rest = value.strip_prefix(SEALED_VERSION)?.strip_prefix(':')?;
+    rest.split_once(':')
+}
+
+/// What a startup re-seal did.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ResealReport {
+    pub resealed: usize,
+    /// Rows left as they were because a value could not be opened or written back.
+    pub failed: usize,
+}
+
+/// Seals legacy plaintext secrets and re-seals values encrypted under a retired key. A row
+/// that cannot be opened, e.g. sealed under a key no longer configured, is logged and
+/// skipped so it does not hold up the rest.
+pub async fn reseal_upstream_secrets(
+    db: &PgPool,
+    keyring: &SecretKeyring,
+) -> ApiResult<ResealReport> {
+    let mut report = ResealReport::default();
+    if keyring.is_empty() {
+        return Ok(report);
+    }
+
+    let rows = sqlx::query_as::<_, (Uuid, DbJson<HashMap<String, String>>)>(
+        "select id, upstream_secret_headers from sponsored_apis",
+    )
+    .fetch_all(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    for (api_id, DbJson(headers)) in rows {
+        if !headers.values().any(|value| keyring.needs_reseal(value)) {
+            continue;
+        }
+
+        let result: ApiResult<()> = async {
+            let resealed = keyring.seal_headers(&keyring.open_headers(&headers)?)?;
+            sqlx::query("update sponsored_apis set upstream_secret_headers = $2 where id = $1")
+                .bind(api_id)
+                .bind(DbJson(resealed))
+                .execute(db)
+                .await
+                .map_err(|err| {
+                    ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+                })?;
+            Ok(())
+        }
+        .await;
+        match result {
+            Ok(()) => report.resealed += 1,
+            Err(err) => {
+                tracing::warn!("failed to re-seal secrets of sponsored api {api_id}: {err}");
+                report.failed += 1;
+            }
+        }
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,141 @@
+use axum::body::Bytes;
+use futures_util::{Stream, StreamExt, stream::BoxStream};
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::time::{Instant, Sleep};
+
+/// How a metered upstream body ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamOutcome {
+    Completed,
+    /// The caller went away before the upstream finished.
+    Aborted,
+    /// The upstream sent more than the configured maximum body size.
+    Truncated,
+    TimedOut,
+    UpstreamError,
+    /// Served from the response cache; the upstream was not contacted.
+    CacheHit,
+    /// The call failed on our side, e.g. blocked by the network policy or a database error,
+    /// so it says nothing about the upstream's health.
+    LocalError,
+}
+
+impl StreamOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Aborted => "aborted",
+            Self::Truncated => "truncated",
+            Self::TimedOut => "timed_out",
+            Self::UpstreamError => "upstream_error",
+            Self::CacheHit => "cache_hit",
+            Self::LocalError => "local_error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StreamReport {
+    pub outcome: StreamOutcome,
+    pub bytes: u64,
+}
+
+type FinishHook = Box<dyn FnOnce(StreamReport) + Send>;
+
+/// Wraps an upstream body, enforcing a byte limit and a deadline for the whole stream. The
+/// finish hook runs exactly once: when the body ends, fails, or is dropped by the caller.
+pub struct MeteredStream {
+    inner: BoxStream<'static, reqwest::Result<Bytes>>,
+    bytes: u64,
+    max_bytes: u64,
+    deadline: Pin<Box<Sleep>>,
+    on_finish: Option<FinishHook>,
+}
+
+impl MeteredStream {
+    pub fn new(
+        response: reqwest::Response,
+        max_bytes: u64,
+        timeout: Duration,
+        on_finish: impl FnOnce(StreamReport) + Send + 'static,
+    ) -> Self {
+        Self {
+            inner: response.bytes_stream().boxed(),
+            bytes: 0,
+            max_bytes,
+            deadline: Box::pin(tokio::time::sleep_until(Instant::now() + timeout)),
+            on_finish: Some(Box::new(on_finish)),
+        }
+    }
+
+    fn finish(&mut self, outcome: StreamOutcome) {
+        if let Some(on_finish) = self.on_finish.take() {
+            on_finish(StreamReport {
+                outcome,
+                bytes: self.bytes,
+            });
+        }
+    }
+
+    fn fail(&mut self, outcome: StreamOutcome, message: String) -> Poll<Option<io::Result<Bytes>>> {
+        self.finish(outcome);
+        Poll::Ready(Some(Err(io::Error::other(message))))
+    }
+}
+
+impl Stream for MeteredStream {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.on_finish.is_none() {
+            return Poll::Ready(None);
+        }
+        if self.deadline.as_mut().poll(cx).is_ready() {
+            return self.fail(
+                StreamOutcome::TimedOut,
+                "upstream stream timed out".to_string(),
+            );
+        }
+
+        match self.inner.poll_next_unpin(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(None) => {
+                self.finish(StreamOutcome::Completed);
+                Poll::Ready(None)
+            }
+            Poll::Ready(Some(Err(err))) => self.fail(StreamOutcome::UpstreamError, err.to_string()),
+            Poll::Ready(Some(Ok(chunk))) => {
+                let total = self.bytes.saturating_add(chunk.len() as u64);
+                if total > self.max_bytes {
+                    let max_bytes = self.max_bytes;
+                    return self.fail(
+                        StreamOutcome::Truncated,
+                        format!("upstream body exceeded {max_bytes} bytes"),
+                    );
+                }
+                self.bytes = total;
+                Poll::Ready(Some(Ok(chunk)))
+            }
+        }
+    }
+}
+
+impl Drop for MeteredStream {
+    fn drop(&mut self) {
+        self.finish(StreamOutcome::Aborted);
+    }
+}
+
+/// Buffers a metered body for callers that need the whole response at once.
+pub async fn collect_metered(mut stream: MeteredStream) -> Result<Bytes, String> {
+    let mut body = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        body.extend_from_slice(&chunk.map_err(|err| err.to_string())?);
+    }
+    Ok(Bytes::from(body))
+}
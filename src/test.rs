@@ -1,7 +1,9 @@
 use super::*;
+use crate::ratelimit::RouteRateLimit;
 use axum::Router;
 use axum::body::{Body, to_bytes};
 use axum::http::{Request, header};
+use base64::{Engine as _, engine::general_purpose::STANDARD};
 use tower::ServiceExt;
 
 fn required_env(key: &str) -> String {
@@ -18,11 +20,99 @@ fn optional_env(key: &str, default: &str) -> String {
 
 fn test_app() -> (Router, SharedState) {
     let state = SharedState {
-        inner: Arc::new(RwLock::new(AppState::new())),
+        inner: Arc::new(RwLock::new(
+            AppState::new().expect("test configuration should be valid"),
+        )),
     };
     (build_app(state.clone()), state)
 }
 
+/// Like `test_app`, backed by `TEST_DATABASE_URL`. Returns `None` when it is unset so
+/// database tests are skipped.
+async fn test_db_app() -> Option<(Router, SharedState)> {
+    let url = std::env::var("TEST_DATABASE_URL").ok()?;
+    let db = sqlx::postgres::PgPoolOptions::new()
+        .max_connections(4)
+        .connect(&url)
+        .await
+        .expect("test database should connect");
+    sqlx::migrate!("./migrations")
+        .run(&db)
+        .await
+        .expect("test database migrations should run");
+    let (app, state) = test_app();
+    state.inner.write().await.db = Some(db);
+    Some((app, state))
+}
+
+async fn send(app: &Router, method: &str, uri: &str) -> axum::response::Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should handle request")
+}
+
+async fn send_json(
+    app: &Router,
+    method: &str,
+    uri: &str,
+    body: serde_json::Value,
+) -> axum::response::Response {
+    app.clone()
+        .oneshot(
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should handle request")
+}
+
+/// Stores `api` directly, skipping the paid creation route.
+async fn insert_test_sponsored_api(state: &SharedState, mut api: SponsoredApi) -> SponsoredApi {
+    let db = state
+        .inner
+        .read()
+        .await
+        .db
+        .clone()
+        .expect("db is configured");
+    api.service_key = sponsored_api_service_key(api.id);
+    let row = insert_sponsored_api(&db, api)
+        .await
+        .expect("sponsored api should insert");
+    SponsoredApi::try_from(row).expect("row should convert")
+}
+
+async fn create_test_profile(app: &Router) -> String {
+    let response = post_json(
+        app,
+        "/profiles",
+        serde_json::json!({
+            "email": format!("{}@example.com", Uuid::new_v4()),
+            "region": "us",
+            "roles": [],
+            "tools_used": [],
+        }),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    read_json(response).await["id"]
+        .as_str()
+        .expect("profile has an id")
+        .to_string()
+}
+
 async fn post_json(
     app: &Router,
     uri: &str,
@@ -182,3 +272,2218 @@ async fn testnet_payment_signature_service_mismatch_is_rejected() {
             .contains("payment rejected")
     );
 }
+
+#[tokio::test]
+async fn route_rate_limit_rejects_bursts_per_user() {
+    let (app, state) = test_app();
+    configure_local_x402(&state).await;
+    state.inner.write().await.rate_limiter = RateLimiter::new(
+        vec![RouteRateLimit {
+            route: "/tool/{service}/run".to_string(),
+            policy: RateLimitPolicy {
+                capacity: 2,
+                refill_per_sec: 0.1,
+                key: RateLimitKey::UserId,
+            },
+        }],
+        false,
+    );
+
+    let user_id = Uuid::new_v4();
+    let run = |user_id: Uuid, credential: Option<String>| {
+        let mut builder = Request::builder()
+            .method("POST")
+            .uri("/tool/design/run")
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(credential) = credential {
+            builder = builder.header(header::AUTHORIZATION, credential);
+        }
+        let request = builder
+            .body(Body::from(
+                serde_json::json!({ "user_id": user_id, "input": "burst" }).to_string(),
+            ))
+            .expect("request should build");
+        app.clone().oneshot(request)
+    };
+    let status = |response: Result<axum::response::Response, _>| {
+        response.expect("router should handle request").status()
+    };
+    for _ in 0..2 {
+        assert_eq!(
+            status(run(user_id, Some("Bearer a".to_string())).await),
+            StatusCode::PAYMENT_REQUIRED
+        );
+    }
+
+    let limited = run(user_id, Some("Bearer a".to_string()))
+        .await
+        .expect("router should handle request");
+    assert_eq!(limited.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(limited.headers()[header::RETRY_AFTER], "10");
+    let json = read_json(limited).await;
+    assert_eq!(json["error"]["code"], "rate_limited");
+
+    // Another user of the same credential has their own bucket.
+    assert_eq!(
+        status(run(Uuid::new_v4(), Some("Bearer a".to_string())).await),
+        StatusCode::PAYMENT_REQUIRED
+    );
+
+    // Without a credential the claimed user id is ignored, so rotating it does not help.
+    for _ in 0..2 {
+        assert_eq!(
+            status(run(Uuid::new_v4(), None).await),
+            StatusCode::PAYMENT_REQUIRED
+        );
+    }
+    assert_eq!(
+        status(run(Uuid::new_v4(), None).await),
+        StatusCode::TOO_MANY_REQUESTS
+    );
+
+    // Credentials are unverified: minting a fresh one per request only gets as far as the
+    // IP's ceiling.
+    let mut accepted = 0;
+    loop {
+        let credential = format!("Bearer {}", Uuid::new_v4());
+        match status(run(user_id, Some(credential)).await) {
+            StatusCode::PAYMENT_REQUIRED => accepted += 1,
+            StatusCode::TOO_MANY_REQUESTS => break,
+            other => panic!("unexpected status {other}"),
+        }
+        assert!(
+            accepted <= 20,
+            "rotating credentials escaped the IP ceiling"
+        );
+    }
+}
+
+#[tokio::test]
+async fn batch_runs_are_priced_and_rate_limited_per_item() {
+    let (app, state) = test_app();
+    configure_local_x402(&state).await;
+    let price = state.inner.read().await.service_price("design");
+
+    let response = post_json(
+        &app,
+        "/tool/design/run-batch",
+        serde_json::json!({ "user_id": Uuid::new_v4(), "inputs": ["a", "b", "c"] }),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+    let json = read_json(response).await;
+    assert_eq!(json["amount_cents"], price * 3);
+
+    let empty = post_json(
+        &app,
+        "/tool/design/run-batch",
+        serde_json::json!({ "user_id": Uuid::new_v4(), "inputs": [] }),
+        None,
+    )
+    .await;
+    assert_eq!(empty.status(), StatusCode::BAD_REQUEST);
+    assert!(validate_batch_size(MAX_BATCH_ITEMS + 1).is_err());
+    assert_eq!(batch_total_cents(5, 3).expect("small totals fit"), 15);
+    assert!(batch_total_cents(u64::MAX / 2, 3).is_err());
+    assert!(batch_total_cents(i64::MAX as u64, 2).is_err());
+
+    let limiter = RateLimiter::new(Vec::new(), false);
+    let policy = RateLimitPolicy {
+        capacity: 3,
+        refill_per_sec: 0.01,
+        key: RateLimitKey::Caller,
+    };
+    let api_id = Uuid::new_v4();
+    let client = ClientKey::default();
+    let oversized = limiter.check_api(api_id, &policy, &client, 4).await;
+    assert!(matches!(oversized, Err(ApiError::Http { .. })));
+    limiter
+        .check_api(api_id, &policy, &client, 2)
+        .await
+        .expect("two of three tokens are available");
+    let limited = limiter.check_api(api_id, &policy, &client, 2).await;
+    assert!(matches!(limited, Err(ApiError::RateLimited { .. })));
+    limiter
+        .check_api(api_id, &policy, &client, 1)
+        .await
+        .expect("a rejected batch takes no tokens");
+
+    let error = ApiError::validation("bad").for_batch_item(4);
+    assert_eq!(error.to_json()["message"], "inputs[4]: bad");
+}
+
+#[tokio::test]
+async fn mcp_lists_tools_and_returns_payment_challenges_as_tool_errors() {
+    let (app, state) = test_app();
+    configure_local_x402(&state).await;
+    let rpc = |method: &str, params: serde_json::Value| {
+        post_json(
+            &app,
+            "/mcp",
+            serde_json::json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }),
+            None,
+        )
+    };
+
+    let json = read_json(
+        rpc(
+            "initialize",
+            serde_json::json!({ "protocolVersion": "1999-01-01" }),
+        )
+        .await,
+    )
+    .await;
+    assert_eq!(
+        json["result"]["protocolVersion"],
+        crate::mcp::LATEST_PROTOCOL_VERSION
+    );
+
+    // Without Postgres only the built-in tools are listed.
+    let json = read_json(rpc("tools/list", serde_json::json!({})).await).await;
+    let names: Vec<&str> = json["result"]["tools"]
+        .as_array()
+        .expect("tools should be a list")
+        .iter()
+        .filter_map(|tool| tool["name"].as_str())
+        .collect();
+    assert_eq!(names, BUILTIN_SERVICES);
+
+    let call = rpc(
+        "tools/call",
+        serde_json::json!({
+            "name": "design",
+            "arguments": { "user_id": Uuid::new_v4(), "input": "logo" },
+        }),
+    )
+    .await;
+    assert_eq!(call.status(), StatusCode::OK);
+    let json = read_json(call).await;
+    let result = &json["result"];
+    assert_eq!(result["isError"], true);
+    assert_eq!(result["structuredContent"]["x402Version"], 2);
+    assert_eq!(result["structuredContent"]["amount_cents"], 8);
+    assert_eq!(
+        result["structuredContent"]["accepts"][0]["payTo"],
+        "0x1111111111111111111111111111111111111111"
+    );
+
+    let json = read_json(rpc("tools/call", serde_json::json!({ "name": "unknown" })).await).await;
+    assert_eq!(json["error"]["code"], crate::mcp::INVALID_PARAMS);
+    assert_eq!(
+        crate::mcp::McpTool::parse(&sponsored_api_service_key(Uuid::nil())),
+        Some(crate::mcp::McpTool::SponsoredApi(Uuid::nil()))
+    );
+}
+
+#[tokio::test]
+async fn x402_discovery_lists_concrete_resources_with_requirements() {
+    let (app, state) = test_app();
+    configure_local_x402(&state).await;
+    let get = |uri: &str| {
+        app.clone().oneshot(
+            Request::builder()
+                .uri(uri)
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+    };
+
+    let manifest = read_json(
+        get("/.well-known/x402")
+            .await
+            .expect("router should respond"),
+    )
+    .await;
+    assert_eq!(manifest["version"], 1);
+    let resources = manifest["resources"]
+        .as_array()
+        .expect("resources should be a list");
+    assert_eq!(resources.len(), BUILTIN_SERVICES.len() * 2);
+    assert!(resources.contains(&serde_json::json!("http://localhost:3000/proxy/design/run")));
+
+    let page = read_json(
+        get("/discovery/resources?type=tool&limit=2&offset=1")
+            .await
+            .expect("router should respond"),
+    )
+    .await;
+    assert_eq!(page["pagination"]["total"], BUILTIN_SERVICES.len());
+    let items = page["items"].as_array().expect("items should be a list");
+    assert_eq!(items.len(), 2);
+    let design = &items[0];
+    assert_eq!(design["resource"], "http://localhost:3000/tool/design/run");
+    assert_eq!(design["metadata"]["price_cents"], 8);
+    assert_eq!(design["metadata"]["sponsorship"]["available"], false);
+    assert_eq!(design["accepts"][0]["maxAmountRequired"], "80000");
+    assert!(design["accepts"][0]["outputSchema"]["input"].is_object());
+}
+
+#[test]
+fn circuit_breaker_opens_on_errors_and_recovers_after_probe() {
+    let mut config = AppConfig::from_env().expect("test configuration should be valid");
+    config.circuit_breaker_min_calls = 4;
+    config.circuit_breaker_error_percent = 50;
+    config.circuit_breaker_open_secs = 30;
+    let health = HealthRegistry::new(&config, Metrics::new());
+    let api_id = Uuid::new_v4();
+
+    assert_eq!(health.status(api_id).status, HealthState::Unknown);
+    for succeeded in [true, false, true] {
+        health.record_call(api_id, succeeded);
+    }
+    assert!(health.check(api_id).is_ok());
+
+    health.record_call(api_id, false);
+    let err = health.check(api_id).expect_err("circuit should be open");
+    let response = err.into_response();
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert!(response.headers().contains_key(header::RETRY_AFTER));
+    let status = health.status(api_id);
+    assert_eq!(status.circuit, CircuitState::Open);
+    assert_eq!(status.recent_failures, 2);
+
+    let probe = |ok| HealthProbeResult {
+        ok,
+        status: Some(if ok { 200 } else { 503 }),
+        error: None,
+        latency_ms: 1,
+        checked_at: Utc::now(),
+    };
+    health.record_probe(api_id, probe(true));
+    assert!(health.check(api_id).is_ok());
+    assert_eq!(health.status(api_id).status, HealthState::Degraded);
+    // Only one trial call runs while half-open.
+    let err = health.check(api_id).expect_err("a trial call is in flight");
+    assert_eq!(
+        err.into_response().status(),
+        StatusCode::SERVICE_UNAVAILABLE
+    );
+
+    health.record_call(api_id, true);
+    let status = health.status(api_id);
+    assert_eq!(status.circuit, CircuitState::Closed);
+    assert_eq!(status.status, HealthState::Healthy);
+
+    // A failed probe trips the breaker without waiting for calls to fail.
+    health.record_probe(api_id, probe(false));
+    assert!(health.check(api_id).is_err());
+}
+
+#[test]
+fn health_check_timeouts_stay_well_below_the_interval() {
+    let policy = |interval_secs, timeout_secs| HealthCheckPolicy {
+        path: "/healthz".to_string(),
+        expected_status: 200,
+        interval_secs,
+        timeout_secs,
+    };
+    assert!(validate_health_check_policy(&policy(60, 5)).is_ok());
+    assert!(validate_health_check_policy(&policy(60, 30)).is_ok());
+    assert!(validate_health_check_policy(&policy(10, 6)).is_err());
+    assert!(validate_health_check_policy(&policy(3600, 600)).is_err());
+}
+
+#[test]
+fn openapi_import_builds_input_schemas_for_selected_operations() {
+    let yaml = r##"
+openapi: 3.0.3
+info:
+  title: Pet Store
+servers:
+  - url: https://{region}.pets.example.com/v1
+    variables:
+      region:
+        default: eu
+paths:
+  /pets/{petId}:
+    parameters:
+      - name: petId
+        in: path
+        required: true
+        schema:
+          type: integer
+    get:
+      operationId: getPet
+      summary: Fetch a pet
+      parameters:
+        - name: fields
+          in: query
+          schema:
+            type: string
+      responses:
+        "200":
+          description: The pet
+          content:
+            application/json:
+              schema:
+                $ref: "#/components/schemas/Pet"
+    put:
+      operationId: updatePet
+      requestBody:
+        content:
+          application/json:
+            schema:
+              $ref: "#/components/schemas/Pet"
+    options:
+      operationId: petOptions
+  /upload:
+    post:
+      operationId: upload
+      requestBody:
+        content:
+          application/octet-stream: {}
+components:
+  schemas:
+    Pet:
+      type: object
+      required: [name]
+      properties:
+        name:
+          type: string
+"##;
+    let document = openapi::parse_document(Value::String(yaml.to_string())).expect("yaml parses");
+    let base_url = openapi::base_url(&document, None).expect("server url resolves");
+    assert_eq!(base_url, "https://eu.pets.example.com/v1");
+
+    let selected = vec![
+        "getPet".to_string(),
+        "put /pets/{petId}".to_string(),
+        "upload".to_string(),
+        "missingOp".to_string(),
+    ];
+    let (imported, skipped) = openapi::operations(&document, &base_url, &selected);
+
+    let get = imported
+        .iter()
+        .find(|operation| operation.key == "GET /pets/{petId}")
+        .expect("GET imported");
+    assert_eq!(
+        get.upstream_url,
+        "https://eu.pets.example.com/v1/pets/{petId}"
+    );
+    assert_eq!(get.description.as_deref(), Some("Fetch a pet"));
+    assert_eq!(get.input_schema["properties"]["petId"]["type"], "integer");
+    assert_eq!(get.input_schema["properties"]["fields"]["type"], "string");
+    assert_eq!(get.input_schema["required"], serde_json::json!(["petId"]));
+    let output = get.output_schema.as_ref().expect("success schema imported");
+    assert_eq!(output["required"], serde_json::json!(["name"]));
+
+    let put = imported
+        .iter()
+        .find(|operation| operation.name == "updatePet")
+        .expect("PUT imported");
+    assert_eq!(put.method, "PUT");
+    assert_eq!(put.input_schema["properties"]["name"]["type"], "string");
+    assert_eq!(
+        put.input_schema["required"],
+        serde_json::json!(["name", "petId"])
+    );
+
+    let reasons: HashMap<&str, &str> = skipped
+        .iter()
+        .map(|skip| (skip.operation.as_str(), skip.reason.as_str()))
+        .collect();
+    assert!(reasons["POST /upload"].contains("application/octet-stream"));
+    assert!(reasons["missingOp"].contains("no matching operation"));
+    assert!(!reasons.contains_key("OPTIONS /pets/{petId}"));
+}
+
+#[test]
+fn sponsored_api_schemas_gate_input_and_output() {
+    let api = SponsoredApi {
+        input_schema: Some(serde_json::json!({
+            "type": "object",
+            "properties": { "query": { "type": "string" } },
+            "required": ["query"],
+        })),
+        output_schema: Some(serde_json::json!({
+            "type": "object",
+            "required": ["results"],
+        })),
+        validate_output: true,
+        ..sample_sponsored_api("https://api.example.com/search")
+    };
+
+    assert!(crate::schema::validate_input(&api, &serde_json::json!({ "query": "rust" })).is_ok());
+    let err = crate::schema::validate_input(&api, &serde_json::json!({ "query": 7 }))
+        .expect_err("wrong type is rejected");
+    let ApiError::Schema {
+        status, violations, ..
+    } = &err
+    else {
+        panic!("expected a schema error, got {err:?}");
+    };
+    assert_eq!(*status, StatusCode::BAD_REQUEST);
+    assert_eq!(violations[0].path, "/query");
+
+    assert!(crate::schema::validate_proxy_body(&api, br#"{"query":"rust"}"#).is_ok());
+    assert!(crate::schema::validate_proxy_body(&api, br#"{"query":7}"#).is_err());
+    assert!(crate::schema::validate_proxy_body(&api, b"").is_err());
+    assert!(
+        crate::schema::validate_proxy_body(&sample_sponsored_api("https://api.example.com"), b"")
+            .is_ok()
+    );
+
+    assert!(crate::schema::output_violations(&api, 200, br#"{"results":[]}"#).is_empty());
+    assert!(!crate::schema::output_violations(&api, 200, b"not json").is_empty());
+    // Error responses are passed through whatever their shape.
+    assert!(crate::schema::output_violations(&api, 500, b"oops").is_empty());
+
+    assert!(crate::schema::validate_schemas(None, None, true).is_err());
+    assert!(
+        crate::schema::validate_schemas(Some(&serde_json::json!({ "type": 12 })), None, false)
+            .is_err()
+    );
+
+    let published = crate::schema::sponsored_api_payment_schema(&api);
+    assert_eq!(
+        published["input"]["bodyFields"]["properties"]["input"]["required"],
+        serde_json::json!(["query"])
+    );
+    assert_eq!(
+        published["output"]["required"],
+        serde_json::json!(["results"])
+    );
+}
+
+#[test]
+fn transforms_reshape_input_and_trim_replies() {
+    let policy: TransformPolicy = serde_json::from_value(serde_json::json!({
+        "request": {
+            "rename": [{ "from": "/q", "to": "/search/query" }],
+            "deny": ["/debug"],
+            "set": {
+                "/api_version": 2,
+                "/search/label": "agent asked: {{/q}}",
+                "/search/limit": "{{/limit}}",
+            },
+        },
+        "response": {
+            "allow": ["/results", "/meta/total"],
+            "max_bytes": 24,
+        },
+    }))
+    .expect("policy parses");
+    assert!(crate::transform::validate_transform_policy(&policy).is_ok());
+
+    let input = serde_json::json!({ "q": "rust", "limit": 5, "debug": true });
+    let upstream = crate::transform::transform_request(Some(&policy), input)
+        .expect("request transform applies");
+    assert_eq!(
+        upstream,
+        serde_json::json!({
+            "api_version": 2,
+            "limit": 5,
+            "search": { "query": "rust", "label": "agent asked: rust", "limit": 5 },
+        })
+    );
+
+    let reply = axum::body::Bytes::from_static(
+        br#"{"results":[1,2],"meta":{"total":2,"internal":"x"},"trace":"abc"}"#,
+    );
+    let filtered =
+        crate::transform::filter_response(Some(&policy), reply).expect("response filter applies");
+    let filtered_json: Value = serde_json::from_slice(&filtered).expect("still json");
+    assert_eq!(
+        filtered_json,
+        serde_json::json!({ "results": [1, 2], "meta": { "total": 2 } })
+    );
+    let (cut, truncated) = crate::transform::truncate_response(Some(&policy), filtered);
+    assert!(truncated);
+    assert_eq!(cut.len(), 24);
+
+    let bad: TransformPolicy = serde_json::from_value(serde_json::json!({
+        "request": { "set": { "/x": "{{/unterminated" } },
+    }))
+    .expect("policy parses");
+    assert!(crate::transform::validate_transform_policy(&bad).is_err());
+}
+
+#[test]
+fn async_jobs_report_where_the_reserved_budget_went() {
+    let row =
+        |status: JobStatus, payment_mode: &str, charged_cents: Option<i64>| SponsoredApiJobRow {
+            id: Uuid::new_v4(),
+            sponsored_api_id: Uuid::new_v4(),
+            status,
+            payment_mode: payment_mode.to_string(),
+            amount_cents: 5,
+            sponsored_by: None,
+            tx_hash: None,
+            charged_cents,
+            callback_url: None,
+            callback_status: None,
+            upstream_status: None,
+            upstream_body: None,
+            truncated: false,
+            error: None,
+            created_at: Utc::now(),
+            started_at: None,
+            completed_at: None,
+            expires_at: None,
+        };
+    let budget = |row| SponsoredApiJob::try_from(row).expect("row converts").budget;
+
+    assert_eq!(
+        budget(row(JobStatus::Queued, "sponsored", Some(5))),
+        Some(JobBudgetState::Reserved)
+    );
+    assert_eq!(
+        budget(row(JobStatus::Succeeded, "sponsored", Some(5))),
+        Some(JobBudgetState::Committed)
+    );
+    assert_eq!(
+        budget(row(JobStatus::Failed, "sponsored", Some(0))),
+        Some(JobBudgetState::Released)
+    );
+    assert_eq!(budget(row(JobStatus::Failed, "user_direct", Some(5))), None);
+
+    let request: SponsoredApiRunRequest = serde_json::from_value(serde_json::json!({
+        "input": {},
+        "mode": "async",
+        "callback_url": "https://example.com/hook",
+    }))
+    .expect("request parses");
+    assert_eq!(request.mode, RunMode::Async);
+}
+
+#[test]
+fn eligibility_targeting_matches_campaign_rules() {
+    let policy: EligibilityPolicy = serde_json::from_value(serde_json::json!({
+        "target_roles": ["backend"],
+        "allowed_callers": [Uuid::nil()],
+    }))
+    .expect("policy parses");
+    assert!(policy.needs_profile(), "targeting needs a profile to match");
+    assert!(
+        !EligibilityPolicy {
+            allowed_callers: vec![Uuid::nil().to_string()],
+            ..EligibilityPolicy::default()
+        }
+        .needs_profile()
+    );
+
+    let profile = UserProfile {
+        id: Uuid::new_v4(),
+        email: "dev@example.com".to_string(),
+        region: "eu".to_string(),
+        roles: vec!["backend".to_string()],
+        tools_used: vec!["cargo".to_string()],
+        attributes: HashMap::new(),
+        created_at: Utc::now(),
+    };
+    assert!(user_matches_targets(
+        &profile,
+        &policy.target_roles,
+        &policy.target_tools
+    ));
+    assert!(!user_matches_targets(
+        &profile,
+        &policy.target_roles,
+        &["docker".to_string()]
+    ));
+}
+
+#[tokio::test]
+async fn local_failures_refund_without_tripping_the_circuit_breaker() {
+    let Some((app, state)) = test_db_app().await else {
+        return;
+    };
+    // A method the upstream client cannot build fails locally, after the charge.
+    let api = insert_test_sponsored_api(
+        &state,
+        SponsoredApi {
+            upstream_method: "NOT A METHOD".to_string(),
+            ..sample_sponsored_api("https://api.example.com/search")
+        },
+    )
+    .await;
+
+    let response = post_json(
+        &app,
+        &format!("/sponsored-apis/{}/run", api.id),
+        serde_json::json!({ "caller": "agent", "input": {} }),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let health = state.inner.read().await.health.clone();
+    assert_eq!(health.status(api.id).recent_calls, 0);
+
+    let calls =
+        read_json(send(&app, "GET", &format!("/sponsored-apis/{}/calls", api.id)).await).await;
+    assert_eq!(calls["calls"][0]["outcome"], "local_error");
+    assert_eq!(calls["calls"][0]["amount_cents"], 0);
+    assert!(calls["calls"][0]["error_class"].is_null());
+}
+
+#[tokio::test]
+async fn budget_is_released_when_the_call_cannot_be_recorded() {
+    let Some((app, state)) = test_db_app().await else {
+        return;
+    };
+    let api = insert_test_sponsored_api(
+        &state,
+        sample_sponsored_api("https://api.example.com/search"),
+    )
+    .await;
+
+    // Postgres rejects NUL in text, so the charge succeeds and the call insert fails.
+    let response = post_json(
+        &app,
+        &format!("/sponsored-apis/{}/run", api.id),
+        serde_json::json!({ "caller": "agent\u{0}", "input": {} }),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let calls =
+        read_json(send(&app, "GET", &format!("/sponsored-apis/{}/calls", api.id)).await).await;
+    assert_eq!(calls["calls"], serde_json::json!([]));
+    let api = read_json(send(&app, "GET", &format!("/sponsored-apis/{}", api.id)).await).await;
+    assert_eq!(api["budget_remaining_cents"], 100);
+}
+
+#[tokio::test]
+async fn nested_query_input_is_rejected_before_charging() {
+    let Some((app, state)) = test_db_app().await else {
+        return;
+    };
+    let api = insert_test_sponsored_api(
+        &state,
+        SponsoredApi {
+            upstream_method: "GET".to_string(),
+            ..sample_sponsored_api("https://api.example.com/search")
+        },
+    )
+    .await;
+
+    let response = post_json(
+        &app,
+        &format!("/sponsored-apis/{}/run", api.id),
+        serde_json::json!({ "caller": "agent", "input": { "filter": { "tag": "a" } } }),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let health = state.inner.read().await.health.clone();
+    assert_eq!(health.status(api.id).recent_calls, 0);
+    let calls =
+        read_json(send(&app, "GET", &format!("/sponsored-apis/{}/calls", api.id)).await).await;
+    assert_eq!(calls["calls"], serde_json::json!([]));
+    let api = read_json(send(&app, "GET", &format!("/sponsored-apis/{}", api.id)).await).await;
+    assert_eq!(api["budget_remaining_cents"], 100);
+}
+
+#[test]
+fn call_log_classifies_failures_and_pages_by_cursor() {
+    use crate::call_log::{classify_call, decode_cursor, encode_cursor, render_csv};
+
+    assert_eq!(classify_call(StreamOutcome::Completed, Some(200)), None);
+    assert_eq!(classify_call(StreamOutcome::CacheHit, Some(200)), None);
+    assert_eq!(
+        classify_call(StreamOutcome::Completed, Some(429)),
+        Some(CallErrorClass::UpstreamClientError)
+    );
+    assert_eq!(
+        classify_call(StreamOutcome::Completed, Some(503)),
+        Some(CallErrorClass::UpstreamServerError)
+    );
+    assert_eq!(
+        classify_call(StreamOutcome::TimedOut, None),
+        Some(CallErrorClass::Timeout)
+    );
+    assert_eq!(
+        classify_call(StreamOutcome::Truncated, Some(200)),
+        Some(CallErrorClass::ResponseTooLarge)
+    );
+    assert_eq!(classify_call(StreamOutcome::LocalError, None), None);
+
+    let call = SponsoredApiCall {
+        id: Uuid::new_v4(),
+        sponsored_api_id: Uuid::new_v4(),
+        payment_mode: "sponsored".to_string(),
+        amount_cents: 5,
+        tx_hash: None,
+        caller: Some("=HYPERLINK(\"x\"), agent".to_string()),
+        created_at: Utc::now(),
+        upstream_status: Some(503),
+        response_bytes: Some(12),
+        outcome: Some("completed".to_string()),
+        duration_ms: Some(40),
+        error_class: Some(CallErrorClass::UpstreamServerError),
+        finalized_at: None,
+    };
+    let (created_at, id) = decode_cursor(&encode_cursor(&call)).expect("cursor round-trips");
+    assert_eq!(id, call.id);
+    assert_eq!(
+        created_at.timestamp_micros(),
+        call.created_at.timestamp_micros()
+    );
+    assert!(decode_cursor("not-a-cursor").is_err());
+
+    let csv = render_csv(std::slice::from_ref(&call));
+    let row = csv.lines().nth(1).expect("one data row");
+    assert!(row.contains(r#""'=HYPERLINK(""x""), agent""#));
+    assert!(row.ends_with(",upstream_server_error,"));
+}
+
+#[test]
+fn profile_email_is_normalized_for_deduplication() {
+    assert_eq!(normalize_email("  Alice@Example.COM "), "alice@example.com");
+    assert_eq!(normalize_email("   "), "");
+}
+
+#[tokio::test]
+async fn deleting_a_profile_anonymizes_its_user_payments() {
+    let Some((app, state)) = test_db_app().await else {
+        return;
+    };
+    let db = state
+        .inner
+        .read()
+        .await
+        .db
+        .clone()
+        .expect("db is configured");
+
+    let user_id = create_test_profile(&app).await;
+
+    let tx_hash = format!("0x{}", Uuid::new_v4().simple());
+    let response = post_json(
+        &app,
+        "/webhooks/x402scan/settlement",
+        serde_json::json!({
+            "tx_hash": tx_hash,
+            "service": "design",
+            "amount_cents": 5,
+            "payer": "0x3333333333333333333333333333333333333333",
+            "source": "user",
+            "status": "settled",
+            "user_id": user_id,
+        }),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::ACCEPTED);
+
+    let export = read_json(send(&app, "GET", &format!("/profiles/{user_id}/export")).await).await;
+    assert_eq!(export["payments"][0]["tx_hash"], tx_hash.as_str());
+
+    let response = send(&app, "DELETE", &format!("/profiles/{user_id}")).await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let (payer, linked_user) = sqlx::query_as::<_, (String, Option<Uuid>)>(
+        "select payer, user_id from payments where tx_hash = $1",
+    )
+    .bind(&tx_hash)
+    .fetch_one(&db)
+    .await
+    .expect("payment row is kept");
+    assert_eq!(payer, "anonymized");
+    assert_eq!(linked_user, None);
+}
+
+#[test]
+fn task_callback_signature_round_trips() {
+    let secret = verification::generate_task_secret();
+    let body = br#"{"status":"verified"}"#;
+    let signature = verification::sign_task_payload(&secret, body);
+
+    assert!(verify_task_signature(&secret, body, &signature));
+    assert!(!verify_task_signature(
+        &secret,
+        br#"{"status":"rejected"}"#,
+        &signature
+    ));
+    assert!(!verify_task_signature("other-secret", body, &signature));
+
+    let completion_id = Uuid::new_v4();
+    let state = oauth_state(&secret, completion_id);
+    let (parsed_id, state_signature) = parse_oauth_state(&state).expect("state should parse");
+    assert_eq!(parsed_id, completion_id);
+    assert!(verify_task_signature(
+        &secret,
+        completion_id.as_bytes(),
+        state_signature
+    ));
+
+    for error in [None, Some("access_denied")] {
+        let signature = payloadexchange_client::oauth_callback_signature(&secret, &state, error);
+        let payload = oauth_callback_payload(&state, error);
+        assert!(verify_task_signature(
+            &secret,
+            payload.as_bytes(),
+            &signature
+        ));
+    }
+}
+
+#[tokio::test]
+async fn campaign_discovery_lists_concrete_service_run_urls() {
+    let Some((app, _)) = test_db_app().await else {
+        return;
+    };
+    let response = post_json(
+        &app,
+        "/campaigns",
+        serde_json::json!({
+            "name": "Discovery launch",
+            "sponsor": "acme",
+            "required_task": "signup",
+            "subsidy_per_call_cents": 5,
+            "budget_cents": 100,
+            "query_urls": ["https://sponsor.example/search"],
+            "task_verification": { "type": "sponsor_callback" },
+        }),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let campaign_id = read_json(response).await["campaign"]["id"].clone();
+
+    let listed = read_json(send(&app, "GET", "/campaigns/discovery").await).await;
+    let item = listed
+        .as_array()
+        .expect("discovery is a list")
+        .iter()
+        .find(|item| item["campaign_id"] == campaign_id)
+        .expect("campaign is listed")
+        .clone();
+    let urls: Vec<&str> = item["service_run_urls"]
+        .as_array()
+        .expect("run urls are listed")
+        .iter()
+        .filter_map(serde_json::Value::as_str)
+        .collect();
+    assert_eq!(urls.len(), BUILTIN_SERVICES.len());
+    assert!(urls.iter().any(|url| url.ends_with("/proxy/design/run")));
+    assert!(!item.to_string().contains(":service"));
+}
+
+#[tokio::test]
+async fn oauth_callback_requires_the_sponsor_signature() {
+    let Some((app, _)) = test_db_app().await else {
+        return;
+    };
+    let user_id = create_test_profile(&app).await;
+    let response = post_json(
+        &app,
+        "/campaigns",
+        serde_json::json!({
+            "name": "OAuth launch",
+            "sponsor": "acme",
+            "required_task": "connect_account",
+            "subsidy_per_call_cents": 5,
+            "budget_cents": 100,
+            "task_verification": {
+                "type": "oauth_redirect",
+                "authorize_url": "https://sponsor.example/authorize",
+            },
+        }),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let created = read_json(response).await;
+    let task_secret = created["task_secret"].as_str().expect("secret is returned");
+
+    let response = post_json(
+        &app,
+        "/tasks/complete",
+        serde_json::json!({
+            "campaign_id": created["campaign"]["id"],
+            "user_id": user_id,
+            "task_name": "connect_account",
+        }),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let redirect = read_json(response).await["redirect_url"]
+        .as_str()
+        .map(|url| reqwest::Url::parse(url).expect("redirect is a URL"))
+        .expect("redirect tasks return a redirect URL");
+    let state = redirect
+        .query_pairs()
+        .find(|(key, _)| key == "state")
+        .map(|(_, value)| value.into_owned())
+        .expect("redirect carries state");
+
+    let callback = |signature: String| {
+        let app = app.clone();
+        let uri = format!("/tasks/oauth/callback?state={state}&signature={signature}");
+        async move { send(&app, "GET", &uri).await }
+    };
+    let (_, state_signature) = parse_oauth_state(&state).expect("state parses");
+    let forged = callback(state_signature.to_string()).await;
+    assert_eq!(forged.status(), StatusCode::UNAUTHORIZED);
+
+    let signature = verification::sign_task_payload(
+        task_secret,
+        oauth_callback_payload(&state, None).as_bytes(),
+    );
+    let verified = callback(signature).await;
+    assert_eq!(verified.status(), StatusCode::OK);
+    assert_eq!(read_json(verified).await["status"], "verified");
+}
+
+#[tokio::test]
+async fn form_task_requires_declared_fields() {
+    let http = reqwest::Client::new();
+    let verification = TaskVerification::FormSubmission {
+        required_fields: vec!["company".to_string(), "team_size".to_string()],
+    };
+
+    let missing = evaluate_task_proof(
+        &http,
+        &verification,
+        Some(&serde_json::json!({ "company": "Acme", "team_size": "" })),
+        None,
+    )
+    .await;
+    assert!(missing.is_err());
+
+    let outcome = evaluate_task_proof(
+        &http,
+        &verification,
+        Some(&serde_json::json!({ "company": "Acme", "team_size": 12 })),
+        None,
+    )
+    .await
+    .expect("complete form should verify");
+    assert_eq!(outcome.status, TaskCompletionStatus::Verified);
+
+    assert!(
+        validate_task_verification(&TaskVerification::FormSubmission {
+            required_fields: Vec::new(),
+        })
+        .is_err()
+    );
+    let without_provider = serde_json::from_value::<CreateCampaignRequest>(serde_json::json!({
+        "name": "Launch",
+        "sponsor": "acme",
+        "required_task": "survey",
+        "subsidy_per_call_cents": 5,
+        "budget_cents": 100,
+    }));
+    assert!(without_provider.is_err());
+
+    let callback = evaluate_task_proof(&http, &TaskVerification::SponsorCallback, None, None)
+        .await
+        .expect("callback tasks start pending");
+    assert_eq!(callback.status, TaskCompletionStatus::Pending);
+}
+
+const TASK_WALLET: &str = "0xAbCdEf0000000000000000000000000000000001";
+
+/// JSON-RPC endpoint whose every receipt is a successful transaction from `TASK_WALLET`.
+async fn mock_rpc_url() -> String {
+    let app = Router::new().route(
+        "/",
+        axum::routing::post(|| async {
+            Json(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "result": { "status": "0x1", "from": TASK_WALLET.to_ascii_lowercase() },
+            }))
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("listener has an address");
+    tokio::spawn(async move { axum::serve(listener, app).await });
+    format!("http://{addr}/")
+}
+
+#[tokio::test]
+async fn onchain_tasks_must_come_from_the_profile_wallet() {
+    let verification = TaskVerification::OnchainAction {
+        rpc_url: mock_rpc_url().await,
+        contract_address: None,
+    };
+    let evidence = serde_json::json!({ "tx_hash": "0xABC" });
+    let http = reqwest::Client::new();
+
+    let outcome = evaluate_task_proof(&http, &verification, Some(&evidence), Some(TASK_WALLET))
+        .await
+        .expect("rpc answers");
+    // The profile's wallet is only claimed, so the sponsor still has to confirm.
+    assert_eq!(outcome.status, TaskCompletionStatus::Pending);
+    assert_eq!(outcome.tx_hash.as_deref(), Some("0xabc"));
+
+    let other = "0x0000000000000000000000000000000000000002";
+    let outcome = evaluate_task_proof(&http, &verification, Some(&evidence), Some(other))
+        .await
+        .expect("rpc answers");
+    assert_eq!(outcome.status, TaskCompletionStatus::Rejected);
+    assert!(outcome.tx_hash.is_none());
+
+    assert!(
+        evaluate_task_proof(&http, &verification, Some(&evidence), None)
+            .await
+            .is_err()
+    );
+
+    let TaskVerification::OnchainAction { rpc_url, .. } = &verification else {
+        unreachable!()
+    };
+    assert!(check_upstream_url(&Default::default(), rpc_url).is_err());
+}
+
+#[tokio::test]
+async fn onchain_transactions_verify_one_completion_per_campaign() {
+    let Some((app, state)) = test_db_app().await else {
+        return;
+    };
+    {
+        let mut locked = state.inner.write().await;
+        locked.config.upstream_policy.allow_private_networks = true;
+        locked.upstream_http =
+            crate::network::build_upstream_client(&locked.config.upstream_policy);
+    }
+    let response = post_json(
+        &app,
+        "/campaigns",
+        serde_json::json!({
+            "name": "Mint launch",
+            "sponsor": "acme",
+            "required_task": "mint",
+            "subsidy_per_call_cents": 5,
+            "budget_cents": 100,
+            "task_verification": { "type": "onchain_action", "rpc_url": mock_rpc_url().await },
+        }),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let campaign_id = read_json(response).await["campaign"]["id"].clone();
+
+    let mut statuses = Vec::new();
+    for _ in 0..2 {
+        let response = post_json(
+            &app,
+            "/profiles",
+            serde_json::json!({
+                "email": format!("{}@example.com", Uuid::new_v4()),
+                "region": "us",
+                "roles": [],
+                "tools_used": [],
+                "attributes": { "wallet_address": TASK_WALLET },
+            }),
+            None,
+        )
+        .await;
+        let user_id = read_json(response).await["id"].clone();
+        let response = post_json(
+            &app,
+            "/tasks/complete",
+            serde_json::json!({
+                "campaign_id": campaign_id,
+                "user_id": user_id,
+                "task_name": "mint",
+                "evidence": { "tx_hash": "0xfeed" },
+            }),
+            None,
+        )
+        .await;
+        let status = response.status();
+        let json = read_json(response).await;
+        statuses.push((status, json["status"].clone()));
+    }
+    assert_eq!(
+        statuses,
+        vec![
+            (StatusCode::CREATED, serde_json::json!("pending")),
+            (StatusCode::CONFLICT, serde_json::Value::Null),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn sponsor_webhooks_respect_the_network_policy() {
+    let Some((app, state)) = test_db_app().await else {
+        return;
+    };
+    let create = |url: &str| {
+        post_json(
+            &app,
+            "/sponsor-webhooks",
+            serde_json::json!({
+                "sponsor": "acme",
+                "url": url,
+                "events": ["budget.low", "task.completed", "budget.low"],
+            }),
+            None,
+        )
+    };
+
+    let internal = create("http://169.254.169.254/latest/meta-data").await;
+    assert_eq!(internal.status(), StatusCode::BAD_REQUEST);
+
+    state
+        .inner
+        .write()
+        .await
+        .config
+        .upstream_policy
+        .allow_private_networks = true;
+    let response = create("http://127.0.0.1:9/hooks").await;
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let events = read_json(response).await["webhook"]["events"].clone();
+    assert_eq!(events, serde_json::json!(["task.completed", "budget.low"]));
+}
+
+#[tokio::test]
+async fn raising_the_price_past_the_budget_deactivates_an_api() {
+    let Some((app, state)) = test_db_app().await else {
+        return;
+    };
+    let api = insert_test_sponsored_api(
+        &state,
+        SponsoredApi {
+            price_cents: 10,
+            budget_remaining_cents: 40,
+            ..sample_sponsored_api("https://api.example.com/search")
+        },
+    )
+    .await;
+    let uri = format!("/sponsored-apis/{}", api.id);
+
+    let response = send_json(
+        &app,
+        "PATCH",
+        &uri,
+        serde_json::json!({ "price_cents": 50 }),
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(read_json(response).await["active"], false);
+
+    let response = send_json(
+        &app,
+        "PATCH",
+        &uri,
+        serde_json::json!({ "price_cents": 40 }),
+    )
+    .await;
+    assert_eq!(read_json(response).await["active"], true);
+}
+
+#[tokio::test]
+async fn webhook_retries_back_off_exponentially() {
+    let (_, state) = test_app();
+    let config = {
+        let mut locked = state.inner.write().await;
+        locked.config.webhook_retry_base_secs = 30;
+        locked.config.clone()
+    };
+
+    assert_eq!(webhooks::retry_delay(&config, 1).num_seconds(), 30);
+    assert_eq!(webhooks::retry_delay(&config, 2).num_seconds(), 60);
+    assert_eq!(webhooks::retry_delay(&config, 5).num_seconds(), 480);
+
+    let events = budget_events("acme", None, serde_json::json!({}), 100, 5, 100, false);
+    let kinds: Vec<_> = events.iter().map(|event| event.event_type).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            WebhookEventType::BudgetLow,
+            WebhookEventType::BudgetExhausted,
+            WebhookEventType::CampaignEnded
+        ]
+    );
+}
+
+#[tokio::test]
+async fn sponsor_timeseries_rejects_oversized_ranges() {
+    let (app, _) = test_app();
+    let uri = format!(
+        "/dashboard/sponsor/{}/timeseries?bucket=hour&from=2020-01-01T00:00:00Z&to=2026-01-01T00:00:00Z",
+        Uuid::new_v4()
+    );
+
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri(uri)
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should handle request");
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let json = read_json(response).await;
+    assert_eq!(json["error"]["code"], "validation_error");
+}
+
+#[tokio::test]
+async fn sponsored_api_updates_share_create_validation() {
+    assert!(validate_price_cents(0).is_err());
+    assert!(validate_price_cents(1).is_ok());
+    let policy = crate::network::UpstreamPolicy::default();
+    assert!(policy.validate("not a url").await.is_err());
+    assert!(policy.validate(" https://93.184.215.14/v1 ").await.is_ok());
+
+    let mut headers = HashMap::new();
+    headers.insert("x-api-key".to_string(), "secret".to_string());
+    assert!(validate_upstream_headers(&headers).is_ok());
+    headers.insert("bad header".to_string(), "value".to_string());
+    assert!(validate_upstream_headers(&headers).is_err());
+}
+
+#[test]
+fn budget_pool_membership_patches_distinguish_detach_from_unchanged() {
+    let pool_id = Uuid::new_v4();
+    let attach: UpdateSponsoredApiRequest = serde_json::from_value(serde_json::json!({
+        "budget_pool_id": pool_id,
+        "pool_limit_cents": 500,
+    }))
+    .expect("attach should parse");
+    assert_eq!(attach.budget_pool_id, Some(Some(pool_id)));
+    assert_eq!(attach.pool_limit_cents, Some(Some(500)));
+
+    let detach: UpdateSponsoredApiRequest =
+        serde_json::from_value(serde_json::json!({ "budget_pool_id": null }))
+            .expect("detach should parse");
+    assert_eq!(detach.budget_pool_id, Some(None));
+    assert_eq!(detach.pool_limit_cents, None);
+
+    let untouched: UpdateSponsoredApiRequest =
+        serde_json::from_value(serde_json::json!({ "name": "renamed" }))
+            .expect("rename should parse");
+    assert_eq!(untouched.budget_pool_id, None);
+
+    let detail = BudgetPoolDetail {
+        pool: BudgetPool {
+            id: pool_id,
+            sponsor: "acme".to_string(),
+            name: "shared".to_string(),
+            budget_total_cents: 1_000,
+            budget_remaining_cents: 400,
+            created_at: Utc::now(),
+        },
+        members: vec![BudgetPoolMember {
+            kind: BudgetPoolMemberKind::SponsoredApi,
+            id: Uuid::new_v4(),
+            name: "search".to_string(),
+            pool_limit_cents: Some(500),
+            pool_spent_cents: 300,
+        }],
+    };
+    let json = serde_json::to_value(&detail).expect("detail should serialize");
+    assert_eq!(json["budget_remaining_cents"], 400);
+    assert_eq!(json["members"][0]["kind"], "sponsored_api");
+}
+
+#[test]
+fn upstream_secrets_survive_key_rotation() {
+    let old = crate::secrets::SecretKeyring::parse(&format!("old:{}", STANDARD.encode([7u8; 32])))
+        .expect("keyring should parse");
+    let rotated = crate::secrets::SecretKeyring::parse(&format!(
+        "new:{},old:{}",
+        STANDARD.encode([9u8; 32]),
+        STANDARD.encode([7u8; 32])
+    ))
+    .expect("keyring should parse");
+
+    let sealed = old.seal("sk-live-123").expect("value should seal");
+    assert!(!sealed.contains("sk-live-123"));
+    assert_eq!(
+        rotated.open(&sealed).expect("old key still opens"),
+        "sk-live-123"
+    );
+    assert_eq!(
+        rotated
+            .open("plain:legacy-plaintext")
+            .expect("legacy passthrough"),
+        "legacy-plaintext"
+    );
+    // Legacy plaintext that happens to look sealed is still read as plaintext.
+    assert_eq!(
+        rotated.open("plain:v1:new:abc").expect("marked plaintext"),
+        "v1:new:abc"
+    );
+    assert!(rotated.open("unmarked-plaintext").is_err());
+    assert!(
+        crate::secrets::SecretKeyring::default()
+            .store_headers(&HashMap::from([("x".to_string(), "v1:k:y".to_string())]))
+            .expect("plaintext is marked")["x"]
+            .starts_with("plain:")
+    );
+    assert!(
+        crate::secrets::SecretKeyring::default()
+            .seal("value")
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn resealing_skips_rows_it_cannot_open() {
+    let Some((_, state)) = test_db_app().await else {
+        return;
+    };
+    let header = |value: &str| HashMap::from([("x-api-key".to_string(), value.to_string())]);
+    let legacy = insert_test_sponsored_api(
+        &state,
+        SponsoredApi {
+            sealed_headers: header("plain:v1:looks:sealed"),
+            ..sample_sponsored_api("https://api.example.com/search")
+        },
+    )
+    .await;
+    let orphaned = insert_test_sponsored_api(
+        &state,
+        SponsoredApi {
+            sealed_headers: header("v1:retired:AAAA"),
+            ..sample_sponsored_api("https://api.example.com/search")
+        },
+    )
+    .await;
+
+    let keyring =
+        crate::secrets::SecretKeyring::parse(&format!("reseal:{}", STANDARD.encode([3u8; 32])))
+            .expect("keyring should parse");
+    let db = state
+        .inner
+        .read()
+        .await
+        .db
+        .clone()
+        .expect("db is configured");
+    let report = crate::secrets::reseal_upstream_secrets(&db, &keyring)
+        .await
+        .expect("reseal runs past bad rows");
+    assert!(report.resealed >= 1 && report.failed >= 1, "{report:?}");
+
+    let legacy = load_sponsored_api(&db, legacy.id).await.expect("api loads");
+    assert_eq!(
+        keyring
+            .open_headers(&legacy.sealed_headers)
+            .expect("resealed under the active key")["x-api-key"],
+        "v1:looks:sealed"
+    );
+    let orphaned = load_sponsored_api(&db, orphaned.id)
+        .await
+        .expect("api loads");
+    assert_eq!(orphaned.sealed_headers["x-api-key"], "v1:retired:AAAA");
+}
+
+#[test]
+fn sponsored_api_responses_redact_secret_headers() {
+    let mut headers = HashMap::new();
+    headers.insert(
+        "Authorization".to_string(),
+        "Bearer sk-live-123".to_string(),
+    );
+    headers.insert("X-Client".to_string(), "payloadexchange".to_string());
+
+    let (public, secret) = split_upstream_headers(headers.clone(), &["x-client".to_string()])
+        .expect("headers should split");
+    assert_eq!(
+        public.get("x-client").map(String::as_str),
+        Some("payloadexchange")
+    );
+    assert!(secret.contains_key("authorization"));
+    assert!(split_upstream_headers(headers, &["x-missing".to_string()]).is_err());
+
+    let api = SponsoredApi {
+        upstream_headers: public,
+        secret_header_names: sorted_header_names(&secret),
+        sealed_headers: secret,
+        ..sample_sponsored_api("https://api.example.com")
+    };
+    let json = serde_json::to_string(&api).expect("api should serialize");
+    assert!(!json.contains("sk-live-123"));
+    assert!(json.contains("\"secret_header_names\":[\"authorization\"]"));
+}
+
+#[tokio::test]
+async fn upstream_policy_blocks_internal_targets() {
+    let policy = crate::network::UpstreamPolicy::default();
+    for url in [
+        "http://169.254.169.254/latest/meta-data",
+        "http://127.0.0.1:8080/",
+        "http://localhost/admin",
+        "http://10.0.0.5/",
+        "http://[::1]/",
+        "http://[::ffff:192.168.1.1]/",
+        "ftp://93.184.215.14/",
+    ] {
+        let err = policy.validate(url).await.expect_err(url);
+        assert!(matches!(
+            err,
+            ApiError::Http {
+                status: StatusCode::BAD_REQUEST,
+                ..
+            }
+        ));
+    }
+
+    let allowlisted = crate::network::UpstreamPolicy {
+        allowed_hosts: vec!["*.example.com".to_string()],
+        ..crate::network::UpstreamPolicy::default()
+    };
+    let url = reqwest::Url::parse("https://api.example.com/v1").expect("url should parse");
+    assert!(allowlisted.check_url(&url).is_ok());
+    let url = reqwest::Url::parse("https://example.com.evil.io/").expect("url should parse");
+    assert!(allowlisted.check_url(&url).is_err());
+}
+
+fn sample_sponsored_api(upstream_url: &str) -> SponsoredApi {
+    SponsoredApi {
+        id: Uuid::new_v4(),
+        name: "search".to_string(),
+        sponsor: "acme".to_string(),
+        description: None,
+        upstream_url: upstream_url.to_string(),
+        upstream_method: "POST".to_string(),
+        upstream_headers: HashMap::new(),
+        secret_header_names: Vec::new(),
+        sealed_headers: HashMap::new(),
+        price_cents: 1,
+        budget_total_cents: 100,
+        budget_remaining_cents: 100,
+        active: true,
+        paused: false,
+        service_key: "sponsored-api-test".to_string(),
+        forward_headers: Vec::new(),
+        body_encoding: UpstreamBodyEncoding::Json,
+        cache: None,
+        rate_limit: None,
+        health_check: None,
+        budget_pool_id: None,
+        pool_limit_cents: None,
+        pool_spent_cents: 0,
+        input_schema: None,
+        output_schema: None,
+        validate_output: false,
+        transform: None,
+        eligibility: None,
+        health: None,
+        created_at: Utc::now(),
+    }
+}
+
+#[test]
+fn upstream_requests_template_paths_and_forward_allowed_headers() {
+    let api = SponsoredApi {
+        upstream_method: "PATCH".to_string(),
+        forward_headers: normalize_forward_headers(vec![
+            "Accept-Language".to_string(),
+            "x-api-key".to_string(),
+        ])
+        .expect("forward headers should normalize"),
+        upstream_headers: HashMap::from([("x-api-key".to_string(), "sponsor".to_string())]),
+        ..sample_sponsored_api("https://api.example.com/users/{id}/posts/{slug}")
+    };
+
+    let mut caller_headers = HeaderMap::new();
+    caller_headers.insert("accept-language", HeaderValue::from_static("de"));
+    caller_headers.insert("x-api-key", HeaderValue::from_static("caller-override"));
+    caller_headers.insert("cookie", HeaderValue::from_static("session=1"));
+
+    let prepared = prepare_upstream_request(
+        &api,
+        serde_json::json!({ "id": 42, "slug": "a b/c", "title": "hi" }),
+        &caller_headers,
+    )
+    .expect("request should prepare");
+
+    assert_eq!(
+        prepared.url,
+        "https://api.example.com/users/42/posts/a%20b%2Fc"
+    );
+    assert_eq!(prepared.input, serde_json::json!({ "title": "hi" }));
+    let forwarded: Vec<&str> = prepared
+        .forwarded_headers
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert_eq!(forwarded, vec!["accept-language"]);
+
+    let missing = prepare_upstream_request(&api, serde_json::json!({ "id": 1 }), &caller_headers);
+    assert!(missing.is_err());
+    for dots in ["..", "."] {
+        let input = serde_json::json!({ "id": dots, "slug": "x" });
+        assert!(prepare_upstream_request(&api, input, &caller_headers).is_err());
+    }
+    let dotted = serde_json::json!({ "id": "...", "slug": ".x" });
+    assert_eq!(
+        prepare_upstream_request(&api, dotted, &caller_headers)
+            .expect("dots inside a value are fine")
+            .url,
+        "https://api.example.com/users/.../posts/.x"
+    );
+    let form = SponsoredApi {
+        body_encoding: UpstreamBodyEncoding::Form,
+        ..sample_sponsored_api("https://api.example.com/submit")
+    };
+    assert!(prepare_upstream_request(&form, serde_json::json!(["a"]), &caller_headers).is_err());
+    assert!(normalize_forward_headers(vec!["Host".to_string()]).is_err());
+    assert!(upstream_path_params("https://api.example.com/{unclosed").is_err());
+    for template in [
+        "https://{host}/v1",
+        "https://api.example.com:{port}/v1",
+        "https://{user}@api.example.com/v1",
+        "{scheme}://api.example.com/v1",
+    ] {
+        assert!(upstream_path_params(template).is_err(), "{template}");
+    }
+    assert_eq!(
+        upstream_path_params("https://api.example.com/v1/{id}?q={q}").expect("path is fine"),
+        vec!["id".to_string(), "q".to_string()]
+    );
+    assert_eq!(
+        normalize_upstream_method(Some("delete".to_string())).expect("delete is allowed"),
+        "DELETE"
+    );
+}
+
+#[test]
+fn proxy_urls_join_base_path_and_query() {
+    assert_eq!(
+        proxy_upstream_url("https://api.example.com/v1/", "users/7", Some("expand=1"))
+            .expect("url should join"),
+        "https://api.example.com/v1/users/7?expand=1"
+    );
+    assert_eq!(
+        proxy_upstream_url("https://api.example.com/v1?key=a", "", Some("q=x"))
+            .expect("url should join"),
+        "https://api.example.com/v1?key=a&q=x"
+    );
+    assert!(proxy_upstream_url("https://api.example.com/v1", "../admin", None).is_err());
+    for (path, expected) in [
+        ("..\\admin", "https://api.example.com/v1/..%5Cadmin"),
+        (
+            "%2e%2e/admin",
+            "https://api.example.com/v1/%252e%252e/admin",
+        ),
+        (
+            "a?admin=1#x",
+            "https://api.example.com/v1/a%3Fadmin%3D1%23x",
+        ),
+    ] {
+        assert_eq!(
+            proxy_upstream_url("https://api.example.com/v1", path, None)
+                .expect("segments are encoded"),
+            expected
+        );
+    }
+    assert!(proxy_upstream_url("https://api.example.com/users/{id}", "posts", None).is_err());
+}
+
+#[tokio::test]
+async fn metered_streams_report_how_they_ended() {
+    use crate::streaming::{MeteredStream, StreamOutcome, collect_metered};
+    use std::sync::{Arc, Mutex};
+
+    let upstream = |body: &'static str| reqwest::Response::from(axum::http::Response::new(body));
+
+    let report = Arc::new(Mutex::new(None));
+    let sink = report.clone();
+    let body = collect_metered(MeteredStream::new(
+        upstream("data: hello\n\n"),
+        64,
+        std::time::Duration::from_secs(5),
+        move |finished| *sink.lock().expect("lock") = Some(finished),
+    ))
+    .await
+    .expect("body should stream");
+    assert_eq!(&body[..], b"data: hello\n\n");
+    let finished = report.lock().expect("lock").expect("hook should run");
+    assert_eq!(finished.outcome, StreamOutcome::Completed);
+    assert_eq!(finished.bytes, 13);
+
+    let sink = report.clone();
+    let truncated = collect_metered(MeteredStream::new(
+        upstream("0123456789"),
+        4,
+        std::time::Duration::from_secs(5),
+        move |finished| *sink.lock().expect("lock") = Some(finished),
+    ))
+    .await;
+    assert!(truncated.is_err());
+    let finished = report.lock().expect("lock").expect("hook should run");
+    assert_eq!(finished.outcome, StreamOutcome::Truncated);
+
+    let sink = report.clone();
+    drop(MeteredStream::new(
+        upstream("never read"),
+        64,
+        std::time::Duration::from_secs(5),
+        move |finished| *sink.lock().expect("lock") = Some(finished),
+    ));
+    let finished = report.lock().expect("lock").expect("hook should run");
+    assert_eq!(finished.outcome, StreamOutcome::Aborted);
+}
+
+#[test]
+fn response_cache_honours_upstream_cache_control() {
+    use crate::cache::{ResponseCache, cache_key, cache_ttl};
+
+    let policy = ResponseCachePolicy {
+        ttl_secs: 300,
+        max_entry_bytes: 1024,
+        hit_price_cents: 0,
+    };
+    let with_cache_control = |value: &'static str| {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(value));
+        headers
+    };
+    assert_eq!(
+        cache_ttl(&policy, &HeaderMap::new()),
+        Some(std::time::Duration::from_secs(300))
+    );
+    assert_eq!(
+        cache_ttl(&policy, &with_cache_control("public, max-age=60")),
+        Some(std::time::Duration::from_secs(60))
+    );
+    assert_eq!(
+        cache_ttl(&policy, &with_cache_control("max-age=60, s-maxage=120")),
+        Some(std::time::Duration::from_secs(120))
+    );
+    assert_eq!(cache_ttl(&policy, &with_cache_control("no-store")), None);
+    assert_eq!(cache_ttl(&policy, &with_cache_control("max-age=0")), None);
+
+    let api = sample_sponsored_api("https://api.example.com/search");
+    let request = |input: serde_json::Value| {
+        prepare_upstream_request(&api, input, &HeaderMap::new()).expect("request should prepare")
+    };
+    let key = cache_key(
+        api.id,
+        "GET",
+        &request(serde_json::json!({ "q": "a", "n": 1 })),
+    );
+    assert_eq!(
+        key,
+        cache_key(
+            api.id,
+            "GET",
+            &request(serde_json::json!({ "n": 1, "q": "a" }))
+        )
+    );
+    assert_ne!(
+        key,
+        cache_key(api.id, "GET", &request(serde_json::json!({ "q": "b" })))
+    );
+
+    let cache = ResponseCache::new(8);
+    cache.insert(
+        key.clone(),
+        api.id,
+        200,
+        axum::body::Bytes::from_static(b"cached"),
+        std::time::Duration::from_secs(60),
+    );
+    assert_eq!(cache.get(&key).map(|hit| hit.status), Some(200));
+    cache.insert(
+        "other".to_string(),
+        api.id,
+        200,
+        axum::body::Bytes::from_static(b"evicts"),
+        std::time::Duration::from_secs(60),
+    );
+    assert!(
+        cache.get(&key).is_none(),
+        "oldest entry is evicted when full"
+    );
+    cache.invalidate(api.id);
+    assert!(cache.get("other").is_none());
+}
+
+#[tokio::test]
+async fn batch_draws_stay_active_while_one_more_call_fits() {
+    let Some((_, state)) = test_db_app().await else {
+        return;
+    };
+    let db = state
+        .inner
+        .read()
+        .await
+        .db
+        .clone()
+        .expect("db is configured");
+    let api = insert_test_sponsored_api(
+        &state,
+        SponsoredApi {
+            price_cents: 2,
+            budget_total_cents: 10,
+            budget_remaining_cents: 10,
+            ..sample_sponsored_api("https://api.example.com/search")
+        },
+    )
+    .await;
+
+    let draw = draw_api_budget(&db, &api, 6)
+        .await
+        .expect("budget is drawn")
+        .expect("budget covers the batch");
+    assert_eq!(draw.new_remaining, 4);
+    assert!(draw.still_active, "4 cents still cover a 2 cent call");
+
+    let draw = draw_api_budget(&db, &api, 3)
+        .await
+        .expect("budget is drawn")
+        .expect("budget covers the batch");
+    assert!(!draw.still_active, "1 cent does not cover a 2 cent call");
+}
+
+#[tokio::test]
+async fn eligibility_allowlists_only_match_profile_ids() {
+    let Some((app, state)) = test_db_app().await else {
+        return;
+    };
+    let db = state
+        .inner
+        .read()
+        .await
+        .db
+        .clone()
+        .expect("db is configured");
+    let user_id = create_test_profile(&app).await;
+    let email = read_json(send(&app, "GET", &format!("/profiles/{user_id}")).await).await["email"]
+        .as_str()
+        .expect("profile has an email")
+        .to_string();
+
+    let mut by_name = EligibilityPolicy {
+        allowed_callers: vec!["agent-7".to_string()],
+        ..Default::default()
+    };
+    assert!(
+        validate_eligibility_policy(&db, &mut by_name)
+            .await
+            .is_err()
+    );
+
+    let mut policy = EligibilityPolicy {
+        allowed_callers: vec![format!(" {} ", user_id.to_uppercase())],
+        ..Default::default()
+    };
+    validate_eligibility_policy(&db, &mut policy)
+        .await
+        .expect("profile ids are accepted");
+    assert_eq!(policy.allowed_callers, vec![user_id.clone()]);
+
+    let reason = |caller: String| {
+        let (db, policy) = (db.clone(), policy.clone());
+        async move {
+            ineligibility_reason(&db, &policy, Some(&caller))
+                .await
+                .expect("eligibility is evaluated")
+        }
+    };
+    assert_eq!(reason(user_id).await, None);
+    assert!(
+        reason(email).await.is_some(),
+        "an email does not stand in for the id"
+    );
+}
+
+#[tokio::test]
+async fn ineligible_callers_do_not_get_free_cache_hits() {
+    let Some((app, state)) = test_db_app().await else {
+        return;
+    };
+    configure_local_x402(&state).await;
+    state
+        .inner
+        .write()
+        .await
+        .config
+        .upstream_policy
+        .allow_private_networks = true;
+    let upstream = Router::new().route(
+        "/search",
+        axum::routing::get(|| async { Json(serde_json::json!({ "hits": 1 })) }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("listener has an address");
+    tokio::spawn(async move { axum::serve(listener, upstream).await });
+
+    let vip = create_test_profile(&app).await;
+    let api = insert_test_sponsored_api(
+        &state,
+        SponsoredApi {
+            upstream_method: "GET".to_string(),
+            cache: Some(ResponseCachePolicy {
+                ttl_secs: 60,
+                max_entry_bytes: DEFAULT_CACHE_MAX_ENTRY_BYTES,
+                hit_price_cents: 0,
+            }),
+            eligibility: Some(EligibilityPolicy {
+                allowed_callers: vec![vip.clone()],
+                ..Default::default()
+            }),
+            ..sample_sponsored_api(&format!("http://{addr}/search"))
+        },
+    )
+    .await;
+    let uri = format!("/sponsored-apis/{}/run", api.id);
+    let run = |caller: &str| serde_json::json!({ "caller": caller, "input": { "q": "a" } });
+
+    let miss = post_json(&app, &uri, run(&vip), None).await;
+    assert_eq!(miss.status(), StatusCode::OK);
+    let hit = post_json(&app, &uri, run(&vip), None).await;
+    assert_eq!(hit.status(), StatusCode::OK);
+    assert_eq!(read_json(hit).await["payment_mode"], "free");
+
+    let stranger = post_json(&app, &uri, run("stranger"), None).await;
+    assert_eq!(stranger.status(), StatusCode::PAYMENT_REQUIRED);
+    assert_eq!(read_json(stranger).await["amount_cents"], api.price_cents);
+}
+
+#[tokio::test]
+async fn off_contract_output_is_delivered_and_logged_against_the_api() {
+    let Some((app, state)) = test_db_app().await else {
+        return;
+    };
+    state
+        .inner
+        .write()
+        .await
+        .config
+        .upstream_policy
+        .allow_private_networks = true;
+    let upstream = Router::new().route(
+        "/search",
+        axum::routing::post(|| async { Json(serde_json::json!({ "results": "none" })) }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("listener has an address");
+    tokio::spawn(async move { axum::serve(listener, upstream).await });
+
+    let api = insert_test_sponsored_api(
+        &state,
+        SponsoredApi {
+            output_schema: Some(serde_json::json!({
+                "type": "object",
+                "properties": { "results": { "type": "array" } },
+            })),
+            validate_output: true,
+            ..sample_sponsored_api(&format!("http://{addr}/search"))
+        },
+    )
+    .await;
+
+    let response = post_json(
+        &app,
+        &format!("/sponsored-apis/{}/run", api.id),
+        serde_json::json!({ "caller": "agent", "input": { "q": "a" } }),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::OK);
+    let json = read_json(response).await;
+    assert_eq!(json["upstream_body"], r#"{"results":"none"}"#);
+    assert_eq!(json["output_violations"][0]["path"], "/results");
+
+    let calls =
+        read_json(send(&app, "GET", &format!("/sponsored-apis/{}/calls", api.id)).await).await;
+    assert_eq!(
+        calls["calls"][0]["error_class"],
+        "output_contract_violation"
+    );
+    assert_eq!(calls["calls"][0]["amount_cents"], 1);
+}
+
+#[tokio::test]
+async fn moving_the_upstream_host_drops_sealed_headers() {
+    let Some((app, state)) = test_db_app().await else {
+        return;
+    };
+    configure_local_x402(&state).await;
+    state
+        .inner
+        .write()
+        .await
+        .config
+        .upstream_policy
+        .allow_private_networks = true;
+    let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let upstream = Router::new().route(
+        "/search",
+        axum::routing::post({
+            let seen = seen.clone();
+            move |headers: HeaderMap| async move {
+                seen.lock().unwrap().push(
+                    headers
+                        .get("x-api-key")
+                        .map(|value| value.to_str().unwrap_or_default().to_string()),
+                );
+                Json(serde_json::json!({ "hits": 1 }))
+            }
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0")
+        .await
+        .expect("listener should bind");
+    let addr = listener.local_addr().expect("listener has an address");
+    tokio::spawn(async move { axum::serve(listener, upstream).await });
+
+    let keyring =
+        crate::secrets::SecretKeyring::parse(&format!("k1:{}", STANDARD.encode([9u8; 32])))
+            .expect("keyring should parse");
+    state.inner.write().await.config.upstream_secret_keys = keyring.clone();
+    let sealed = keyring
+        .seal_headers(&HashMap::from([(
+            "x-api-key".to_string(),
+            "old-host-secret".to_string(),
+        )]))
+        .expect("headers should seal");
+    let api = insert_test_sponsored_api(
+        &state,
+        SponsoredApi {
+            secret_header_names: sorted_header_names(&sealed),
+            sealed_headers: sealed,
+            ..sample_sponsored_api("https://api.example.com/search")
+        },
+    )
+    .await;
+
+    let moved = send_json(
+        &app,
+        "PATCH",
+        &format!("/sponsored-apis/{}", api.id),
+        serde_json::json!({ "upstream_url": format!("http://{addr}/search") }),
+    )
+    .await;
+    assert_eq!(moved.status(), StatusCode::OK);
+    let moved = read_json(moved).await;
+    assert_eq!(moved["secret_header_names"], serde_json::json!([]));
+
+    let run = post_json(
+        &app,
+        &format!("/sponsored-apis/{}/run", api.id),
+        serde_json::json!({ "caller": "agent", "input": { "q": "a" } }),
+        None,
+    )
+    .await;
+    assert_eq!(run.status(), StatusCode::OK);
+    assert_eq!(*seen.lock().unwrap(), vec![None]);
+}
+
+#[tokio::test]
+async fn openapi_document_covers_paid_routes_and_resolves_every_ref() {
+    fn collect_refs<'a>(value: &'a serde_json::Value, refs: &mut Vec<&'a str>) {
+        match value {
+            serde_json::Value::Object(map) => {
+                if let Some(reference) = map.get("$ref").and_then(|value| value.as_str()) {
+                    refs.push(reference);
+                }
+                map.values().for_each(|value| collect_refs(value, refs));
+            }
+            serde_json::Value::Array(items) => {
+                items.iter().for_each(|value| collect_refs(value, refs))
+            }
+            _ => {}
+        }
+    }
+
+    let (app, _) = test_app();
+    let response = app
+        .clone()
+        .oneshot(
+            Request::builder()
+                .uri("/openapi.json")
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should handle request");
+    assert_eq!(response.status(), StatusCode::OK);
+    let spec = read_json(response).await;
+
+    assert_eq!(spec["openapi"], "3.1.0");
+    let run = &spec["paths"]["/sponsored-apis/{api_id}/run"]["post"];
+    assert_eq!(
+        run["responses"]["402"]["$ref"],
+        "#/components/responses/PaymentRequired"
+    );
+    assert!(run["responses"]["202"].is_object());
+    assert!(
+        spec["paths"]["/sponsored-apis/{api_id}/proxy/{path}"]["post"]["parameters"]
+            .as_array()
+            .is_some_and(|parameters| parameters.iter().any(|p| p["name"] == "path"))
+    );
+    let timeseries = &spec["paths"]["/dashboard/sponsor/{campaign_id}/timeseries"]["get"];
+    assert!(
+        timeseries["parameters"]
+            .as_array()
+            .is_some_and(|parameters| parameters.iter().any(|p| p["name"] == "bucket"))
+    );
+    assert!(spec["paths"]["/health"]["get"]["requestBody"].is_null());
+
+    let mut refs = Vec::new();
+    collect_refs(&spec, &mut refs);
+    assert!(refs.contains(&"#/components/schemas/ErrorResponse"));
+    for reference in refs {
+        let pointer = reference.trim_start_matches('#');
+        assert!(spec.pointer(pointer).is_some(), "dangling {reference}");
+    }
+
+    let docs = app
+        .oneshot(
+            Request::builder()
+                .uri("/docs")
+                .body(Body::empty())
+                .expect("request should build"),
+        )
+        .await
+        .expect("router should handle request");
+    assert_eq!(docs.status(), StatusCode::OK);
+    let body = to_bytes(docs.into_body(), usize::MAX)
+        .await
+        .expect("body should read");
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("/openapi.json"));
+    assert!(
+        !body.contains("<script src"),
+        "no renderer without an integrity hash"
+    );
+
+    let mut config = AppConfig::from_env().expect("test configuration should be valid");
+    config.api_docs_script_integrity = Some("sha384-abc".to_string());
+    let page = docs_page(&config);
+    assert!(page.contains(&format!(
+        r#"src="{DEFAULT_API_DOCS_SCRIPT_URL}" integrity="sha384-abc" crossorigin="anonymous""#
+    )));
+    assert!(DEFAULT_API_DOCS_SCRIPT_URL.contains("@scalar/api-reference@"));
+}
+
+#[tokio::test]
+async fn openapi_operations_match_the_router() {
+    let (app, _) = test_app();
+    let config = AppConfig::from_env().expect("test configuration should be valid");
+    let spec = openapi_document(&config);
+    let documented: std::collections::BTreeSet<(String, String)> = spec["paths"]
+        .as_object()
+        .expect("paths is an object")
+        .iter()
+        .flat_map(|(path, item)| {
+            item.as_object()
+                .expect("path item is an object")
+                .keys()
+                .map(|method| (path.clone(), method.clone()))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+
+    // `build_app` cannot list its routes, so read them from its source.
+    let source = include_str!("main.rs");
+    let start = source.find("fn build_app(").expect("build_app is defined");
+    let end = start + source[start..].find(".route_layer(").expect("routes end");
+    let mut routed = std::collections::BTreeSet::new();
+    for registration in source[start..end].split(".route(").skip(1) {
+        let path = registration
+            .split('"')
+            .nth(1)
+            .expect("route path is a string literal")
+            .replace("{*", "{");
+        if matches!(path.as_str(), "/openapi.json" | "/docs") {
+            continue;
+        }
+        let methods: Vec<&str> = ["get", "post", "put", "patch", "delete", "any"]
+            .into_iter()
+            .filter(|method| {
+                registration
+                    .match_indices(&format!("{method}("))
+                    .any(|(index, _)| {
+                        !registration[..index]
+                            .ends_with(|c: char| c == '_' || c.is_ascii_alphanumeric())
+                    })
+            })
+            .collect();
+        assert!(!methods.is_empty(), "no handler found for {path}");
+        for method in methods {
+            if method == "any" {
+                assert!(
+                    documented.iter().any(|(documented, _)| *documented == path),
+                    "{path} is routed but not documented"
+                );
+            } else {
+                routed.insert((path.clone(), method.to_string()));
+            }
+        }
+    }
+    for (path, method) in &routed {
+        assert!(
+            documented.contains(&(path.clone(), method.clone())),
+            "{} {path} is routed but not documented",
+            method.to_uppercase()
+        );
+    }
+
+    // Every documented operation reaches a handler rather than the router's 404 or 405.
+    for (path, method) in &documented {
+        let uri = path
+            .split('/')
+            .map(|segment| match segment.starts_with('{') {
+                true => Uuid::nil().to_string(),
+                false => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        let response = send(&app, &method.to_uppercase(), &uri).await;
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("body should read");
+        assert_ne!(
+            status,
+            StatusCode::METHOD_NOT_ALLOWED,
+            "{method} {path} is documented but not routed"
+        );
+        assert!(
+            status != StatusCode::NOT_FOUND || !body.is_empty(),
+            "{method} {path} is documented but not routed"
+        );
+    }
+}
+
+#[tokio::test]
+async fn client_sdk_decodes_server_payment_challenges() {
+    let (app, state) = test_app();
+    configure_local_x402(&state).await;
+
+    let response = post_json(
+        &app,
+        "/tool/design/run",
+        serde_json::json!({
+            "user_id": Uuid::new_v4(),
+            "input": "test payload"
+        }),
+        None,
+    )
+    .await;
+    assert_eq!(response.status(), StatusCode::PAYMENT_REQUIRED);
+    let header = response.headers()[PAYMENT_REQUIRED_HEADER]
+        .to_str()
+        .expect("header should be ascii")
+        .to_string();
+    let body: payloadexchange_client::types::PaymentRequired =
+        serde_json::from_value(read_json(response).await).expect("client should parse 402 body");
+
+    let challenge = payloadexchange_client::PaymentChallenge::try_from(body)
+        .expect("client should decode requirements");
+    assert_eq!(challenge.service, "design");
+    assert_eq!(
+        challenge.accepts[0].pay_to,
+        "0x1111111111111111111111111111111111111111"
+    );
+    let from_header = payloadexchange_client::decode_payment_requirements(&header)
+        .expect("client should decode PAYMENT-REQUIRED");
+    assert_eq!(from_header, challenge.accepts);
+}
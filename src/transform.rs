@@ -0,0 +1,252 @@
+use axum::body::Bytes;
+use serde_json::{Map, Value};
+
+use crate::error::{ApiError, ApiResult};
+use crate::types::{RequestTransform, ResponseTransform, TransformPolicy};
+
+pub fn validate_transform_policy(policy: &TransformPolicy) -> ApiResult<()> {
+    let request = &policy.request;
+    for mapping in &request.rename {
+        check_pointer("transform.request.rename.from", &mapping.from)?;
+        check_pointer("transform.request.rename.to", &mapping.to)?;
+    }
+    for pointer in request.allow.iter().chain(&request.deny) {
+        check_pointer("transform.request allow/deny entries", pointer)?;
+    }
+    for (pointer, value) in &request.set {
+        check_pointer("transform.request.set keys", pointer)?;
+        check_templates(value)?;
+    }
+    let response = &policy.response;
+    for pointer in response.allow.iter().chain(&response.deny) {
+        check_pointer("transform.response allow/deny entries", pointer)?;
+    }
+    if response.max_bytes == Some(0) {
+        return Err(ApiError::validation(
+            "transform.response.max_bytes must be greater than 0",
+        ));
+    }
+    Ok(())
+}
+
+/// Pointers must name a field; rewriting the whole document is what `set` at a parent is for.
+fn check_pointer(field: &str, pointer: &str) -> ApiResult<()> {
+    if pointer.starts_with('/') && pointer.len() > 1 {
+        Ok(())
+    } else {
+        Err(ApiError::validation(format!(
+            "{field} must be JSON Pointers like /field, got '{pointer}'"
+        )))
+    }
+}
+
+fn check_templates(value: &Value) -> ApiResult<()> {
+    match value {
+        Value::String(text) => placeholders(text).map(|_| ()),
+        Value::Array(items) => items.iter().try_for_each(check_templates),
+        Value::Object(fields) => fields.values().try_for_each(check_templates),
+        _ => Ok(()),
+    }
+}
+
+/// Reshapes a caller's run input before it becomes the upstream request.
+pub fn transform_request(policy: Option<&TransformPolicy>, input: Value) -> ApiResult<Value> {
+    let Some(RequestTransform {
+        rename,
+        allow,
+        deny,
+        set,
+    }) = policy.map(|policy| &policy.request)
+    else {
+        return Ok(input);
+    };
+    let original = input.clone();
+    let mut input = input;
+
+    for mapping in rename {
+        if let Some(value) = remove_pointer(&mut input, &mapping.from) {
+            set_pointer(&mut input, &mapping.to, value)?;
+        }
+    }
+    if !allow.is_empty() {
+        input = keep_pointers(&input, allow)?;
+    }
+    for pointer in deny {
+        remove_pointer(&mut input, pointer);
+    }
+    for (pointer, template) in set {
+        set_pointer(&mut input, pointer, render(template, &original)?)?;
+    }
+    Ok(input)
+}
+
+/// Applies the response allow and deny lists to a JSON body. Anything else passes through.
+pub fn filter_response(policy: Option<&TransformPolicy>, body: Bytes) -> ApiResult<Bytes> {
+    let Some(ResponseTransform { allow, deny, .. }) = policy.map(|policy| &policy.response) else {
+        return Ok(body);
+    };
+    if allow.is_empty() && deny.is_empty() {
+        return Ok(body);
+    }
+    let Ok(mut value) = serde_json::from_slice::<Value>(&body) else {
+        return Ok(body);
+    };
+
+    if !allow.is_empty() {
+        value = keep_pointers(&value, allow)?;
+    }
+    for pointer in deny {
+        remove_pointer(&mut value, pointer);
+    }
+    serde_json::to_vec(&value)
+        .map(Bytes::from)
+        .map_err(|err| ApiError::internal(err.to_string()))
+}
+
+/// Cuts the body to `max_bytes`, backing off to a UTF-8 boundary. Returns whether anything
+/// was dropped.
+pub fn truncate_response(policy: Option<&TransformPolicy>, body: Bytes) -> (Bytes, bool) {
+    let Some(limit) = policy.and_then(|policy| policy.response.max_bytes) else {
+        return (body, false);
+    };
+    let limit = usize::try_from(limit).unwrap_or(usize::MAX);
+    if body.len() <= limit {
+        return (body, false);
+    }
+    let mut end = limit;
+    while end > 0 && std::str::from_utf8(&body[..end]).is_err() && limit - end < 4 {
+        end -= 1;
+    }
+    (body.slice(..end), true)
+}
+
+fn tokens(pointer: &str) -> impl Iterator<Item = String> + '_ {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|token| token.replace("~1", "/").replace("~0", "~"))
+}
+
+fn remove_pointer(root: &mut Value, pointer: &str) -> Option<Value> {
+    let (parent, last) = pointer.rsplit_once('/')?;
+    let last = last.replace("~1", "/").replace("~0", "~");
+    match root.pointer_mut(parent)? {
+        Value::Object(fields) => fields.remove(&last),
+        Value::Array(items) => {
+            let index = last.parse::<usize>().ok()?;
+            (index < items.len()).then(|| items.remove(index))
+        }
+        _ => None,
+    }
+}
+
+/// Writes `value` at `pointer`, creating objects along the way.
+fn set_pointer(root: &mut Value, pointer: &str, value: Value) -> ApiResult<()> {
+    let mut current = root;
+    let mut tokens = tokens(pointer).peekable();
+    while let Some(token) = tokens.next() {
+        if current.is_null() {
+            *current = Value::Object(Map::new());
+        }
+        let is_last = tokens.peek().is_none();
+        current = match current {
+            Value::Object(fields) => fields.entry(token).or_insert(Value::Null),
+            Value::Array(items) => {
+                let index = token
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|index| *index < items.len())
+                    .ok_or_else(|| {
+                        ApiError::validation(format!(
+                            "transform cannot write {pointer}: no array element '{token}'"
+                        ))
+                    })?;
+                &mut items[index]
+            }
+            _ => {
+                return Err(ApiError::validation(format!(
+                    "transform cannot write {pointer}: '{token}' is inside a non-object value"
+                )));
+            }
+        };
+        if is_last {
+            *current = value;
+            return Ok(());
+        }
+    }
+    Ok(())
+}
+
+/// A copy of `source` holding only the listed pointers that exist in it.
+fn keep_pointers(source: &Value, pointers: &[String]) -> ApiResult<Value> {
+    let mut kept = Value::Object(Map::new());
+    for pointer in pointers {
+        if let Some(value) = source.pointer(pointer) {
+            set_pointer(&mut kept, pointer, value.clone())?;
+        }
+    }
+    Ok(kept)
+}
+
+/// `{{/pointer}}` placeholders in `text`, as byte ranges and pointers.
+fn placeholders(text: &str) -> ApiResult<Vec<(usize, usize, &str)>> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = text[offset..].find("{{") {
+        let start = offset + start;
+        let end = text[start..]
+            .find("}}")
+            .map(|end| start + end + 2)
+            .ok_or_else(|| {
+                ApiError::validation(format!(
+                    "unterminated '{{{{' in transform template '{text}'"
+                ))
+            })?;
+        let pointer = text[start + 2..end - 2].trim();
+        if !pointer.is_empty() && !pointer.starts_with('/') {
+            return Err(ApiError::validation(format!(
+                "transform template placeholders must be JSON Pointers, got '{pointer}'"
+            )));
+        }
+        found.push((start, end, pointer));
+        offset = end;
+    }
+    Ok(found)
+}
+
+fn render(template: &Value, input: &Value) -> ApiResult<Value> {
+    match template {
+        Value::String(text) => {
+            let found = placeholders(text)?;
+            if let [(0, end, pointer)] = found.as_slice()
+                && *end == text.len()
+            {
+                return Ok(input.pointer(pointer).cloned().unwrap_or(Value::Null));
+            }
+            let mut rendered = String::with_capacity(text.len());
+            let mut offset = 0;
+            for (start, end, pointer) in found {
+                rendered.push_str(&text[offset..start]);
+                match input.pointer(pointer) {
+                    Some(Value::String(value)) => rendered.push_str(value),
+                    Some(Value::Null) | None => {}
+                    Some(value) => rendered.push_str(&value.to_string()),
+                }
+                offset = end;
+            }
+            rendered.push_str(&text[offset..]);
+            Ok(Value::String(rendered))
+        }
+        Value::Array(items) => items
+            .iter()
+            .map(|item| render(item, input))
+            .collect::<ApiResult<Vec<_>>>()
+            .map(Value::Array),
+        Value::Object(fields) => fields
+            .iter()
+            .map(|(key, value)| Ok((key.clone(), render(value, input)?)))
+            .collect::<ApiResult<Map<_, _>>>()
+            .map(Value::Object),
+        other => Ok(other.clone()),
+    }
+}
@@ -1,32 +1,111 @@
 use chrono::{DateTime, Utc};
-use prometheus::{IntCounter, IntCounterVec, Opts, Registry};
+use prometheus::{IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry};
 use reqwest::Client;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sqlx::{PgPool, postgres::PgPoolOptions};
-use std::{collections::HashMap, sync::Arc, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+    time::Duration,
+};
 use tokio::sync::RwLock;
 use uuid::Uuid;
 
+use crate::cache::ResponseCache;
+use crate::health::HealthRegistry;
+use crate::network::{UpstreamPolicy, build_upstream_client};
+use crate::ratelimit::RateLimiter;
+use crate::secrets::SecretKeyring;
+
 pub const PAYMENT_SIGNATURE_HEADER: &str = "payment-signature";
 pub const PAYMENT_REQUIRED_HEADER: &str = "payment-required";
 pub const PAYMENT_RESPONSE_HEADER: &str = "payment-response";
 pub const X402_VERSION_HEADER: &str = "x402-version";
+pub const TASK_SIGNATURE_HEADER: &str = "x-task-signature";
+pub const SPONSORED_CALLER_HEADER: &str = "x-sponsored-caller";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "x-webhook-signature";
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+pub const WEBHOOK_EVENT_HEADER: &str = "x-webhook-event";
+pub const WEBHOOK_DELIVERY_HEADER: &str = "x-webhook-delivery";
 pub const DEFAULT_PRICE_CENTS: u64 = 5;
+/// Tools with their own price, advertised to MCP clients. `/tool/{service}/run` accepts any
+/// other name at the default price.
+pub const BUILTIN_SERVICES: [&str; 4] = ["scraping", "design", "storage", "data-tooling"];
 pub const SPONSORED_API_CREATE_SERVICE: &str = "sponsored-api-create";
 pub const SPONSORED_API_SERVICE_PREFIX: &str = "sponsored-api";
 pub const DEFAULT_SPONSORED_API_CREATE_PRICE_CENTS: u64 = 25;
 pub const DEFAULT_SPONSORED_API_TIMEOUT_SECS: u64 = 12;
+pub const DEFAULT_SPONSORED_API_STREAM_TIMEOUT_SECS: u64 = 300;
+pub const DEFAULT_SPONSORED_API_JOB_TIMEOUT_SECS: u64 = 900;
+pub const DEFAULT_JOB_RETENTION_SECS: u64 = 24 * 60 * 60;
+pub const DEFAULT_JOB_POLL_INTERVAL_SECS: u64 = 2;
+pub const DEFAULT_SPONSORED_API_MAX_RESPONSE_BYTES: u64 = 10 * 1024 * 1024;
+pub const DEFAULT_RESPONSE_CACHE_MAX_BYTES: u64 = 64 * 1024 * 1024;
+pub const DEFAULT_CACHE_MAX_ENTRY_BYTES: u64 = 256 * 1024;
+pub const DEFAULT_CIRCUIT_BREAKER_WINDOW_SECS: u64 = 60;
+pub const DEFAULT_CIRCUIT_BREAKER_MIN_CALLS: u64 = 10;
+pub const DEFAULT_CIRCUIT_BREAKER_ERROR_PERCENT: u64 = 50;
+pub const DEFAULT_CIRCUIT_BREAKER_OPEN_SECS: u64 = 30;
+pub const DEFAULT_HEALTH_CHECK_INTERVAL_SECS: u64 = 60;
+pub const DEFAULT_HEALTH_CHECK_TIMEOUT_SECS: u64 = 5;
+/// Probes are abandoned after this long, whatever the policy says.
+pub const MAX_HEALTH_CHECK_TIMEOUT_SECS: u64 = 30;
+pub const CACHE_STATUS_HEADER: &str = "x-cache";
 pub const DEFAULT_X402_FACILITATOR_URL: &str = "https://x402.org/facilitator";
 pub const DEFAULT_X402_VERIFY_PATH: &str = "/verify";
 pub const DEFAULT_X402_SETTLE_PATH: &str = "/settle";
 pub const DEFAULT_X402_NETWORK: &str = "base-sepolia";
 pub const DEFAULT_PUBLIC_BASE_URL: &str = "http://localhost:3000";
+/// Pinned so the bytes behind the URL cannot change under a fixed integrity hash.
+pub const DEFAULT_API_DOCS_SCRIPT_URL: &str =
+    "https://cdn.jsdelivr.net/npm/@scalar/api-reference@1.25.0/dist/browser/standalone.js";
+/// Column list matching `SponsoredApiRow`, shared by every query that loads sponsored APIs.
+pub const SPONSORED_API_COLUMNS: &str = "id, name, sponsor, description, upstream_url, \
+    upstream_method, upstream_headers, upstream_secret_headers, price_cents, budget_total_cents, \
+    budget_remaining_cents, active, paused, service_key, forward_headers, body_encoding, \
+    cache_policy, rate_limit_policy, health_check_policy, budget_pool_id, pool_limit_cents, \
+    pool_spent_cents, input_schema, output_schema, validate_output, transform_policy, \
+    eligibility_policy, created_at";
+pub const BUDGET_POOL_COLUMNS: &str =
+    "id, sponsor, name, budget_total_cents, budget_remaining_cents, created_at";
+pub const CAMPAIGN_COLUMNS: &str = "id, name, sponsor, target_roles, target_tools, required_task, \
+    subsidy_per_call_cents, budget_total_cents, budget_remaining_cents, query_urls, \
+    task_verification, active, budget_pool_id, pool_limit_cents, pool_spent_cents, created_at";
+pub const SPONSORED_API_CALL_COLUMNS: &str = "id, sponsored_api_id, payment_mode, amount_cents, \
+    tx_hash, caller, created_at, upstream_status, response_bytes, outcome, duration_ms, \
+    error_class, finalized_at";
+pub const DEFAULT_CALL_LOG_PAGE_SIZE: u32 = 100;
+pub const MAX_CALL_LOG_PAGE_SIZE: u32 = 1000;
+pub const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+pub const DEFAULT_WEBHOOK_MAX_ATTEMPTS: u64 = 8;
+pub const DEFAULT_WEBHOOK_RETRY_BASE_SECS: u64 = 30;
+pub const DEFAULT_WEBHOOK_POLL_INTERVAL_SECS: u64 = 5;
+pub const DEFAULT_WEBHOOK_BUDGET_LOW_PERCENT: u8 = 20;
 
 #[derive(Clone)]
 pub struct AppConfig {
     pub sponsored_api_create_price_cents: u64,
+    /// Time allowed for an upstream to start responding, and for buffered bodies to arrive.
     pub sponsored_api_timeout_secs: u64,
+    /// Upper bound on how long a streamed upstream body may stay open.
+    pub sponsored_api_stream_timeout_secs: u64,
+    /// Replaces both timeouts above for calls run as background jobs.
+    pub sponsored_api_job_timeout_secs: u64,
+    /// How long finished job results stay readable.
+    pub job_retention_secs: u64,
+    pub job_poll_interval_secs: u64,
+    pub sponsored_api_max_response_bytes: u64,
+    /// Total size of the in-process response cache shared by all sponsored APIs.
+    pub response_cache_max_bytes: u64,
+    /// Rolling window over which upstream errors are counted for the circuit breaker.
+    pub circuit_breaker_window_secs: u64,
+    /// Calls needed in the window before the error rate can open the circuit.
+    pub circuit_breaker_min_calls: u64,
+    pub circuit_breaker_error_percent: u64,
+    /// How long an open circuit rejects calls before letting traffic through again.
+    pub circuit_breaker_open_secs: u64,
     pub x402_facilitator_url: String,
     pub x402_verify_path: String,
     pub x402_settle_path: String,
@@ -35,11 +114,26 @@ pub struct AppConfig {
     pub x402_pay_to: Option<String>,
     pub x402_asset: Option<String>,
     pub public_base_url: String,
+    pub webhook_max_attempts: u64,
+    pub webhook_retry_base_secs: u64,
+    pub webhook_poll_interval_secs: u64,
+    pub upstream_secret_keys: SecretKeyring,
+    pub upstream_policy: UpstreamPolicy,
+    /// Renderer for `/docs`, loaded from a CDN. It only runs with a subresource integrity
+    /// hash; without one `/docs` links to the raw spec instead.
+    pub api_docs_script_url: String,
+    pub api_docs_script_integrity: Option<String>,
 }
 
 impl AppConfig {
-    pub fn from_env() -> Self {
-        Self {
+    /// Reads the configuration. Unset or unparsable numbers fall back to defaults; settings
+    /// that cannot safely default, like secret keys, are reported as errors.
+    pub fn from_env() -> Result<Self, String> {
+        let upstream_secret_keys =
+            SecretKeyring::parse(&std::env::var("UPSTREAM_SECRET_KEYS").unwrap_or_default())
+                .map_err(|err| format!("UPSTREAM_SECRET_KEYS is invalid: {err}"))?;
+
+        Ok(Self {
             sponsored_api_create_price_cents: read_env_u64(
                 "SPONSORED_API_CREATE_PRICE_CENTS",
                 DEFAULT_SPONSORED_API_CREATE_PRICE_CENTS,
@@ -48,6 +142,43 @@ impl AppConfig {
                 "SPONSORED_API_TIMEOUT_SECS",
                 DEFAULT_SPONSORED_API_TIMEOUT_SECS,
             ),
+            sponsored_api_stream_timeout_secs: read_env_u64(
+                "SPONSORED_API_STREAM_TIMEOUT_SECS",
+                DEFAULT_SPONSORED_API_STREAM_TIMEOUT_SECS,
+            ),
+            sponsored_api_job_timeout_secs: read_env_u64(
+                "SPONSORED_API_JOB_TIMEOUT_SECS",
+                DEFAULT_SPONSORED_API_JOB_TIMEOUT_SECS,
+            ),
+            job_retention_secs: read_env_u64("JOB_RETENTION_SECS", DEFAULT_JOB_RETENTION_SECS),
+            job_poll_interval_secs: read_env_u64(
+                "JOB_POLL_INTERVAL_SECS",
+                DEFAULT_JOB_POLL_INTERVAL_SECS,
+            ),
+            sponsored_api_max_response_bytes: read_env_u64(
+                "SPONSORED_API_MAX_RESPONSE_BYTES",
+                DEFAULT_SPONSORED_API_MAX_RESPONSE_BYTES,
+            ),
+            response_cache_max_bytes: read_env_u64(
+                "RESPONSE_CACHE_MAX_BYTES",
+                DEFAULT_RESPONSE_CACHE_MAX_BYTES,
+            ),
+            circuit_breaker_window_secs: read_env_u64(
+                "CIRCUIT_BREAKER_WINDOW_SECS",
+                DEFAULT_CIRCUIT_BREAKER_WINDOW_SECS,
+            ),
+            circuit_breaker_min_calls: read_env_u64(
+                "CIRCUIT_BREAKER_MIN_CALLS",
+                DEFAULT_CIRCUIT_BREAKER_MIN_CALLS,
+            ),
+            circuit_breaker_error_percent: read_env_u64(
+                "CIRCUIT_BREAKER_ERROR_PERCENT",
+                DEFAULT_CIRCUIT_BREAKER_ERROR_PERCENT,
+            ),
+            circuit_breaker_open_secs: read_env_u64(
+                "CIRCUIT_BREAKER_OPEN_SECS",
+                DEFAULT_CIRCUIT_BREAKER_OPEN_SECS,
+            ),
             x402_facilitator_url: std::env::var("X402_FACILITATOR_URL")
                 .unwrap_or_else(|_| DEFAULT_X402_FACILITATOR_URL.to_string()),
             x402_verify_path: std::env::var("X402_VERIFY_PATH")
@@ -61,7 +192,26 @@ impl AppConfig {
             x402_asset: std::env::var("X402_ASSET").ok(),
             public_base_url: std::env::var("PUBLIC_BASE_URL")
                 .unwrap_or_else(|_| DEFAULT_PUBLIC_BASE_URL.to_string()),
-        }
+            webhook_max_attempts: read_env_u64(
+                "WEBHOOK_MAX_ATTEMPTS",
+                DEFAULT_WEBHOOK_MAX_ATTEMPTS,
+            ),
+            webhook_retry_base_secs: read_env_u64(
+                "WEBHOOK_RETRY_BASE_SECS",
+                DEFAULT_WEBHOOK_RETRY_BASE_SECS,
+            ),
+            webhook_poll_interval_secs: read_env_u64(
+                "WEBHOOK_POLL_INTERVAL_SECS",
+                DEFAULT_WEBHOOK_POLL_INTERVAL_SECS,
+            ),
+            upstream_secret_keys,
+            upstream_policy: UpstreamPolicy::from_env(),
+            api_docs_script_url: std::env::var("API_DOCS_SCRIPT_URL")
+                .unwrap_or_else(|_| DEFAULT_API_DOCS_SCRIPT_URL.to_string()),
+            api_docs_script_integrity: std::env::var("API_DOCS_SCRIPT_INTEGRITY")
+                .ok()
+                .filter(|value| !value.trim().is_empty()),
+        })
     }
 }
 
@@ -74,6 +224,11 @@ pub struct AppState {
     pub metrics: Metrics,
     pub db: Option<PgPool>,
     pub http: Client,
+    /// Client for sponsor-registered upstreams, bound to `config.upstream_policy`.
+    pub upstream_http: Client,
+    pub response_cache: ResponseCache,
+    pub rate_limiter: RateLimiter,
+    pub health: HealthRegistry,
     pub config: AppConfig,
 }
 
@@ -84,6 +239,12 @@ pub struct Metrics {
     pub payment_events_total: IntCounterVec,
     pub creator_events_total: IntCounterVec,
     pub sponsor_spend_cents_total: IntCounter,
+    pub sponsored_api_cache_total: IntCounterVec,
+    pub rate_limited_total: IntCounterVec,
+    /// 1 while the last health probe succeeded, 0 after a failed probe.
+    pub sponsored_api_up: IntGaugeVec,
+    pub sponsored_api_circuit_open: IntGaugeVec,
+    pub sponsored_api_circuit_rejected_total: IntCounterVec,
 }
 
 impl Metrics {
@@ -114,6 +275,48 @@ impl Metrics {
         )
         .expect("sponsor counter should build");
 
+        let sponsored_api_cache_total = IntCounterVec::new(
+            Opts::new(
+                "sponsored_api_cache_total",
+                "Sponsored API response cache lookups",
+            ),
+            &["sponsored_api_id", "result"],
+        )
+        .expect("cache counter vec should build");
+
+        let rate_limited_total = IntCounterVec::new(
+            Opts::new("rate_limited_total", "Requests rejected by a rate limit"),
+            &["scope"],
+        )
+        .expect("rate limit counter vec should build");
+
+        let sponsored_api_up = IntGaugeVec::new(
+            Opts::new(
+                "sponsored_api_up",
+                "Whether the last sponsored API health probe succeeded",
+            ),
+            &["sponsored_api_id"],
+        )
+        .expect("health gauge vec should build");
+
+        let sponsored_api_circuit_open = IntGaugeVec::new(
+            Opts::new(
+                "sponsored_api_circuit_open",
+                "Whether a sponsored API circuit breaker is open",
+            ),
+            &["sponsored_api_id"],
+        )
+        .expect("circuit gauge vec should build");
+
+        let sponsored_api_circuit_rejected_total = IntCounterVec::new(
+            Opts::new(
+                "sponsored_api_circuit_rejected_total",
+                "Sponsored API calls rejected by an open circuit",
+            ),
+            &["sponsored_api_id"],
+        )
+        .expect("circuit counter vec should build");
+
         registry
             .register(Box::new(http_requests_total.clone()))
             .expect("register http counter vec");
@@ -126,6 +329,21 @@ impl Metrics {
         registry
             .register(Box::new(sponsor_spend_cents_total.clone()))
             .expect("register sponsor spend counter");
+        registry
+            .register(Box::new(sponsored_api_cache_total.clone()))
+            .expect("register cache counter vec");
+        registry
+            .register(Box::new(rate_limited_total.clone()))
+            .expect("register rate limit counter vec");
+        registry
+            .register(Box::new(sponsored_api_up.clone()))
+            .expect("register health gauge vec");
+        registry
+            .register(Box::new(sponsored_api_circuit_open.clone()))
+            .expect("register circuit gauge vec");
+        registry
+            .register(Box::new(sponsored_api_circuit_rejected_total.clone()))
+            .expect("register circuit counter vec");
 
         Self {
             registry,
@@ -133,31 +351,47 @@ impl Metrics {
             payment_events_total,
             creator_events_total,
             sponsor_spend_cents_total,
+            sponsored_api_cache_total,
+            rate_limited_total,
+            sponsored_api_up,
+            sponsored_api_circuit_open,
+            sponsored_api_circuit_rejected_total,
         }
     }
 }
 
 impl AppState {
-    pub fn new() -> Self {
+    pub fn new() -> Result<Self, String> {
         let http = Client::builder()
             .timeout(Duration::from_secs(20))
             .build()
             .expect("http client should build");
 
-        let config = AppConfig::from_env();
+        let config = AppConfig::from_env()?;
+        let upstream_http = build_upstream_client(&config.upstream_policy);
+        let response_cache = ResponseCache::new(
+            usize::try_from(config.response_cache_max_bytes).unwrap_or(usize::MAX),
+        );
         let db = std::env::var("DATABASE_URL").ok().and_then(|url| {
             PgPoolOptions::new()
                 .max_connections(10)
                 .connect_lazy(&url)
                 .ok()
         });
+        let rate_limiter = RateLimiter::from_env(db.as_ref())?;
+        let metrics = Metrics::new();
+        let health = HealthRegistry::new(&config, metrics.clone());
 
-        Self {
-            metrics: Metrics::new(),
+        Ok(Self {
+            metrics,
             db,
             http,
+            upstream_http,
+            response_cache,
+            rate_limiter,
+            health,
             config,
-        }
+        })
     }
 
     pub fn service_price(&self, service: &str) -> u64 {
@@ -171,7 +405,7 @@ impl AppState {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, JsonSchema)]
 pub struct UserProfile {
     pub id: Uuid,
     pub email: String,
@@ -183,7 +417,7 @@ pub struct UserProfile {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct CreateUserRequest {
     pub email: String,
     pub region: String,
@@ -193,7 +427,26 @@ pub struct CreateUserRequest {
     pub attributes: HashMap<String, String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UpdateUserProfileRequest {
+    #[serde(default)]
+    pub roles: Option<Vec<String>>,
+    #[serde(default)]
+    pub tools_used: Option<Vec<String>>,
+    #[serde(default)]
+    pub attributes: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct UserDataExport {
+    pub profile: UserProfile,
+    pub task_completions: Vec<TaskCompletion>,
+    pub payments: Vec<PaymentRecord>,
+    pub sponsored_api_calls: Vec<SponsoredApiCall>,
+    pub exported_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Campaign {
     pub id: Uuid,
     pub name: String,
@@ -206,11 +459,53 @@ pub struct Campaign {
     pub budget_remaining_cents: u64,
     #[serde(default)]
     pub query_urls: Vec<String>,
+    pub task_verification: TaskVerification,
     pub active: bool,
+    /// When set, subsidies draw on this pool instead of the campaign's own budget.
+    #[serde(default)]
+    pub budget_pool_id: Option<Uuid>,
+    /// Most this campaign may take from its pool, across all calls.
+    #[serde(default)]
+    pub pool_limit_cents: Option<u64>,
+    #[serde(default)]
+    pub pool_spent_cents: u64,
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+/// How a campaign's required task is proven before it unlocks sponsored usage.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum TaskVerification {
+    SponsorCallback,
+    /// The sponsor redirects back to `redirect_uri` with the `state` and a `signature` over
+    /// the state and outcome, keyed with the task secret.
+    OauthRedirect {
+        authorize_url: String,
+    },
+    FormSubmission {
+        #[serde(default)]
+        required_fields: Vec<String>,
+    },
+    /// A receipt from the profile's `wallet_address` leaves the completion pending: the
+    /// address is not proven to belong to the user until the sponsor confirms it through
+    /// the task callback.
+    OnchainAction {
+        rpc_url: String,
+        #[serde(default)]
+        contract_address: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum TaskCompletionStatus {
+    Pending,
+    Verified,
+    Rejected,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct CreateCampaignRequest {
     pub name: String,
     pub sponsor: String,
@@ -220,9 +515,16 @@ pub struct CreateCampaignRequest {
     pub target_tools: Vec<String>,
     pub required_task: String,
     pub subsidy_per_call_cents: u64,
+    /// Must be 0 when the campaign draws on a budget pool.
+    #[serde(default)]
     pub budget_cents: u64,
     #[serde(default)]
+    pub budget_pool_id: Option<Uuid>,
+    #[serde(default)]
+    pub pool_limit_cents: Option<u64>,
+    #[serde(default)]
     pub query_urls: Vec<String>,
+    pub task_verification: TaskVerification,
 }
 
 #[derive(Debug, Clone, sqlx::FromRow)]
@@ -237,7 +539,11 @@ pub struct CampaignRow {
     pub budget_total_cents: i64,
     pub budget_remaining_cents: i64,
     pub query_urls: Vec<String>,
+    pub task_verification: sqlx::types::Json<TaskVerification>,
     pub active: bool,
+    pub budget_pool_id: Option<Uuid>,
+    pub pool_limit_cents: Option<i64>,
+    pub pool_spent_cents: i64,
     pub created_at: DateTime<Utc>,
 }
 
@@ -259,55 +565,202 @@ impl TryFrom<CampaignRow> for Campaign {
             budget_remaining_cents: u64::try_from(value.budget_remaining_cents)
                 .map_err(|_| "budget_remaining_cents must be non-negative".to_string())?,
             query_urls: value.query_urls,
+            task_verification: value.task_verification.0,
             active: value.active,
+            budget_pool_id: value.budget_pool_id,
+            pool_limit_cents: value
+                .pool_limit_cents
+                .map(u64::try_from)
+                .transpose()
+                .map_err(|_| "pool_limit_cents must be non-negative".to_string())?,
+            pool_spent_cents: u64::try_from(value.pool_spent_cents)
+                .map_err(|_| "pool_spent_cents must be non-negative".to_string())?,
             created_at: value.created_at,
         })
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct CreateCampaignResponse {
     pub campaign: Campaign,
     pub campaign_url: String,
     pub dashboard_url: String,
+    /// Shared secret for signing task callbacks; only returned at creation time.
+    pub task_secret: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct CampaignDiscoveryItem {
     pub campaign_id: Uuid,
     pub name: String,
     pub sponsor: String,
     pub active: bool,
     pub query_urls: Vec<String>,
+    /// Deprecated: now the same listing as `resources_url`. Use `service_run_urls`.
     pub service_run_url: String,
+    /// `/proxy/{service}/run` for every service a campaign can sponsor.
+    pub service_run_urls: Vec<String>,
+    /// x402 `outputSchema` of the service run endpoints.
+    pub service_schema: Value,
     pub sponsored_api_discovery_url: String,
+    /// Proxy URLs with their payment requirements and sponsorship status.
+    pub resources_url: String,
+}
+
+pub const DEFAULT_DISCOVERY_PAGE_SIZE: usize = 100;
+pub const MAX_DISCOVERY_PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ResourceKind {
+    /// `/tool/{service}/run`, always paid by the caller.
+    Tool,
+    /// `/proxy/{service}/run`, sponsored by campaigns for users who completed their task.
+    Proxy,
+    SponsoredApi,
+}
+
+/// One paid resource, shaped like an x402 Bazaar discovery item.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryResource {
+    pub resource: String,
+    #[serde(rename = "type")]
+    pub resource_type: String,
+    pub x402_version: u8,
+    pub accepts: Vec<X402PaymentRequirement>,
+    pub last_updated: DateTime<Utc>,
+    pub metadata: DiscoveryMetadata,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct DiscoveryMetadata {
+    pub kind: ResourceKind,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub method: String,
+    pub price_cents: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sponsored_api_id: Option<Uuid>,
+    pub sponsorship: Sponsorship,
+}
+
+/// Whether someone else may pay for a call right now.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Sponsorship {
+    pub available: bool,
+    pub sponsors: Vec<String>,
+    /// Sponsorship also depends on the caller: eligibility rules or a campaign task.
+    pub conditional: bool,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct DiscoveryQuery {
+    #[serde(default, rename = "type")]
+    pub kind: Option<ResourceKind>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryResourcePage {
+    pub x402_version: u8,
+    pub items: Vec<DiscoveryResource>,
+    pub pagination: DiscoveryPagination,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DiscoveryPagination {
+    pub limit: usize,
+    pub offset: usize,
+    pub total: usize,
+}
+
+/// The `/.well-known/x402` manifest: every paid resource URL, with the full listing linked
+/// from `instructions`.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct X402Manifest {
+    pub version: u8,
+    pub resources: Vec<String>,
+    pub instructions: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow, JsonSchema)]
 pub struct TaskCompletion {
     pub id: Uuid,
     pub campaign_id: Uuid,
     pub user_id: Uuid,
     pub task_name: String,
     pub details: Option<String>,
+    pub status: TaskCompletionStatus,
+    pub evidence: Option<Value>,
+    pub rejection_reason: Option<String>,
+    pub verified_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct TaskCompletionRequest {
     pub campaign_id: Uuid,
     pub user_id: Uuid,
     pub task_name: String,
     pub details: Option<String>,
+    #[serde(default)]
+    pub evidence: Option<Value>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TaskCompletionResponse {
+    #[serde(flatten)]
+    pub completion: TaskCompletion,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TaskCallbackRequest {
+    pub status: TaskCompletionStatus,
+    #[serde(default)]
+    pub reason: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TaskOauthCallbackQuery {
+    pub state: String,
+    #[serde(default)]
+    pub error: Option<String>,
+    /// Hex HMAC-SHA256 of the state and outcome, keyed with the campaign's task secret.
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ServiceRunRequest {
     pub user_id: Uuid,
     pub input: String,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ServiceBatchRunRequest {
+    pub user_id: Uuid,
+    pub inputs: Vec<String>,
+}
+
+/// One output per input, in input order, paid for with a single payment.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ServiceBatchRunResponse {
+    pub service: String,
+    pub outputs: Vec<String>,
+    pub payment_mode: String,
+    pub sponsored_by: Option<String>,
+    pub tx_hash: Option<String>,
+    pub amount_cents: u64,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct ServiceRunResponse {
     pub service: String,
     pub output: String,
@@ -316,31 +769,88 @@ pub struct ServiceRunResponse {
     pub tx_hash: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, JsonSchema)]
 pub struct PaymentRequired {
     pub service: String,
     pub amount_cents: u64,
     pub accepted_header: String,
+    /// Base64-encoded x402 requirements, the same value as the `PAYMENT-REQUIRED` header.
     pub payment_required: String,
     pub message: String,
     pub next_step: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum PaymentSource {
     User,
     Sponsor,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum PaymentStatus {
     Settled,
     Failed,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct PaymentRecord {
+    pub tx_hash: String,
+    pub campaign_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub service: String,
+    pub amount_cents: u64,
+    pub payer: String,
+    pub source: PaymentSource,
+    pub status: PaymentStatus,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct PaymentRow {
+    pub tx_hash: String,
+    pub campaign_id: Option<Uuid>,
+    pub user_id: Option<Uuid>,
+    pub service: String,
+    pub amount_cents: i64,
+    pub payer: String,
+    pub source: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<PaymentRow> for PaymentRecord {
+    type Error = String;
+
+    fn try_from(value: PaymentRow) -> Result<Self, Self::Error> {
+        let source = match value.source.as_str() {
+            "user" => PaymentSource::User,
+            "sponsor" => PaymentSource::Sponsor,
+            other => return Err(format!("unknown payment source: {other}")),
+        };
+        let status = match value.status.as_str() {
+            "settled" => PaymentStatus::Settled,
+            "failed" => PaymentStatus::Failed,
+            other => return Err(format!("unknown payment status: {other}")),
+        };
+
+        Ok(Self {
+            tx_hash: value.tx_hash,
+            campaign_id: value.campaign_id,
+            user_id: value.user_id,
+            service: value.service,
+            amount_cents: u64::try_from(value.amount_cents)
+                .map_err(|_| "amount_cents must be non-negative".to_string())?,
+            payer: value.payer,
+            source,
+            status,
+            created_at: value.created_at,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct X402PaymentRequirement {
     pub scheme: String,
@@ -358,7 +868,7 @@ pub struct X402PaymentRequirement {
     pub extra: HashMap<String, Value>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct X402VerifyResponse {
     pub is_valid: bool,
@@ -368,7 +878,7 @@ pub struct X402VerifyResponse {
     pub payer: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct X402SettleResponse {
     pub success: bool,
@@ -380,7 +890,7 @@ pub struct X402SettleResponse {
     pub error_reason: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct X402ScanSettlementRequest {
     pub tx_hash: String,
     pub service: String,
@@ -389,9 +899,12 @@ pub struct X402ScanSettlementRequest {
     pub source: PaymentSource,
     pub status: PaymentStatus,
     pub campaign_id: Option<Uuid>,
+    /// Profile that paid, so the payment is exported with it and anonymized on delete.
+    #[serde(default)]
+    pub user_id: Option<Uuid>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CreatorEvent {
     pub id: Uuid,
     pub skill_name: String,
@@ -402,7 +915,7 @@ pub struct CreatorEvent {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct CreatorMetricEventRequest {
     pub skill_name: String,
     pub platform: String,
@@ -411,7 +924,7 @@ pub struct CreatorMetricEventRequest {
     pub success: bool,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct CreatorMetricSummary {
     pub total_events: usize,
     pub success_events: usize,
@@ -419,7 +932,7 @@ pub struct CreatorMetricSummary {
     pub per_skill: Vec<SkillMetrics>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct SkillMetrics {
     pub skill_name: String,
     pub total_events: usize,
@@ -428,7 +941,7 @@ pub struct SkillMetrics {
     pub last_seen_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct SponsorDashboard {
     pub campaign: Campaign,
     pub tasks_completed: usize,
@@ -437,12 +950,295 @@ pub struct SponsorDashboard {
     pub remaining_budget_cents: u64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DashboardBucket {
+    Hour,
+    #[default]
+    Day,
+}
+
+impl DashboardBucket {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hour => "hour",
+            Self::Day => "day",
+        }
+    }
+
+    pub fn duration(&self) -> chrono::Duration {
+        match self {
+            Self::Hour => chrono::Duration::hours(1),
+            Self::Day => chrono::Duration::days(1),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SponsorTimeseriesQuery {
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub bucket: DashboardBucket,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SponsorTimeseriesPoint {
+    pub bucket_start: DateTime<Utc>,
+    pub spend_cents: u64,
+    pub sponsored_calls: u64,
+    pub unique_users: u64,
+    pub task_completions: u64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SponsorBreakdownRow {
+    pub key: String,
+    pub spend_cents: u64,
+    pub sponsored_calls: u64,
+    pub unique_users: u64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SponsorTimeseries {
+    pub campaign_id: Uuid,
+    pub bucket: DashboardBucket,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub series: Vec<SponsorTimeseriesPoint>,
+    pub by_service: Vec<SponsorBreakdownRow>,
+    pub by_region: Vec<SponsorBreakdownRow>,
+    pub spend_cents: u64,
+    pub task_completed_users: u64,
+    pub converted_users: u64,
+    /// Share of users with a verified task in range whose first sponsored call came after it.
+    pub conversion_rate: f64,
+    pub cost_per_acquired_user_cents: Option<f64>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct MessageResponse {
     pub message: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// How the remaining `input` is sent to upstreams that take a request body.
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, sqlx::Type, JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum UpstreamBodyEncoding {
+    #[default]
+    Json,
+    Form,
+    /// `input` (or `input.body` when path parameters are used) is sent verbatim as a string.
+    Raw,
+}
+
+/// Opt-in caching of identical GET calls.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ResponseCachePolicy {
+    pub ttl_secs: u64,
+    #[serde(default = "default_cache_max_entry_bytes")]
+    pub max_entry_bytes: u64,
+    /// Charged instead of `price_cents` when the response comes from the cache. Free hits
+    /// are still subject to the API's eligibility policy.
+    #[serde(default)]
+    pub hit_price_cents: u64,
+}
+
+fn default_cache_max_entry_bytes() -> u64 {
+    DEFAULT_CACHE_MAX_ENTRY_BYTES
+}
+
+/// Declarative reshaping of run input on the way to the upstream and of its reply on the way
+/// back. Paths are JSON Pointers (RFC 6901).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct TransformPolicy {
+    #[serde(default)]
+    pub request: RequestTransform,
+    #[serde(default)]
+    pub response: ResponseTransform,
+}
+
+/// Applied in field order: rename, allow, deny, then set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct RequestTransform {
+    #[serde(default)]
+    pub rename: Vec<PointerMapping>,
+    /// When non-empty, only these pointers are kept.
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// Values written last, keyed by pointer. Strings may embed `{{/pointer}}` to pull from
+    /// the caller's original input; a string that is only a placeholder keeps the value's type.
+    #[serde(default)]
+    pub set: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct PointerMapping {
+    pub from: String,
+    pub to: String,
+}
+
+/// Allow and deny lists only touch JSON bodies; `max_bytes` cuts any body.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct ResponseTransform {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+}
+
+/// Who a sponsored API subsidizes. Every rule that is set must hold; callers that fail any
+/// of them can still pay for the call themselves with x402.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct EligibilityPolicy {
+    /// Callers must name a registered profile, by id or email.
+    #[serde(default)]
+    pub require_profile: bool,
+    /// Same matching as campaign targeting; setting either implies `require_profile`.
+    #[serde(default)]
+    pub target_roles: Vec<String>,
+    #[serde(default)]
+    pub target_tools: Vec<String>,
+    /// The caller's profile must have a verified completion of this campaign's task.
+    #[serde(default)]
+    pub required_campaign_id: Option<Uuid>,
+    /// Profile ids that may be subsidized. Empty means anyone. Callers are not
+    /// authenticated, so this steers sponsor spend but is not access control: anyone who
+    /// learns a listed id can call as that profile.
+    #[serde(default)]
+    pub allowed_callers: Vec<String>,
+}
+
+impl EligibilityPolicy {
+    pub fn needs_profile(&self) -> bool {
+        self.require_profile
+            || !self.target_roles.is_empty()
+            || !self.target_tools.is_empty()
+            || self.required_campaign_id.is_some()
+    }
+}
+
+/// Background probe of a sponsored API's upstream.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct HealthCheckPolicy {
+    /// Absolute path on the upstream host, e.g. `/healthz`.
+    pub path: String,
+    #[serde(default = "default_health_check_expected_status")]
+    pub expected_status: u16,
+    #[serde(default = "default_health_check_interval_secs")]
+    pub interval_secs: u64,
+    #[serde(default = "default_health_check_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+fn default_health_check_expected_status() -> u16 {
+    200
+}
+
+fn default_health_check_interval_secs() -> u64 {
+    DEFAULT_HEALTH_CHECK_INTERVAL_SECS
+}
+
+fn default_health_check_timeout_secs() -> u64 {
+    DEFAULT_HEALTH_CHECK_TIMEOUT_SECS
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthState {
+    /// No probe has run and no calls have been made yet.
+    Unknown,
+    Healthy,
+    /// The circuit is half open: traffic is flowing again but has not proven itself.
+    Degraded,
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct HealthProbeResult {
+    pub ok: bool,
+    pub status: Option<u16>,
+    pub error: Option<String>,
+    pub latency_ms: u64,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Health of a sponsored API as seen by this instance.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct UpstreamHealth {
+    pub status: HealthState,
+    pub circuit: CircuitState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub circuit_open_until: Option<DateTime<Utc>>,
+    /// Upstream calls and failures in the circuit breaker window.
+    pub recent_calls: u64,
+    pub recent_failures: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_probe: Option<HealthProbeResult>,
+}
+
+/// What a rate limit counts requests against.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RateLimitKey {
+    /// The client IP. Unverified claims never move a request into a fresh bucket.
+    #[default]
+    Auto,
+    /// The `Authorization` credential, falling back to the IP. Advisory: the credential is
+    /// not verified here, so every IP is also held to ten times the policy across all of
+    /// its credentials.
+    Identity,
+    Ip,
+    /// `caller` in the JSON body, or the caller header on proxy routes, within the
+    /// credential's bucket. Requests without a credential are counted by IP.
+    Caller,
+    /// `user_id` in the JSON body, within the credential's bucket, like `Caller`.
+    UserId,
+}
+
+/// A token bucket: `capacity` calls in a burst, refilled at `refill_per_sec`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+pub struct RateLimitPolicy {
+    pub capacity: u32,
+    pub refill_per_sec: f64,
+    #[serde(default)]
+    pub key: RateLimitKey,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheStatus {
+    Hit,
+    Miss,
+}
+
+impl CacheStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Hit => "hit",
+            Self::Miss => "miss",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SponsoredApi {
     pub id: Uuid,
     pub name: String,
@@ -450,13 +1246,57 @@ pub struct SponsoredApi {
     pub description: Option<String>,
     pub upstream_url: String,
     pub upstream_method: String,
+    /// Public headers only; secret header values are never serialized.
     #[serde(default)]
     pub upstream_headers: HashMap<String, String>,
+    #[serde(default)]
+    pub secret_header_names: Vec<String>,
+    #[serde(skip)]
+    pub sealed_headers: HashMap<String, String>,
     pub price_cents: u64,
     pub budget_total_cents: u64,
     pub budget_remaining_cents: u64,
     pub active: bool,
+    #[serde(default)]
+    pub paused: bool,
     pub service_key: String,
+    /// Caller request headers passed through to the upstream.
+    #[serde(default)]
+    pub forward_headers: Vec<String>,
+    #[serde(default)]
+    pub body_encoding: UpstreamBodyEncoding,
+    #[serde(default)]
+    pub cache: Option<ResponseCachePolicy>,
+    /// Per-caller limit for this API, applied on top of the route limits.
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitPolicy>,
+    #[serde(default)]
+    pub health_check: Option<HealthCheckPolicy>,
+    /// When set, calls draw on this pool instead of the API's own budget.
+    #[serde(default)]
+    pub budget_pool_id: Option<Uuid>,
+    /// Most this API may take from its pool, across all calls.
+    #[serde(default)]
+    pub pool_limit_cents: Option<u64>,
+    #[serde(default)]
+    pub pool_spent_cents: u64,
+    /// JSON Schema for `input`, e.g. derived from an imported OpenAPI operation.
+    #[serde(default)]
+    pub input_schema: Option<Value>,
+    /// JSON Schema for a successful upstream body, published to payers.
+    #[serde(default)]
+    pub output_schema: Option<Value>,
+    /// Check successful upstream bodies against `output_schema` and flag the ones that break it.
+    #[serde(default)]
+    pub validate_output: bool,
+    #[serde(default)]
+    pub transform: Option<TransformPolicy>,
+    /// Unset means every caller is subsidized.
+    #[serde(default)]
+    pub eligibility: Option<EligibilityPolicy>,
+    /// Filled in from the health registry when the API is returned to clients.
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub health: Option<UpstreamHealth>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -469,14 +1309,35 @@ pub struct SponsoredApiRow {
     pub upstream_url: String,
     pub upstream_method: String,
     pub upstream_headers: sqlx::types::Json<HashMap<String, String>>,
+    pub upstream_secret_headers: sqlx::types::Json<HashMap<String, String>>,
     pub price_cents: i64,
     pub budget_total_cents: i64,
     pub budget_remaining_cents: i64,
     pub active: bool,
+    pub paused: bool,
     pub service_key: String,
+    pub forward_headers: Vec<String>,
+    pub body_encoding: UpstreamBodyEncoding,
+    pub cache_policy: Option<sqlx::types::Json<ResponseCachePolicy>>,
+    pub rate_limit_policy: Option<sqlx::types::Json<RateLimitPolicy>>,
+    pub health_check_policy: Option<sqlx::types::Json<HealthCheckPolicy>>,
+    pub budget_pool_id: Option<Uuid>,
+    pub pool_limit_cents: Option<i64>,
+    pub pool_spent_cents: i64,
+    pub input_schema: Option<Value>,
+    pub output_schema: Option<Value>,
+    pub validate_output: bool,
+    pub transform_policy: Option<sqlx::types::Json<TransformPolicy>>,
+    pub eligibility_policy: Option<sqlx::types::Json<EligibilityPolicy>>,
     pub created_at: DateTime<Utc>,
 }
 
+pub fn sorted_header_names(headers: &HashMap<String, String>) -> Vec<String> {
+    let mut names: Vec<String> = headers.keys().cloned().collect();
+    names.sort();
+    names
+}
+
 impl TryFrom<SponsoredApiRow> for SponsoredApi {
     type Error = String;
 
@@ -489,6 +1350,8 @@ impl TryFrom<SponsoredApiRow> for SponsoredApi {
             upstream_url: value.upstream_url,
             upstream_method: value.upstream_method,
             upstream_headers: value.upstream_headers.0,
+            secret_header_names: sorted_header_names(&value.upstream_secret_headers),
+            sealed_headers: value.upstream_secret_headers.0,
             price_cents: u64::try_from(value.price_cents)
                 .map_err(|_| "price_cents must be non-negative".to_string())?,
             budget_total_cents: u64::try_from(value.budget_total_cents)
@@ -496,13 +1359,33 @@ impl TryFrom<SponsoredApiRow> for SponsoredApi {
             budget_remaining_cents: u64::try_from(value.budget_remaining_cents)
                 .map_err(|_| "budget_remaining_cents must be non-negative".to_string())?,
             active: value.active,
+            paused: value.paused,
             service_key: value.service_key,
+            forward_headers: value.forward_headers,
+            body_encoding: value.body_encoding,
+            cache: value.cache_policy.map(|policy| policy.0),
+            rate_limit: value.rate_limit_policy.map(|policy| policy.0),
+            health_check: value.health_check_policy.map(|policy| policy.0),
+            budget_pool_id: value.budget_pool_id,
+            pool_limit_cents: value
+                .pool_limit_cents
+                .map(u64::try_from)
+                .transpose()
+                .map_err(|_| "pool_limit_cents must be non-negative".to_string())?,
+            pool_spent_cents: u64::try_from(value.pool_spent_cents)
+                .map_err(|_| "pool_spent_cents must be non-negative".to_string())?,
+            input_schema: value.input_schema,
+            output_schema: value.output_schema,
+            validate_output: value.validate_output,
+            transform: value.transform_policy.map(|policy| policy.0),
+            eligibility: value.eligibility_policy.map(|policy| policy.0),
+            health: None,
             created_at: value.created_at,
         })
     }
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct CreateSponsoredApiRequest {
     pub name: String,
     pub sponsor: String,
@@ -512,20 +1395,447 @@ pub struct CreateSponsoredApiRequest {
     pub upstream_method: Option<String>,
     #[serde(default)]
     pub upstream_headers: HashMap<String, String>,
+    /// Header names that may be shown publicly; every other header is stored encrypted.
     #[serde(default)]
-    pub price_cents: Option<u64>,
-    pub budget_cents: u64,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct SponsoredApiRunRequest {
+    pub public_headers: Vec<String>,
+    #[serde(default)]
+    pub forward_headers: Vec<String>,
+    #[serde(default)]
+    pub body_encoding: UpstreamBodyEncoding,
+    #[serde(default)]
+    pub cache: Option<ResponseCachePolicy>,
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitPolicy>,
+    #[serde(default)]
+    pub health_check: Option<HealthCheckPolicy>,
+    #[serde(default)]
+    pub input_schema: Option<Value>,
+    #[serde(default)]
+    pub output_schema: Option<Value>,
+    #[serde(default)]
+    pub validate_output: bool,
+    #[serde(default)]
+    pub transform: Option<TransformPolicy>,
+    #[serde(default)]
+    pub eligibility: Option<EligibilityPolicy>,
+    #[serde(default)]
+    pub price_cents: Option<u64>,
+    pub budget_cents: u64,
+}
+
+/// Money shared by several sponsored APIs and campaigns from the same sponsor.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct BudgetPool {
+    pub id: Uuid,
+    pub sponsor: String,
+    pub name: String,
+    pub budget_total_cents: u64,
+    pub budget_remaining_cents: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct BudgetPoolRow {
+    pub id: Uuid,
+    pub sponsor: String,
+    pub name: String,
+    pub budget_total_cents: i64,
+    pub budget_remaining_cents: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CreateBudgetPoolRequest {
+    pub sponsor: String,
+    pub name: String,
+    pub budget_cents: u64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TopUpBudgetPoolRequest {
+    pub amount_cents: u64,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct BudgetPoolQuery {
+    pub sponsor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetPoolMemberKind {
+    SponsoredApi,
+    Campaign,
+}
+
+/// A sponsored API or campaign drawing on a pool, with what it has taken so far.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct BudgetPoolMember {
+    pub kind: BudgetPoolMemberKind,
+    pub id: Uuid,
+    pub name: String,
+    pub pool_limit_cents: Option<u64>,
+    pub pool_spent_cents: u64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct BudgetPoolDetail {
+    #[serde(flatten)]
+    pub pool: BudgetPool,
+    pub members: Vec<BudgetPoolMember>,
+}
+
+impl TryFrom<BudgetPoolRow> for BudgetPool {
+    type Error = String;
+
+    fn try_from(value: BudgetPoolRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.id,
+            sponsor: value.sponsor,
+            name: value.name,
+            budget_total_cents: u64::try_from(value.budget_total_cents)
+                .map_err(|_| "budget_total_cents must be non-negative".to_string())?,
+            budget_remaining_cents: u64::try_from(value.budget_remaining_cents)
+                .map_err(|_| "budget_remaining_cents must be non-negative".to_string())?,
+            created_at: value.created_at,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ImportSponsoredApisRequest {
+    pub sponsor: String,
+    /// An OpenAPI 3 document, either as JSON or as a JSON or YAML string.
+    pub document: Value,
+    /// Overrides the document's first server URL.
+    #[serde(default)]
+    pub base_url: Option<String>,
+    /// Operation ids or `METHOD /path` keys; empty imports every operation.
+    #[serde(default)]
+    pub operations: Vec<String>,
+    #[serde(default)]
+    pub name_prefix: Option<String>,
+    #[serde(default)]
+    pub upstream_headers: HashMap<String, String>,
+    #[serde(default)]
+    pub public_headers: Vec<String>,
+    #[serde(default)]
+    pub price_cents: Option<u64>,
+    /// Funds a single budget pool shared by every imported API.
+    pub budget_cents: u64,
+    #[serde(default)]
+    pub pool_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SkippedOperation {
+    pub operation: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ImportSponsoredApisResponse {
+    pub budget_pool: BudgetPool,
+    pub apis: Vec<SponsoredApi>,
+    pub skipped: Vec<SkippedOperation>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UpdateSponsoredApiRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Moving to another scheme, host or port drops the secret headers; set them again with
+    /// `PUT /sponsored-apis/{api_id}/headers`.
+    #[serde(default)]
+    pub upstream_url: Option<String>,
+    #[serde(default)]
+    pub upstream_method: Option<String>,
+    #[serde(default)]
+    pub forward_headers: Option<Vec<String>>,
+    #[serde(default)]
+    pub body_encoding: Option<UpstreamBodyEncoding>,
+    /// `null` turns caching off; omitting the field leaves it unchanged.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub cache: Option<Option<ResponseCachePolicy>>,
+    /// `null` removes the API's own limit; route limits still apply.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub rate_limit: Option<Option<RateLimitPolicy>>,
+    /// `null` stops background probes; the circuit breaker keeps watching real calls.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub health_check: Option<Option<HealthCheckPolicy>>,
+    /// Moves the API onto a pool of the same sponsor; `null` goes back to its own budget.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub budget_pool_id: Option<Option<Uuid>>,
+    /// `null` lets the API draw on the whole pool.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub pool_limit_cents: Option<Option<u64>>,
+    /// `null` accepts any input.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub input_schema: Option<Option<Value>>,
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub output_schema: Option<Option<Value>>,
+    #[serde(default)]
+    pub validate_output: Option<bool>,
+    /// `null` sends input and returns replies unchanged.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub transform: Option<Option<TransformPolicy>>,
+    /// `null` subsidizes every caller again.
+    #[serde(default, deserialize_with = "deserialize_present")]
+    pub eligibility: Option<Option<EligibilityPolicy>>,
+    #[serde(default)]
+    pub price_cents: Option<u64>,
+}
+
+/// Distinguishes an explicit `null` from a missing field in PATCH bodies.
+fn deserialize_present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RotateSponsoredApiHeadersRequest {
+    pub upstream_headers: HashMap<String, String>,
+    #[serde(default)]
+    pub public_headers: Vec<String>,
+    /// Keep headers that are not mentioned instead of replacing the whole set.
+    #[serde(default)]
+    pub merge: bool,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TopUpSponsoredApiRequest {
+    pub amount_cents: u64,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, JsonSchema)]
+pub struct SponsoredApiAuditEntry {
+    pub id: Uuid,
+    pub sponsored_api_id: Uuid,
+    pub action: String,
+    pub changes: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SponsoredApiRunRequest {
     #[serde(default)]
     pub caller: Option<String>,
     #[serde(default)]
     pub input: Value,
+    /// Return the upstream body as a raw stream (chunked or SSE) instead of wrapping it.
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub mode: RunMode,
+    /// Async mode only: notified with the finished job.
+    #[serde(default)]
+    pub callback_url: Option<String>,
+}
+
+/// Largest number of inputs one batch run accepts.
+pub const MAX_BATCH_ITEMS: usize = 100;
+/// Items of one batch that call the upstream at the same time.
+pub const BATCH_CONCURRENCY: usize = 8;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SponsoredApiBatchRunRequest {
+    #[serde(default)]
+    pub caller: Option<String>,
+    pub inputs: Vec<Value>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchItemStatus {
+    /// The upstream answered, whatever its status code.
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SponsoredApiBatchItem {
+    pub index: usize,
+    pub status: BatchItemStatus,
+    /// What this item finally cost; zero when it was refunded.
+    pub amount_cents: u64,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub refunded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream_body: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Output schema violations of a succeeded item, as on a single run.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub output_violations: Vec<SchemaViolation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<Value>,
+}
+
+/// Results come back in input order. The whole batch is charged up front and items that
+/// fail before the upstream answers are refunded to the sponsor budget.
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SponsoredApiBatchRunResponse {
+    pub api_id: Uuid,
+    pub payment_mode: String,
+    pub sponsored_by: Option<String>,
+    pub tx_hash: Option<String>,
+    /// Total charged after refunds.
+    pub amount_cents: u64,
+    pub refunded_cents: u64,
+    pub results: Vec<SponsoredApiBatchItem>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RunMode {
+    #[default]
+    Sync,
+    /// Answer `202` with a job id and call the upstream in the background.
+    Async,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Succeeded | Self::Failed)
+    }
 }
 
-#[derive(Debug, Serialize)]
+/// Where a sponsored job's price stands. The budget is drawn when the job is accepted and
+/// only kept once the upstream has answered.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobBudgetState {
+    Reserved,
+    Committed,
+    Released,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SponsoredApiJob {
+    pub id: Uuid,
+    pub sponsored_api_id: Uuid,
+    pub status: JobStatus,
+    pub payment_mode: String,
+    pub amount_cents: u64,
+    pub sponsored_by: Option<String>,
+    pub tx_hash: Option<String>,
+    /// Only set for sponsored jobs; x402 payments are settled up front.
+    pub budget: Option<JobBudgetState>,
+    pub callback_url: Option<String>,
+    pub callback_status: Option<String>,
+    pub upstream_status: Option<u16>,
+    pub upstream_body: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// The error envelope the synchronous call would have returned. On a succeeded job it
+    /// lists the output schema violations the body was delivered with.
+    pub error: Option<Value>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SponsoredApiJobRow {
+    pub id: Uuid,
+    pub sponsored_api_id: Uuid,
+    pub status: JobStatus,
+    pub payment_mode: String,
+    pub amount_cents: i64,
+    pub sponsored_by: Option<String>,
+    pub tx_hash: Option<String>,
+    /// What the call log still holds; zero once a refund went through.
+    pub charged_cents: Option<i64>,
+    pub callback_url: Option<String>,
+    pub callback_status: Option<String>,
+    pub upstream_status: Option<i32>,
+    pub upstream_body: Option<String>,
+    pub truncated: bool,
+    pub error: Option<Value>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<SponsoredApiJobRow> for SponsoredApiJob {
+    type Error = String;
+
+    fn try_from(value: SponsoredApiJobRow) -> Result<Self, Self::Error> {
+        let amount_cents = u64::try_from(value.amount_cents)
+            .map_err(|_| "amount_cents must be non-negative".to_string())?;
+        let budget = (value.payment_mode == "sponsored").then(|| {
+            if !value.status.is_finished() {
+                JobBudgetState::Reserved
+            } else if amount_cents > 0 && value.charged_cents == Some(0) {
+                JobBudgetState::Released
+            } else {
+                JobBudgetState::Committed
+            }
+        });
+        Ok(Self {
+            id: value.id,
+            sponsored_api_id: value.sponsored_api_id,
+            status: value.status,
+            payment_mode: value.payment_mode,
+            amount_cents,
+            sponsored_by: value.sponsored_by,
+            tx_hash: value.tx_hash,
+            budget,
+            callback_url: value.callback_url,
+            callback_status: value.callback_status,
+            upstream_status: value
+                .upstream_status
+                .map(u16::try_from)
+                .transpose()
+                .map_err(|_| "upstream_status must be a valid HTTP status".to_string())?,
+            upstream_body: value.upstream_body,
+            truncated: value.truncated,
+            error: value.error,
+            created_at: value.created_at,
+            started_at: value.started_at,
+            completed_at: value.completed_at,
+            expires_at: value.expires_at,
+        })
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SponsoredApiJobAccepted {
+    #[serde(flatten)]
+    pub job: SponsoredApiJob,
+    /// Signs callback deliveries like sponsor webhooks. Only shown here.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub callback_secret: Option<String>,
+}
+
+/// One place where a value failed its JSON Schema.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+pub struct SchemaViolation {
+    /// JSON Pointer into the validated value; empty for the value itself.
+    pub path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct SponsoredApiRunResponse {
     pub api_id: Uuid,
     pub payment_mode: String,
@@ -533,9 +1843,50 @@ pub struct SponsoredApiRunResponse {
     pub tx_hash: Option<String>,
     pub upstream_status: u16,
     pub upstream_body: String,
+    /// Present only for APIs with caching enabled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cache: Option<CacheStatus>,
+    /// Set when `transform.response.max_bytes` cut the body short.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+    /// Where a successful body broke the API's output schema. The call stays charged, since
+    /// payment settles before the upstream is called; the call log records it as
+    /// `output_contract_violation`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub output_violations: Vec<SchemaViolation>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TransformPreviewRequest {
+    #[serde(default)]
+    pub input: Value,
+    /// A sample upstream reply: JSON, or a string taken as the raw body.
+    #[serde(default)]
+    pub upstream_body: Option<Value>,
+    /// Previews an unsaved transform instead of the API's current one.
+    #[serde(default)]
+    pub transform: Option<TransformPolicy>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TransformPreviewResponse {
+    pub input_violations: Vec<SchemaViolation>,
+    pub upstream_method: String,
+    pub upstream_url: String,
+    /// Sent as the body, or as the query string for GET and DELETE.
+    pub upstream_input: Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<TransformedResponsePreview>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TransformedResponsePreview {
+    pub body: String,
+    pub truncated: bool,
+    pub output_violations: Vec<SchemaViolation>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SponsoredApiCall {
     pub id: Uuid,
     pub sponsored_api_id: Uuid,
@@ -544,6 +1895,269 @@ pub struct SponsoredApiCall {
     pub tx_hash: Option<String>,
     pub caller: Option<String>,
     pub created_at: DateTime<Utc>,
+    /// The fields below stay null until the call is finalized.
+    pub upstream_status: Option<u16>,
+    pub response_bytes: Option<u64>,
+    pub outcome: Option<String>,
+    pub duration_ms: Option<u64>,
+    pub error_class: Option<CallErrorClass>,
+    pub finalized_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SponsoredApiCallRow {
+    pub id: Uuid,
+    pub sponsored_api_id: Uuid,
+    pub payment_mode: String,
+    pub amount_cents: i64,
+    pub tx_hash: Option<String>,
+    pub caller: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub upstream_status: Option<i32>,
+    pub response_bytes: Option<i64>,
+    pub outcome: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub error_class: Option<CallErrorClass>,
+    pub finalized_at: Option<DateTime<Utc>>,
+}
+
+impl TryFrom<SponsoredApiCallRow> for SponsoredApiCall {
+    type Error = String;
+
+    fn try_from(value: SponsoredApiCallRow) -> Result<Self, Self::Error> {
+        Ok(Self {
+            id: value.id,
+            sponsored_api_id: value.sponsored_api_id,
+            payment_mode: value.payment_mode,
+            amount_cents: u64::try_from(value.amount_cents)
+                .map_err(|_| "amount_cents must be non-negative".to_string())?,
+            tx_hash: value.tx_hash,
+            caller: value.caller,
+            created_at: value.created_at,
+            upstream_status: value
+                .upstream_status
+                .map(u16::try_from)
+                .transpose()
+                .map_err(|_| "upstream_status must be a valid HTTP status".to_string())?,
+            response_bytes: value
+                .response_bytes
+                .map(u64::try_from)
+                .transpose()
+                .map_err(|_| "response_bytes must be non-negative".to_string())?,
+            outcome: value.outcome,
+            duration_ms: value
+                .duration_ms
+                .map(u64::try_from)
+                .transpose()
+                .map_err(|_| "duration_ms must be non-negative".to_string())?,
+            error_class: value.error_class,
+            finalized_at: value.finalized_at,
+        })
+    }
+}
+
+/// Why a sponsored API call did not produce a usable upstream reply.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum CallErrorClass {
+    /// The upstream answered with a 4xx status.
+    UpstreamClientError,
+    /// The upstream answered with a 5xx status.
+    UpstreamServerError,
+    Timeout,
+    /// The upstream could not be reached or failed before sending a response.
+    Connection,
+    /// The upstream body went past the configured maximum size.
+    ResponseTooLarge,
+    /// The caller disconnected before the body was delivered.
+    ClientAborted,
+    /// The upstream succeeded with a body that broke the output schema. Still charged.
+    OutputContractViolation,
+}
+
+impl CallErrorClass {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::UpstreamClientError => "upstream_client_error",
+            Self::UpstreamServerError => "upstream_server_error",
+            Self::Timeout => "timeout",
+            Self::Connection => "connection",
+            Self::ResponseTooLarge => "response_too_large",
+            Self::ClientAborted => "client_aborted",
+            Self::OutputContractViolation => "output_contract_violation",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum CallLogFormat {
+    #[default]
+    Json,
+    Csv,
+    Ndjson,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SponsoredApiCallsQuery {
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub payment_mode: Option<String>,
+    #[serde(default)]
+    pub limit: Option<u32>,
+    /// Opaque `next_cursor` from the previous page.
+    #[serde(default)]
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub format: CallLogFormat,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SponsoredApiCallPage {
+    pub calls: Vec<SponsoredApiCall>,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash, JsonSchema,
+)]
+pub enum WebhookEventType {
+    #[serde(rename = "task.completed")]
+    TaskCompleted,
+    #[serde(rename = "sponsored_call.charged")]
+    SponsoredCallCharged,
+    #[serde(rename = "budget.low")]
+    BudgetLow,
+    #[serde(rename = "budget.exhausted")]
+    BudgetExhausted,
+    #[serde(rename = "campaign.ended")]
+    CampaignEnded,
+}
+
+impl WebhookEventType {
+    pub const ALL: [WebhookEventType; 5] = [
+        Self::TaskCompleted,
+        Self::SponsoredCallCharged,
+        Self::BudgetLow,
+        Self::BudgetExhausted,
+        Self::CampaignEnded,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::TaskCompleted => "task.completed",
+            Self::SponsoredCallCharged => "sponsored_call.charged",
+            Self::BudgetLow => "budget.low",
+            Self::BudgetExhausted => "budget.exhausted",
+            Self::CampaignEnded => "campaign.ended",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|event| event.as_str() == value)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SponsorWebhook {
+    pub id: Uuid,
+    pub sponsor: String,
+    pub campaign_id: Option<Uuid>,
+    pub url: String,
+    pub events: Vec<WebhookEventType>,
+    pub budget_low_threshold_percent: u8,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct SponsorWebhookRow {
+    pub id: Uuid,
+    pub sponsor: String,
+    pub campaign_id: Option<Uuid>,
+    pub url: String,
+    pub events: Vec<String>,
+    pub budget_low_threshold_percent: i32,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl TryFrom<SponsorWebhookRow> for SponsorWebhook {
+    type Error = String;
+
+    fn try_from(value: SponsorWebhookRow) -> Result<Self, Self::Error> {
+        let events = value
+            .events
+            .iter()
+            .map(|event| {
+                WebhookEventType::parse(event).ok_or_else(|| format!("unknown event: {event}"))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            id: value.id,
+            sponsor: value.sponsor,
+            campaign_id: value.campaign_id,
+            url: value.url,
+            events,
+            budget_low_threshold_percent: u8::try_from(value.budget_low_threshold_percent)
+                .map_err(|_| "budget_low_threshold_percent out of range".to_string())?,
+            active: value.active,
+            created_at: value.created_at,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CreateSponsorWebhookRequest {
+    pub sponsor: String,
+    pub url: String,
+    #[serde(default)]
+    pub campaign_id: Option<Uuid>,
+    #[serde(default)]
+    pub events: Vec<WebhookEventType>,
+    #[serde(default)]
+    pub budget_low_threshold_percent: Option<u8>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CreateSponsorWebhookResponse {
+    pub webhook: SponsorWebhook,
+    /// HMAC key for verifying `x-webhook-signature`; only returned at creation time.
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SponsorWebhookListQuery {
+    pub sponsor: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, sqlx::Type, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "text", rename_all = "snake_case")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Succeeded,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow, JsonSchema)]
+pub struct WebhookDelivery {
+    pub id: Uuid,
+    pub webhook_id: Uuid,
+    pub event_type: String,
+    pub payload: Value,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: i32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub delivered_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
 }
 
 fn read_env_u64(key: &str, default: u64) -> u64 {
@@ -10,13 +10,17 @@ use std::{collections::HashMap, time::Duration};
 use uuid::Uuid;
 
 use crate::error::{ApiError, ApiResult};
+use crate::network::{UpstreamPolicy, blocked_upstream_reason};
 use crate::onchain::{VerifiedX402Payment, verify_and_settle_x402_payment};
 use crate::types::{
-    AppConfig, Campaign, Metrics, PAYMENT_RESPONSE_HEADER, PAYMENT_SIGNATURE_HEADER,
-    PaymentRequired, SPONSORED_API_SERVICE_PREFIX, ServiceRunRequest, ServiceRunResponse,
-    SponsoredApi, UserProfile, X402_VERSION_HEADER, X402PaymentRequirement,
+    AppConfig, Campaign, CreateUserRequest, HealthCheckPolicy, HealthProbeResult, MAX_BATCH_ITEMS,
+    MAX_HEALTH_CHECK_TIMEOUT_SECS, Metrics, PAYMENT_RESPONSE_HEADER, PAYMENT_SIGNATURE_HEADER,
+    PaymentRequired, RateLimitPolicy, ResponseCachePolicy, SPONSORED_API_SERVICE_PREFIX,
+    ServiceRunRequest, ServiceRunResponse, SponsoredApi, UpstreamBodyEncoding, UserProfile,
+    X402_VERSION_HEADER, X402PaymentRequirement,
 };
-use sqlx::PgPool;
+use chrono::Utc;
+use sqlx::{FromRow, PgPool, Row, types::Json as DbJson};
 
 const USDC_BASE_UNITS_PER_CENT: u128 = 10_000;
 
@@ -35,25 +39,83 @@ pub fn respond<T: IntoResponse>(
 }
 
 pub fn user_matches_campaign(user: &UserProfile, campaign: &Campaign) -> bool {
-    let role_match = if campaign.target_roles.is_empty() {
+    user_matches_targets(user, &campaign.target_roles, &campaign.target_tools)
+}
+
+/// Empty target lists match everyone; otherwise the user needs one role and one tool.
+pub fn user_matches_targets(
+    user: &UserProfile,
+    target_roles: &[String],
+    target_tools: &[String],
+) -> bool {
+    let role_match = if target_roles.is_empty() {
         true
     } else {
         user.roles
             .iter()
-            .any(|role| campaign.target_roles.iter().any(|target| target == role))
+            .any(|role| target_roles.iter().any(|target| target == role))
     };
 
-    let tool_match = if campaign.target_tools.is_empty() {
+    let tool_match = if target_tools.is_empty() {
         true
     } else {
         user.tools_used
             .iter()
-            .any(|tool| campaign.target_tools.iter().any(|target| target == tool))
+            .any(|tool| target_tools.iter().any(|target| target == tool))
     };
 
     role_match && tool_match
 }
 
+pub fn normalize_email(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+pub async fn upsert_user_profile(
+    db: &PgPool,
+    payload: CreateUserRequest,
+) -> ApiResult<(UserProfile, bool)> {
+    let email = normalize_email(&payload.email);
+    if email.is_empty() {
+        return Err(ApiError::validation("email is required"));
+    }
+    if payload.region.trim().is_empty() {
+        return Err(ApiError::validation("region is required"));
+    }
+
+    let row = sqlx::query(
+        r#"
+        insert into users (id, email, region, roles, tools_used, attributes, created_at)
+        values ($1, $2, $3, $4, $5, $6, $7)
+        on conflict (email) do update
+        set region = excluded.region,
+            roles = excluded.roles,
+            tools_used = excluded.tools_used,
+            attributes = excluded.attributes
+        returning id, email, region, roles, tools_used, attributes, created_at,
+            (xmax = 0) as inserted
+        "#,
+    )
+    .bind(Uuid::new_v4())
+    .bind(email)
+    .bind(payload.region)
+    .bind(payload.roles)
+    .bind(payload.tools_used)
+    .bind(DbJson(payload.attributes))
+    .bind(Utc::now())
+    .fetch_one(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    let profile = UserProfile::from_row(&row)
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+    let inserted: bool = row
+        .try_get("inserted")
+        .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    Ok((profile, inserted))
+}
+
 pub async fn has_completed_task(
     db: &PgPool,
     campaign_id: Uuid,
@@ -67,6 +129,7 @@ pub async fn has_completed_task(
             where campaign_id = $1
               and user_id = $2
               and task_name = $3
+              and status = 'verified'
         )
         "#,
     )
@@ -86,6 +149,7 @@ pub async fn verify_x402_payment(
     service: &str,
     amount_cents: u64,
     resource_path: &str,
+    output_schema: Option<&Value>,
     headers: &HeaderMap,
 ) -> ApiResult<VerifiedX402Payment> {
     let Some(signature) = headers
@@ -97,12 +161,14 @@ pub async fn verify_x402_payment(
             service,
             amount_cents,
             resource_path,
+            output_schema,
             "missing PAYMENT-SIGNATURE header",
             "create a payment from the PAYMENT-REQUIRED challenge and retry",
         ));
     };
 
-    let requirement = build_payment_requirement(config, service, amount_cents, resource_path)?;
+    let requirement =
+        build_payment_requirement(config, service, amount_cents, resource_path, output_schema)?;
     match verify_and_settle_x402_payment(http, config, signature, &requirement).await {
         Ok(payment) => Ok(payment),
         Err(err) => match err {
@@ -112,6 +178,7 @@ pub async fn verify_x402_payment(
                 service,
                 amount_cents,
                 resource_path,
+                output_schema,
                 format!("payment rejected: {err}"),
                 "regenerate PAYMENT-SIGNATURE from the latest challenge and retry",
             )),
@@ -124,11 +191,17 @@ pub fn payment_required_error(
     service: &str,
     amount_cents: u64,
     resource_path: &str,
+    output_schema: Option<&Value>,
     message: impl Into<String>,
     next_step: impl Into<String>,
 ) -> ApiError {
-    let requirement = match build_payment_requirement(config, service, amount_cents, resource_path)
-    {
+    let requirement = match build_payment_requirement(
+        config,
+        service,
+        amount_cents,
+        resource_path,
+        output_schema,
+    ) {
         Ok(value) => value,
         Err(err) => return err,
     };
@@ -138,21 +211,22 @@ pub fn payment_required_error(
         Err(err) => return ApiError::internal(err),
     };
 
-    ApiError::PaymentRequired(PaymentRequired {
+    ApiError::PaymentRequired(Box::new(PaymentRequired {
         service: service.to_string(),
         amount_cents,
         accepted_header: PAYMENT_SIGNATURE_HEADER.to_string(),
         payment_required,
         message: message.into(),
         next_step: next_step.into(),
-    })
+    }))
 }
 
-fn build_payment_requirement(
+pub fn build_payment_requirement(
     config: &AppConfig,
     service: &str,
     amount_cents: u64,
     resource_path: &str,
+    output_schema: Option<&Value>,
 ) -> ApiResult<X402PaymentRequirement> {
     let pay_to = required_non_empty_env_like(config.x402_pay_to.as_deref(), "X402_PAY_TO")?;
     let asset = required_non_empty_env_like(config.x402_asset.as_deref(), "X402_ASSET")?;
@@ -173,7 +247,7 @@ fn build_payment_requirement(
         pay_to,
         max_timeout_seconds: 300,
         asset,
-        output_schema: None,
+        output_schema: output_schema.cloned(),
         extra: HashMap::new(),
     })
 }
@@ -207,32 +281,58 @@ pub fn build_paid_tool_response(
     payment_response_header: Option<&str>,
 ) -> Response {
     let payload = ServiceRunResponse {
-        service: service.clone(),
-        output: format!(
-            "Executed '{}' task for user {} with input: {}",
-            service, request.user_id, request.input
-        ),
+        output: tool_output(&service, request.user_id, &request.input),
+        service,
         payment_mode,
         sponsored_by,
         tx_hash,
     };
 
     let mut response = (StatusCode::OK, Json(payload)).into_response();
+    apply_payment_headers(&mut response, payment_response_header);
+    response
+}
+
+pub fn tool_output(service: &str, user_id: Uuid, input: &str) -> String {
+    format!("Executed '{service}' task for user {user_id} with input: {input}")
+}
+
+pub fn apply_payment_headers(response: &mut Response, payment_response_header: Option<&str>) {
     response.headers_mut().insert(
         HeaderName::from_static(X402_VERSION_HEADER),
         HeaderValue::from_static("2"),
     );
 
-    if let Some(payment_response) = payment_response_header {
-        if let Ok(header_value) = HeaderValue::from_str(payment_response) {
-            response.headers_mut().insert(
-                HeaderName::from_static(PAYMENT_RESPONSE_HEADER),
-                header_value,
-            );
-        }
+    if let Some(payment_response) = payment_response_header
+        && let Ok(header_value) = HeaderValue::from_str(payment_response)
+    {
+        response.headers_mut().insert(
+            HeaderName::from_static(PAYMENT_RESPONSE_HEADER),
+            header_value,
+        );
     }
+}
 
-    response
+/// Checks the number of inputs in a batch run and returns it as a count of calls.
+pub fn validate_batch_size(items: usize) -> ApiResult<u32> {
+    if items == 0 {
+        return Err(ApiError::validation("inputs must not be empty"));
+    }
+    if items > MAX_BATCH_ITEMS {
+        return Err(ApiError::validation(format!(
+            "a batch holds at most {MAX_BATCH_ITEMS} inputs, got {items}"
+        )));
+    }
+    Ok(items as u32)
+}
+
+/// What a batch of `calls` costs at `price` each. Budgets are stored as `bigint`, so the total
+/// has to fit in an `i64`.
+pub fn batch_total_cents(price: u64, calls: u32) -> ApiResult<u64> {
+    price
+        .checked_mul(u64::from(calls))
+        .filter(|total| i64::try_from(*total).is_ok())
+        .ok_or_else(|| ApiError::validation("batch total exceeds the largest chargeable amount"))
 }
 
 pub fn mark_request(metrics: &Metrics, endpoint: &str, status: StatusCode) {
@@ -251,49 +351,596 @@ pub fn normalize_upstream_method(method: Option<String>) -> ApiResult<String> {
     let value = method.unwrap_or_else(|| "POST".to_string());
     let normalized = value.trim().to_uppercase();
     match normalized.as_str() {
-        "GET" | "POST" => Ok(normalized),
-        _ => Err(ApiError::validation("upstream_method must be GET or POST")),
+        "GET" | "POST" | "PUT" | "PATCH" | "DELETE" => Ok(normalized),
+        _ => Err(ApiError::validation(
+            "upstream_method must be GET, POST, PUT, PATCH or DELETE",
+        )),
     }
 }
 
-pub async fn call_upstream(
-    http: &Client,
-    api: &SponsoredApi,
-    payload: Value,
-    timeout_secs: u64,
-) -> ApiResult<(u16, String)> {
-    let method = match api.upstream_method.as_str() {
-        "GET" => Method::GET,
-        "POST" => Method::POST,
-        other => {
-            return Err(ApiError::internal(format!(
-                "unsupported upstream method: {other}"
+/// Caller headers that must never be forwarded: they are connection-level, describe our own
+/// request body, or carry this server's payment protocol.
+const UNFORWARDABLE_HEADERS: &[&str] = &[
+    "host",
+    "connection",
+    "content-length",
+    "transfer-encoding",
+    "upgrade",
+    "te",
+    "trailer",
+    "keep-alive",
+    "proxy-authorization",
+    "proxy-connection",
+    PAYMENT_SIGNATURE_HEADER,
+];
+
+pub fn normalize_forward_headers(names: Vec<String>) -> ApiResult<Vec<String>> {
+    let mut normalized = Vec::with_capacity(names.len());
+    for name in names {
+        let name = name.trim().to_ascii_lowercase();
+        HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| ApiError::validation(format!("invalid forward header: {name}")))?;
+        if UNFORWARDABLE_HEADERS.contains(&name.as_str()) {
+            return Err(ApiError::validation(format!(
+                "header cannot be forwarded: {name}"
+            )));
+        }
+        normalized.push(name);
+    }
+    normalized.sort();
+    normalized.dedup();
+    Ok(normalized)
+}
+
+/// Whether two upstream URLs point at the same scheme, host and port. Unparseable URLs never
+/// match, so callers err on the side of treating the upstream as moved.
+pub fn same_upstream_origin(a: &str, b: &str) -> bool {
+    match (reqwest::Url::parse(a.trim()), reqwest::Url::parse(b.trim())) {
+        (Ok(a), Ok(b)) => a.origin() == b.origin(),
+        _ => false,
+    }
+}
+
+/// Names of the `{param}` placeholders in an upstream URL template, in order. Placeholders
+/// may only appear after the authority, so input can never choose the scheme, host or port.
+pub fn upstream_path_params(upstream_url: &str) -> ApiResult<Vec<String>> {
+    let authority_end = upstream_url
+        .find("://")
+        .map_or(upstream_url.len(), |scheme_end| {
+            let authority = scheme_end + 3;
+            upstream_url[authority..]
+                .find(['/', '?', '#'])
+                .map_or(upstream_url.len(), |end| authority + end)
+        });
+    if upstream_url[..authority_end].contains(['{', '}']) {
+        return Err(ApiError::validation(
+            "upstream_url placeholders are only allowed in the path or query",
+        ));
+    }
+    let mut params = Vec::new();
+    let mut rest = upstream_url;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| ApiError::validation("upstream_url has an unclosed '{' placeholder"))?;
+        let name = &after[..end];
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '_' || ch == '-')
+        {
+            return Err(ApiError::validation(format!(
+                "invalid upstream_url placeholder: {{{name}}}"
             )));
         }
+        params.push(name.to_string());
+        rest = &after[end + 1..];
+    }
+    if rest.contains('}') {
+        return Err(ApiError::validation("upstream_url has an unmatched '}'"));
+    }
+    Ok(params)
+}
+
+#[derive(Debug, Clone)]
+pub struct UpstreamRequest {
+    pub url: String,
+    pub input: Value,
+    pub forwarded_headers: Vec<(HeaderName, HeaderValue)>,
+}
+
+/// Resolves the URL template, body and forwarded headers for one call. Runs before any
+/// budget is reserved so malformed input is rejected without charging anyone.
+pub fn prepare_upstream_request(
+    api: &SponsoredApi,
+    input: Value,
+    caller_headers: &HeaderMap,
+) -> ApiResult<UpstreamRequest> {
+    let params = upstream_path_params(&api.upstream_url)?;
+    let mut url = api.upstream_url.clone();
+    let mut input = input;
+
+    if !params.is_empty() {
+        let fields = input.as_object_mut().ok_or_else(|| {
+            ApiError::validation(format!(
+                "input must be an object with path parameters: {}",
+                params.join(", ")
+            ))
+        })?;
+        for param in params {
+            let value = match fields.remove(&param) {
+                Some(Value::String(value)) => value,
+                Some(value @ (Value::Number(_) | Value::Bool(_))) => value.to_string(),
+                Some(_) => {
+                    return Err(ApiError::validation(format!(
+                        "path parameter '{param}' must be a string, number or boolean"
+                    )));
+                }
+                None => {
+                    return Err(ApiError::validation(format!(
+                        "input is missing path parameter '{param}'"
+                    )));
+                }
+            };
+            if value == "." || value == ".." {
+                return Err(ApiError::validation(format!(
+                    "path parameter '{param}' must not be '.' or '..'"
+                )));
+            }
+            url = url.replace(&format!("{{{param}}}"), &encode_path_segment(&value));
+        }
+    }
+
+    // GET and DELETE send the remaining input as a flat query string.
+    if matches!(api.upstream_method.as_str(), "GET" | "DELETE")
+        && let Some(fields) = input.as_object()
+        && let Some((name, _)) = fields.iter().find(|(_, value)| {
+            !matches!(value, Value::String(_) | Value::Number(_) | Value::Bool(_))
+        })
+    {
+        return Err(ApiError::validation(format!(
+            "query parameter '{name}' must be a string, number or boolean"
+        )));
+    }
+
+    if api.body_encoding == UpstreamBodyEncoding::Raw
+        && !matches!(api.upstream_method.as_str(), "GET" | "DELETE")
+    {
+        input = match input {
+            Value::String(_) => input,
+            Value::Object(mut fields) => match fields.remove("body") {
+                Some(body @ Value::String(_)) => body,
+                _ => {
+                    return Err(ApiError::validation(
+                        "raw upstream bodies must be a string input or input.body",
+                    ));
+                }
+            },
+            _ => {
+                return Err(ApiError::validation(
+                    "raw upstream bodies must be a string input or input.body",
+                ));
+            }
+        };
+    }
+
+    if api.body_encoding == UpstreamBodyEncoding::Form
+        && !matches!(api.upstream_method.as_str(), "GET" | "DELETE")
+        && !input.is_object()
+    {
+        return Err(ApiError::validation(
+            "form upstream input must be an object",
+        ));
+    }
+
+    let configured = |name: &str| {
+        api.upstream_headers
+            .keys()
+            .chain(api.sealed_headers.keys())
+            .any(|header| header.eq_ignore_ascii_case(name))
     };
+    let forwarded_headers = api
+        .forward_headers
+        .iter()
+        .filter(|name| !configured(name))
+        .filter_map(|name| {
+            let value = caller_headers.get(name.as_str())?;
+            Some((HeaderName::from_bytes(name.as_bytes()).ok()?, value.clone()))
+        })
+        .collect();
+
+    Ok(UpstreamRequest {
+        url,
+        input,
+        forwarded_headers,
+    })
+}
+
+fn encode_path_segment(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+    }
+    encoded
+}
+
+pub fn validate_cache_policy(policy: &ResponseCachePolicy) -> ApiResult<()> {
+    if policy.ttl_secs == 0 {
+        return Err(ApiError::validation(
+            "cache.ttl_secs must be greater than 0",
+        ));
+    }
+    if policy.max_entry_bytes == 0 {
+        return Err(ApiError::validation(
+            "cache.max_entry_bytes must be greater than 0",
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_rate_limit_policy(policy: &RateLimitPolicy) -> ApiResult<()> {
+    if policy.capacity == 0 {
+        return Err(ApiError::validation(
+            "rate_limit.capacity must be greater than 0",
+        ));
+    }
+    if !(policy.refill_per_sec.is_finite() && policy.refill_per_sec > 0.0) {
+        return Err(ApiError::validation(
+            "rate_limit.refill_per_sec must be greater than 0",
+        ));
+    }
+    Ok(())
+}
+
+pub fn validate_health_check_policy(policy: &HealthCheckPolicy) -> ApiResult<()> {
+    if !policy.path.starts_with('/') || policy.path.starts_with("//") {
+        return Err(ApiError::validation(
+            "health_check.path must be an absolute path such as /healthz",
+        ));
+    }
+    if policy.path.contains(['{', '}']) {
+        return Err(ApiError::validation(
+            "health_check.path cannot contain path templates",
+        ));
+    }
+    if !(100..=599).contains(&policy.expected_status) {
+        return Err(ApiError::validation(
+            "health_check.expected_status must be an HTTP status code",
+        ));
+    }
+    if policy.interval_secs == 0 || policy.timeout_secs == 0 {
+        return Err(ApiError::validation(
+            "health_check.interval_secs and timeout_secs must be greater than 0",
+        ));
+    }
+    if policy.timeout_secs > MAX_HEALTH_CHECK_TIMEOUT_SECS
+        || policy.timeout_secs.saturating_mul(2) > policy.interval_secs
+    {
+        return Err(ApiError::validation(format!(
+            "health_check.timeout_secs must be at most {MAX_HEALTH_CHECK_TIMEOUT_SECS} and at \
+             most half of interval_secs"
+        )));
+    }
+    Ok(())
+}
+
+pub fn validate_price_cents(price_cents: u64) -> ApiResult<()> {
+    if price_cents == 0 {
+        return Err(ApiError::validation("price_cents must be greater than 0"));
+    }
+    Ok(())
+}
+
+pub fn validate_upstream_headers(headers: &HashMap<String, String>) -> ApiResult<()> {
+    for (header, value) in headers {
+        HeaderName::from_bytes(header.as_bytes())
+            .map_err(|_| ApiError::validation(format!("invalid upstream header: {header}")))?;
+        HeaderValue::from_str(value).map_err(|_| {
+            ApiError::validation(format!("invalid upstream header value for: {header}"))
+        })?;
+    }
+    Ok(())
+}
+
+/// Splits headers into public values and secrets. Headers are secret unless listed in
+/// `public_names`; names are lowercased so both maps key the same header the same way.
+pub fn split_upstream_headers(
+    headers: HashMap<String, String>,
+    public_names: &[String],
+) -> ApiResult<(HashMap<String, String>, HashMap<String, String>)> {
+    let public_names: Vec<String> = public_names
+        .iter()
+        .map(|name| name.trim().to_ascii_lowercase())
+        .collect();
+    let headers: HashMap<String, String> = headers
+        .into_iter()
+        .map(|(name, value)| (name.trim().to_ascii_lowercase(), value))
+        .collect();
+
+    if let Some(missing) = public_names
+        .iter()
+        .find(|name| !headers.contains_key(*name))
+    {
+        return Err(ApiError::validation(format!(
+            "public_headers lists unknown header: {missing}"
+        )));
+    }
+
+    Ok(headers
+        .into_iter()
+        .partition(|(name, _)| public_names.contains(name)))
+}
+
+pub fn check_upstream_url(policy: &UpstreamPolicy, upstream_url: &str) -> ApiResult<()> {
+    let url = reqwest::Url::parse(upstream_url)
+        .map_err(|_| ApiError::internal("stored upstream_url is not a valid URL"))?;
+    policy.check_url(&url).map_err(upstream_blocked_error)
+}
+
+pub fn upstream_blocked_error(reason: String) -> ApiError {
+    ApiError::Http {
+        status: StatusCode::BAD_GATEWAY,
+        code: "upstream_blocked".to_string(),
+        message: format!("upstream blocked by network policy: {reason}"),
+    }
+}
+
+pub async fn call_upstream(
+    http: &Client,
+    config: &AppConfig,
+    api: &SponsoredApi,
+    upstream: UpstreamRequest,
+) -> ApiResult<reqwest::Response> {
+    let method = Method::from_bytes(api.upstream_method.as_bytes()).map_err(|_| {
+        ApiError::internal(format!(
+            "unsupported upstream method: {}",
+            api.upstream_method
+        ))
+    })?;
+
+    check_upstream_url(&config.upstream_policy, &upstream.url)?;
 
     let mut request = http
-        .request(method.clone(), &api.upstream_url)
-        .timeout(Duration::from_secs(timeout_secs));
+        .request(method.clone(), &upstream.url)
+        .timeout(Duration::from_secs(
+            config.sponsored_api_stream_timeout_secs,
+        ));
 
-    for (header, value) in &api.upstream_headers {
+    for (header, value) in upstream.forwarded_headers {
         request = request.header(header, value);
     }
+    request = apply_sponsor_headers(request, config, api)?;
 
-    if matches!(method, Method::GET) {
-        if let Some(params) = payload.as_object() {
+    let payload = upstream.input;
+    if matches!(method, Method::GET | Method::DELETE) {
+        if let Some(params) = payload.as_object()
+            && !params.is_empty()
+        {
             request = request.query(params);
         }
     } else {
-        request = request.json(&payload);
+        request = match api.body_encoding {
+            UpstreamBodyEncoding::Json => request.json(&payload),
+            // `prepare_upstream_request` has already required an object.
+            UpstreamBodyEncoding::Form => {
+                let fields: Vec<(String, String)> = payload
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .map(|(key, value)| match value {
+                        Value::String(value) => (key.clone(), value.clone()),
+                        other => (key.clone(), other.to_string()),
+                    })
+                    .collect();
+                request.form(&fields)
+            }
+            UpstreamBodyEncoding::Raw => {
+                request.body(payload.as_str().unwrap_or_default().to_string())
+            }
+        };
     }
 
-    let response = request
-        .send()
-        .await
-        .map_err(|err| ApiError::upstream(StatusCode::BAD_GATEWAY, err.to_string()))?;
+    send_upstream(config, request).await
+}
 
-    let status = response.status().as_u16();
-    let body = response.text().await.unwrap_or_default();
-    Ok((status, body))
+/// Upstream response headers that proxy mode passes back to the caller.
+pub const PROXY_RESPONSE_HEADERS: &[&str] = &[
+    "content-type",
+    "content-encoding",
+    "content-language",
+    "content-disposition",
+    "cache-control",
+    "etag",
+    "last-modified",
+    "expires",
+    "retry-after",
+];
+
+/// Caller headers that proxy mode always forwards so bodies stay interpretable.
+const PROXY_REQUEST_HEADERS: &[&str] = &["accept", "content-type", "content-encoding"];
+
+/// Joins the proxied sub-path and query onto the sponsor's base URL. Each segment is
+/// percent-encoded so nothing in the path can climb out of the base path or rewrite the
+/// query, since the sponsor's secret headers go wherever the result points.
+pub fn proxy_upstream_url(base: &str, path: &str, query: Option<&str>) -> ApiResult<String> {
+    if !upstream_path_params(base)?.is_empty() {
+        return Err(ApiError::validation(
+            "proxy mode is not available for upstream_url templates with path parameters",
+        ));
+    }
+    let base = reqwest::Url::parse(base)
+        .map_err(|_| ApiError::internal("stored upstream_url is not a valid URL"))?;
+    let base_path = base.path().trim_end_matches('/');
+
+    let mut url = base.clone();
+    url.set_fragment(None);
+    let path = path.trim_start_matches('/');
+    if !path.is_empty() {
+        let mut segments = Vec::new();
+        for segment in path.split('/') {
+            if matches!(segment, "." | "..") {
+                return Err(ApiError::validation(
+                    "proxy path must not contain '.' or '..' segments",
+                ));
+            }
+            segments.push(encode_path_segment(segment));
+        }
+        url.set_path(&format!("{base_path}/{}", segments.join("/")));
+    }
+    let query = [base.query(), query]
+        .into_iter()
+        .flatten()
+        .filter(|query| !query.is_empty())
+        .collect::<Vec<_>>()
+        .join("&");
+    url.set_query(Some(query.as_str()).filter(|query| !query.is_empty()));
+
+    let joined = reqwest::Url::parse(url.as_str())
+        .map_err(|_| ApiError::validation("proxy path does not form a valid URL"))?;
+    let under_base = joined.path() == base_path
+        || joined.path() == base.path()
+        || joined.path().starts_with(&format!("{base_path}/"));
+    if joined.origin() != base.origin() || !under_base {
+        return Err(ApiError::validation(
+            "proxy path must stay under the sponsored API's upstream_url",
+        ));
+    }
+    Ok(joined.to_string())
+}
+
+pub async fn proxy_upstream(
+    http: &Client,
+    config: &AppConfig,
+    api: &SponsoredApi,
+    method: Method,
+    url: &str,
+    caller_headers: &HeaderMap,
+    body: axum::body::Bytes,
+) -> ApiResult<reqwest::Response> {
+    let mut request = http.request(method, url).timeout(Duration::from_secs(
+        config.sponsored_api_stream_timeout_secs,
+    ));
+
+    let configured = |name: &str| {
+        api.upstream_headers
+            .keys()
+            .chain(api.sealed_headers.keys())
+            .any(|header| header.eq_ignore_ascii_case(name))
+    };
+    let forwarded = PROXY_REQUEST_HEADERS
+        .iter()
+        .copied()
+        .chain(api.forward_headers.iter().map(String::as_str))
+        .filter(|name| !configured(name));
+    for name in forwarded {
+        for value in caller_headers.get_all(name) {
+            request = request.header(name, value.clone());
+        }
+    }
+    request = apply_sponsor_headers(request, config, api)?;
+
+    send_upstream(config, request.body(body)).await
+}
+
+pub fn apply_sponsor_headers(
+    mut request: reqwest::RequestBuilder,
+    config: &AppConfig,
+    api: &SponsoredApi,
+) -> ApiResult<reqwest::RequestBuilder> {
+    for (header, value) in &api.upstream_headers {
+        request = request.header(header, value);
+    }
+    for (header, value) in config
+        .upstream_secret_keys
+        .open_headers(&api.sealed_headers)?
+    {
+        request = request.header(header, value);
+    }
+    Ok(request)
+}
+
+/// Requests the health check path on the upstream's host with the sponsor's headers. Never
+/// fails; problems are reported in the result.
+pub async fn probe_upstream(
+    http: &Client,
+    config: &AppConfig,
+    api: &SponsoredApi,
+    policy: &HealthCheckPolicy,
+) -> HealthProbeResult {
+    let started = std::time::Instant::now();
+    let outcome: Result<u16, String> = async {
+        let url = reqwest::Url::parse(&api.upstream_url)
+            .and_then(|base| base.join(&policy.path))
+            .map_err(|_| "health check URL is invalid".to_string())?;
+        config
+            .upstream_policy
+            .check_url(&url)
+            .map_err(|err| format!("blocked by network policy: {err}"))?;
+        let request = apply_sponsor_headers(
+            http.get(url).timeout(Duration::from_secs(
+                policy.timeout_secs.clamp(1, MAX_HEALTH_CHECK_TIMEOUT_SECS),
+            )),
+            config,
+            api,
+        )
+        .map_err(|err| err.to_string())?;
+        let response = request.send().await.map_err(|err| {
+            blocked_upstream_reason(&err)
+                .map(|reason| format!("blocked by network policy: {reason}"))
+                .unwrap_or_else(|| err.to_string())
+        })?;
+        Ok(response.status().as_u16())
+    }
+    .await;
+
+    let latency_ms = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+    match outcome {
+        Ok(status) => HealthProbeResult {
+            ok: status == policy.expected_status,
+            status: Some(status),
+            error: (status != policy.expected_status)
+                .then(|| format!("expected status {}", policy.expected_status)),
+            latency_ms,
+            checked_at: Utc::now(),
+        },
+        Err(error) => HealthProbeResult {
+            ok: false,
+            status: None,
+            error: Some(error),
+            latency_ms,
+            checked_at: Utc::now(),
+        },
+    }
+}
+
+/// Waits at most `sponsored_api_timeout_secs` for response headers. The request itself is
+/// built with the longer stream timeout so the body can keep flowing afterwards.
+async fn send_upstream(
+    config: &AppConfig,
+    request: reqwest::RequestBuilder,
+) -> ApiResult<reqwest::Response> {
+    tokio::time::timeout(
+        Duration::from_secs(config.sponsored_api_timeout_secs),
+        request.send(),
+    )
+    .await
+    .map_err(|_| {
+        ApiError::upstream(
+            StatusCode::GATEWAY_TIMEOUT,
+            "upstream did not respond in time",
+        )
+    })?
+    .map_err(|err| match blocked_upstream_reason(&err) {
+        Some(reason) => upstream_blocked_error(reason),
+        // The request never left; that is the caller's input, not the upstream's health.
+        None if err.is_builder() => {
+            ApiError::validation(format!("could not build upstream request: {err}"))
+        }
+        None => ApiError::upstream(StatusCode::BAD_GATEWAY, err.to_string()),
+    })
 }
@@ -0,0 +1,280 @@
+use axum::http::StatusCode;
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::error::{ApiError, ApiResult};
+use crate::network::blocked_upstream_reason;
+use crate::types::{TaskCompletionStatus, TaskVerification};
+use crate::utils::upstream_blocked_error;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Profile attribute holding the wallet that on-chain tasks must be sent from.
+pub const WALLET_ADDRESS_ATTRIBUTE: &str = "wallet_address";
+
+#[derive(Debug, Clone)]
+pub struct TaskProofOutcome {
+    pub status: TaskCompletionStatus,
+    pub rejection_reason: Option<String>,
+    /// Transaction claimed for an on-chain task; each backs one completion per campaign.
+    pub tx_hash: Option<String>,
+}
+
+impl TaskProofOutcome {
+    fn verified() -> Self {
+        Self {
+            status: TaskCompletionStatus::Verified,
+            rejection_reason: None,
+            tx_hash: None,
+        }
+    }
+
+    fn pending() -> Self {
+        Self {
+            status: TaskCompletionStatus::Pending,
+            rejection_reason: None,
+            tx_hash: None,
+        }
+    }
+
+    fn rejected(reason: impl Into<String>) -> Self {
+        Self {
+            status: TaskCompletionStatus::Rejected,
+            rejection_reason: Some(reason.into()),
+            tx_hash: None,
+        }
+    }
+}
+
+pub fn generate_task_secret() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+pub fn validate_task_verification(verification: &TaskVerification) -> ApiResult<()> {
+    match verification {
+        TaskVerification::SponsorCallback => Ok(()),
+        TaskVerification::OauthRedirect { authorize_url } => {
+            reqwest::Url::parse(authorize_url.trim()).map_err(|_| {
+                ApiError::validation("task_verification.authorize_url must be a valid URL")
+            })?;
+            Ok(())
+        }
+        TaskVerification::FormSubmission { required_fields } => {
+            // An empty form would verify any completion without evidence.
+            if required_fields.is_empty() {
+                return Err(ApiError::validation(
+                    "task_verification.required_fields must name at least one field",
+                ));
+            }
+            if required_fields.iter().any(|field| field.trim().is_empty()) {
+                return Err(ApiError::validation(
+                    "task_verification.required_fields must not contain empty names",
+                ));
+            }
+            Ok(())
+        }
+        TaskVerification::OnchainAction { rpc_url, .. } => {
+            reqwest::Url::parse(rpc_url.trim()).map_err(|_| {
+                ApiError::validation("task_verification.rpc_url must be a valid URL")
+            })?;
+            Ok(())
+        }
+    }
+}
+
+/// Runs the synchronous part of a campaign's proof provider against submitted evidence.
+/// Callback and redirect providers always start out pending until the sponsor confirms.
+/// `http` must be the policy-bound upstream client, since the RPC URL is sponsor-supplied.
+pub async fn evaluate_task_proof(
+    http: &reqwest::Client,
+    verification: &TaskVerification,
+    evidence: Option<&Value>,
+    wallet_address: Option<&str>,
+) -> ApiResult<TaskProofOutcome> {
+    match verification {
+        TaskVerification::SponsorCallback | TaskVerification::OauthRedirect { .. } => {
+            Ok(TaskProofOutcome::pending())
+        }
+        TaskVerification::FormSubmission { required_fields } => {
+            let fields = evidence.and_then(Value::as_object);
+            let missing: Vec<&str> = required_fields
+                .iter()
+                .filter(|field| {
+                    !fields
+                        .and_then(|fields| fields.get(field.as_str()))
+                        .is_some_and(is_filled)
+                })
+                .map(String::as_str)
+                .collect();
+
+            if !missing.is_empty() {
+                return Err(ApiError::validation(format!(
+                    "evidence is missing required form fields: {}",
+                    missing.join(", ")
+                )));
+            }
+            Ok(TaskProofOutcome::verified())
+        }
+        TaskVerification::OnchainAction {
+            rpc_url,
+            contract_address,
+        } => {
+            let Some(tx_hash) = evidence
+                .and_then(|value| value.get("tx_hash"))
+                .and_then(Value::as_str)
+                .filter(|value| !value.trim().is_empty())
+            else {
+                return Err(ApiError::validation(
+                    "evidence.tx_hash is required for on-chain tasks",
+                ));
+            };
+            let Some(wallet_address) = wallet_address.filter(|value| !value.trim().is_empty())
+            else {
+                return Err(ApiError::validation(format!(
+                    "profile attribute '{WALLET_ADDRESS_ATTRIBUTE}' is required for on-chain tasks"
+                )));
+            };
+
+            let mut outcome = check_onchain_action(
+                http,
+                rpc_url,
+                tx_hash,
+                wallet_address.trim(),
+                contract_address.as_deref(),
+            )
+            .await?;
+            // `wallet_address` is self-declared on the profile, so a matching receipt shows the
+            // wallet acted, not that this user holds it. The sponsor confirms through the
+            // signed callback; the transaction stays claimed meanwhile.
+            if outcome.status == TaskCompletionStatus::Verified {
+                outcome = TaskProofOutcome {
+                    tx_hash: Some(tx_hash.trim().to_ascii_lowercase()),
+                    ..TaskProofOutcome::pending()
+                };
+            }
+            Ok(outcome)
+        }
+    }
+}
+
+fn is_filled(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::String(text) => !text.trim().is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        _ => true,
+    }
+}
+
+async fn check_onchain_action(
+    http: &reqwest::Client,
+    rpc_url: &str,
+    tx_hash: &str,
+    wallet_address: &str,
+    contract_address: Option<&str>,
+) -> ApiResult<TaskProofOutcome> {
+    let body = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "eth_getTransactionReceipt",
+        "params": [tx_hash]
+    });
+
+    let response =
+        http.post(rpc_url).json(&body).send().await.map_err(
+            |err| match blocked_upstream_reason(&err) {
+                Some(reason) => upstream_blocked_error(reason),
+                None => ApiError::upstream(StatusCode::BAD_GATEWAY, err.to_string()),
+            },
+        )?;
+
+    let status = response.status();
+    let payload = response
+        .json::<Value>()
+        .await
+        .map_err(|err| ApiError::upstream(StatusCode::BAD_GATEWAY, err.to_string()))?;
+
+    if !status.is_success() {
+        return Err(ApiError::upstream(
+            StatusCode::BAD_GATEWAY,
+            format!("rpc call failed with status={status}"),
+        ));
+    }
+    if let Some(error) = payload.get("error") {
+        return Err(ApiError::upstream(
+            StatusCode::BAD_GATEWAY,
+            format!("rpc returned error: {error}"),
+        ));
+    }
+
+    let Some(receipt) = payload.get("result").filter(|value| !value.is_null()) else {
+        return Ok(TaskProofOutcome::rejected("transaction receipt not found"));
+    };
+
+    if receipt.get("status").and_then(Value::as_str) != Some("0x1") {
+        return Ok(TaskProofOutcome::rejected("transaction did not succeed"));
+    }
+
+    let from = receipt
+        .get("from")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    if !from.eq_ignore_ascii_case(wallet_address) {
+        return Ok(TaskProofOutcome::rejected(format!(
+            "transaction was sent from {from}, not the profile's wallet {wallet_address}"
+        )));
+    }
+
+    if let Some(expected) = contract_address {
+        let to = receipt
+            .get("to")
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if !to.eq_ignore_ascii_case(expected) {
+            return Ok(TaskProofOutcome::rejected(format!(
+                "transaction was sent to {to}, expected {expected}"
+            )));
+        }
+    }
+
+    Ok(TaskProofOutcome::verified())
+}
+
+pub fn sign_task_payload(secret: &str, payload: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any length");
+    mac.update(payload);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub fn verify_task_signature(secret: &str, payload: &[u8], signature: &str) -> bool {
+    let Ok(expected) = hex::decode(signature.trim()) else {
+        return false;
+    };
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any length");
+    mac.update(payload);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// The OAuth-style `state` parameter binds a redirect to one pending completion.
+pub fn oauth_state(secret: &str, completion_id: Uuid) -> String {
+    let signature = sign_task_payload(secret, completion_id.as_bytes());
+    format!("{completion_id}.{signature}")
+}
+
+/// What the sponsor signs with the task secret when redirecting back. Covering the outcome
+/// means the `state` a caller was handed cannot verify a completion on its own.
+pub fn oauth_callback_payload(state: &str, error: Option<&str>) -> String {
+    match error {
+        Some(error) => format!("{state}\nrejected\n{error}"),
+        None => format!("{state}\nverified"),
+    }
+}
+
+pub fn parse_oauth_state(state: &str) -> Option<(Uuid, &str)> {
+    let (id, signature) = state.split_once('.')?;
+    Some((Uuid::parse_str(id).ok()?, signature))
+}
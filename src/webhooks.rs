@@ -0,0 +1,352 @@
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use sqlx::PgPool;
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::error::{ApiError, ApiResult};
+use crate::network::blocked_upstream_reason;
+use crate::types::{
+    AppConfig, SharedState, WEBHOOK_DELIVERY_HEADER, WEBHOOK_EVENT_HEADER,
+    WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER, WebhookDelivery, WebhookEventType,
+};
+use crate::utils::check_upstream_url;
+
+type HmacSha256 = Hmac<Sha256>;
+
+const DISPATCH_BATCH_SIZE: i64 = 50;
+const DISPATCH_LEASE_SECS: i64 = 60;
+const MAX_ERROR_LEN: usize = 500;
+
+#[derive(Debug, Clone)]
+pub struct SponsorEvent {
+    pub sponsor: String,
+    pub campaign_id: Option<Uuid>,
+    pub event_type: WebhookEventType,
+    pub data: Value,
+    /// Budget before/after/total, used to match each webhook's low-budget threshold.
+    pub budget: Option<(u64, u64, u64)>,
+}
+
+impl SponsorEvent {
+    pub fn new(
+        sponsor: impl Into<String>,
+        campaign_id: Option<Uuid>,
+        event_type: WebhookEventType,
+        data: Value,
+    ) -> Self {
+        Self {
+            sponsor: sponsor.into(),
+            campaign_id,
+            event_type,
+            data,
+            budget: None,
+        }
+    }
+}
+
+/// Builds the budget lifecycle events triggered by a single charge.
+pub fn budget_events(
+    sponsor: &str,
+    campaign_id: Option<Uuid>,
+    data: Value,
+    before: u64,
+    after: u64,
+    total: u64,
+    still_active: bool,
+) -> Vec<SponsorEvent> {
+    let mut events = vec![SponsorEvent {
+        budget: Some((before, after, total)),
+        ..SponsorEvent::new(
+            sponsor,
+            campaign_id,
+            WebhookEventType::BudgetLow,
+            data.clone(),
+        )
+    }];
+
+    if !still_active {
+        events.push(SponsorEvent::new(
+            sponsor,
+            campaign_id,
+            WebhookEventType::BudgetExhausted,
+            data.clone(),
+        ));
+        events.push(SponsorEvent::new(
+            sponsor,
+            campaign_id,
+            WebhookEventType::CampaignEnded,
+            data,
+        ));
+    }
+
+    events
+}
+
+/// Queues deliveries for every matching webhook. Failures are logged rather than surfaced so
+/// that notification problems never fail the request that produced the event.
+pub async fn emit_sponsor_events(db: &PgPool, events: Vec<SponsorEvent>) {
+    for event in events {
+        if let Err(err) = enqueue_sponsor_event(db, &event).await {
+            warn!(
+                "failed to enqueue {} webhook for sponsor {}: {err}",
+                event.event_type.as_str(),
+                event.sponsor
+            );
+        }
+    }
+}
+
+async fn enqueue_sponsor_event(db: &PgPool, event: &SponsorEvent) -> ApiResult<u64> {
+    let (before, after, total) = match event.budget {
+        Some((before, after, total)) => {
+            (Some(before as i64), Some(after as i64), Some(total as i64))
+        }
+        None => (None, None, None),
+    };
+
+    let result = sqlx::query(
+        r#"
+        insert into webhook_deliveries (id, webhook_id, event_type, payload, created_at)
+        select gen_random_uuid(), w.id, $3, $4, now()
+        from sponsor_webhooks w
+        where w.active
+          and w.sponsor = $1
+          and $3 = any(w.events)
+          and (w.campaign_id is null or w.campaign_id = $2)
+          and (
+            $5::bigint is null
+            or ($5 * 100 > $7 * w.budget_low_threshold_percent
+                and $6 * 100 <= $7 * w.budget_low_threshold_percent)
+          )
+        "#,
+    )
+    .bind(&event.sponsor)
+    .bind(event.campaign_id)
+    .bind(event.event_type.as_str())
+    .bind(&event.data)
+    .bind(before)
+    .bind(after)
+    .bind(total)
+    .execute(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    Ok(result.rows_affected())
+}
+
+pub fn sign_webhook_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        HmacSha256::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    hex::encode(mac.finalize().into_bytes())
+}
+
+pub fn retry_delay(config: &AppConfig, attempts: u32) -> chrono::Duration {
+    let exponent = attempts.saturating_sub(1).min(16);
+    let secs = config
+        .webhook_retry_base_secs
+        .saturating_mul(1u64 << exponent);
+    chrono::Duration::seconds(i64::try_from(secs).unwrap_or(i64::MAX / 1000))
+}
+
+#[derive(Debug, sqlx::FromRow)]
+struct DueDelivery {
+    id: Uuid,
+    event_type: String,
+    payload: Value,
+    attempts: i32,
+    created_at: DateTime<Utc>,
+    url: String,
+    secret: String,
+}
+
+pub async fn run_webhook_dispatcher(state: SharedState) {
+    loop {
+        let (db, http, config) = {
+            let state = state.inner.read().await;
+            (
+                state.db.clone(),
+                state.upstream_http.clone(),
+                state.config.clone(),
+            )
+        };
+        let Some(db) = db else {
+            return;
+        };
+
+        if let Err(err) = dispatch_due_deliveries(&db, &http, &config).await {
+            warn!("webhook dispatch failed: {err}");
+        }
+
+        tokio::time::sleep(Duration::from_secs(
+            config.webhook_poll_interval_secs.max(1),
+        ))
+        .await;
+    }
+}
+
+async fn dispatch_due_deliveries(
+    db: &PgPool,
+    http: &reqwest::Client,
+    config: &AppConfig,
+) -> ApiResult<usize> {
+    // Claim a batch by pushing it into the future so concurrent dispatchers skip it.
+    let due = sqlx::query_as::<_, DueDelivery>(
+        r#"
+        update webhook_deliveries d
+        set next_attempt_at = now() + make_interval(secs => $2)
+        from sponsor_webhooks w
+        where w.id = d.webhook_id
+          and d.id in (
+            select id from webhook_deliveries
+            where status = 'pending' and next_attempt_at <= now()
+            order by next_attempt_at
+            limit $1
+            for update skip locked
+          )
+        returning d.id, d.event_type, d.payload, d.attempts, d.created_at, w.url, w.secret
+        "#,
+    )
+    .bind(DISPATCH_BATCH_SIZE)
+    .bind(DISPATCH_LEASE_SECS as f64)
+    .fetch_all(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
+
+    let count = due.len();
+    for delivery in due {
+        attempt_delivery(db, http, config, delivery).await?;
+    }
+    Ok(count)
+}
+
+async fn attempt_delivery(
+    db: &PgPool,
+    http: &reqwest::Client,
+    config: &AppConfig,
+    delivery: DueDelivery,
+) -> ApiResult<WebhookDelivery> {
+    let envelope = serde_json::json!({
+        "id": delivery.id,
+        "type": delivery.event_type,
+        "created_at": delivery.created_at,
+        "data": delivery.payload,
+    });
+    let body = serde_json::to_vec(&envelope)
+        .map_err(|err| ApiError::internal(format!("failed to encode webhook: {err}")))?;
+    let timestamp = Utc::now().timestamp();
+    let signature = sign_webhook_payload(&delivery.secret, timestamp, &body);
+
+    // `http` is the policy-bound upstream client; literal IPs never reach its resolver.
+    let outcome = match check_upstream_url(&config.upstream_policy, &delivery.url) {
+        Ok(()) => http
+            .post(&delivery.url)
+            .timeout(Duration::from_secs(10))
+            .header("content-type", "application/json")
+            .header(WEBHOOK_SIGNATURE_HEADER, signature)
+            .header(WEBHOOK_TIMESTAMP_HEADER, timestamp.to_string())
+            .header(WEBHOOK_EVENT_HEADER, &delivery.event_type)
+            .header(WEBHOOK_DELIVERY_HEADER, delivery.id.to_string())
+            .body(body)
+            .send()
+            .await
+            .map_err(|err| match blocked_upstream_reason(&err) {
+                Some(reason) => format!("blocked by network policy: {reason}"),
+                None => err.to_string(),
+            }),
+        Err(err) => Err(err.to_string()),
+    };
+
+    let attempts = delivery.attempts.saturating_add(1);
+    let (status_code, error) = match outcome {
+        Ok(response) if response.status().is_success() => {
+            (Some(i32::from(response.status().as_u16())), None)
+        }
+        Ok(response) => (
+            Some(i32::from(response.status().as_u16())),
+            Some(format!("endpoint responded with {}", response.status())),
+        ),
+        Err(err) => (None, Some(err)),
+    };
+
+    let updated = match error {
+        None => {
+            sqlx::query_as::<_, WebhookDelivery>(
+                r#"
+            update webhook_deliveries
+            set status = 'succeeded', attempts = $2, last_status_code = $3,
+                last_error = null, delivered_at = now()
+            where id = $1
+            returning id, webhook_id, event_type, payload, status, attempts, next_attempt_at,
+                last_status_code, last_error, delivered_at, created_at
+            "#,
+            )
+            .bind(delivery.id)
+            .bind(attempts)
+            .bind(status_code)
+            .fetch_one(db)
+            .await
+        }
+        Some(error) => {
+            let exhausted =
+                u64::try_from(attempts).unwrap_or(u64::MAX) >= config.webhook_max_attempts;
+            let next_attempt_at =
+                Utc::now() + retry_delay(config, u32::try_from(attempts).unwrap_or(u32::MAX));
+            let error: String = error.chars().take(MAX_ERROR_LEN).collect();
+            sqlx::query_as::<_, WebhookDelivery>(
+                r#"
+                update webhook_deliveries
+                set status = case when $5 then 'failed' else 'pending' end,
+                    attempts = $2, last_status_code = $3, last_error = $4,
+                    next_attempt_at = $6
+                where id = $1
+                returning id, webhook_id, event_type, payload, status, attempts, next_attempt_at,
+                    last_status_code, last_error, delivered_at, created_at
+                "#,
+            )
+            .bind(delivery.id)
+            .bind(attempts)
+            .bind(status_code)
+            .bind(error)
+            .bind(exhausted)
+            .bind(next_attempt_at)
+            .fetch_one(db)
+            .await
+        }
+    };
+
+    updated.map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))
+}
+
+/// Sends a delivery again right away, regardless of its current status.
+pub async fn redeliver(
+    db: &PgPool,
+    http: &reqwest::Client,
+    config: &AppConfig,
+    delivery_id: Uuid,
+) -> ApiResult<WebhookDelivery> {
+    let delivery = sqlx::query_as::<_, DueDelivery>(
+        r#"
+        update webhook_deliveries d
+        set status = 'pending', next_attempt_at = now() + make_interval(secs => $2)
+        from sponsor_webhooks w
+        where w.id = d.webhook_id and d.id = $1
+        returning d.id, d.event_type, d.payload, d.attempts, d.created_at, w.url, w.secret
+        "#,
+    )
+    .bind(delivery_id)
+    .bind(DISPATCH_LEASE_SECS as f64)
+    .fetch_optional(db)
+    .await
+    .map_err(|err| ApiError::database(StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?
+    .ok_or_else(|| ApiError::not_found("webhook delivery not found"))?;
+
+    attempt_delivery(db, http, config, delivery).await
+}